@@ -1,26 +1,60 @@
 use bevy::prelude::*;
-use crate::resources::{AIState, DatabaseConnection};
-use crate::components::{MapTile, TileType};
-use crate::ai::mod_stub;
+use crate::resources::{DatabaseConnection, GameState};
+use crate::ai::map_generator::{generate_procedural, parse_map, serialize_map, set_active_map_seed, spawn_map_tiles, MapRoot, MapSeed};
 
-pub fn generate_and_store_map(seed: i64, db: &DatabaseConnection) {
-    let grid = mod_stub::generate_map(seed);
+/// Generate the procedural map for `seed` into a slot, unless it is already stored there
+pub fn generate_and_store_map(slot: u32, seed: i64, db: &DatabaseConnection) {
+    if db.load_map(slot, seed).is_ok() {
+        return;
+    }
+    // Same generator as `MapGenerator`, so startup and regenerated maps match for a seed
+    let grid = generate_procedural(seed);
     // serialize to simple CSV-like string
-    let serialized = grid.iter()
-        .map(|row| row.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(","))
-        .collect::<Vec<_>>()
-        .join("\n");
-    let _ = db.save_map(seed, &serialized);
+    let serialized = serialize_map(&grid);
+    let _ = db.save_map(slot, seed, &serialized);
 }
 
-pub fn load_map_into_world(seed: i64, db: &DatabaseConnection, mut commands: Commands) {
-    if let Ok(serialized) = db.load_map(seed) {
-        for (y, line) in serialized.lines().enumerate() {
-            for (x, cell) in line.split(',').enumerate() {
-                let val: i32 = cell.parse().unwrap_or(0);
-                let tile_type = match val { 0 => TileType::Empty, 1 => TileType::Resource, 2 => TileType::Enemy, 3 => TileType::Quest, _ => TileType::Empty };
-                commands.spawn(MapTile { tile_type, grid_x: x as i32, grid_y: y as i32 });
+pub fn load_map_into_world(slot: u32, seed: i64, db: &DatabaseConnection, mut commands: Commands) {
+    if let Ok(serialized) = db.load_map(slot, seed) {
+        match parse_map(&serialized) {
+            Ok(grid) => {
+                let tiles = spawn_map_tiles(&mut commands, &grid);
+                commands.insert_resource(MapRoot { tiles });
             }
+            Err(e) => warn!("Stored map for seed {} is invalid: {}", seed, e),
+        }
+    }
+}
+
+/// Seed of the map last active in world `slot`, falling back to its newest stored map and then the default
+pub fn restore_map_seed(slot: u32, db: &DatabaseConnection) -> MapSeed {
+    db.load_current_map_seed(slot)
+        .or_else(|_| db.load_latest_map(slot).map(|(seed, _)| seed))
+        .map(MapSeed)
+        .unwrap_or_default()
+}
+
+/// Make `slot` the active world slot, restoring its active map seed and portal depth into
+/// `GameState` and `MapSeed`, and return that map's stored grid
+pub fn switch_slot(
+    slot: u32,
+    game_state: &mut GameState,
+    map_seed: &mut MapSeed,
+    db: &DatabaseConnection,
+) -> Option<Vec<Vec<i32>>> {
+    game_state.world_slot = slot;
+    game_state.map_depth = db.load_map_depth(slot).unwrap_or(0);
+    let seed = restore_map_seed(slot, db).0;
+    set_active_map_seed(seed, Some(&mut *game_state), Some(map_seed), None);
+    let serialized = db.load_map(slot, seed).ok()?;
+    match parse_map(&serialized) {
+        Ok(grid) => {
+            info!("Switched to slot {} (map seed {})", slot, seed);
+            Some(grid)
+        }
+        Err(e) => {
+            warn!("Stored map for slot {} is invalid: {}", slot, e);
+            None
         }
     }
 }
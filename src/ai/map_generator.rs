@@ -1,11 +1,24 @@
 //! AI-powered map generation using torch-rs
 
 use bevy::prelude::*;
-use tch::{nn, Device, Tensor, CModule};
+use bevy::tasks::{futures_lite::future, AsyncComputeTaskPool, Task};
+use tch::{Device, Kind, Tensor, CModule};
 use rand::{SeedableRng, Rng};
 use rand_chacha::ChaCha8Rng;
-use crate::components::{TileType, MapTile};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::components::{TileType, MapTile, Player, Position};
+use crate::config::keys::{Action, KeyBindings};
+use crate::config::map::MapConfig;
+use crate::resources::{DatabaseConnection, GameRng, GameState};
+use crate::movement::TileEntered;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+/// Side length of generated maps
+pub const MAP_SIZE: usize = 16;
+
+/// Length of the seed vector fed to the model
+const MODEL_INPUT_SIZE: i64 = 64;
 
 /// AI Map Generator resource
 #[derive(Resource, Debug)]
@@ -14,13 +27,108 @@ pub struct MapGenerator {
     pub model: Option<CModule>,
     pub cache: HashMap<i64, Vec<Vec<i32>>>,
     pub generation_stats: GenerationStats,
+    /// Seeds (and optional biomes) requested by peers, generated one at a time
+    pub queued_seeds: VecDeque<(i64, Option<Biome>)>,
+    /// Maps table backing the in-memory cache; generated maps are stored under world `slot`
+    pub db: Option<DatabaseConnection>,
+    pub slot: u32,
 }
 
 #[derive(Debug, Default)]
 pub struct GenerationStats {
     pub maps_generated: u32,
     pub cache_hits: u32,
-    pub average_generation_time_ms: f32,
+    /// Mean time to generate a map with the model this session
+    pub avg_ai_time_ms: f32,
+    /// Mean time to generate a map procedurally this session
+    pub avg_procedural_time_ms: f32,
+    /// Maps generated this session per source
+    pub source_counts: HashMap<GenerationSource, u32>,
+    /// Counts from earlier sessions, loaded from the database
+    pub persisted_counts: HashMap<GenerationSource, u32>,
+}
+
+/// Terrain theme of a procedurally generated map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Biome {
+    Forest,
+    Desert,
+    Mountains,
+    Swamp,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [Biome::Forest, Biome::Desert, Biome::Mountains, Biome::Swamp];
+}
+
+/// Which path produced a generated map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenerationSource {
+    Ai,
+    Procedural,
+}
+
+impl GenerationSource {
+    pub const ALL: [GenerationSource; 2] = [GenerationSource::Ai, GenerationSource::Procedural];
+    
+    /// Stable name used in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GenerationSource::Ai => "ai",
+            GenerationSource::Procedural => "procedural",
+        }
+    }
+    
+    /// Parse a name produced by `as_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.as_str() == name)
+    }
+}
+
+impl GenerationStats {
+    /// Count a map produced by `source` this session and fold its time into that source's average
+    pub fn record_generation(&mut self, source: GenerationSource, generation_time_ms: f32) {
+        self.maps_generated += 1;
+        let count = self.source_counts.entry(source).or_default();
+        *count += 1;
+        let count = *count as f32;
+        let average = match source {
+            GenerationSource::Ai => &mut self.avg_ai_time_ms,
+            GenerationSource::Procedural => &mut self.avg_procedural_time_ms,
+        };
+        *average = (*average * (count - 1.0) + generation_time_ms) / count;
+    }
+    
+    /// Maps generated by `source` this session
+    pub fn session_count(&self, source: GenerationSource) -> u32 {
+        self.source_counts.get(&source).copied().unwrap_or(0)
+    }
+    
+    /// Mean generation time for `source` this session
+    pub fn average_time_ms(&self, source: GenerationSource) -> f32 {
+        match source {
+            GenerationSource::Ai => self.avg_ai_time_ms,
+            GenerationSource::Procedural => self.avg_procedural_time_ms,
+        }
+    }
+    
+    /// One-line comparison of the AI and procedural paths
+    pub fn timing_summary(&self) -> String {
+        GenerationSource::ALL
+            .iter()
+            .map(|&source| format!(
+                "{}: {} maps, avg {:.2} ms",
+                source.as_str(), self.session_count(source), self.average_time_ms(source)
+            ))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+    
+    /// Maps generated by `source` across all sessions
+    pub fn lifetime_count(&self, source: GenerationSource) -> u32 {
+        self.persisted_counts.get(&source).copied().unwrap_or(0)
+            + self.session_count(source)
+    }
 }
 
 impl Default for MapGenerator {
@@ -38,15 +146,23 @@ impl Default for MapGenerator {
             model: None,
             cache: HashMap::new(),
             generation_stats: GenerationStats::default(),
+            queued_seeds: VecDeque::new(),
+            db: None,
+            slot: 0,
         }
     }
 }
 
 impl MapGenerator {
-    /// Initialize the AI model for map generation
+    /// Initialize the AI model for map generation from the TorchScript file in `CQ_MAP_MODEL`
     pub fn initialize_model(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Ok(path) = std::env::var("CQ_MAP_MODEL") else {
+            info!("CQ_MAP_MODEL not set, using procedural generation");
+            return Ok(());
+        };
+        
         // Try to load a pre-trained model, fallback to procedural generation
-        match self.load_pretrained_model() {
+        match self.load_pretrained_model(&path) {
             Ok(model) => {
                 self.model = Some(model);
                 info!("AI model loaded successfully for map generation");
@@ -59,57 +175,111 @@ impl MapGenerator {
         Ok(())
     }
     
-    /// Load a pre-trained PyTorch model
-    fn load_pretrained_model(&self) -> Result<CModule, Box<dyn std::error::Error>> {
-        // This would load an actual trained model in production
-        // For now, we'll create a simple neural network as a placeholder
-        let vs = nn::VarStore::new(self.device);
-        let model = nn::seq()
-            .add(nn::linear(&vs.root(), 64, 128, Default::default()))
-            .add_fn(|x| x.relu())
-            .add(nn::linear(&vs.root(), 128, 256, Default::default()))
-            .add_fn(|x| x.relu())
-            .add(nn::linear(&vs.root(), 256, 16 * 16 * 4, Default::default()))
-            .add_fn(|x| x.softmax(-1, tch::Kind::Float));
-        
-        // Convert to CModule for inference
-        let dummy_input = Tensor::randn(&[1, 64], (tch::Kind::Float, self.device));
-        let traced = model.forward(&dummy_input);
+    /// Load a TorchScript model, checking it produces one score per tile type per tile
+    fn load_pretrained_model(&self, path: impl AsRef<Path>) -> Result<CModule, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(format!("Model file {} not found", path.display()).into());
+        }
+        let model = CModule::load_on_device(path, self.device)?;
         
-        // In a real scenario, you would load from a .pt file:
-        // CModule::load("models/map_generator.pt")
-        Err("No pre-trained model available".into())
+        let dummy_input = Tensor::zeros(&[1, MODEL_INPUT_SIZE], (Kind::Float, self.device));
+        let output = tch::no_grad(|| model.forward_ts(&[dummy_input]))?;
+        let expected = MAP_SIZE * MAP_SIZE * 4;
+        if output.numel() != expected {
+            return Err(format!(
+                "Model {} outputs {} values, expected {} ({}x{}x4)",
+                path.display(), output.numel(), expected, MAP_SIZE, MAP_SIZE
+            ).into());
+        }
+        Ok(model)
+    }
+    
+    /// Back the map cache with the maps of world `slot`
+    pub fn with_database(mut self, db: DatabaseConnection, slot: u32) -> Self {
+        self.db = Some(db);
+        self.slot = slot;
+        self
     }
     
     /// Generate a 16x16 map using AI or procedural fallback
     pub fn generate_map(&mut self, seed: i64) -> Vec<Vec<i32>> {
         let start_time = std::time::Instant::now();
         
-        // Check cache first
-        if let Some(cached_map) = self.cache.get(&seed) {
-            self.generation_stats.cache_hits += 1;
-            return cached_map.clone();
+        if let Some(existing) = self.lookup_map(seed) {
+            return existing;
         }
         
-        let map = if let Some(ref model) = self.model {
-            self.generate_with_ai(model, seed)
+        let (map, source) = if let Some(ref model) = self.model {
+            (self.generate_with_ai(model, seed), GenerationSource::Ai)
         } else {
-            self.generate_procedural(seed)
+            (self.generate_procedural(seed), GenerationSource::Procedural)
         };
         
-        let generation_time = start_time.elapsed().as_millis() as f32;
-        self.update_stats(generation_time);
+        let generation_time = start_time.elapsed().as_secs_f32() * 1000.0;
+        self.update_stats(generation_time, source);
+        self.store_map(seed, &map);
         
-        // Cache the result
-        self.cache.insert(seed, map.clone());
+        map
+    }
+    
+    /// Generate the map for a shareable text seed such as "dragon-valley"
+    pub fn generate_map_from_str(&mut self, seed_text: &str) -> Vec<Vec<i32>> {
+        let seed = seed_from_str(seed_text);
+        info!("Map seed '{}' -> {}", seed_text, seed);
+        let map = self.generate_map(seed);
+        if let Some(db) = &self.db {
+            if let Err(e) = db.save_map_seed_text(self.slot, seed, seed_text) {
+                warn!("Failed to store seed text for map {}: {}", seed, e);
+            }
+        }
+        map
+    }
+    
+    /// Find a previously generated map in the cache, then in the maps table
+    pub fn lookup_map(&mut self, seed: i64) -> Option<Vec<Vec<i32>>> {
+        if let Some(cached_map) = self.cache.get(&seed) {
+            self.generation_stats.cache_hits += 1;
+            return Some(cached_map.clone());
+        }
+        
+        let serialized = self.db.as_ref()?.load_map(self.slot, seed).ok()?;
+        match parse_map(&serialized) {
+            Ok(grid) => {
+                self.generation_stats.cache_hits += 1;
+                self.cache_map(seed, grid.clone());
+                Some(grid)
+            }
+            Err(e) => {
+                warn!("Stored map for seed {} is corrupt, regenerating: {}", seed, e);
+                None
+            }
+        }
+    }
+    
+    /// Cache a freshly generated map and persist it to the maps table
+    pub fn store_map(&mut self, seed: i64, map: &[Vec<i32>]) {
+        if let Some(db) = &self.db {
+            if let Err(e) = db.save_map(self.slot, seed, &serialize_map(map)) {
+                warn!("Failed to store map for seed {}: {}", seed, e);
+            }
+        }
+        self.cache_map(seed, map.to_vec());
+    }
+    
+    /// Record `seed` as the active map in `GameState`, `MapSeed` and this generator's world slot
+    pub fn activate_seed(&self, seed: i64, game_state: Option<&mut GameState>, map_seed: Option<&mut MapSeed>) {
+        set_active_map_seed(seed, game_state, map_seed, self.db.as_ref().map(|db| (db, self.slot)));
+    }
+    
+    fn cache_map(&mut self, seed: i64, map: Vec<Vec<i32>>) {
+        self.cache.insert(seed, map);
         
         // Limit cache size to prevent memory issues
         if self.cache.len() > 100 {
             let oldest_key = *self.cache.keys().next().unwrap();
             self.cache.remove(&oldest_key);
         }
-        
-        map
     }
     
     /// Generate map using the AI model
@@ -118,7 +288,7 @@ impl MapGenerator {
         let seed_tensor = Tensor::of_slice(&[seed as f32])
             .to_device(self.device)
             .unsqueeze(0)
-            .expand(&[1, 64], true); // Expand to expected input size
+            .expand(&[1, MODEL_INPUT_SIZE], true); // Expand to expected input size
         
         // Run inference
         let output = tch::no_grad(|| {
@@ -131,54 +301,12 @@ impl MapGenerator {
     
     /// Generate map using procedural method
     fn generate_procedural(&self, seed: i64) -> Vec<Vec<i32>> {
-        let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
-        let mut grid = vec![vec![0; 16]; 16];
-        
-        // Enhanced procedural generation with biomes and structures
-        let biome = rng.gen_range(0..4); // 0: Forest, 1: Desert, 2: Mountains, 3: Swamp
-        
-        for x in 0..16 {
-            for y in 0..16 {
-                let distance_from_center = ((x as f32 - 8.0).powi(2) + (y as f32 - 8.0).powi(2)).sqrt();
-                let noise = (x as f32 * 0.3).sin() * (y as f32 * 0.3).cos() * 0.5;
-                
-                let base_tile = match biome {
-                    0 => { // Forest
-                        if rng.gen_bool(0.3) { 1 } else { 0 } // Resources in forest
-                    }
-                    1 => { // Desert
-                        if rng.gen_bool(0.1) { 1 } else if rng.gen_bool(0.15) { 2 } else { 0 }
-                    }
-                    2 => { // Mountains
-                        if distance_from_center > 6.0 && rng.gen_bool(0.4) { 1 } else { 0 }
-                    }
-                    _ => { // Swamp
-                        if rng.gen_bool(0.2) { 2 } else { 0 } // More enemies
-                    }
-                };
-                
-                // Add some structure
-                let tile = if distance_from_center < 2.0 && rng.gen_bool(0.1) {
-                    3 // Quest location near center
-                } else if x == 0 || x == 15 || y == 0 || y == 15 {
-                    if rng.gen_bool(0.05) { 4 } else { base_tile } // Rare portals on edges
-                } else {
-                    base_tile
-                };
-                
-                grid[x][y] = tile;
-            }
-        }
-        
-        // Ensure at least one quest and one resource node
-        if !grid.iter().any(|row| row.contains(&3)) {
-            grid[8][8] = 3; // Quest in center
-        }
-        if !grid.iter().any(|row| row.contains(&1)) {
-            grid[rng.gen_range(1..15)][rng.gen_range(1..15)] = 1; // Random resource
-        }
-        
-        grid
+        generate_procedural(seed)
+    }
+    
+    /// Generate a procedural map with a fixed biome instead of the seed's random one
+    pub fn generate_procedural_biome(&self, seed: i64, biome: Biome) -> Vec<Vec<i32>> {
+        generate_procedural_with_biome(seed, Some(biome))
     }
     
     /// Convert AI tensor output to 16x16 grid
@@ -229,20 +357,385 @@ impl MapGenerator {
             let y = rng.gen_range(1..15);
             grid[x][y] = 1;
         }
+        
+        validate_connectivity(grid);
     }
     
     /// Update generation statistics
-    fn update_stats(&mut self, generation_time_ms: f32) {
-        self.generation_stats.maps_generated += 1;
-        let count = self.generation_stats.maps_generated as f32;
-        self.generation_stats.average_generation_time_ms = 
-            (self.generation_stats.average_generation_time_ms * (count - 1.0) + generation_time_ms) / count;
+    fn update_stats(&mut self, generation_time_ms: f32, source: GenerationSource) {
+        self.generation_stats.record_generation(source, generation_time_ms);
     }
     
     /// Get generation statistics
     pub fn get_stats(&self) -> &GenerationStats {
         &self.generation_stats
     }
+    
+    /// Load lifetime per-source counts saved by earlier sessions
+    pub fn load_persisted_stats(&mut self, db: &DatabaseConnection) -> rusqlite::Result<()> {
+        for (name, count) in db.load_generation_counts()? {
+            match GenerationSource::from_name(&name) {
+                Some(source) => {
+                    self.generation_stats.persisted_counts.insert(source, count);
+                }
+                None => warn!("Ignoring unknown generation source '{}' in database", name),
+            }
+        }
+        Ok(())
+    }
+    
+    /// Save lifetime per-source counts
+    pub fn persist_stats(&self, db: &DatabaseConnection) -> rusqlite::Result<()> {
+        for source in GenerationSource::ALL {
+            db.save_generation_count(source.as_str(), self.generation_stats.lifetime_count(source))?;
+        }
+        Ok(())
+    }
+    
+    /// Load a hand-authored map from a file in the maps table format
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Vec<Vec<i32>>, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read map file {}: {}", path.display(), e))?;
+        parse_map(&contents)
+    }
+}
+
+/// Derive a numeric map seed from text with 64-bit FNV-1a, stable across platforms and releases
+pub fn seed_from_str(seed_text: &str) -> i64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    seed_text.bytes()
+        .fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME)) as i64
+}
+
+/// Procedural 16x16 map generation; pure and `Send`, so it can run on a task pool
+pub fn generate_procedural(seed: i64) -> Vec<Vec<i32>> {
+    generate_procedural_with_biome(seed, None)
+}
+
+/// Procedural generation using `biome`, or a biome picked from the seed when `None`
+pub fn generate_procedural_with_biome(seed: i64, biome: Option<Biome>) -> Vec<Vec<i32>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+    let mut grid = vec![vec![0; 16]; 16];
+    
+    // Enhanced procedural generation with biomes and structures.
+    // The roll is always drawn so a fixed biome leaves the rest of the RNG stream unchanged
+    let rolled = Biome::ALL[rng.gen_range(0..4)];
+    let biome = biome.unwrap_or(rolled);
+    
+    for x in 0..16 {
+        for y in 0..16 {
+            let distance_from_center = ((x as f32 - 8.0).powi(2) + (y as f32 - 8.0).powi(2)).sqrt();
+            let noise = (x as f32 * 0.3).sin() * (y as f32 * 0.3).cos() * 0.5;
+            
+            let base_tile = match biome {
+                Biome::Forest => {
+                    if rng.gen_bool(0.3) { 1 } else { 0 } // Resources in forest
+                }
+                Biome::Desert => {
+                    if rng.gen_bool(0.1) { 1 } else if rng.gen_bool(0.15) { 2 } else { 0 }
+                }
+                Biome::Mountains => {
+                    if distance_from_center > 6.0 && rng.gen_bool(0.4) { 1 } else { 0 }
+                }
+                Biome::Swamp => {
+                    if rng.gen_bool(0.2) { 2 } else { 0 } // More enemies
+                }
+            };
+            
+            // Add some structure
+            let tile = if distance_from_center < 2.0 && rng.gen_bool(0.1) {
+                3 // Quest location near center
+            } else if x == 0 || x == 15 || y == 0 || y == 15 {
+                if rng.gen_bool(0.05) { 4 } else { base_tile } // Rare portals on edges
+            } else {
+                base_tile
+            };
+            
+            grid[x][y] = tile;
+        }
+    }
+    
+    // Ensure at least one quest and one resource node
+    if !grid.iter().any(|row| row.contains(&3)) {
+        grid[8][8] = 3; // Quest in center
+    }
+    if !grid.iter().any(|row| row.contains(&1)) {
+        grid[rng.gen_range(1..15)][rng.gen_range(1..15)] = 1; // Random resource
+    }
+    
+    validate_connectivity(&mut grid);
+    grid
+}
+
+/// Outcome of a connectivity check
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapValidation {
+    /// Tiles reachable from the spawn point
+    pub reachable_tiles: usize,
+    /// Tiles that are not enemies
+    pub walkable_tiles: usize,
+    pub quests_reachable: usize,
+    pub portals_reachable: usize,
+    /// Enemy tiles cleared to connect unreachable quests and portals
+    pub tiles_carved: usize,
+}
+
+/// Whether a tile can be walked through; enemies block movement
+fn is_walkable(tile: i32) -> bool {
+    tile != 2
+}
+
+/// Flood fill over walkable tiles from `start`
+fn reachable_from(grid: &[Vec<i32>], start: (usize, usize)) -> Vec<Vec<bool>> {
+    let mut reached: Vec<Vec<bool>> = grid.iter().map(|row| vec![false; row.len()]).collect();
+    let mut frontier = VecDeque::from([start]);
+    reached[start.0][start.1] = true;
+    
+    while let Some((x, y)) = frontier.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            let Some(&tile) = grid.get(nx).and_then(|row| row.get(ny)) else { continue };
+            if is_walkable(tile) && !reached[nx][ny] {
+                reached[nx][ny] = true;
+                frontier.push_back((nx, ny));
+            }
+        }
+    }
+    reached
+}
+
+/// Make every quest and portal reachable from the spawn point at the map center,
+/// clearing enemy tiles along a path toward the center where needed
+pub fn validate_connectivity(grid: &mut [Vec<i32>]) -> MapValidation {
+    let mut report = MapValidation::default();
+    if grid.is_empty() || grid[0].is_empty() {
+        return report;
+    }
+    
+    let spawn = (grid.len() / 2, grid[0].len() / 2);
+    if !is_walkable(grid[spawn.0][spawn.1]) {
+        grid[spawn.0][spawn.1] = 0;
+        report.tiles_carved += 1;
+    }
+    
+    let mut reached = reachable_from(grid, spawn);
+    let targets: Vec<(usize, usize)> = grid.iter().enumerate()
+        .flat_map(|(x, row)| row.iter().enumerate().filter(|(_, &t)| t == 3 || t == 4).map(move |(y, _)| (x, y)))
+        .collect();
+    
+    for (tx, ty) in targets {
+        if reached[tx][ty] {
+            continue;
+        }
+        // Walk toward the spawn point, clearing enemies until we join the reachable area
+        let (mut x, mut y) = (tx, ty);
+        while !reached[x][y] {
+            if grid[x][y] == 2 {
+                grid[x][y] = 0;
+                report.tiles_carved += 1;
+            }
+            if x != spawn.0 {
+                x = if x < spawn.0 { x + 1 } else { x - 1 };
+            } else if y != spawn.1 {
+                y = if y < spawn.1 { y + 1 } else { y - 1 };
+            } else {
+                break;
+            }
+        }
+        reached = reachable_from(grid, spawn);
+    }
+    
+    for (x, row) in grid.iter().enumerate() {
+        for (y, &tile) in row.iter().enumerate() {
+            if is_walkable(tile) {
+                report.walkable_tiles += 1;
+            }
+            if reached[x][y] {
+                report.reachable_tiles += 1;
+                match tile {
+                    3 => report.quests_reachable += 1,
+                    4 => report.portals_reachable += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Serialize a grid into the CSV-like format stored in the maps table.
+/// Grids are indexed `grid[x][y]` everywhere, so line `x` of the text lists column `x` by `y`
+pub fn serialize_map(grid: &[Vec<i32>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse and validate a serialized map grid into `grid[x][y]`, the inverse of `serialize_map`
+pub fn parse_map(serialized: &str) -> Result<Vec<Vec<i32>>, String> {
+    let mut grid = Vec::new();
+    
+    for (x, line) in serialized.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        
+        let mut column = Vec::new();
+        for (y, cell) in line.split(',').enumerate() {
+            let value: i32 = cell.trim().parse()
+                .map_err(|_| format!("Invalid tile '{}' at ({}, {})", cell.trim(), x, y))?;
+            if !(0..=4).contains(&value) {
+                return Err(format!("Unknown tile type {} at ({}, {})", value, x, y));
+            }
+            column.push(value);
+        }
+        
+        if let Some(first) = grid.first() {
+            if column.len() != first.len() {
+                return Err(format!("Line {} has {} tiles, expected {}", x + 1, column.len(), first.len()));
+            }
+        }
+        grid.push(column);
+    }
+    
+    if grid.is_empty() {
+        return Err("Map is empty".to_string());
+    }
+    
+    Ok(grid)
+}
+
+/// Spawn map tile entities for a `grid[x][y]` grid, returning the spawned entities
+pub fn spawn_map_tiles(commands: &mut Commands, grid: &[Vec<i32>]) -> Vec<Entity> {
+    let mut tiles = Vec::new();
+    for (x, column) in grid.iter().enumerate() {
+        for (y, &tile_value) in column.iter().enumerate() {
+            let tile = commands.spawn(MapTile {
+                tile_type: int_to_tile_type(tile_value),
+                grid_x: x as i32,
+                grid_y: y as i32,
+            }).id();
+            tiles.push(tile);
+        }
+    }
+    tiles
+}
+
+/// Tile entities of the map currently in the world, so they can be despawned without a world scan
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MapRoot {
+    pub tiles: Vec<Entity>,
+}
+
+impl MapRoot {
+    /// Despawn every tracked tile
+    pub fn despawn_all(&mut self, commands: &mut Commands) {
+        for tile in self.tiles.drain(..) {
+            commands.entity(tile).despawn();
+        }
+    }
+}
+
+/// Seed of the active map
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapSeed(pub i64);
+
+/// Seed of the map shown on a fresh save slot
+pub const DEFAULT_MAP_SEED: i64 = 1337;
+
+impl Default for MapSeed {
+    fn default() -> Self {
+        Self(DEFAULT_MAP_SEED)
+    }
+}
+
+/// Make `seed` the active map, recording it for the HUD and the world slot
+pub fn set_active_map_seed(
+    seed: i64,
+    game_state: Option<&mut GameState>,
+    map_seed: Option<&mut MapSeed>,
+    db: Option<(&DatabaseConnection, u32)>,
+) {
+    if let Some(game_state) = game_state {
+        game_state.current_map_seed = seed;
+    }
+    if let Some(map_seed) = map_seed {
+        map_seed.0 = seed;
+    }
+    if let Some((db, slot)) = db {
+        if let Err(e) = db.save_current_map_seed(slot, seed) {
+            warn!("Failed to save current map seed: {}", e);
+        }
+    }
+}
+
+/// Map tiles still waiting to be spawned, drained a few per frame by `spawn_pending_map_tiles`
+#[derive(Resource, Debug, Clone)]
+pub struct PendingMapSpawn {
+    pub grid: Vec<Vec<i32>>,
+    /// Index of the next tile to spawn, in `spawn_map_tiles` order
+    pub next: usize,
+}
+
+impl PendingMapSpawn {
+    pub fn new(grid: Vec<Vec<i32>>) -> Self {
+        Self { grid, next: 0 }
+    }
+    
+    /// Tiles not yet spawned
+    pub fn remaining(&self) -> usize {
+        let total: usize = self.grid.iter().map(|row| row.len()).sum();
+        total - self.next
+    }
+    
+    /// Spawn up to `max` of the remaining tiles, tracking them in `root`; returns how many were spawned
+    pub fn spawn_next(&mut self, commands: &mut Commands, root: &mut MapRoot, max: usize) -> usize {
+        let tiles = self.grid.iter().enumerate()
+            .flat_map(|(x, row)| row.iter().enumerate().map(move |(y, &tile_value)| (x, y, tile_value)))
+            .skip(self.next)
+            .take(max);
+        let mut count = 0;
+        for (x, y, tile_value) in tiles {
+            let tile = commands.spawn(MapTile {
+                tile_type: int_to_tile_type(tile_value),
+                grid_x: x as i32,
+                grid_y: y as i32,
+            }).id();
+            root.tiles.push(tile);
+            count += 1;
+        }
+        self.next += count;
+        count
+    }
+}
+
+/// System to spawn a bounded number of pending map tiles per frame,
+/// replacing the previous map's tiles when a new map starts
+pub fn spawn_pending_map_tiles(
+    mut commands: Commands,
+    pending: Option<ResMut<PendingMapSpawn>>,
+    mut root: ResMut<MapRoot>,
+    config: Option<Res<MapConfig>>,
+) {
+    let Some(mut pending) = pending else { return };
+    if pending.next == 0 {
+        root.despawn_all(&mut commands);
+    }
+    let tiles_per_frame = config.map_or(MapConfig::default().tiles_per_frame, |c| c.tiles_per_frame);
+    pending.spawn_next(&mut commands, &mut root, tiles_per_frame);
+    if pending.remaining() == 0 {
+        info!("Finished spawning {} map tiles", pending.next);
+        commands.remove_resource::<PendingMapSpawn>();
+    }
 }
 
 /// Convert internal tile representation to TileType
@@ -257,45 +750,246 @@ pub fn int_to_tile_type(tile_int: i32) -> TileType {
     }
 }
 
+/// Integer encoding of a tile type, the inverse of `int_to_tile_type`
+pub fn tile_type_to_int(tile_type: &TileType) -> i32 {
+    match tile_type {
+        TileType::Empty => 0,
+        TileType::Resource => 1,
+        TileType::Enemy => 2,
+        TileType::Quest => 3,
+        TileType::Portal => 4,
+    }
+}
+
 /// System to initialize AI map generation
-pub fn setup_ai_map_generator(mut commands: Commands) {
-    let mut generator = MapGenerator::default();
+pub fn setup_ai_map_generator(mut commands: Commands, db: Res<DatabaseConnection>, game_state: Option<Res<GameState>>) {
+    let slot = game_state.map_or(crate::resources::DEFAULT_WORLD_SLOT, |state| state.world_slot);
+    let mut generator = MapGenerator::default().with_database(db.clone(), slot);
     
     if let Err(e) = generator.initialize_model() {
         warn!("Failed to initialize AI model: {}", e);
     }
+    if let Err(e) = generator.load_persisted_stats(&db) {
+        warn!("Failed to load map generation stats: {}", e);
+    }
     
     commands.insert_resource(generator);
     info!("AI Map Generator initialized");
 }
 
-/// System to handle map generation requests
+/// In-flight procedural map generation running on the async compute pool
+#[derive(Resource)]
+pub struct MapGenerationTask {
+    pub seed: i64,
+    /// Fixed biome, if one was requested; such maps bypass the cache
+    pub biome: Option<Biome>,
+    pub started: std::time::Instant,
+    pub task: Task<Vec<Vec<i32>>>,
+    /// Portal trip waiting on this map, if any
+    pub portal: Option<PortalArrival>,
+}
+
+/// A player travelling through a portal to the map at `depth`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortalArrival {
+    pub player: Entity,
+    pub depth: u32,
+}
+
+impl MapGenerationTask {
+    /// Start generating a map for `seed` off the main thread
+    pub fn spawn(seed: i64) -> Self {
+        Self::spawn_with_biome(seed, None)
+    }
+    
+    /// Start generating a map for `seed`, optionally with a fixed biome
+    pub fn spawn_with_biome(seed: i64, biome: Option<Biome>) -> Self {
+        let task = AsyncComputeTaskPool::get()
+            .spawn(async move { generate_procedural_with_biome(seed, biome) });
+        Self { seed, biome, started: std::time::Instant::now(), task, portal: None }
+    }
+    
+    /// Start generating the map behind a portal, completing the trip once it is ready
+    pub fn spawn_for_portal(seed: i64, arrival: PortalArrival) -> Self {
+        Self { portal: Some(arrival), ..Self::spawn(seed) }
+    }
+    
+    /// Abandon the generation; dropping a Bevy task cancels it, so its map is never delivered
+    pub fn cancel(self) {
+        info!("Cancelled map generation for seed {}", self.seed);
+        drop(self.task);
+    }
+}
+
+/// System to handle map generation requests from the map key (M by default) and peer-queued seeds
 pub fn handle_map_generation(
     mut map_generator: ResMut<MapGenerator>,
     mut commands: Commands,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    pending: Option<Res<MapGenerationTask>>,
+    mut game_state: Option<ResMut<GameState>>,
+    mut map_seed: Option<ResMut<MapSeed>>,
+    mut rng: Option<ResMut<GameRng>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::KeyM) {
-        let seed = rand::random::<i64>();
-        let map_data = map_generator.generate_map(seed);
-        
-        info!("Generated new map with seed: {}", seed);
-        info!("Map generation stats: {:?}", map_generator.get_stats());
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    let requested = if bindings.just_pressed(&keyboard_input, Action::GenerateMap) {
+        if pending.is_some() {
+            info!("Map generation already in progress");
+            return;
+        }
+        let seed = match rng.as_deref_mut() {
+            Some(rng) => rng.gen::<i64>(),
+            None => rand::random::<i64>(),
+        };
+        Some((seed, None))
+    } else if pending.is_none() {
+        map_generator.queued_seeds.pop_front()
+    } else {
+        None
+    };
+    
+    if let Some((seed, biome)) = requested {
+        // A new map supersedes any tiles still being spawned for the previous one
+        commands.remove_resource::<PendingMapSpawn>();
         
-        // Spawn map tiles as entities
-        for (x, row) in map_data.iter().enumerate() {
-            for (y, &tile_value) in row.iter().enumerate() {
-                let tile_type = int_to_tile_type(tile_value);
-                let tile = MapTile {
-                    tile_type,
-                    grid_x: x as i32,
-                    grid_y: y as i32,
-                };
-                
-                commands.spawn(tile);
+        // Stored and AI-generated maps are produced synchronously; procedural generation runs async
+        if let Some(biome) = biome {
+            info!("Generating new {:?} map with seed {} in the background", biome, seed);
+            commands.insert_resource(MapGenerationTask::spawn_with_biome(seed, Some(biome)));
+        } else if let Some(map_data) = map_generator.lookup_map(seed) {
+            info!("Loaded stored map with seed: {}", seed);
+            map_generator.activate_seed(seed, game_state.as_deref_mut(), map_seed.as_deref_mut());
+            commands.insert_resource(PendingMapSpawn::new(map_data));
+        } else if map_generator.model.is_some() {
+            let map_data = map_generator.generate_map(seed);
+            info!("Generated new map with seed: {}", seed);
+            info!("Map generation times: {}", map_generator.get_stats().timing_summary());
+            map_generator.activate_seed(seed, game_state.as_deref_mut(), map_seed.as_deref_mut());
+            commands.insert_resource(PendingMapSpawn::new(map_data));
+        } else {
+            info!("Generating new map with seed {} in the background", seed);
+            commands.insert_resource(MapGenerationTask::spawn(seed));
+        }
+    }
+}
+
+/// System to queue tiles for spawning once the background map generation task finishes
+pub fn poll_map_generation(
+    mut map_generator: ResMut<MapGenerator>,
+    mut commands: Commands,
+    pending: Option<ResMut<MapGenerationTask>>,
+    mut game_state: Option<ResMut<GameState>>,
+    mut map_seed: Option<ResMut<MapSeed>>,
+    mut players: Query<&mut Position, With<Player>>,
+) {
+    let Some(mut pending) = pending else { return };
+    let Some(map_data) = future::block_on(future::poll_once(&mut pending.task)) else { return };
+    
+    map_generator.update_stats(pending.started.elapsed().as_secs_f32() * 1000.0, GenerationSource::Procedural);
+    if pending.biome.is_none() {
+        map_generator.store_map(pending.seed, &map_data);
+    }
+    info!("Generated new map with seed: {}", pending.seed);
+    info!("Map generation times: {}", map_generator.get_stats().timing_summary());
+    map_generator.activate_seed(pending.seed, game_state.as_deref_mut(), map_seed.as_deref_mut());
+    if let Some(arrival) = pending.portal {
+        arrive_through_portal(arrival, &map_data, &map_generator, game_state.as_deref_mut(), &mut players);
+    }
+    
+    commands.insert_resource(PendingMapSpawn::new(map_data));
+    commands.remove_resource::<MapGenerationTask>();
+}
+
+/// Seed of the map behind a portal, derived from the current seed and the depth being entered
+pub fn portal_seed(seed: i64, depth: u32) -> i64 {
+    seed_from_str(&format!("{}/portal/{}", seed, depth))
+}
+
+/// Where the player arrives on a new map: the walkable cell closest to the center
+pub fn entry_point(grid: &[Vec<i32>]) -> (i32, i32) {
+    let center = (MAP_SIZE / 2) as i32;
+    grid.iter().enumerate()
+        .flat_map(|(x, row)| row.iter().enumerate().map(move |(y, &tile)| (x as i32, y as i32, tile)))
+        .filter(|&(_, _, tile)| !matches!(int_to_tile_type(tile), TileType::Enemy))
+        .min_by_key(|&(x, y, _)| (x - center).abs() + (y - center).abs())
+        .map_or((center, center), |(x, y, _)| (x, y))
+}
+
+/// Stepping onto a portal replaces the map with one a level deeper; the player moves to its
+/// entry point once the map is ready
+pub fn enter_portal(
+    mut commands: Commands,
+    mut entered: EventReader<TileEntered>,
+    mut map_generator: ResMut<MapGenerator>,
+    in_flight: Option<Res<MapGenerationTask>>,
+    mut game_state: Option<ResMut<GameState>>,
+    mut map_seed: Option<ResMut<MapSeed>>,
+    mut players: Query<&mut Position, With<Player>>,
+) {
+    // Any later steps this frame happened on the map being left
+    let Some(portal) = entered.read().find(|e| matches!(e.tile_type, TileType::Portal)).cloned() else { return };
+    entered.clear();
+    
+    let current_seed = map_seed.as_deref().map(|s| s.0)
+        .or(game_state.as_deref().map(|gs| gs.current_map_seed))
+        .unwrap_or_default();
+    let arrival = PortalArrival {
+        player: portal.player,
+        depth: game_state.as_deref().map_or(0, |gs| gs.map_depth) + 1,
+    };
+    let seed = portal_seed(current_seed, arrival.depth);
+    info!("Portal at ({}, {}) leads to depth {} (map seed {})", portal.grid_x, portal.grid_y, arrival.depth, seed);
+    
+    // The portal supersedes any map still being generated or spawned
+    if in_flight.is_some() {
+        commands.add(|world: &mut World| {
+            if let Some(task) = world.remove_resource::<MapGenerationTask>() {
+                task.cancel();
             }
+        });
+    }
+    commands.remove_resource::<PendingMapSpawn>();
+    
+    // As in `handle_map_generation`: stored and AI-generated maps are ready now, procedural ones run async
+    let map_data = map_generator.lookup_map(seed)
+        .or_else(|| map_generator.model.is_some().then(|| map_generator.generate_map(seed)));
+    match map_data {
+        Some(map_data) => {
+            map_generator.activate_seed(seed, game_state.as_deref_mut(), map_seed.as_deref_mut());
+            arrive_through_portal(arrival, &map_data, &map_generator, game_state.as_deref_mut(), &mut players);
+            commands.insert_resource(PendingMapSpawn::new(map_data));
         }
-        
-        info!("Spawned {} map tiles", map_data.len() * map_data[0].len());
+        None => commands.insert_resource(MapGenerationTask::spawn_for_portal(seed, arrival)),
+    }
+}
+
+/// Finish a portal trip once its map is ready: record the new depth and move the player to the entry point
+fn arrive_through_portal(
+    arrival: PortalArrival,
+    grid: &[Vec<i32>],
+    map_generator: &MapGenerator,
+    game_state: Option<&mut GameState>,
+    players: &mut Query<&mut Position, With<Player>>,
+) {
+    if let Some(game_state) = game_state {
+        game_state.map_depth = arrival.depth;
+    }
+    if let Some(db) = &map_generator.db {
+        if let Err(e) = db.save_map_depth(map_generator.slot, arrival.depth) {
+            warn!("Failed to save map depth: {}", e);
+        }
+    }
+    if let Ok(mut position) = players.get_mut(arrival.player) {
+        let (x, y) = entry_point(grid);
+        position.x = x as f32;
+        position.y = y as f32;
     }
-}
\ No newline at end of file
+}
+
+/// System to periodically save lifetime map generation stats
+pub fn persist_generation_stats(map_generator: Res<MapGenerator>, db: Res<DatabaseConnection>) {
+    if let Err(e) = map_generator.persist_stats(&db) {
+        error!("Failed to persist map generation stats: {}", e);
+    }
+}
@@ -1,16 +1,61 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use bevy::input::InputPlugin;
 use bevy::prelude::*;
+use std::time::Duration;
 use crate::game_plugin::GamePlugin;
+use crate::ui::font::asset_dir_from_env;
+
+/// Simulation updates per second without a window to pace the loop
+pub const HEADLESS_TICK_RATE: f64 = 60.0;
+
+/// Whether the game renders to a window; headless runs skip camera, HUD and other rendering systems
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunMode {
+    #[default]
+    Windowed,
+    Headless,
+}
+
+impl RunMode {
+    /// Read `CQ_HEADLESS`, defaulting to a windowed run
+    pub fn from_env() -> Self {
+        match std::env::var("CQ_HEADLESS") {
+            Ok(v) if v != "0" && !v.is_empty() => RunMode::Headless,
+            _ => RunMode::Windowed,
+        }
+    }
+}
 
 pub fn run_game() {
     App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "ChainQuest Idle - MVP".into(),
-                resolution: (1024.0, 768.0).into(),
+        .add_plugins(DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "ChainQuest Idle - MVP".into(),
+                    resolution: (1024.0, 768.0).into(),
+                    ..default()
+                }),
                 ..default()
-            }),
-            ..default()
-        }))
+            })
+            .set(AssetPlugin { file_path: asset_dir_from_env(), ..default() }))
+        .add_plugins(FrameTimeDiagnosticsPlugin)
         .add_plugins(GamePlugin)
         .run();
 }
+
+/// Run the simulation without a window, e.g. for dedicated hosting or CI
+pub fn run_game_headless() {
+    headless_app().run();
+}
+
+/// App with the game logic but no window or rendering, updated `HEADLESS_TICK_RATE` times per second
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / HEADLESS_TICK_RATE))))
+        // Key bindings are still read; without a window no key is ever pressed
+        .add_plugins(InputPlugin)
+        .insert_resource(RunMode::Headless)
+        .add_plugins(GamePlugin);
+    app
+}
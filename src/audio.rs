@@ -0,0 +1,97 @@
+//! Sound effects for collection, level-ups and quest completion
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use crate::resources::{LevelUpEvent, QuestCompleted, ResourcesCredited};
+use crate::ui::font::resolve_asset_path;
+
+pub const COLLECT_SOUND: &str = "sounds/collect.ogg";
+pub const LEVEL_UP_SOUND: &str = "sounds/levelup.ogg";
+pub const QUEST_COMPLETE_SOUND: &str = "sounds/questcomplete.ogg";
+
+/// Audio settings
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct AudioConfig {
+    /// Volume applied to every sound effect, from 0 (muted) to 1
+    pub master_volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { master_volume: 1.0 }
+    }
+}
+
+impl AudioConfig {
+    /// Read `CQ_MASTER_VOLUME` over the default, clamping it to 0..=1
+    pub fn from_env() -> Self {
+        let Ok(value) = std::env::var("CQ_MASTER_VOLUME") else { return Self::default() };
+        match value.parse::<f32>() {
+            Ok(volume) if volume.is_finite() => Self { master_volume: volume.clamp(0.0, 1.0) },
+            _ => {
+                warn!("Invalid CQ_MASTER_VOLUME '{}', using default", value);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Loaded sound effects; a missing file leaves its slot empty and that effect silent
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SoundEffects {
+    pub collect: Option<Handle<AudioSource>>,
+    pub level_up: Option<Handle<AudioSource>>,
+    pub quest_complete: Option<Handle<AudioSource>>,
+}
+
+/// Load the sound effects found in the asset folder; none are shipped, so missing ones stay silent
+pub fn setup_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut missing = Vec::new();
+    let mut load = |path: &str| {
+        if resolve_asset_path(path).is_file() {
+            Some(asset_server.load(path.to_string()))
+        } else {
+            missing.push(path.to_string());
+            None
+        }
+    };
+    let effects = SoundEffects {
+        collect: load(COLLECT_SOUND),
+        level_up: load(LEVEL_UP_SOUND),
+        quest_complete: load(QUEST_COMPLETE_SOUND),
+    };
+    if !missing.is_empty() {
+        info!("No sound effects at {}, playing without them", missing.join(", "));
+    }
+    commands.insert_resource(effects);
+}
+
+/// Play each effect at most once per frame for the events it reacts to
+pub fn play_sound_effects(
+    mut commands: Commands,
+    effects: Option<Res<SoundEffects>>,
+    config: Option<Res<AudioConfig>>,
+    mut collections: EventReader<ResourcesCredited>,
+    mut level_ups: EventReader<LevelUpEvent>,
+    mut quests: EventReader<QuestCompleted>,
+) {
+    let triggered = [
+        collections.read().last().is_some(),
+        level_ups.read().last().is_some(),
+        quests.read().last().is_some(),
+    ];
+    let Some(effects) = effects else { return };
+    let volume = config.map_or(AudioConfig::default().master_volume, |c| c.master_volume);
+    if volume <= 0.0 {
+        return;
+    }
+    let sounds = [&effects.collect, &effects.level_up, &effects.quest_complete];
+    for (sound, triggered) in sounds.into_iter().zip(triggered) {
+        if let Some(source) = sound.as_ref().filter(|_| triggered) {
+            commands.spawn(AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+            });
+        }
+    }
+}
@@ -0,0 +1,607 @@
+//! Off-chain client for the IdleSFT contract
+
+use bevy::prelude::*;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::ai::seed_from_str;
+use crate::blockchain::{MAX_SFT_POWER, RARITY_VARIANTS};
+use crate::components::{Rarity, SFTAttributes};
+use crate::resources::{BlockchainState, DatabaseConnection, GameState, TxStatus};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Blockchain client configuration resource
+#[derive(Resource, Debug, Clone)]
+pub struct BlockchainClient {
+    pub gateway_url: String,
+    pub contract_address: String,
+    /// Simulate transactions on the stub ledger, prefixing their hashes with `dryrun-`.
+    /// Signing is not implemented, so nothing can be submitted while this is off
+    pub dry_run: bool,
+    /// Stand-in for the contract's views while `mintReward` is a stub
+    pub ledger: Arc<Mutex<StubLedger>>,
+}
+
+impl Default for BlockchainClient {
+    fn default() -> Self {
+        Self {
+            gateway_url: "https://testnet-gateway.multiversx.com".into(),
+            contract_address: String::new(),
+            dry_run: false,
+            ledger: Arc::default(),
+        }
+    }
+}
+
+/// Transactions submitted through this client, settled immediately unless marked failed
+#[derive(Debug, Default)]
+pub struct StubLedger {
+    settled: HashSet<String>,
+    failed: HashSet<String>,
+    nonce: u64,
+}
+
+impl StubLedger {
+    /// Next account nonce, so repeated identical calls get distinct transactions
+    fn next_nonce(&mut self) -> u64 {
+        self.nonce += 1;
+        self.nonce
+    }
+    
+    /// Settle a submitted transaction as failed, as the chain would on a rejected call
+    #[cfg(test)]
+    fn fail(&mut self, tx_hash: &str) {
+        self.settled.remove(tx_hash);
+        self.failed.insert(tx_hash.to_string());
+    }
+}
+
+impl BlockchainClient {
+    /// Read `CQ_MX_GATEWAY`, `CQ_MX_CONTRACT` and `CQ_MX_DRY_RUN` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            gateway_url: std::env::var("CQ_MX_GATEWAY").unwrap_or(defaults.gateway_url),
+            contract_address: std::env::var("CQ_MX_CONTRACT").unwrap_or(defaults.contract_address),
+            dry_run: std::env::var("CQ_MX_DRY_RUN").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false),
+            ledger: defaults.ledger,
+        }
+    }
+    
+    /// Validate a wallet address, check the gateway is reachable and mark the wallet connected.
+    /// Blocking: the reachability check opens a TCP connection with a short timeout
+    pub fn connect_wallet(
+        &self,
+        address: &str,
+        state: &mut BlockchainState,
+        game_state: &mut GameState,
+    ) -> Result<(), String> {
+        let result = validate_wallet_address(address).and_then(|address| {
+            self.check_gateway()?;
+            Ok(address)
+        });
+        match result {
+            Ok(address) => {
+                info!("Wallet {} connected via {}", address, self.gateway_url);
+                state.wallet_address = address;
+                state.testnet_connected = true;
+                state.connection_error = None;
+                game_state.blockchain_connected = true;
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Wallet connection failed: {}", e);
+                state.testnet_connected = false;
+                state.connection_error = Some(e.clone());
+                game_state.blockchain_connected = false;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Forget the connected wallet
+    pub fn disconnect_wallet(&self, state: &mut BlockchainState, game_state: &mut GameState) {
+        if !state.wallet_address.is_empty() {
+            info!("Wallet {} disconnected", state.wallet_address);
+        }
+        state.wallet_address.clear();
+        state.testnet_connected = false;
+        state.connection_error = None;
+        state.sft_balance = 0;
+        game_state.blockchain_connected = false;
+    }
+    
+    /// Check the gateway accepts TCP connections; always succeeds in dry-run mode
+    fn check_gateway(&self) -> Result<(), String> {
+        if self.dry_run {
+            return Ok(());
+        }
+        let (default_port, rest) = match self.gateway_url.split_once("://") {
+            Some(("http", rest)) => (80, rest),
+            Some((_, rest)) => (443, rest),
+            None => (443, self.gateway_url.as_str()),
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        let target = if authority.contains(':') { authority.to_string() } else { format!("{}:{}", authority, default_port) };
+        let addr = target.to_socket_addrs()
+            .map_err(|e| format!("Cannot resolve gateway {}: {}", self.gateway_url, e))?
+            .next()
+            .ok_or_else(|| format!("Gateway {} has no address", self.gateway_url))?;
+        TcpStream::connect_timeout(&addr, Duration::from_secs(3))
+            .map(|_| ())
+            .map_err(|e| format!("Gateway {} unreachable: {}", self.gateway_url, e))
+    }
+    
+    /// Build a call to a contract endpoint with one raw argument
+    fn build_call_tx(&self, endpoint: &str, argument: &[u8]) -> MintTransaction {
+        let hex: String = argument.iter().map(|b| format!("{:02x}", b)).collect();
+        MintTransaction {
+            receiver: self.contract_address.clone(),
+            data: format!("{}@{}", endpoint, hex),
+            nonce: self.ledger.lock().next_nonce(),
+        }
+    }
+    
+    /// Build the `mintReward` transaction for a set of attributes
+    pub fn build_mint_tx(&self, attributes: &SFTAttributes) -> Result<MintTransaction, String> {
+        let payload = encode_sft_attributes(attributes)?;
+        Ok(self.build_call_tx("mintReward", &payload))
+    }
+    
+    /// Hash a transaction will be tracked under once submitted
+    pub fn tx_hash(&self, tx: &MintTransaction) -> String {
+        if self.dry_run {
+            format!("dryrun-{}", tx.synthetic_hash())
+        } else {
+            tx.synthetic_hash()
+        }
+    }
+    
+    /// Submit a transaction and queue it in `pending_transactions`.
+    /// Only dry-run mode can submit: the transaction is logged and settled on the stub ledger.
+    /// Outside dry-run this fails, as there is no signer to send a real transaction with
+    pub fn submit_tx(&self, tx: &MintTransaction, state: &mut BlockchainState) -> Result<String, String> {
+        if !self.dry_run {
+            return Err(format!(
+                "Cannot send {} to {}: transaction signing is not available (set CQ_MX_DRY_RUN=1 to simulate)",
+                tx.data.split('@').next().unwrap_or_default(), tx.receiver
+            ));
+        }
+        let tx_hash = self.tx_hash(tx);
+        info!("[dry-run] Would send to {} via {}: {}", tx.receiver, self.gateway_url, tx.data);
+        state.pending_transactions.push((tx_hash.clone(), TxStatus::Pending));
+        self.ledger.lock().settled.insert(tx_hash.clone());
+        Ok(tx_hash)
+    }
+    
+    /// Submit a mint and queue it in `pending_transactions`, returning the tx hash
+    pub fn submit_mint(&self, attributes: &SFTAttributes, state: &mut BlockchainState) -> Result<String, String> {
+        let tx = self.build_mint_tx(attributes)?;
+        self.submit_tx(&tx, state)
+    }
+    
+    /// Submit a `stakeSft` (or `unstakeSft`) call for a token, returning the tx hash
+    pub fn submit_stake(&self, token_id: &str, staked: bool, state: &mut BlockchainState) -> Result<String, String> {
+        let endpoint = if staked { "stakeSft" } else { "unstakeSft" };
+        let tx = self.build_call_tx(endpoint, token_id.as_bytes());
+        self.submit_tx(&tx, state)
+    }
+    
+    /// Number of reward SFTs owned by `wallet_address`.
+    /// Counted from the `sft_assets` table until the contract exposes a balance view
+    pub fn fetch_sft_balance(&self, wallet_address: &str, db: &DatabaseConnection) -> Result<u32, String> {
+        if wallet_address.is_empty() {
+            return Err("No wallet address configured".to_string());
+        }
+        db.count_owned_sfts(wallet_address)
+            .map(|count| count as u32)
+            .map_err(|e| format!("Failed to count SFTs: {}", e))
+    }
+    
+    /// Resume tracking a transaction submitted in an earlier session
+    pub fn resume_tx(&self, tx_hash: &str, state: &mut BlockchainState) {
+        if !state.pending_transactions.iter().any(|(hash, _)| hash == tx_hash) {
+            state.pending_transactions.push((tx_hash.to_string(), TxStatus::Pending));
+        }
+        // The stub ledger settled it on submission, but its memory didn't survive the restart
+        self.ledger.lock().settled.insert(tx_hash.to_string());
+    }
+    
+    /// Current status of a submitted transaction
+    pub fn tx_status(&self, tx_hash: &str) -> Result<TxStatus, String> {
+        let ledger = self.ledger.lock();
+        if ledger.failed.contains(tx_hash) {
+            Ok(TxStatus::Failed)
+        } else if ledger.settled.contains(tx_hash) {
+            Ok(TxStatus::Confirmed)
+        } else {
+            Ok(TxStatus::Pending)
+        }
+    }
+}
+
+/// Seconds a confirmed or failed transaction stays listed before removal
+pub const TX_GRACE_PERIOD_SECS: f64 = 30.0;
+
+/// System to poll pending transactions, settle the SFT changes waiting on them
+/// and drop resolved ones after the grace period
+pub fn poll_pending_transactions(
+    client: Res<BlockchainClient>,
+    db: Res<DatabaseConnection>,
+    mut state: ResMut<BlockchainState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let BlockchainState { pending_transactions, tx_resolved_at, .. } = &mut *state;
+    
+    for (tx_hash, status) in pending_transactions.iter_mut().filter(|(_, s)| *s == TxStatus::Pending) {
+        match client.tx_status(tx_hash) {
+            Ok(TxStatus::Pending) => {}
+            Ok(new_status) => {
+                info!("Transaction {} {:?} -> {:?}", tx_hash, status, new_status);
+                *status = new_status;
+                tx_resolved_at.insert(tx_hash.clone(), now);
+                if let Err(e) = db.resolve_sft_tx(tx_hash, new_status == TxStatus::Confirmed) {
+                    warn!("Failed to settle SFTs of transaction {}: {}", tx_hash, e);
+                }
+            }
+            Err(e) => warn!("Failed to poll transaction {}: {}", tx_hash, e),
+        }
+    }
+    
+    pending_transactions.retain(|(tx_hash, status)| {
+        *status == TxStatus::Pending
+            || tx_resolved_at.get(tx_hash).map_or(true, |resolved| now - resolved < TX_GRACE_PERIOD_SECS)
+    });
+    tx_resolved_at.retain(|tx_hash, _| pending_transactions.iter().any(|(hash, _)| hash == tx_hash));
+}
+
+/// Startup system: re-queue the transactions SFTs in the database are still waiting on,
+/// so `poll_pending_transactions` settles them
+pub fn restore_pending_transactions(
+    client: Res<BlockchainClient>,
+    db: Res<DatabaseConnection>,
+    mut state: ResMut<BlockchainState>,
+) {
+    match db.pending_sft_txs() {
+        Ok(hashes) => {
+            for tx_hash in &hashes {
+                client.resume_tx(tx_hash, &mut state);
+            }
+            if !hashes.is_empty() {
+                info!("Resumed {} pending SFT transactions", hashes.len());
+            }
+        }
+        Err(e) => warn!("Failed to restore pending SFT transactions: {}", e),
+    }
+}
+
+/// System to refresh the SFT balance of the connected wallet
+pub fn refresh_sft_balance(
+    client: Res<BlockchainClient>,
+    db: Res<DatabaseConnection>,
+    mut state: ResMut<BlockchainState>,
+) {
+    if state.wallet_address.is_empty() {
+        return;
+    }
+    match client.fetch_sft_balance(&state.wallet_address, &db) {
+        Ok(balance) => state.sft_balance = balance,
+        Err(e) => warn!("Failed to fetch SFT balance: {}", e),
+    }
+}
+
+/// Total `power` of the staked SFTs, which boosts the idle resource rate
+#[derive(Resource, Debug, Clone, Default)]
+pub struct StakingBonus {
+    pub staked_power: u32,
+}
+
+/// Sum the power of every staked SFT recorded in the database
+pub fn staked_power(db: &DatabaseConnection) -> Result<u32, String> {
+    let rows = db.list_sfts().map_err(|e| format!("Failed to list SFTs: {}", e))?;
+    let mut total = 0u32;
+    for row in rows.iter().filter(|row| row.staked) {
+        match serde_json::from_str::<SFTAttributes>(&row.attributes) {
+            Ok(attributes) => total = total.saturating_add(attributes.power),
+            Err(e) => warn!("Ignoring SFT {} with unreadable attributes: {}", row.token_id, e),
+        }
+    }
+    Ok(total)
+}
+
+/// Stake an owned SFT: submit the contract call and mark it staked locally
+pub fn stake_sft(
+    client: &BlockchainClient,
+    token_id: &str,
+    db: &DatabaseConnection,
+    state: &mut BlockchainState,
+) -> Result<String, String> {
+    set_sft_staked(client, token_id, true, db, state)
+}
+
+/// Unstake an SFT: submit the contract call and clear the local flag
+pub fn unstake_sft(
+    client: &BlockchainClient,
+    token_id: &str,
+    db: &DatabaseConnection,
+    state: &mut BlockchainState,
+) -> Result<String, String> {
+    set_sft_staked(client, token_id, false, db, state)
+}
+
+fn set_sft_staked(
+    client: &BlockchainClient,
+    token_id: &str,
+    staked: bool,
+    db: &DatabaseConnection,
+    state: &mut BlockchainState,
+) -> Result<String, String> {
+    let row = db.list_sfts()
+        .map_err(|e| format!("Failed to list SFTs: {}", e))?
+        .into_iter()
+        .find(|row| row.token_id == token_id)
+        .ok_or_else(|| format!("Unknown SFT {}", token_id))?;
+    if row.mint_tx.is_some() {
+        return Err(format!("SFT {} is still being minted", token_id));
+    }
+    if row.staked == staked {
+        return Err(format!("SFT {} is already {}", token_id, if staked { "staked" } else { "unstaked" }));
+    }
+    
+    let tx_hash = client.submit_stake(token_id, staked, state)?;
+    db.set_staked_pending(token_id, staked, &tx_hash)
+        .map_err(|e| format!("Failed to update SFT {}: {}", token_id, e))?;
+    info!("SFT {} {} in tx {}", token_id, if staked { "staked" } else { "unstaked" }, tx_hash);
+    Ok(tx_hash)
+}
+
+/// System to reload the staking bonus from the database, the source of truth for staked SFTs
+pub fn refresh_staking_bonus(db: Res<DatabaseConnection>, mut bonus: ResMut<StakingBonus>) {
+    match staked_power(&db) {
+        Ok(power) => bonus.staked_power = power,
+        Err(e) => warn!("Failed to refresh staking bonus: {}", e),
+    }
+}
+
+/// A contract call ready to be signed and sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct MintTransaction {
+    pub receiver: String,
+    pub data: String,
+    pub nonce: u64,
+}
+
+impl MintTransaction {
+    /// Deterministic stand-in for a transaction hash, stable across platforms and releases
+    pub fn synthetic_hash(&self) -> String {
+        let hash = seed_from_str(&format!("{}\n{}\n{}", self.receiver, self.data, self.nonce));
+        format!("{:016x}", hash as u64)
+    }
+}
+
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Check a MultiversX wallet address (`erd1...`, bech32 over a 32-byte public key),
+/// returning it in canonical lowercase
+pub fn validate_wallet_address(address: &str) -> Result<String, String> {
+    let address = address.trim();
+    if address.chars().any(|c| c.is_ascii_uppercase()) && address.chars().any(|c| c.is_ascii_lowercase()) {
+        return Err("Wallet address mixes upper and lower case".to_string());
+    }
+    let address = address.to_ascii_lowercase();
+    let (hrp, data) = address.rsplit_once('1')
+        .ok_or_else(|| "Wallet address is not bech32".to_string())?;
+    if hrp != "erd" {
+        return Err(format!("Wallet address must start with erd1, got {}1", hrp));
+    }
+    // 32 bytes in 5-bit groups (52 characters) plus a 6-character checksum
+    if data.len() != 58 {
+        return Err(format!("Wallet address has {} data characters, expected 58", data.len()));
+    }
+    
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    for c in data.chars() {
+        let value = BECH32_CHARSET.find(c)
+            .ok_or_else(|| format!("Invalid character '{}' in wallet address", c))?;
+        values.push(value as u8);
+    }
+    if bech32_polymod(&values) != 1 {
+        return Err("Wallet address checksum mismatch".to_string());
+    }
+    Ok(address)
+}
+
+/// Startup system: connect the wallet named by `CQ_WALLET`, if set
+pub fn connect_wallet_from_env(
+    client: Res<BlockchainClient>,
+    mut state: ResMut<BlockchainState>,
+    mut game_state: ResMut<GameState>,
+) {
+    let Ok(address) = std::env::var("CQ_WALLET") else { return };
+    // Failures are recorded in `BlockchainState::connection_error` for the HUD
+    let _ = client.connect_wallet(&address, &mut state, &mut game_state);
+}
+
+/// Index of a rarity in the contract's encoding
+pub fn rarity_code(rarity: &Rarity) -> u8 {
+    match rarity {
+        Rarity::Common => 0,
+        Rarity::Uncommon => 1,
+        Rarity::Rare => 2,
+        Rarity::Epic => 3,
+        Rarity::Legendary => 4,
+    }
+}
+
+/// Rarity for an index produced by `rarity_code`
+pub fn rarity_from_code(code: u8) -> Option<Rarity> {
+    match code {
+        0 => Some(Rarity::Common),
+        1 => Some(Rarity::Uncommon),
+        2 => Some(Rarity::Rare),
+        3 => Some(Rarity::Epic),
+        4 => Some(Rarity::Legendary),
+        _ => None,
+    }
+}
+
+/// Serialize SFT attributes into the raw bytes passed as the `mintReward` ManagedBuffer.
+/// Matches the top-level encoding of the contract's `OnChainSftAttributes`: big-endian
+/// fixed-width fields followed by the length-prefixed metadata
+pub fn encode_sft_attributes(attributes: &SFTAttributes) -> Result<Vec<u8>, String> {
+    if attributes.power > MAX_SFT_POWER {
+        return Err(format!("SFT power {} exceeds the maximum of {}", attributes.power, MAX_SFT_POWER));
+    }
+    let metadata = attributes.metadata.as_bytes();
+    let mut bytes = Vec::with_capacity(21 + metadata.len());
+    bytes.extend_from_slice(&attributes.quest_id.to_be_bytes());
+    bytes.extend_from_slice(&attributes.map_seed.to_be_bytes());
+    bytes.push(rarity_code(&attributes.rarity));
+    bytes.extend_from_slice(&attributes.power.to_be_bytes());
+    bytes.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(metadata);
+    Ok(bytes)
+}
+
+/// Decode bytes produced by `encode_sft_attributes`, applying the contract's checks
+pub fn decode_sft_attributes(bytes: &[u8]) -> Result<SFTAttributes, String> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+        if bytes.len() < len {
+            return Err("Truncated SFT attributes".to_string());
+        }
+        let (head, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(head)
+    }
+    
+    let mut input = bytes;
+    let quest_id = u32::from_be_bytes(take(&mut input, 4)?.try_into().unwrap());
+    let map_seed = i64::from_be_bytes(take(&mut input, 8)?.try_into().unwrap());
+    let rarity_byte = take(&mut input, 1)?[0];
+    let power = u32::from_be_bytes(take(&mut input, 4)?.try_into().unwrap());
+    let metadata_len = u32::from_be_bytes(take(&mut input, 4)?.try_into().unwrap()) as usize;
+    let metadata = String::from_utf8(take(&mut input, metadata_len)?.to_vec())
+        .map_err(|_| "SFT metadata is not valid UTF-8".to_string())?;
+    if !input.is_empty() {
+        return Err(format!("{} trailing bytes after SFT attributes", input.len()));
+    }
+    
+    let rarity = rarity_from_code(rarity_byte)
+        .ok_or_else(|| format!("Unknown rarity {} (expected < {})", rarity_byte, RARITY_VARIANTS))?;
+    if power > MAX_SFT_POWER {
+        return Err(format!("SFT power {} exceeds the maximum of {}", power, MAX_SFT_POWER));
+    }
+    Ok(SFTAttributes { quest_id, map_seed, rarity, power, metadata })
+}
+
+/// Token identifier used for the `serial`-th SFT recorded, minted as a quest reward
+pub fn reward_token_id(attributes: &SFTAttributes, serial: i64) -> String {
+    format!("CQSFT-{:08x}-{:04x}", attributes.quest_id, serial)
+}
+
+/// Queue the mint of an SFT reward to the connected wallet and record it as pending,
+/// returning its token id. Nothing is recorded when the submission fails
+pub fn mint_sft_reward(
+    client: &BlockchainClient,
+    attributes: &SFTAttributes,
+    db: &DatabaseConnection,
+    state: &mut BlockchainState,
+) -> Result<String, String> {
+    let tx = client.build_mint_tx(attributes)?;
+    let serial = db.next_sft_serial().map_err(|e| format!("Failed to list SFTs: {}", e))?;
+    let token_id = reward_token_id(attributes, serial);
+    let attributes_json = serde_json::to_string(attributes)
+        .map_err(|e| format!("Attribute serialization error: {}", e))?;
+    
+    let tx_hash = client.submit_tx(&tx, state)?;
+    db.insert_pending_sft(&token_id, &attributes_json, &tx_hash, &state.wallet_address)
+        .map_err(|e| format!("Failed to record SFT {}: {}", token_id, e))?;
+    info!("Mint of {} pending as tx {}", token_id, tx_hash);
+    Ok(token_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample_attributes() -> SFTAttributes {
+        SFTAttributes {
+            quest_id: 7,
+            map_seed: 1337,
+            rarity: Rarity::Epic,
+            power: 120,
+            metadata: "Quest 7 Reward".to_string(),
+        }
+    }
+    
+    #[test]
+    fn rejected_transactions_are_reported_failed() {
+        let client = BlockchainClient { dry_run: true, ..Default::default() };
+        let mut state = BlockchainState::default();
+        let tx_hash = client.submit_mint(&sample_attributes(), &mut state).expect("mint");
+        client.ledger.lock().fail(&tx_hash);
+        assert_eq!(client.tx_status(&tx_hash), Ok(TxStatus::Failed));
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(client);
+        app.insert_resource(DatabaseConnection::open(":memory:"));
+        app.insert_resource(state);
+        app.add_systems(Update, poll_pending_transactions);
+        app.update();
+
+        let state = app.world.resource::<BlockchainState>();
+        assert_eq!(state.pending_transactions, vec![(tx_hash, TxStatus::Failed)]);
+    }
+
+    #[test]
+    fn failed_transactions_revert_optimistic_sft_changes() {
+        let db = DatabaseConnection::open(":memory:");
+        let client = BlockchainClient { dry_run: true, ..Default::default() };
+        let mut state = BlockchainState::default();
+        let kept = mint_sft_reward(&client, &sample_attributes(), &db, &mut state).expect("mint");
+        mint_sft_reward(&client, &SFTAttributes { quest_id: 8, ..sample_attributes() }, &db, &mut state).expect("mint");
+        let failed_mint = state.pending_transactions[1].0.clone();
+        client.ledger.lock().fail(&failed_mint);
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(client.clone());
+        app.insert_resource(db.clone());
+        app.insert_resource(state);
+        app.add_systems(Update, poll_pending_transactions);
+        app.update();
+
+        let rows = db.list_sfts().expect("list ok");
+        assert_eq!(rows.len(), 1, "the failed mint is removed");
+        assert_eq!((rows[0].token_id.as_str(), rows[0].mint_tx.as_deref()), (kept.as_str(), None));
+
+        let stake_tx = stake_sft(&client, &kept, &db, &mut app.world.resource_mut::<BlockchainState>()).expect("stake");
+        assert!(db.list_sfts().expect("list ok")[0].staked, "staked optimistically");
+        client.ledger.lock().fail(&stake_tx);
+        app.update();
+
+        assert!(!db.list_sfts().expect("list ok")[0].staked, "the failed stake is undone");
+        let state = app.world.resource::<BlockchainState>();
+        assert!(state.pending_transactions.contains(&(stake_tx, TxStatus::Failed)));
+    }
+}
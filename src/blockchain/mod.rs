@@ -1,5 +1,23 @@
-pub mod client;
 use multiversx_sc::*;
+use multiversx_sc::derive_imports::*;
+
+pub mod client;
+
+/// Highest `power` an SFT may carry
+pub const MAX_SFT_POWER: u32 = 1000;
+
+/// Number of `Rarity` variants; encoded rarities are indices below this
+pub const RARITY_VARIANTS: u8 = 5;
+
+/// On-chain mirror of `SFTAttributes`, with the rarity as its variant index
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi)]
+pub struct OnChainSftAttributes<M: ManagedTypeApi> {
+    pub quest_id: u32,
+    pub map_seed: i64,
+    pub rarity: u8,
+    pub power: u32,
+    pub metadata: ManagedBuffer<M>,
+}
 
 #[multiversx_sc::contract]
 pub trait IdleSFTContract {
@@ -8,8 +26,38 @@ pub trait IdleSFTContract {
 
     #[endpoint(mintReward)]
     fn mint_reward(&self, attributes: ManagedBuffer) {
-        // MVP stub for offchain integration tests; real minting requires ESDT/NFT module wiring
+        // Attributes are validated and counted; real minting still requires ESDT/NFT module wiring
         let _caller = self.blockchain().get_caller();
-        let _attr = attributes;
+        let decoded = OnChainSftAttributes::<Self::Api>::top_decode(attributes)
+            .unwrap_or_else(|_| sc_panic!("invalid SFT attributes encoding"));
+        require!(decoded.power <= MAX_SFT_POWER, "SFT power out of bounds");
+        require!(decoded.rarity < RARITY_VARIANTS, "unknown SFT rarity");
+
+        self.mint_count().update(|count| *count += 1);
+    }
+
+    #[endpoint(stakeSft)]
+    fn stake_sft(&self, token_id: ManagedBuffer) {
+        let caller = self.blockchain().get_caller();
+        let stake = self.staked_at(&caller, &token_id);
+        require!(stake.is_empty(), "SFT already staked");
+        stake.set(self.blockchain().get_block_timestamp());
     }
+
+    #[endpoint(unstakeSft)]
+    fn unstake_sft(&self, token_id: ManagedBuffer) {
+        let caller = self.blockchain().get_caller();
+        let stake = self.staked_at(&caller, &token_id);
+        require!(!stake.is_empty(), "SFT not staked");
+        stake.clear();
+    }
+
+    /// Block timestamp at which the owner staked the SFT; empty when unstaked
+    #[view(getStakedAt)]
+    #[storage_mapper("stakedAt")]
+    fn staked_at(&self, owner: &ManagedAddress, token_id: &ManagedBuffer) -> SingleValueMapper<u64>;
+
+    #[view(getMintCount)]
+    #[storage_mapper("mintCount")]
+    fn mint_count(&self) -> SingleValueMapper<u64>;
 }
@@ -1,12 +1,19 @@
 //! ChainQuest Idle - Client application
 
-use chainquest_idle::run_game;
+use chainquest_idle::app::RunMode;
+use chainquest_idle::{run_game, run_game_headless};
 use env_logger;
 
 fn main() {
     env_logger::Builder::from_default_env()
         .filter_level(log::LevelFilter::Info)
         .init();
-    println!("Starting ChainQuest Idle - MVP Client");
-    run_game();
+    let headless = std::env::args().any(|arg| arg == "--headless") || RunMode::from_env() == RunMode::Headless;
+    if headless {
+        println!("Starting ChainQuest Idle - MVP Client (headless)");
+        run_game_headless();
+    } else {
+        println!("Starting ChainQuest Idle - MVP Client");
+        run_game();
+    }
 }
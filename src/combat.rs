@@ -0,0 +1,270 @@
+//! Combat resolution for enemy encounters
+
+use bevy::prelude::*;
+use rand::Rng;
+use std::env;
+use crate::ai::{entry_point, tile_type_to_int, MapRoot, MAP_SIZE};
+use crate::components::{Health, IdleProgress, MapTile, Player, Position, TileType};
+use crate::movement::TileEntered;
+use crate::resources::{GameRng, GameState};
+
+/// Experience granted per point of power of a defeated enemy
+pub const EXP_PER_ENEMY_POWER: f64 = 0.5;
+
+/// Share of resources lost when the player is defeated or runs out of health
+pub const DEFEAT_RESOURCE_PENALTY: f64 = 0.1;
+
+/// Share of max health a knocked-out player respawns with
+pub const RESPAWN_HEALTH_FRACTION: f32 = 0.25;
+
+/// Health recovered per second outside of fights
+pub const HEALTH_REGEN_PER_SEC: f32 = 1.0;
+
+/// How an enemy encounter is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CombatMode {
+    /// Resolve instantly on contact
+    #[default]
+    Auto,
+    /// Run a short fight that resolves after a duration, with a chance to flee
+    Timed,
+}
+
+/// Combat configuration resource
+#[derive(Resource, Debug, Clone)]
+pub struct CombatConfig {
+    pub mode: CombatMode,
+    pub fight_duration: f32, // seconds
+    pub flee_chance: f64,
+}
+
+impl Default for CombatConfig {
+    fn default() -> Self {
+        Self {
+            mode: CombatMode::Auto,
+            fight_duration: 3.0,
+            flee_chance: 0.5,
+        }
+    }
+}
+
+impl CombatConfig {
+    /// Read combat settings from `CQ_COMBAT_MODE` (`auto`/`timed`) and `CQ_FIGHT_DURATION`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let mode = match env::var("CQ_COMBAT_MODE").map(|s| s.to_lowercase()).as_deref() {
+            Ok("timed") => CombatMode::Timed,
+            _ => CombatMode::Auto,
+        };
+        let fight_duration = env::var("CQ_FIGHT_DURATION").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|d: &f32| *d > 0.0)
+            .unwrap_or(defaults.fight_duration);
+        Self { mode, fight_duration, ..defaults }
+    }
+}
+
+/// Result of a resolved encounter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombatOutcome {
+    Victory,
+    Defeat,
+    Fled,
+}
+
+/// A timed fight in progress
+#[derive(Debug, Clone)]
+pub struct ActiveFight {
+    pub enemy_power: u32,
+    pub elapsed: f32,
+}
+
+/// Current combat state resource
+#[derive(Resource, Debug, Default)]
+pub struct CombatState {
+    pub active_fight: Option<ActiveFight>,
+}
+
+/// Resolve a single exchange between the player and an enemy
+pub fn resolve_combat(player_level: u32, enemy_power: u32, rng: &mut impl Rng) -> CombatOutcome {
+    let player_power = (player_level.max(1) * 10) as f64;
+    let win_chance = player_power / (player_power + enemy_power as f64);
+    if rng.gen_bool(win_chance.clamp(0.0, 1.0)) {
+        CombatOutcome::Victory
+    } else {
+        CombatOutcome::Defeat
+    }
+}
+
+/// Handle contact with an enemy; returns the outcome if the encounter resolved immediately
+pub fn begin_combat(
+    config: &CombatConfig,
+    state: &mut CombatState,
+    player_level: u32,
+    enemy_power: u32,
+    rng: &mut impl Rng,
+) -> Option<CombatOutcome> {
+    match config.mode {
+        CombatMode::Auto => Some(resolve_combat(player_level, enemy_power, rng)),
+        CombatMode::Timed => {
+            if state.active_fight.is_none() {
+                state.active_fight = Some(ActiveFight { enemy_power, elapsed: 0.0 });
+            }
+            None
+        }
+    }
+}
+
+/// Advance a timed fight; returns the outcome once the fight ends
+pub fn advance_combat(
+    config: &CombatConfig,
+    state: &mut CombatState,
+    player_level: u32,
+    delta: f32,
+    flee_requested: bool,
+    rng: &mut impl Rng,
+) -> Option<CombatOutcome> {
+    let fight = state.active_fight.as_mut()?;
+    
+    if flee_requested && rng.gen_bool(config.flee_chance.clamp(0.0, 1.0)) {
+        state.active_fight = None;
+        return Some(CombatOutcome::Fled);
+    }
+    
+    fight.elapsed += delta;
+    if fight.elapsed < config.fight_duration {
+        return None;
+    }
+    
+    let enemy_power = fight.enemy_power;
+    state.active_fight = None;
+    Some(resolve_combat(player_level, enemy_power, rng))
+}
+
+/// Power of the enemy on a tile: varies with its position and grows with each portal taken
+pub fn enemy_power(grid_x: i32, grid_y: i32, map_depth: u32) -> u32 {
+    let variation = (grid_x * 7 + grid_y * 13).rem_euclid(11) as u32;
+    10 + variation * 2 + map_depth * 15
+}
+
+/// Health lost in an exchange: a quarter of the enemy's power when winning, all of it when losing
+pub fn combat_damage(enemy_power: u32, outcome: CombatOutcome) -> f32 {
+    match outcome {
+        CombatOutcome::Victory => enemy_power as f32 / 4.0,
+        CombatOutcome::Defeat => enemy_power as f32,
+        CombatOutcome::Fled => 0.0,
+    }
+}
+
+/// Resolve encounters on enemy tiles the player steps onto. Timed fights advance each frame and
+/// stepping off the tile tries to flee. Winning clears the tile and grants experience; losing or
+/// running out of health costs resources and respawns the player at the active map's entry point
+/// without healing them, beyond bringing a knocked-out player back at `RESPAWN_HEALTH_FRACTION`.
+pub fn combat_system(
+    mut entered: EventReader<TileEntered>,
+    config: Option<Res<CombatConfig>>,
+    mut state: ResMut<CombatState>,
+    time: Res<Time>,
+    game_state: Option<Res<GameState>>,
+    mut rng: Option<ResMut<GameRng>>,
+    mut players: Query<(&mut IdleProgress, &mut Health, &mut Position), With<Player>>,
+    mut tiles: Query<&mut MapTile>,
+    root: Option<Res<MapRoot>>,
+    mut fight_tile: Local<Option<Entity>>,
+) {
+    let default_config = CombatConfig::default();
+    let config = config.as_deref().unwrap_or(&default_config);
+    let mut fallback_rng = GameRng::default();
+    let rng = rng.as_deref_mut().unwrap_or(&mut fallback_rng);
+    let depth = game_state.map_or(0, |gs| gs.map_depth);
+    let Ok((mut progress, mut health, mut position)) = players.get_single_mut() else { return };
+    
+    for event in entered.read().filter(|e| matches!(e.tile_type, TileType::Enemy)) {
+        if state.active_fight.is_some() {
+            continue;
+        }
+        let power = enemy_power(event.grid_x, event.grid_y, depth);
+        *fight_tile = Some(event.tile);
+        if let Some(outcome) = begin_combat(config, &mut state, progress.level, power, rng) {
+            if apply_outcome(outcome, power, event.tile, &mut progress, &mut health, &mut tiles) {
+                respawn(&mut position, root.as_deref(), &tiles);
+            }
+        }
+    }
+    
+    let Some(power) = state.active_fight.as_ref().map(|fight| fight.enemy_power) else { return };
+    let Some(tile) = *fight_tile else { return };
+    let fleeing = tiles.get(tile).map_or(true, |tile| {
+        tile.grid_x != position.x.round() as i32 || tile.grid_y != position.y.round() as i32
+    });
+    if let Some(outcome) = advance_combat(config, &mut state, progress.level, time.delta_seconds(), fleeing, rng) {
+        if apply_outcome(outcome, power, tile, &mut progress, &mut health, &mut tiles) {
+            respawn(&mut position, root.as_deref(), &tiles);
+        }
+    }
+}
+
+/// Apply a resolved encounter; returns whether the player was knocked out and must respawn
+fn apply_outcome(
+    outcome: CombatOutcome,
+    enemy_power: u32,
+    tile: Entity,
+    progress: &mut IdleProgress,
+    health: &mut Health,
+    tiles: &mut Query<&mut MapTile>,
+) -> bool {
+    let knocked_out = health.take_damage(combat_damage(enemy_power, outcome));
+    match outcome {
+        CombatOutcome::Victory => {
+            let experience = enemy_power as f64 * EXP_PER_ENEMY_POWER;
+            progress.experience += experience;
+            if let Ok(mut tile) = tiles.get_mut(tile) {
+                tile.tile_type = TileType::Empty;
+            }
+            info!("Defeated an enemy of power {} (+{} exp, {} health left)", enemy_power, experience, health.current);
+        }
+        CombatOutcome::Defeat => info!("Lost to an enemy of power {} ({} health left)", enemy_power, health.current),
+        CombatOutcome::Fled => info!("Fled from an enemy of power {}", enemy_power),
+    }
+    
+    if !(knocked_out || outcome == CombatOutcome::Defeat) {
+        return false;
+    }
+    let penalty = progress.resources * DEFEAT_RESOURCE_PENALTY;
+    progress.resources -= penalty;
+    health.current = health.current.max(health.max * RESPAWN_HEALTH_FRACTION);
+    info!("Knocked out! Lost {:.0} resources ({} health left)", penalty, health.current);
+    true
+}
+
+/// Move a knocked-out player to the active map's entry point
+fn respawn(position: &mut Position, root: Option<&MapRoot>, tiles: &Query<&mut MapTile>) {
+    let (x, y) = respawn_point(root, tiles);
+    position.x = x as f32;
+    position.y = y as f32;
+    info!("Respawned at ({}, {})", x, y);
+}
+
+/// The active map's entry point, computed from the tiles tracked by its `MapRoot` so tiles of a
+/// map still being despawned during a transition don't leak in
+fn respawn_point(root: Option<&MapRoot>, tiles: &Query<&mut MapTile>) -> (i32, i32) {
+    let mut grid = vec![vec![0; MAP_SIZE]; MAP_SIZE];
+    for tile in root.into_iter().flat_map(|root| tiles.iter_many(&root.tiles)) {
+        if let Some(cell) = grid.get_mut(tile.grid_x as usize).and_then(|row| row.get_mut(tile.grid_y as usize)) {
+            *cell = tile_type_to_int(&tile.tile_type);
+        }
+    }
+    entry_point(&grid)
+}
+
+/// Slowly restore the player's health while no fight is in progress
+pub fn regenerate_health(time: Res<Time>, state: Res<CombatState>, mut players: Query<&mut Health, With<Player>>) {
+    if state.active_fight.is_some() {
+        return;
+    }
+    for mut health in &mut players {
+        if health.current < health.max {
+            health.current = (health.current + HEALTH_REGEN_PER_SEC * time.delta_seconds()).min(health.max);
+        }
+    }
+}
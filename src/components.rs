@@ -6,10 +6,13 @@ use serde::{Deserialize, Serialize};
 /// Player progress in idle mechanics
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct IdleProgress {
-    pub resources: f32,
-    pub experience: f32,
+    pub resources: f64,
+    pub experience: f64,
     pub level: u32,
     pub last_update: f64,
+    /// Manual collection runs automatically once unlocked
+    #[serde(default)]
+    pub auto_collect: bool,
 }
 
 impl Default for IdleProgress {
@@ -19,10 +22,92 @@ impl Default for IdleProgress {
             experience: 0.0,
             level: 1,
             last_update: 0.0,
+            auto_collect: false,
         }
     }
 }
 
+/// Lifetime statistics tracked alongside idle progress
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// Total resources ever earned, ignoring spending
+    pub lifetime_resources: f64,
+    pub milestones_reached: u32,
+    /// Resource balance seen on the previous tick; `None` until the first observation
+    pub last_seen_resources: Option<f64>,
+    /// Secondary currency distilled from resources produced beyond the storage cap
+    pub essence: f64,
+}
+
+/// Kinds of purchasable resource generators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GeneratorKind {
+    Miner,
+    Harvester,
+    Refinery,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 3] = [GeneratorKind::Miner, GeneratorKind::Harvester, GeneratorKind::Refinery];
+    
+    /// Stable name used for persistence
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GeneratorKind::Miner => "Miner",
+            GeneratorKind::Harvester => "Harvester",
+            GeneratorKind::Refinery => "Refinery",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == name)
+    }
+    
+    /// The kind after this one in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// A resource generator the player upgrades with resources; tier 0 is not yet owned
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Generator {
+    pub kind: GeneratorKind,
+    /// Save slot that owns this generator
+    pub slot: u32,
+    pub tier: u32,
+    /// Resources per second added by each tier
+    pub base_rate: f64,
+    /// Price of the first tier
+    pub cost: f64,
+}
+
+impl Generator {
+    /// Each tier costs this much more than the previous one
+    pub const COST_GROWTH: f64 = 1.15;
+    
+    /// An unowned generator with the kind's base stats, belonging to save `slot`
+    pub fn new(kind: GeneratorKind, slot: u32) -> Self {
+        let (base_rate, cost) = match kind {
+            GeneratorKind::Miner => (1.0, 50.0),
+            GeneratorKind::Harvester => (5.0, 400.0),
+            GeneratorKind::Refinery => (25.0, 3000.0),
+        };
+        Self { kind, slot, tier: 0, base_rate, cost }
+    }
+    
+    /// Resources per second at the current tier
+    pub fn rate(&self) -> f64 {
+        self.base_rate * self.tier as f64
+    }
+    
+    /// Price of the next tier
+    pub fn upgrade_cost(&self) -> f64 {
+        self.cost * Self::COST_GROWTH.powi(self.tier as i32)
+    }
+}
+
 /// Position component for entities
 #[derive(Component, Debug, Clone)]
 pub struct Position {
@@ -34,6 +119,37 @@ pub struct Position {
 #[derive(Component)]
 pub struct Player;
 
+/// Stable id the security and network layers know a player by; persisted per save slot
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerId(pub u32);
+
+impl Default for PlayerId {
+    fn default() -> Self {
+        Self(crate::security::LOCAL_PLAYER_ID)
+    }
+}
+
+/// Player hit points, worn down by enemy encounters; persisted per save slot
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self { current: 100.0, max: 100.0 }
+    }
+}
+
+impl Health {
+    /// Lose up to `amount` hit points; returns true once none are left
+    pub fn take_damage(&mut self, amount: f32) -> bool {
+        self.current = (self.current - amount).max(0.0);
+        self.current <= 0.0
+    }
+}
+
 /// Map tile component
 #[derive(Component, Debug, Clone)]
 pub struct MapTile {
@@ -86,6 +202,8 @@ pub struct NetworkPlayer {
     pub peer_id: u32,
     pub username: String,
     pub connected: bool,
+    /// Last validated resource total reported by the peer
+    pub resources: f32,
 }
 
 /// Quest component
@@ -97,4 +215,47 @@ pub struct Quest {
     pub completed: bool,
     pub reward_resources: f32,
     pub reward_sft: Option<SFTAttributes>,
+    /// Progress toward completion
+    #[serde(default)]
+    pub objective: QuestObjective,
+}
+
+/// What a quest's objective counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ObjectiveKind {
+    /// Seconds since the quest appeared
+    #[default]
+    Elapsed,
+    /// Resources gained while the quest is active
+    CollectResources,
+    /// Levels gained while the quest is active
+    GainLevels,
+}
+
+/// A quest's progress toward its target
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct QuestObjective {
+    pub kind: ObjectiveKind,
+    pub target: f32,
+    pub current: f32,
+}
+
+impl QuestObjective {
+    pub fn new(kind: ObjectiveKind, target: f32) -> Self {
+        Self { kind, target, current: 0.0 }
+    }
+    
+    /// Add progress, never past the target
+    pub fn advance(&mut self, amount: f32) {
+        self.current = (self.current + amount.max(0.0)).min(self.target);
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.target
+    }
+    
+    /// "current/target", rounded down
+    pub fn progress_text(&self) -> String {
+        format!("{:.0}/{:.0}", self.current.floor(), self.target)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use std::env;
+
+/// Debug-only actions, enabled by `CQ_DEBUG`
+#[derive(Resource, Debug, Clone)]
+pub struct DebugConfig {
+    pub enabled: bool,
+    /// Simulated seconds applied by one fast-forward (F5)
+    pub fast_forward_secs: f64,
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fast_forward_secs: 3600.0,
+        }
+    }
+}
+
+impl DebugConfig {
+    /// Read `CQ_DEBUG` and `CQ_FAST_FORWARD_SECS` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let enabled = env::var("CQ_DEBUG").map(|v| v != "0" && !v.is_empty()).unwrap_or(false);
+        let fast_forward_secs = env::var("CQ_FAST_FORWARD_SECS").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|secs: &f64| *secs > 0.0)
+            .unwrap_or(defaults.fast_forward_secs);
+        Self { enabled, fast_forward_secs }
+    }
+}
@@ -1,16 +1,109 @@
 use bevy::prelude::*;
 use std::env;
+use std::net::Ipv4Addr;
+use crate::multiplayer::config::{HostParams, DEFAULT_RATE_LIMIT};
 
 #[derive(Resource, Default, Clone)]
 pub struct EnvConfig {
     pub host: String,
     pub port: u16,
+    pub incoming_bandwidth: u32,
+    pub outgoing_bandwidth: u32,
+    pub default_rate_limit: u32,
 }
 
 impl EnvConfig {
     pub fn from_env() -> Self {
         let host = env::var("CQ_HOST").unwrap_or_else(|_| "127.0.0.1".into());
         let port = env::var("CQ_PORT").ok().and_then(|s| s.parse().ok()).unwrap_or(8080);
-        Self { host, port }
+        let incoming_bandwidth = env::var("CQ_IN_BANDWIDTH").ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let outgoing_bandwidth = env::var("CQ_OUT_BANDWIDTH").ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let default_rate_limit = env::var("CQ_RATE_LIMIT").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_RATE_LIMIT);
+        Self { host, port, incoming_bandwidth, outgoing_bandwidth, default_rate_limit }
+    }
+}
+
+/// Listening parameters for the ENet server binary
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub bind_ip: Ipv4Addr,
+    pub port: u16,
+    pub max_clients: usize,
+    pub channels: usize,
+    /// ENet bandwidth caps in bytes/sec, 0 for unlimited; see `HostParams`
+    pub incoming_bandwidth: u32,
+    pub outgoing_bandwidth: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_ip: Ipv4Addr::UNSPECIFIED,
+            port: 8080,
+            max_clients: 8,
+            channels: 2,
+            incoming_bandwidth: 0,
+            outgoing_bandwidth: 0,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read `CQ_BIND_IP`, `CQ_PORT`, `CQ_MAX_CLIENTS`, `CQ_CHANNELS`, `CQ_IN_BANDWIDTH` and
+    /// `CQ_OUT_BANDWIDTH` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            bind_ip: env::var("CQ_BIND_IP").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.bind_ip),
+            port: env::var("CQ_PORT").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.port),
+            max_clients: env::var("CQ_MAX_CLIENTS").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.max_clients),
+            channels: env::var("CQ_CHANNELS").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.channels),
+            incoming_bandwidth: env::var("CQ_IN_BANDWIDTH").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.incoming_bandwidth),
+            outgoing_bandwidth: env::var("CQ_OUT_BANDWIDTH").ok().and_then(|s| s.parse().ok()).unwrap_or(defaults.outgoing_bandwidth),
+        }
+    }
+    
+    /// Override with `--bind`, `--port`, `--max-clients`, `--channels`, `--in-bandwidth` and
+    /// `--out-bandwidth` command line flags
+    pub fn with_args<I: IntoIterator<Item = String>>(mut self, args: I) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("Missing value for {}", flag))?;
+            let invalid = || format!("Invalid value '{}' for {}", value, flag);
+            match flag.as_str() {
+                "--bind" => self.bind_ip = value.parse().map_err(|_| invalid())?,
+                "--port" => self.port = value.parse().map_err(|_| invalid())?,
+                "--max-clients" => self.max_clients = value.parse().map_err(|_| invalid())?,
+                "--channels" => self.channels = value.parse().map_err(|_| invalid())?,
+                "--in-bandwidth" => self.incoming_bandwidth = value.parse().map_err(|_| invalid())?,
+                "--out-bandwidth" => self.outgoing_bandwidth = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Unknown argument: {}", flag)),
+            }
+        }
+        Ok(self)
+    }
+    
+    /// Parameters for the server's ENet host
+    pub fn host_params(&self) -> HostParams {
+        HostParams {
+            max_peers: self.max_clients,
+            channels: self.channels,
+            incoming_bandwidth: self.incoming_bandwidth,
+            outgoing_bandwidth: self.outgoing_bandwidth,
+        }
+    }
+    
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("Server port must be non-zero".to_string());
+        }
+        if self.max_clients < 1 {
+            return Err("Server max clients must be at least 1".to_string());
+        }
+        // Control and state traffic use separate channels
+        if self.channels < 2 {
+            return Err("Server channel count must be at least 2".to_string());
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use std::env;
+
+/// Game actions that can be bound to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Collect,
+    Quest,
+    GenerateMap,
+    Pause,
+    BuyUpgrade,
+    SelectGenerator,
+    SelectQuest,
+    RerollQuest,
+    AbandonQuest,
+    Save,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::Collect,
+        Action::Quest,
+        Action::GenerateMap,
+        Action::Pause,
+        Action::BuyUpgrade,
+        Action::SelectGenerator,
+        Action::SelectQuest,
+        Action::RerollQuest,
+        Action::AbandonQuest,
+        Action::Save,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+    ];
+    
+    /// Environment variable overriding this action's key
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            Action::Collect => "CQ_KEY_COLLECT",
+            Action::Quest => "CQ_KEY_QUEST",
+            Action::GenerateMap => "CQ_KEY_GENERATE_MAP",
+            Action::Pause => "CQ_KEY_PAUSE",
+            Action::BuyUpgrade => "CQ_KEY_BUY_UPGRADE",
+            Action::SelectGenerator => "CQ_KEY_SELECT_GENERATOR",
+            Action::SelectQuest => "CQ_KEY_SELECT_QUEST",
+            Action::RerollQuest => "CQ_KEY_REROLL_QUEST",
+            Action::AbandonQuest => "CQ_KEY_ABANDON_QUEST",
+            Action::Save => "CQ_KEY_SAVE",
+            Action::MoveUp => "CQ_KEY_MOVE_UP",
+            Action::MoveDown => "CQ_KEY_MOVE_DOWN",
+            Action::MoveLeft => "CQ_KEY_MOVE_LEFT",
+            Action::MoveRight => "CQ_KEY_MOVE_RIGHT",
+        }
+    }
+}
+
+/// Key assigned to each game action
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    pub collect: KeyCode,
+    pub quest: KeyCode,
+    pub generate_map: KeyCode,
+    pub pause: KeyCode,
+    pub buy_upgrade: KeyCode,
+    pub select_generator: KeyCode,
+    pub select_quest: KeyCode,
+    pub reroll_quest: KeyCode,
+    pub abandon_quest: KeyCode,
+    pub save: KeyCode,
+    pub move_up: KeyCode,
+    pub move_down: KeyCode,
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            collect: KeyCode::Space,
+            quest: KeyCode::KeyQ,
+            generate_map: KeyCode::KeyM,
+            pause: KeyCode::KeyP,
+            buy_upgrade: KeyCode::KeyU,
+            select_generator: KeyCode::KeyG,
+            select_quest: KeyCode::Tab,
+            reroll_quest: KeyCode::KeyR,
+            abandon_quest: KeyCode::KeyX,
+            save: KeyCode::F6,
+            move_up: KeyCode::KeyW,
+            move_down: KeyCode::KeyS,
+            move_left: KeyCode::KeyA,
+            move_right: KeyCode::KeyD,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Read `CQ_KEY_*` for each action (see `Action::env_var`)
+    /// (key names such as "Space", "Q" or "F6") over the defaults
+    pub fn from_env() -> Self {
+        let mut bindings = Self::default();
+        for action in Action::ALL {
+            let Ok(name) = env::var(action.env_var()) else { continue };
+            match parse_key(&name) {
+                Some(key) => *bindings.key_mut(action) = key,
+                None => warn!("Unknown key '{}' in {}, keeping {:?}", name, action.env_var(), bindings.key(action)),
+            }
+        }
+        bindings
+    }
+    
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Collect => self.collect,
+            Action::Quest => self.quest,
+            Action::GenerateMap => self.generate_map,
+            Action::Pause => self.pause,
+            Action::BuyUpgrade => self.buy_upgrade,
+            Action::SelectGenerator => self.select_generator,
+            Action::SelectQuest => self.select_quest,
+            Action::RerollQuest => self.reroll_quest,
+            Action::AbandonQuest => self.abandon_quest,
+            Action::Save => self.save,
+            Action::MoveUp => self.move_up,
+            Action::MoveDown => self.move_down,
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+        }
+    }
+    
+    fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::Collect => &mut self.collect,
+            Action::Quest => &mut self.quest,
+            Action::GenerateMap => &mut self.generate_map,
+            Action::Pause => &mut self.pause,
+            Action::BuyUpgrade => &mut self.buy_upgrade,
+            Action::SelectGenerator => &mut self.select_generator,
+            Action::SelectQuest => &mut self.select_quest,
+            Action::RerollQuest => &mut self.reroll_quest,
+            Action::AbandonQuest => &mut self.abandon_quest,
+            Action::Save => &mut self.save,
+            Action::MoveUp => &mut self.move_up,
+            Action::MoveDown => &mut self.move_down,
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+        }
+    }
+    
+    /// Whether the key bound to `action` was pressed this frame
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.just_pressed(self.key(action))
+    }
+    
+    /// Whether the key bound to `action` is held down
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.pressed(self.key(action))
+    }
+}
+
+/// Parse a key name: a letter, a digit, an F-key or one of a few named keys
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+        KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+        KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+        KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+        KeyCode::KeyY, KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+        KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+    const FUNCTION_KEYS: [KeyCode; 12] = [
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+    
+    let name = name.trim().to_uppercase();
+    let name = name.strip_prefix("KEY").filter(|rest| rest.len() == 1).unwrap_or(name.as_str());
+    match name {
+        "SPACE" => return Some(KeyCode::Space),
+        "ENTER" | "RETURN" => return Some(KeyCode::Enter),
+        "TAB" => return Some(KeyCode::Tab),
+        "ESC" | "ESCAPE" => return Some(KeyCode::Escape),
+        "BACKSPACE" => return Some(KeyCode::Backspace),
+        _ => {}
+    }
+    
+    let mut chars = name.chars();
+    match (chars.next(), chars.as_str()) {
+        (Some(c @ 'A'..='Z'), "") => Some(LETTERS[(c as u8 - b'A') as usize]),
+        (Some(c @ '0'..='9'), "") => Some(DIGITS[(c as u8 - b'0') as usize]),
+        (Some('F'), number) => number.parse::<usize>().ok()
+            .filter(|n| (1..=12).contains(n))
+            .map(|n| FUNCTION_KEYS[n - 1]),
+        _ => None,
+    }
+}
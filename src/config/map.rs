@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use std::env;
+
+/// Map spawning limits
+#[derive(Resource, Debug, Clone)]
+pub struct MapConfig {
+    /// Most tile entities spawned in a single frame
+    pub tiles_per_frame: usize,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self { tiles_per_frame: 64 }
+    }
+}
+
+impl MapConfig {
+    /// Read `CQ_TILES_PER_FRAME` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let tiles_per_frame = env::var("CQ_TILES_PER_FRAME").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|n: &usize| *n > 0)
+            .unwrap_or(defaults.tiles_per_frame);
+        Self { tiles_per_frame }
+    }
+}
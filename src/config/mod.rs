@@ -1,2 +1,8 @@
 pub mod env;
 pub mod startup;
+pub mod multiplier;
+pub mod progression;
+pub mod debug;
+pub mod map;
+pub mod keys;
+pub mod save;
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Timed global resource multiplier ("happy hour"), window in UNIX seconds
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobalMultiplier {
+    pub factor: f32,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Default for GlobalMultiplier {
+    fn default() -> Self {
+        Self { factor: 1.0, start: 0.0, end: 0.0 }
+    }
+}
+
+impl GlobalMultiplier {
+    /// Load a multiplier event from a JSON file
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read multiplier file {}: {}", path, e))?;
+        let multiplier: Self = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid multiplier file {}: {}", path, e))?;
+        if multiplier.factor.is_nan() || multiplier.factor <= 0.0 {
+            return Err(format!("Multiplier factor must be positive, got {}", multiplier.factor));
+        }
+        Ok(multiplier)
+    }
+    
+    /// Whether the event window contains `now`
+    pub fn is_active(&self, now: f64) -> bool {
+        now >= self.start && now < self.end
+    }
+    
+    /// Factor to apply at `now`; 1.0 outside the window
+    pub fn factor_at(&self, now: f64) -> f32 {
+        if self.is_active(now) { self.factor } else { 1.0 }
+    }
+}
+
+/// Current wall-clock time in UNIX seconds
+pub fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Reload the multiplier event from `CQ_MULTIPLIER_FILE` if set, keeping the current one on error
+pub fn reload_global_multiplier(mut multiplier: ResMut<GlobalMultiplier>) {
+    let Ok(path) = std::env::var("CQ_MULTIPLIER_FILE") else { return };
+    match GlobalMultiplier::load_from_file(&path) {
+        Ok(loaded) => {
+            if *multiplier != loaded {
+                info!("Global multiplier updated: x{} from {} to {}", loaded.factor, loaded.start, loaded.end);
+                *multiplier = loaded;
+            }
+        }
+        Err(e) => warn!("{}", e),
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use std::env;
+
+/// Tunable idle progression numbers
+#[derive(Resource, Debug, Clone)]
+pub struct ProgressionConfig {
+    /// Resources per second per level
+    pub base_resource_rate: f32,
+    pub exp_per_second: f32,
+    /// Experience curve coefficients: required = a*level^2 + b*level + c
+    pub exp_curve: (f32, f32, f32),
+    /// Lifetime resources between experience milestones
+    pub resource_milestone: f64,
+    /// Experience granted per milestone crossed
+    pub milestone_exp: f32,
+    /// Resource rate bonus per point of staked SFT power (0.001 = +0.1%)
+    pub stake_bonus_per_power: f32,
+    /// Storage cap at level 1; cap = base * growth^(level - 1)
+    pub resource_cap_base: f32,
+    pub resource_cap_growth: f32,
+    /// Essence gained per resource produced beyond the cap
+    pub overflow_conversion: f32,
+    /// Level at which auto-collect unlocks
+    pub auto_collect_level: u32,
+    /// Seconds between automatic collections
+    pub auto_collect_interval_secs: f32,
+}
+
+impl Default for ProgressionConfig {
+    fn default() -> Self {
+        Self {
+            base_resource_rate: 0.5,
+            exp_per_second: 0.1,
+            exp_curve: (10.0, 0.0, 0.0),
+            resource_milestone: 1000.0,
+            milestone_exp: 25.0,
+            stake_bonus_per_power: 0.001,
+            resource_cap_base: 1000.0,
+            resource_cap_growth: 2.0,
+            overflow_conversion: 0.01,
+            auto_collect_level: 10,
+            auto_collect_interval_secs: 5.0,
+        }
+    }
+}
+
+impl ProgressionConfig {
+    /// Read `CQ_BASE_RESOURCE_RATE`, `CQ_EXP_PER_SECOND`, `CQ_EXP_CURVE` ("a,b,c"),
+    /// `CQ_RESOURCE_MILESTONE`, `CQ_MILESTONE_EXP`, `CQ_STAKE_BONUS_PER_POWER`, `CQ_RESOURCE_CAP_BASE`,
+    /// `CQ_RESOURCE_CAP_GROWTH`, `CQ_OVERFLOW_CONVERSION`, `CQ_AUTO_COLLECT_LEVEL` and
+    /// `CQ_AUTO_COLLECT_INTERVAL` over the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let base_resource_rate = env::var("CQ_BASE_RESOURCE_RATE").ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.base_resource_rate);
+        let exp_per_second = env::var("CQ_EXP_PER_SECOND").ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.exp_per_second);
+        let exp_curve = env::var("CQ_EXP_CURVE").ok()
+            .and_then(|s| {
+                let parts: Vec<f32> = s.split(',').filter_map(|p| p.trim().parse().ok()).collect();
+                match parts.as_slice() {
+                    [a, b, c] => Some((*a, *b, *c)),
+                    _ => None,
+                }
+            })
+            .unwrap_or(defaults.exp_curve);
+        let resource_milestone = env::var("CQ_RESOURCE_MILESTONE").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|m: &f64| *m > 0.0)
+            .unwrap_or(defaults.resource_milestone);
+        let milestone_exp = env::var("CQ_MILESTONE_EXP").ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.milestone_exp);
+        let stake_bonus_per_power = env::var("CQ_STAKE_BONUS_PER_POWER").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|b: &f32| *b >= 0.0)
+            .unwrap_or(defaults.stake_bonus_per_power);
+        let resource_cap_base = env::var("CQ_RESOURCE_CAP_BASE").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|b: &f32| *b > 0.0)
+            .unwrap_or(defaults.resource_cap_base);
+        let resource_cap_growth = env::var("CQ_RESOURCE_CAP_GROWTH").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|g: &f32| *g >= 1.0)
+            .unwrap_or(defaults.resource_cap_growth);
+        let overflow_conversion = env::var("CQ_OVERFLOW_CONVERSION").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|c: &f32| *c >= 0.0)
+            .unwrap_or(defaults.overflow_conversion);
+        let auto_collect_level = env::var("CQ_AUTO_COLLECT_LEVEL").ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.auto_collect_level);
+        let auto_collect_interval_secs = env::var("CQ_AUTO_COLLECT_INTERVAL").ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|i: &f32| *i > 0.0)
+            .unwrap_or(defaults.auto_collect_interval_secs);
+        Self {
+            base_resource_rate,
+            exp_per_second,
+            exp_curve,
+            resource_milestone,
+            milestone_exp,
+            stake_bonus_per_power,
+            resource_cap_base,
+            resource_cap_growth,
+            overflow_conversion,
+            auto_collect_level,
+            auto_collect_interval_secs,
+        }
+    }
+    
+    /// Most resources a player can store at a level
+    pub fn resource_cap(&self, level: u32) -> f32 {
+        self.resource_cap_base * self.resource_cap_growth.powi(level.saturating_sub(1) as i32)
+    }
+    
+    /// Resource rate multiplier granted by staked SFT power
+    pub fn staking_factor(&self, staked_power: u32) -> f32 {
+        1.0 + staked_power as f32 * self.stake_bonus_per_power
+    }
+    
+    /// Resources generated per second at a level
+    pub fn resource_rate(&self, level: u32) -> f32 {
+        level as f32 * self.base_resource_rate
+    }
+    
+    /// Number of milestones contained in a lifetime resource total
+    pub fn milestones_for(&self, lifetime_resources: f64) -> u32 {
+        if self.resource_milestone <= 0.0 {
+            return 0;
+        }
+        (lifetime_resources / self.resource_milestone).floor() as u32
+    }
+    
+    /// Experience required to advance past a level
+    pub fn required_exp(&self, level: u32) -> f32 {
+        let (a, b, c) = self.exp_curve;
+        let level = level as f32;
+        a * level * level + b * level + c
+    }
+}
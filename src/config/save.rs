@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use std::env;
+
+/// How often progress is written to the database
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SaveConfig {
+    /// Seconds between automatic saves
+    pub interval_secs: f32,
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        Self { interval_secs: 10.0 }
+    }
+}
+
+impl SaveConfig {
+    pub fn new(interval_secs: f32) -> Result<Self, String> {
+        let config = Self { interval_secs };
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Read `CQ_SAVE_INTERVAL` over the default, keeping the default for invalid values
+    pub fn from_env() -> Self {
+        let Ok(value) = env::var("CQ_SAVE_INTERVAL") else { return Self::default() };
+        match value.parse().map_err(|_| format!("'{}' is not a number", value)).and_then(Self::new) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Invalid CQ_SAVE_INTERVAL: {}, using default", e);
+                Self::default()
+            }
+        }
+    }
+    
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.interval_secs.is_finite() && self.interval_secs > 0.0) {
+            return Err(format!("save interval must be positive, got {}", self.interval_secs));
+        }
+        Ok(())
+    }
+}
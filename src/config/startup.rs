@@ -1,8 +1,15 @@
 use bevy::prelude::*;
 use crate::config::env::EnvConfig;
-use crate::multiplayer::client::{NetConfig};
+use crate::multiplayer::config::NetConfig;
 
 pub fn apply_env(mut commands: Commands) {
     let cfg = EnvConfig::from_env();
-    commands.insert_resource(NetConfig { host: cfg.host, port: cfg.port });
+    commands.insert_resource(NetConfig {
+        host: cfg.host,
+        port: cfg.port,
+        incoming_bandwidth: cfg.incoming_bandwidth,
+        outgoing_bandwidth: cfg.outgoing_bandwidth,
+        default_rate_limit: cfg.default_rate_limit,
+        ..Default::default()
+    });
 }
@@ -4,42 +4,144 @@ use std::time::Duration;
 
 use crate::components::*;
 use crate::resources::*;
-use crate::systems_idle::update_idle_progress;
-use crate::systems_setup::{setup_camera, setup_ui, setup_map};
-use crate::quest_system::{setup_quest_system, generate_quests, process_quest_completion};
-use crate::ai::{setup_ai_map_generator, handle_map_generation};
-use crate::security::{setup_security_manager, security_cleanup};
-use crate::multiplayer::client::{net_setup, net_connect, net_service, net_ping};
-use crate::ui::hud::{ui_setup, ui_update};
+use crate::systems::{apply_validated_collection, autosave, backup_database, save_now, save_on_exit, toggle_pause};
+use crate::systems_idle::{update_idle_progress, check_resource_milestones, check_achievements, debug_fast_forward, select_generator, buy_upgrade, auto_collect, manual_collect};
+use crate::systems_setup::{setup_camera, setup_ui, load_saved_progress, load_player_profile, load_achievements, load_generators, setup_map};
+use crate::quest_system::{setup_quest_system, generate_quests, quest_tile_encounters, process_quest_completion, load_daily_quest_state, reset_daily_quests, complete_daily_quests, advance_quest_objectives, select_quest, reroll_quest, abandon_quest};
+use crate::ai::{MapRoot, enter_portal, setup_ai_map_generator, handle_map_generation, poll_map_generation, spawn_pending_map_tiles, persist_generation_stats};
+use crate::security::{setup_security_manager, persist_security_state, security_cleanup};
+use crate::multiplayer::client::{net_setup, net_connect, net_service, net_ping, network_statistics, NetworkMode};
+use crate::ui::hud::{flash_hud_on_level_up, ui_setup, ui_update};
+use crate::ui::font::{FontConfig, setup_ui_font, watch_ui_font};
+use crate::ui::i18n::Lang;
+use crate::ui::map_view::{map_tile_hover, refresh_tile_sprites, render_map_tiles, render_player_marker, sync_player_marker, tile_tooltip_setup};
+use crate::movement::{TileEntered, collect_resource_tiles, consume_collected_tiles, player_movement};
+use crate::combat::{CombatConfig, CombatState, combat_system, regenerate_health};
+use crate::ui::debug::{DebugOverlay, debug_overlay_setup, debug_overlay_update, fps_update, toggle_debug_overlay};
+use crate::app::RunMode;
+use crate::audio::{AudioConfig, setup_audio, play_sound_effects};
 use crate::config::startup::apply_env;
+use crate::config::multiplier::{GlobalMultiplier, reload_global_multiplier};
+use crate::config::progression::ProgressionConfig;
+use crate::config::debug::DebugConfig;
+use crate::config::map::MapConfig;
+use crate::config::keys::KeyBindings;
+use crate::config::save::SaveConfig;
+use crate::blockchain::client::{BlockchainClient, StakingBonus, connect_wallet_from_env, poll_pending_transactions, refresh_sft_balance, refresh_staking_bonus, restore_pending_transactions};
 
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        let save_config = SaveConfig::from_env();
         app
+            .init_resource::<RunMode>()
             .insert_resource(GameState::default())
+            .insert_resource(GameRng::from_env())
             .insert_resource(DatabaseConnection::new())
+            .insert_resource(BlockchainState::default())
+            .insert_resource(BlockchainClient::from_env())
+            .init_resource::<StakingBonus>()
+            .init_resource::<SelectedGenerator>()
+            .insert_resource(Achievements::default())
+            .insert_resource(GlobalMultiplier::default())
+            .insert_resource(ProgressionConfig::from_env())
+            .insert_resource(NetworkMode::from_env())
+            .insert_resource(DebugOverlay::from_env())
+            .insert_resource(Lang::from_env())
+            .insert_resource(DebugConfig::from_env())
+            .insert_resource(MapConfig::from_env())
+            .insert_resource(KeyBindings::from_env())
+            .insert_resource(save_config.clone())
+            .insert_resource(FontConfig::from_env())
+            .insert_resource(AudioConfig::from_env())
+            .insert_resource(CombatConfig::from_env())
+            .init_resource::<CombatState>()
+            .init_resource::<MapRoot>()
+            .init_state::<SimState>()
+            .add_event::<AchievementUnlocked>()
+            .add_event::<ResourceCollected>()
+            .add_event::<ResourcesCredited>()
+            .add_event::<ResourcesProduced>()
+            .add_event::<LevelUpEvent>()
+            .add_event::<QuestCompleted>()
+            .add_event::<TileEntered>()
             .add_systems(Startup, (
                 apply_env, 
-                setup_camera, 
+                setup_camera.run_if(resource_equals(RunMode::Windowed)), 
                 setup_ui, 
+                load_saved_progress.after(setup_ui),
+                load_player_profile,
+                load_achievements,
+                load_generators,
                 setup_map, 
                 setup_quest_system,
+                load_daily_quest_state,
                 setup_ai_map_generator,
                 setup_security_manager,
-                net_setup, 
-                ui_setup
+                net_setup.after(apply_env),
+                (setup_ui_font, ui_setup.after(setup_ui_font), debug_overlay_setup.after(setup_ui_font),
+                    tile_tooltip_setup.after(setup_ui_font), setup_audio)
+                    .run_if(resource_equals(RunMode::Windowed)),
+                connect_wallet_from_env,
+                restore_pending_transactions,
+                // Also at startup, before the save is loaded, so an event already running applies
+                // to the offline catch-up as well as from the first frame
+                reload_global_multiplier.before(load_saved_progress),
             ))
             .add_systems(Update, (
-                update_idle_progress,
-                generate_quests,
-                process_quest_completion,
-                handle_map_generation,
+                // Paused simulations skip these entirely, so progress resumes from where it stopped
+                (update_idle_progress, auto_collect, generate_quests,
+                    advance_quest_objectives.after(update_idle_progress),
+                    process_quest_completion.after(advance_quest_objectives),
+                    reset_daily_quests, complete_daily_quests.after(reset_daily_quests),
+                    player_movement, collect_resource_tiles.after(player_movement), quest_tile_encounters.after(player_movement),
+                    enter_portal.after(player_movement), combat_system.after(player_movement),
+                    regenerate_health.after(combat_system))
+                    .run_if(in_state(SimState::Running)),
+                check_resource_milestones.after(update_idle_progress),
+                check_achievements.run_if(on_timer(Duration::from_secs(1))),
+                // No collecting while paused, from the key or from anything queued before the pause
+                (manual_collect,
+                    apply_validated_collection.after(manual_collect).after(auto_collect).after(collect_resource_tiles))
+                    .run_if(in_state(SimState::Running)),
+                consume_collected_tiles.after(apply_validated_collection),
+                // Game input waits for the simulation to resume; pausing, saving and overlays stay live
+                ((select_quest, reroll_quest, abandon_quest).chain(), (select_generator, buy_upgrade).chain(), debug_fast_forward)
+                    .run_if(in_state(SimState::Running)),
+                toggle_pause,
+                autosave.run_if(on_timer(Duration::from_secs_f32(save_config.interval_secs))),
+                save_now,
+                (ui_update, flash_hud_on_level_up.after(ui_update), watch_ui_font, play_sound_effects, map_tile_hover, toggle_debug_overlay,
+                    debug_overlay_update.after(toggle_debug_overlay),
+                    fps_update.after(toggle_debug_overlay))
+                    .run_if(resource_equals(RunMode::Windowed)),
+            ))
+            .add_systems(Update, (
+                // After portals, so a trip's cancellation of the in-flight map has been applied
+                handle_map_generation.after(enter_portal),
+                poll_map_generation.after(enter_portal),
+                spawn_pending_map_tiles.after(poll_map_generation),
+                // Sprites are attached before this frame's despawns of the previous map are queued
+                render_map_tiles.before(spawn_pending_map_tiles).run_if(resource_equals(RunMode::Windowed)),
+                (refresh_tile_sprites, render_player_marker, sync_player_marker).run_if(resource_equals(RunMode::Windowed)),
+                persist_generation_stats.run_if(on_timer(Duration::from_secs(60))),
                 security_cleanup.run_if(on_timer(Duration::from_secs(300))), // Every 5 minutes
-                ui_update,
+                persist_security_state.run_if(on_timer(Duration::from_secs(30))),
+                reload_global_multiplier.run_if(on_timer(Duration::from_secs(60))),
+                refresh_sft_balance.run_if(on_timer(Duration::from_secs(15))),
+                refresh_staking_bonus.run_if(on_timer(Duration::from_secs(5))),
+                poll_pending_transactions.run_if(on_timer(Duration::from_secs(5))),
+            ))
+            .add_systems(Update, (
                 net_connect,
                 net_service,
                 net_ping.run_if(on_timer(Duration::from_millis(1000))),
-            ));
+                network_statistics.run_if(on_timer(Duration::from_secs(30))),
+            ).run_if(resource_equals(NetworkMode::Multiplayer)))
+            .add_systems(Last, save_on_exit);
+        
+        if let Some(interval) = backup_interval_from_env() {
+            app.add_systems(Update, backup_database.run_if(on_timer(interval)));
+        }
     }
 }
\ No newline at end of file
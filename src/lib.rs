@@ -1,14 +1,20 @@
 pub mod components;
+pub mod systems;
 pub mod systems_idle;
 pub mod systems_setup;
 pub mod quest_system;
+pub mod combat;
+pub mod movement;
+pub mod audio;
 pub mod security;
 pub mod resources;
-pub mod ai { pub mod mod_stub; pub mod integration; pub mod startup; pub mod map_generator; }
-pub mod multiplayer { pub mod client; pub mod network; }
-pub mod ui { pub mod hud; }
+pub mod ai;
+pub mod config;
+pub mod blockchain;
+pub mod multiplayer { pub mod config; pub mod client; pub mod network; pub mod server; }
+pub mod ui { pub mod hud; pub mod debug; pub mod font; pub mod i18n; pub mod map_view; }
 pub mod game_plugin;
 pub mod app;
 pub mod utils;
 
-pub use app::run_game;
\ No newline at end of file
+pub use app::{run_game, run_game_headless};
\ No newline at end of file
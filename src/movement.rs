@@ -0,0 +1,117 @@
+//! Player movement across the map grid and the effects of the tiles stepped onto
+
+use bevy::prelude::*;
+use crate::ai::MAP_SIZE;
+use crate::combat::CombatState;
+use crate::components::{IdleProgress, MapTile, Player, PlayerId, Position, TileType};
+use crate::config::keys::{Action, KeyBindings};
+use crate::resources::{ResourceCollected, ResourcesCredited};
+use crate::systems::collect_amount;
+
+/// Seconds between steps while a movement key is held
+pub const STEP_INTERVAL: f32 = 0.15;
+
+/// Resource tiles grant this many manual collections at once
+pub const RESOURCE_TILE_COLLECTIONS: f64 = 3.0;
+
+/// The player stepped onto a map tile
+#[derive(Event, Debug, Clone)]
+pub struct TileEntered {
+    pub player: Entity,
+    pub tile: Entity,
+    pub tile_type: TileType,
+    pub grid_x: i32,
+    pub grid_y: i32,
+}
+
+/// Direction of the held movement keys (WASD by default, arrows always), if any
+pub fn movement_direction(input: &ButtonInput<KeyCode>, bindings: &KeyBindings) -> Option<IVec2> {
+    let held = |action, arrow| bindings.pressed(input, action) || input.pressed(arrow);
+    let x = held(Action::MoveRight, KeyCode::ArrowRight) as i32 - held(Action::MoveLeft, KeyCode::ArrowLeft) as i32;
+    let y = held(Action::MoveUp, KeyCode::ArrowUp) as i32 - held(Action::MoveDown, KeyCode::ArrowDown) as i32;
+    // One axis at a time, horizontal first, so diagonal input can't cut corners between blocked tiles
+    match (x, y) {
+        (0, 0) => None,
+        (x, _) if x != 0 => Some(IVec2::new(x, 0)),
+        (_, y) => Some(IVec2::new(0, y)),
+    }
+}
+
+/// Cell reached by one step from `from`, unless it is off the map or `blocked`
+pub fn step_target(from: IVec2, direction: IVec2, blocked: impl Fn(IVec2) -> bool) -> Option<IVec2> {
+    let target = from + direction;
+    let range = 0..MAP_SIZE as i32;
+    (range.contains(&target.x) && range.contains(&target.y) && !blocked(target)).then_some(target)
+}
+
+/// Step the player one cell per `STEP_INTERVAL` while a movement key is held. Enemy tiles start a
+/// fight when entered, and block the way while another fight is still in progress
+pub fn player_movement(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    time: Res<Time>,
+    mut players: Query<(Entity, &mut Position), With<Player>>,
+    tiles: Query<(Entity, &MapTile)>,
+    mut entered: EventWriter<TileEntered>,
+    combat: Option<Res<CombatState>>,
+    mut cooldown: Local<f32>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    let Some(direction) = movement_direction(&keyboard, &bindings) else {
+        *cooldown = 0.0;
+        return;
+    };
+    *cooldown -= time.delta_seconds();
+    
+    let Ok((player, mut position)) = players.get_single_mut() else { return };
+    let in_fight = combat.map_or(false, |c| c.active_fight.is_some());
+    let tile_at = |cell: IVec2| tiles.iter().find(|(_, tile)| tile.grid_x == cell.x && tile.grid_y == cell.y);
+    // Catch up on every step due this frame so speed doesn't depend on the frame rate
+    while *cooldown <= 0.0 {
+        *cooldown += STEP_INTERVAL;
+        let from = IVec2::new(position.x.round() as i32, position.y.round() as i32);
+        let blocked = |cell| in_fight && tile_at(cell).map_or(false, |(_, tile)| matches!(tile.tile_type, TileType::Enemy));
+        let Some(target) = step_target(from, direction, blocked) else {
+            *cooldown = (*cooldown).max(0.0);
+            break;
+        };
+        position.x = target.x as f32;
+        position.y = target.y as f32;
+        if let Some((tile, map_tile)) = tile_at(target) {
+            entered.send(TileEntered {
+                player,
+                tile,
+                tile_type: map_tile.tile_type.clone(),
+                grid_x: target.x,
+                grid_y: target.y,
+            });
+        }
+    }
+}
+
+/// Resource tiles grant a bundle of collections, validated like any collection; the tile
+/// is used up by `consume_collected_tiles` once the bundle is credited
+pub fn collect_resource_tiles(
+    mut entered: EventReader<TileEntered>,
+    players: Query<(&IdleProgress, Option<&PlayerId>), With<Player>>,
+    mut collections: EventWriter<ResourceCollected>,
+) {
+    for event in entered.read().filter(|e| matches!(e.tile_type, TileType::Resource)) {
+        let Ok((progress, player_id)) = players.get(event.player) else { continue };
+        let player_id = player_id.copied().unwrap_or_default().0;
+        collections.send(ResourceCollected {
+            player_id,
+            amount: collect_amount(progress.level) * RESOURCE_TILE_COLLECTIONS,
+            source: Some(event.tile),
+        });
+    }
+}
+
+/// Empty the tiles whose collections were credited; rejected ones stay for another try
+pub fn consume_collected_tiles(mut credited: EventReader<ResourcesCredited>, mut tiles: Query<&mut MapTile>) {
+    for source in credited.read().filter_map(|e| e.source) {
+        if let Ok(mut tile) = tiles.get_mut(source) {
+            tile.tile_type = TileType::Empty;
+        }
+    }
+}
@@ -1,61 +1,219 @@
 use bevy::prelude::*;
 use bevy::time::common_conditions::on_timer;
-use enet::{Address, Event, Host, Packet, PacketMode, Peer};
-use std::net::Ipv4Addr;
+use enet::{Address, Event, Host, Peer};
 use std::time::Duration;
 use std::sync::Arc;
 use parking_lot::Mutex;
+use crate::resources::{MultiplayerState, PlayerProfile};
+use crate::multiplayer::config::{HostParams, NetConfig};
+use crate::multiplayer::network::{check_protocol_version, log_stats, GameMessage, NetworkManager, PROTOCOL_VERSION};
 
-#[derive(Resource, Default, Clone)]
-pub struct NetConfig { pub host: String, pub port: u16 }
+/// Longest wait between reconnection attempts
+pub const MAX_RECONNECT_DELAY_SECS: f64 = 30.0;
+
+/// Backoff before the attempt following `failed_attempts` failures: 1s, 2s, 4s... capped at 30s
+pub fn reconnect_delay(failed_attempts: u32) -> f64 {
+    2f64.powi(failed_attempts.min(16) as i32).min(MAX_RECONNECT_DELAY_SECS)
+}
+
+/// Whether the game talks to a server at all
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetworkMode {
+    SinglePlayer,
+    #[default]
+    Multiplayer,
+}
+
+impl NetworkMode {
+    /// Read `CQ_NETWORK_MODE` ("single" or "multi"), defaulting to multiplayer
+    pub fn from_env() -> Self {
+        match std::env::var("CQ_NETWORK_MODE").map(|m| m.to_lowercase()) {
+            Ok(mode) if mode == "single" || mode == "singleplayer" => NetworkMode::SinglePlayer,
+            _ => NetworkMode::Multiplayer,
+        }
+    }
+}
 
 #[derive(Resource, Default, Clone)]
-pub struct NetState { pub connected: bool, pub last_rtt: u32, pub last_msg: String }
+pub struct NetState {
+    pub connected: bool,
+    pub last_rtt: u32,
+    pub last_msg: String,
+    /// Protocol version agreed with the server
+    pub protocol_version: Option<u16>,
+    /// A connection attempt is in flight; no other is started until it resolves
+    pub connecting: bool,
+    /// Attempts made since the last successful connection
+    pub reconnect_attempts: u32,
+    /// Elapsed game time before which no new attempt is made
+    pub next_attempt_at: f64,
+    /// Reconnection stopped after `max_reconnect_attempts`
+    pub gave_up: bool,
+}
+
+impl NetState {
+    /// Whether a new connection attempt should start at `now`; records the attempt if so
+    pub fn begin_attempt(&mut self, now: f64, max_attempts: u32) -> bool {
+        if self.connected || self.connecting || self.gave_up || now < self.next_attempt_at {
+            return false;
+        }
+        if self.reconnect_attempts >= max_attempts {
+            self.gave_up = true;
+            self.last_msg = format!("Giving up after {} attempts", self.reconnect_attempts);
+            return false;
+        }
+        self.connecting = true;
+        self.next_attempt_at = now + reconnect_delay(self.reconnect_attempts);
+        self.reconnect_attempts += 1;
+        true
+    }
+    
+    /// Connection established: reset the backoff
+    pub fn on_connected(&mut self) {
+        self.connected = true;
+        self.connecting = false;
+        self.reconnect_attempts = 0;
+        self.next_attempt_at = 0.0;
+        self.gave_up = false;
+    }
+    
+    /// Connection dropped or an attempt failed
+    pub fn on_disconnected(&mut self) {
+        self.connected = false;
+        self.connecting = false;
+        self.protocol_version = None;
+    }
+}
+
+/// Id the client's `NetworkManager` tracks the server connection under
+pub const SERVER_PEER_ID: u32 = 0;
 
 #[derive(Resource)]
 pub struct NetClient {
     pub host: Arc<Mutex<Host>>,
     pub peer: Arc<Mutex<Option<Peer>>>,
+    /// Rate limits, compresses and frames everything sent to the server
+    pub manager: Arc<Mutex<NetworkManager>>,
+    /// Server address, resolved once in `net_setup` so reconnects never block on DNS
+    pub server_addr: Address,
 }
 
 impl NetClient {
-    pub fn new() -> Self {
+    pub fn new(params: HostParams, server_addr: Address) -> Self {
         let _enet = enet::initialize().expect("ENet init");
-        let host = Host::new(None, 1, 2, 0, 0).expect("client host");
-        Self { host: Arc::new(Mutex::new(host)), peer: Arc::new(Mutex::new(None)) }
+        let host = Host::new(None, params.max_peers, params.channels, params.incoming_bandwidth, params.outgoing_bandwidth)
+            .expect("client host");
+        Self {
+            host: Arc::new(Mutex::new(host)),
+            peer: Arc::new(Mutex::new(None)),
+            manager: Arc::new(Mutex::new(NetworkManager::default())),
+            server_addr,
+        }
+    }
+    
+    /// Queue a message to the server through the `NetworkManager`
+    pub fn send_message(&self, message: &GameMessage, reliable: bool) {
+        if let Err(e) = self.manager.lock().send_message(SERVER_PEER_ID, message, reliable) {
+            warn!("Failed to send {:?} to server: {}", message.kind(), e);
+        }
+    }
+    
+    /// Hand the manager's queued packets to the server peer; they are dropped while disconnected
+    pub fn flush(&self) {
+        let mut peer = self.peer.lock();
+        self.manager.lock().flush_with(|packet, enet_packet| {
+            peer.as_mut().map_or(false, |peer| peer.send_packet(enet_packet, packet.channel).is_ok())
+        });
     }
 }
 
-pub fn net_setup(mut commands: Commands) {
-    commands.insert_resource(NetClient::new());
-    commands.insert_resource(NetConfig { host: "127.0.0.1".into(), port: 8080 });
+pub fn net_setup(mut commands: Commands, cfg: Option<Res<NetConfig>>) {
+    // `apply_env` provides the configured address; only fill in defaults if it hasn't
+    let cfg = cfg.map(|c| c.clone()).unwrap_or_default();
+    let client = NetClient::new(cfg.host_params(), Address::new(cfg.resolve_ip(), cfg.port));
+    client.manager.lock().default_rate_limit = cfg.default_rate_limit;
+    commands.insert_resource(client);
+    commands.insert_resource(cfg);
     commands.insert_resource(NetState::default());
+    commands.init_resource::<MultiplayerState>();
 }
 
-pub fn net_connect(client: Res<NetClient>, cfg: Res<NetConfig>, mut state: ResMut<NetState>) {
-    if state.connected { return; }
-    let addr = Address::new(Ipv4Addr::new(127,0,0,1), cfg.port);
-    if let Ok(p) = client.host.lock().connect(&addr, 2, 0) {
-        *client.peer.lock() = Some(p);
+pub fn net_connect(client: Res<NetClient>, cfg: Res<NetConfig>, mut state: ResMut<NetState>, time: Res<Time>) {
+    if !state.begin_attempt(time.elapsed_seconds_f64(), cfg.max_reconnect_attempts) { return; }
+    match client.host.lock().connect(&client.server_addr, 2, 0) {
+        Ok(p) => *client.peer.lock() = Some(p),
+        Err(_) => state.connecting = false,
     }
 }
 
-pub fn net_service(client: Res<NetClient>, mut state: ResMut<NetState>) {
+pub fn net_service(
+    client: Res<NetClient>,
+    mut state: ResMut<NetState>,
+    profile: Option<Res<PlayerProfile>>,
+    mut multiplayer: Option<ResMut<MultiplayerState>>,
+) {
     if let Some(event) = client.host.lock().service(Duration::from_millis(5)).unwrap() {
         match event {
-            Event::Connect(_peer) => { state.connected = true; state.last_msg = "Connected".into(); }
-            Event::Disconnect(_peer, _reason) => { state.connected = false; state.last_msg = "Disconnected".into(); }
-            Event::Receive{packet, ..} => {
-                state.last_msg = format!("Echo {} bytes", packet.data().len());
+            Event::Connect(_peer) => {
+                state.on_connected();
+                state.last_msg = "Connected".into();
+                state.protocol_version = None;
+                client.manager.lock().register_peer(SERVER_PEER_ID);
+                let hello = GameMessage::Hello { protocol_version: PROTOCOL_VERSION };
+                let join = profile.map(|p| p.join_message()).unwrap_or_else(|| PlayerProfile::default().join_message());
+                for message in [hello, join] {
+                    client.send_message(&message, true);
+                }
+            }
+            Event::Disconnect(_peer, _reason) => {
+                state.on_disconnected();
+                state.last_msg = "Disconnected".into();
+                client.manager.lock().remove_peer(SERVER_PEER_ID);
+            }
+            Event::Receive{mut sender, packet, ..} => {
+                let payloads = client.manager.lock().receive_bytes(SERVER_PEER_ID, packet.data());
+                for payload in payloads {
+                    match GameMessage::from_bytes_lenient(&payload) {
+                        Ok(GameMessage::Hello { protocol_version }) => match check_protocol_version(protocol_version) {
+                            Ok(version) => state.protocol_version = Some(version),
+                            Err(reason) => {
+                                warn!("{}", reason);
+                                client.send_message(&GameMessage::Disconnect { reason: reason.clone() }, true);
+                                client.flush();
+                                sender.disconnect_later(0);
+                                state.last_msg = reason;
+                            }
+                        },
+                        Ok(GameMessage::JoinAccepted { player_id }) => {
+                            // Peers know us by the server's id; the local player keeps its own for validation
+                            if let Some(multiplayer) = multiplayer.as_deref_mut() {
+                                multiplayer.player_id = player_id;
+                            }
+                            state.last_msg = format!("Joined as player {}", player_id);
+                        }
+                        Ok(GameMessage::Disconnect { reason }) => state.last_msg = format!("Disconnected: {}", reason),
+                        _ => state.last_msg = format!("Received {} bytes", payload.len()),
+                    }
+                }
             }
             _ => {}
         }
     }
+    client.flush();
 }
 
 pub fn net_ping(client: Res<NetClient>, state: Res<NetState>) {
     if !state.connected { return; }
-    if let Some(peer) = client.peer.lock().as_ref() {
-        let _ = peer.send_packet(Packet::new(b"ping", PacketMode::ReliableSequenced).unwrap(), 0);
+    client.send_message(&GameMessage::Ping, true);
+    client.flush();
+}
+
+/// System to log network statistics; scheduled every 30 seconds with `on_timer`
+pub fn network_statistics(network_manager: Option<Res<NetworkManager>>, client: Option<Res<NetClient>>) {
+    if let Some(network_manager) = network_manager {
+        log_stats(network_manager.get_stats());
+    }
+    if let Some(client) = client {
+        log_stats(client.manager.lock().get_stats());
     }
 }
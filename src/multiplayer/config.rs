@@ -0,0 +1,82 @@
+//! Connection settings shared by the client and the network manager
+
+use bevy::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs};
+
+/// Per-peer packet rate used when nothing else is configured
+pub const DEFAULT_RATE_LIMIT: u32 = 10;
+
+/// Arguments for creating an ENet host.
+///
+/// The bandwidth caps are enforced by ENet in bytes per second (0 = unlimited) and apply
+/// on top of the per-peer `RateLimit`, which counts packets: whichever is tighter wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostParams {
+    pub max_peers: usize,
+    pub channels: usize,
+    pub incoming_bandwidth: u32,
+    pub outgoing_bandwidth: u32,
+}
+
+impl Default for HostParams {
+    fn default() -> Self {
+        Self { max_peers: 4, channels: 2, incoming_bandwidth: 0, outgoing_bandwidth: 0 }
+    }
+}
+
+/// Where the client connects and how its connection is shaped
+#[derive(Resource, Clone)]
+pub struct NetConfig {
+    pub host: String,
+    pub port: u16,
+    /// Consecutive failed connection attempts before giving up
+    pub max_reconnect_attempts: u32,
+    /// ENet bandwidth caps in bytes/sec, 0 for unlimited; see `HostParams`
+    pub incoming_bandwidth: u32,
+    pub outgoing_bandwidth: u32,
+    /// Packets per second each peer may be sent before `NetworkManager` starts refusing sends
+    pub default_rate_limit: u32,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".into(),
+            port: 8080,
+            max_reconnect_attempts: 10,
+            incoming_bandwidth: 0,
+            outgoing_bandwidth: 0,
+            default_rate_limit: DEFAULT_RATE_LIMIT,
+        }
+    }
+}
+
+impl NetConfig {
+    /// Parameters for the client's ENet host: a single server connection
+    pub fn host_params(&self) -> HostParams {
+        HostParams {
+            max_peers: 1,
+            channels: 2,
+            incoming_bandwidth: self.incoming_bandwidth,
+            outgoing_bandwidth: self.outgoing_bandwidth,
+        }
+    }
+    
+    /// Resolve `host` (an IPv4 address or a hostname) to the address to connect to,
+    /// falling back to localhost with a warning. Hostnames block on DNS, so this runs once at startup.
+    pub fn resolve_ip(&self) -> Ipv4Addr {
+        if let Ok(IpAddr::V4(ip)) = self.host.parse::<IpAddr>() {
+            return ip;
+        }
+        let resolved = (self.host.as_str(), self.port).to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.find_map(|addr| match addr.ip() {
+                IpAddr::V4(ip) => Some(ip),
+                IpAddr::V6(_) => None,
+            }));
+        resolved.unwrap_or_else(|| {
+            warn!("Could not resolve host '{}' to an IPv4 address, using localhost", self.host);
+            Ipv4Addr::LOCALHOST
+        })
+    }
+}
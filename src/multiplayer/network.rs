@@ -8,16 +8,121 @@ use std::io::Write;
 use std::collections::HashMap;
 use std::time::{Instant, Duration};
 use serde::{Serialize, Deserialize};
+use crate::security::input_sanitization::{sanitize_text_input, sanitize_username};
+use crate::security::{SecurityManager, ValidationResult};
+use crate::components::NetworkPlayer;
+use crate::resources::{GameState, MultiplayerState};
+use crate::multiplayer::config::{HostParams, NetConfig, DEFAULT_RATE_LIMIT};
+use crate::ai::{Biome, MapGenerator};
+use crate::quest_system::complete_quest_by_id;
 
 /// Network manager resource with rate limiting
 #[derive(Resource, Debug)]
 pub struct NetworkManager {
     pub host: Option<Host<u32>>,
     pub peer_rate_limits: HashMap<u32, RateLimit>,
-    pub compression_enabled: bool,
+    /// Algorithm applied to outgoing payloads over 100 bytes
+    pub compression: CompressionAlgo,
+    /// Log and skip messages with unrecognized tags instead of treating them as parse errors
+    pub ignore_unknown_messages: bool,
+    /// Packets queued by sends, handed to the host on the next flush
+    pub outbox: Vec<OutgoingPacket>,
+    /// Per-peer buffers reassembling length-prefixed frames
+    pub reassembly: HashMap<u32, FrameDecoder>,
+    /// Protocol version negotiated with each peer through `Hello`
+    pub peer_versions: HashMap<u32, u16>,
+    /// When each connected peer that hasn't sent a valid `Hello` yet will be dropped
+    pub handshake_deadlines: HashMap<u32, Instant>,
+    /// Peers to disconnect once their queued packets are flushed
+    pub pending_disconnects: Vec<u32>,
+    /// Packets per second newly connected peers may be sent; `set_peer_rate_limit` overrides it per peer
+    pub default_rate_limit: u32,
     pub stats: NetworkStats,
 }
 
+/// How long a new peer has to send a valid `Hello` before it is disconnected
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ENet channel for reliable control, chat and quest traffic
+pub const CONTROL_CHANNEL: u8 = 0;
+
+/// ENet channel for frequent state updates, so a stalled reliable packet can't hold them up
+pub const STATE_CHANNEL: u8 = 1;
+
+/// Channel a message is sent on
+pub fn channel_for(message: &GameMessage) -> u8 {
+    match message {
+        GameMessage::ResourceUpdate { .. } => STATE_CHANNEL,
+        _ => CONTROL_CHANNEL,
+    }
+}
+
+/// Version of the `GameMessage` protocol spoken by this build
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Check a peer's announced protocol version against ours
+pub fn check_protocol_version(remote: u16) -> Result<u16, String> {
+    if remote == PROTOCOL_VERSION {
+        Ok(remote)
+    } else {
+        Err(format!("Protocol version mismatch: expected {}, got {}", PROTOCOL_VERSION, remote))
+    }
+}
+
+/// Largest frame accepted from a peer; longer length prefixes are treated as corruption
+pub const MAX_FRAME_LEN: usize = 1024 * 1024;
+
+/// Wrap a payload in a frame: u32 big-endian length followed by the payload
+pub fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(payload.len() + 4);
+    framed.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    framed.extend_from_slice(payload);
+    framed
+}
+
+/// Reassembles length-prefixed frames from arbitrarily split or coalesced receives
+#[derive(Debug, Clone, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    /// Append received bytes and return every frame they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        self.buffer.extend_from_slice(bytes);
+        
+        let mut frames = Vec::new();
+        loop {
+            let Some(header) = self.buffer.get(..4) else { break };
+            let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            if len > MAX_FRAME_LEN {
+                self.buffer.clear();
+                return Err(format!("Frame length {} exceeds maximum {}", len, MAX_FRAME_LEN));
+            }
+            if self.buffer.len() < 4 + len {
+                break;
+            }
+            frames.push(self.buffer[4..4 + len].to_vec());
+            self.buffer.drain(..4 + len);
+        }
+        Ok(frames)
+    }
+    
+    /// Bytes buffered towards an incomplete frame
+    pub fn pending_len(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// A packet waiting to be handed to the ENet host
+#[derive(Debug, Clone)]
+pub struct OutgoingPacket {
+    pub peer_id: u32,
+    pub channel: u8,
+    pub data: Vec<u8>,
+    pub reliable: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct RateLimit {
     pub packets_sent: u32,
@@ -32,7 +137,87 @@ pub struct NetworkStats {
     pub bytes_sent: u64,
     pub bytes_received: u64,
     pub compression_ratio: f32,
+    /// Algorithm used for the most recent compressed payload
+    pub last_compression_algo: CompressionAlgo,
+    /// Payloads sent uncompressed because compression didn't shrink them
+    pub compression_skipped: u64,
     pub rate_limit_violations: u32,
+    /// Per-peer sends made by broadcasts that were queued successfully
+    pub broadcast_delivered: u64,
+    /// Per-peer sends made by broadcasts that failed (e.g. rate limited)
+    pub broadcast_failed: u64,
+    pub compression_by_kind: HashMap<MessageKind, CompressionStat>,
+    pub compression_by_algo: HashMap<CompressionAlgo, CompressionStat>,
+}
+
+/// Payload compression algorithm, sent as a one-byte prefix on every packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompressionAlgo {
+    None,
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+impl CompressionAlgo {
+    /// Wire prefix identifying the algorithm
+    pub fn prefix(&self) -> u8 {
+        match self {
+            CompressionAlgo::None => 0,
+            CompressionAlgo::Gzip => 1,
+            CompressionAlgo::Zstd => 2,
+        }
+    }
+    
+    /// Algorithm for a wire prefix
+    pub fn from_prefix(prefix: u8) -> Option<Self> {
+        match prefix {
+            0 => Some(CompressionAlgo::None),
+            1 => Some(CompressionAlgo::Gzip),
+            2 => Some(CompressionAlgo::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl CompressionStat {
+    fn record(&mut self, ratio: f32) {
+        self.samples += 1;
+        let count = self.samples as f32;
+        self.average_ratio = (self.average_ratio * (count - 1.0) + ratio) / count;
+    }
+}
+
+/// Running compression effectiveness for one message kind
+#[derive(Debug, Clone, Default)]
+pub struct CompressionStat {
+    pub samples: u32,
+    pub average_ratio: f32,
+}
+
+impl NetworkStats {
+    /// Record a compressed payload's ratio (compressed / original) for a message kind and algorithm
+    pub fn record_compression(&mut self, kind: MessageKind, algo: CompressionAlgo, original_len: usize, compressed_len: usize) {
+        if original_len == 0 {
+            return;
+        }
+        let ratio = compressed_len as f32 / original_len as f32;
+        self.compression_ratio = ratio;
+        self.last_compression_algo = algo;
+        
+        self.compression_by_kind.entry(kind).or_default().record(ratio);
+        self.compression_by_algo.entry(algo).or_default().record(ratio);
+    }
+    
+    /// Average compression ratio observed for a message kind
+    pub fn compression_ratio_for(&self, kind: MessageKind) -> Option<f32> {
+        self.compression_by_kind.get(&kind).map(|stat| stat.average_ratio)
+    }
+    
+    /// Average compression ratio observed for an algorithm
+    pub fn compression_ratio_for_algo(&self, algo: CompressionAlgo) -> Option<f32> {
+        self.compression_by_algo.get(&algo).map(|stat| stat.average_ratio)
+    }
 }
 
 impl Default for NetworkManager {
@@ -40,7 +225,14 @@ impl Default for NetworkManager {
         Self {
             host: None,
             peer_rate_limits: HashMap::new(),
-            compression_enabled: true,
+            compression: CompressionAlgo::Gzip,
+            ignore_unknown_messages: true,
+            outbox: Vec::new(),
+            reassembly: HashMap::new(),
+            peer_versions: HashMap::new(),
+            handshake_deadlines: HashMap::new(),
+            pending_disconnects: Vec::new(),
+            default_rate_limit: DEFAULT_RATE_LIMIT,
             stats: NetworkStats::default(),
         }
     }
@@ -48,13 +240,13 @@ impl Default for NetworkManager {
 
 impl NetworkManager {
     /// Initialize network manager with rate limiting
-    pub fn initialize(&mut self, max_connections: usize, port: u16) -> Result<(), String> {
+    pub fn initialize(&mut self, port: u16, params: HostParams) -> Result<(), String> {
         let address = enet::Address::new_any(port);
         
-        match Host::new(Some(address), max_connections, 2, 0, 0) {
+        match Host::new(Some(address), params.max_peers, params.channels, params.incoming_bandwidth, params.outgoing_bandwidth) {
             Some(host) => {
                 self.host = Some(host);
-                info!("Network manager initialized on port {} with {} max connections", port, max_connections);
+                info!("Network manager initialized on port {} with {} max connections", port, params.max_peers);
                 Ok(())
             }
             None => Err("Failed to create ENet host".to_string())
@@ -62,78 +254,206 @@ impl NetworkManager {
     }
     
     /// Send packet with rate limiting and compression
-    pub fn send_packet(&mut self, peer_id: u32, data: &[u8], reliable: bool) -> Result<(), String> {
+    pub fn send_packet(&mut self, peer_id: u32, data: &[u8], reliable: bool) -> Result<SendOutcome, String> {
+        self.send_packet_of_kind(peer_id, MessageKind::Raw, CONTROL_CHANNEL, data, reliable)
+    }
+    
+    /// Serialize and send a game message on its `channel_for`, tracking compression per message kind
+    pub fn send_message(&mut self, peer_id: u32, message: &GameMessage, reliable: bool) -> Result<SendOutcome, String> {
+        let data = message.to_bytes()?;
+        self.send_packet_of_kind(peer_id, message.kind(), channel_for(message), &data, reliable)
+    }
+    
+    /// Prefix an outgoing payload with its algorithm byte, compressing payloads
+    /// over 100 bytes when that makes them smaller and recording the ratio under `kind`
+    pub fn prepare_payload(&mut self, kind: MessageKind, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut algo = if data.len() > 100 { self.compression } else { CompressionAlgo::None };
+        let mut body = compress_data(algo, data)?;
+        if algo != CompressionAlgo::None {
+            self.stats.record_compression(kind, algo, data.len(), body.len());
+            if body.len() >= data.len() {
+                self.stats.compression_skipped += 1;
+                algo = CompressionAlgo::None;
+                body = data.to_vec();
+            }
+        }
+        
+        let mut payload = Vec::with_capacity(body.len() + 1);
+        payload.push(algo.prefix());
+        payload.extend_from_slice(&body);
+        Ok(payload)
+    }
+    
+    /// Prepare a payload and wrap it in a length-prefixed frame, ready to hand to a peer
+    pub fn encode_frame(&mut self, kind: MessageKind, data: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(frame(&self.prepare_payload(kind, data)?))
+    }
+    
+    /// Feed raw bytes received from a peer, returning the decoded payloads of all completed frames
+    pub fn receive_bytes(&mut self, peer_id: u32, data: &[u8]) -> Vec<Vec<u8>> {
+        let frames = match self.reassembly.entry(peer_id).or_default().push(data) {
+            Ok(frames) => frames,
+            Err(e) => {
+                warn!("Dropping corrupt stream from peer {}: {}", peer_id, e);
+                return Vec::new();
+            }
+        };
+        
+        frames.into_iter()
+            .filter_map(|payload| match self.decode_payload(&payload) {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    warn!("Failed to decode payload from peer {}: {}", peer_id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+    
+    /// Strip the algorithm prefix from a received payload and decompress it
+    pub fn decode_payload(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let (&prefix, body) = data.split_first().ok_or("Empty payload")?;
+        let algo = CompressionAlgo::from_prefix(prefix)
+            .ok_or_else(|| format!("Unknown compression prefix {}", prefix))?;
+        decompress_data(algo, body)
+    }
+    
+    fn send_packet_of_kind(&mut self, peer_id: u32, kind: MessageKind, channel: u8, data: &[u8], reliable: bool) -> Result<SendOutcome, String> {
+        // Peers without tracking have disconnected; sends racing with that are not errors
+        if !self.peer_rate_limits.contains_key(&peer_id) {
+            debug!("Skipping send to disconnected peer {}", peer_id);
+            return Ok(SendOutcome::Skipped);
+        }
+        
         // Check rate limit
         if !self.check_rate_limit(peer_id) {
             self.stats.rate_limit_violations += 1;
             return Err("Rate limit exceeded".to_string());
         }
         
-        let processed_data = if self.compression_enabled && data.len() > 100 {
-            self.compress_data(data)?
-        } else {
-            data.to_vec()
+        let processed_data = self.prepare_payload(kind, data)?;
+        
+        self.outbox.push(OutgoingPacket {
+            peer_id,
+            channel,
+            data: processed_data,
+            reliable,
+        });
+        Ok(SendOutcome::Queued)
+    }
+    
+    /// Hand queued packets to the ENet host, dropping those for peers that have gone away
+    pub fn flush_outbox(&mut self) -> Result<usize, String> {
+        let Self { host, outbox, stats, pending_disconnects, .. } = self;
+        let Some(host) = host.as_mut() else {
+            return Err("Network not initialized".to_string());
         };
         
-        if let Some(ref mut host) = self.host {
-            let packet_mode = if reliable {
-                PacketMode::ReliableSequenced
-            } else {
-                PacketMode::UnreliableSequenced
-            };
-            
-            match Packet::new(&processed_data, packet_mode) {
-                Some(packet) => {
-                    if let Some(peer) = host.peer(peer_id) {
-                        peer.send_packet(packet, 0);
-                        
-                        // Update stats
-                        self.stats.packets_sent += 1;
-                        self.stats.bytes_sent += processed_data.len() as u64;
-                        
-                        if self.compression_enabled && data.len() > 100 {
-                            self.stats.compression_ratio = (processed_data.len() as f32) / (data.len() as f32);
-                        }
-                        
-                        Ok(())
-                    } else {
-                        Err("Peer not found".to_string())
-                    }
-                }
-                None => Err("Failed to create packet".to_string())
+        let delivered = send_queued(outbox, stats, |outgoing, packet| match host.peer(outgoing.peer_id) {
+            Some(peer) => peer.send_packet(packet, outgoing.channel).is_ok(),
+            None => {
+                debug!("Dropping packet for disconnected peer {}", outgoing.peer_id);
+                false
+            }
+        });
+        for peer_id in pending_disconnects.drain(..) {
+            if let Some(mut peer) = host.peer(peer_id) {
+                peer.disconnect_later(0);
             }
-        } else {
-            Err("Network not initialized".to_string())
         }
+        Ok(delivered)
+    }
+    
+    /// Hand queued packets to `send`, for connections not owned by `host`; see `send_queued`
+    pub fn flush_with(&mut self, send: impl FnMut(&OutgoingPacket, Packet) -> bool) -> usize {
+        send_queued(&mut self.outbox, &mut self.stats, send)
+    }
+    
+    /// Record a peer's `Hello`, disconnecting it if its protocol version doesn't match
+    pub fn handle_hello(&mut self, peer_id: u32, protocol_version: u16) -> bool {
+        match check_protocol_version(protocol_version) {
+            Ok(version) => {
+                self.peer_versions.insert(peer_id, version);
+                self.handshake_deadlines.remove(&peer_id);
+                info!("Peer {} speaks protocol version {}", peer_id, version);
+                true
+            }
+            Err(reason) => {
+                warn!("Disconnecting peer {}: {}", peer_id, reason);
+                self.disconnect_peer(peer_id, reason);
+                false
+            }
+        }
+    }
+    
+    /// Whether a peer has completed the `Hello` handshake
+    pub fn has_negotiated(&self, peer_id: u32) -> bool {
+        self.peer_versions.contains_key(&peer_id)
+    }
+    
+    /// Disconnect peers that haven't completed the handshake by their deadline, returning them
+    pub fn expire_handshakes(&mut self, now: Instant) -> Vec<u32> {
+        let expired: Vec<u32> = self.handshake_deadlines.iter()
+            .filter(|(_, deadline)| now >= **deadline)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+        for &peer_id in &expired {
+            self.handshake_deadlines.remove(&peer_id);
+            warn!("Disconnecting peer {}: no Hello within {:?}", peer_id, HANDSHAKE_TIMEOUT);
+            self.disconnect_peer(peer_id, "Protocol handshake timed out".to_string());
+        }
+        expired
+    }
+    
+    /// Tell a peer why it's being dropped, then disconnect it after the next flush
+    pub fn disconnect_peer(&mut self, peer_id: u32, reason: String) {
+        if let Err(e) = self.send_message(peer_id, &GameMessage::Disconnect { reason }, true) {
+            warn!("Failed to send disconnect reason to peer {}: {}", peer_id, e);
+        }
+        self.pending_disconnects.push(peer_id);
+    }
+    
+    /// Start tracking a newly connected peer
+    pub fn register_peer(&mut self, peer_id: u32) {
+        self.peer_rate_limits.insert(peer_id, RateLimit {
+            packets_sent: 0,
+            last_reset: Instant::now(),
+            max_packets_per_second: self.default_rate_limit,
+        });
+        self.handshake_deadlines.insert(peer_id, Instant::now() + HANDSHAKE_TIMEOUT);
+    }
+    
+    /// Stop tracking a disconnected peer; later sends to it are skipped
+    pub fn remove_peer(&mut self, peer_id: u32) {
+        self.peer_rate_limits.remove(&peer_id);
+        self.reassembly.remove(&peer_id);
+        self.peer_versions.remove(&peer_id);
+        self.handshake_deadlines.remove(&peer_id);
+        self.outbox.retain(|packet| packet.peer_id != peer_id);
     }
     
     /// Process network events with decompression
     pub fn process_events(&mut self) -> Vec<NetworkEvent> {
         let mut events = Vec::new();
         
+        if self.host.is_some() {
+            self.expire_handshakes(Instant::now());
+            if let Err(e) = self.flush_outbox() {
+                warn!("Failed to flush outgoing packets: {}", e);
+            }
+        }
+        
         if let Some(ref mut host) = self.host {
             while let Some(event) = host.service(Duration::from_millis(0)) {
                 match event {
                     Event::Connect(peer) => {
                         let peer_id = peer.data();
                         info!("Peer {} connected", peer_id);
-                        
-                        // Initialize rate limit for new peer
-                        self.peer_rate_limits.insert(peer_id, RateLimit {
-                            packets_sent: 0,
-                            last_reset: Instant::now(),
-                            max_packets_per_second: 10, // Default 10 packets/sec
-                        });
-                        
                         events.push(NetworkEvent::PeerConnected(peer_id));
                     }
                     Event::Disconnect(peer, _) => {
                         let peer_id = peer.data();
                         info!("Peer {} disconnected", peer_id);
-                        
-                        // Clean up rate limit tracking
-                        self.peer_rate_limits.remove(&peer_id);
-                        
                         events.push(NetworkEvent::PeerDisconnected(peer_id));
                     }
                     Event::Receive { sender, data, .. } => {
@@ -143,34 +463,29 @@ impl NetworkManager {
                         self.stats.packets_received += 1;
                         self.stats.bytes_received += data.len() as u64;
                         
-                        // Decompress if needed
-                        let processed_data = if self.compression_enabled && data.len() > 4 {
-                            // Check if data is compressed (simple heuristic)
-                            if data[0] == 0x1f && data[1] == 0x8b {
-                                match self.decompress_data(&data) {
-                                    Ok(decompressed) => decompressed,
-                                    Err(e) => {
-                                        warn!("Failed to decompress data from peer {}: {}", peer_id, e);
-                                        data
-                                    }
-                                }
-                            } else {
-                                data
-                            }
-                        } else {
-                            data
-                        };
-                        
-                        events.push(NetworkEvent::DataReceived {
-                            peer_id,
-                            data: processed_data,
-                        });
+                        events.push(NetworkEvent::DataReceived { peer_id, data });
                     }
                 }
             }
         }
         
-        events
+        // Track peers and reassemble complete messages once the host borrow is released
+        events.into_iter()
+            .flat_map(|event| match event {
+                NetworkEvent::PeerConnected(peer_id) => {
+                    self.register_peer(peer_id);
+                    vec![event]
+                }
+                NetworkEvent::PeerDisconnected(peer_id) => {
+                    self.remove_peer(peer_id);
+                    vec![event]
+                }
+                NetworkEvent::DataReceived { peer_id, data } => self.receive_bytes(peer_id, &data)
+                    .into_iter()
+                    .map(|data| NetworkEvent::DataReceived { peer_id, data })
+                    .collect(),
+            })
+            .collect()
     }
     
     /// Check and update rate limit for peer
@@ -192,25 +507,11 @@ impl NetworkManager {
             rate_limit.packets_sent += 1;
             true
         } else {
-            // No rate limit tracking for this peer yet
+            // Untracked peers are skipped before reaching the limiter
             true
         }
     }
     
-    /// Compress data using gzip
-    fn compress_data(&self, data: &[u8]) -> Result<Vec<u8>, String> {
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
-        encoder.write_all(data).map_err(|e| format!("Compression write error: {}", e))?;
-        encoder.finish().map_err(|e| format!("Compression finish error: {}", e))
-    }
-    
-    /// Decompress data using gzip
-    fn decompress_data(&self, data: &[u8]) -> Result<Vec<u8>, String> {
-        let mut decoder = GzDecoder::new(Vec::new());
-        decoder.write_all(data).map_err(|e| format!("Decompression write error: {}", e))?;
-        decoder.finish().map_err(|e| format!("Decompression finish error: {}", e))
-    }
-    
     /// Get network statistics
     pub fn get_stats(&self) -> &NetworkStats {
         &self.stats
@@ -224,17 +525,174 @@ impl NetworkManager {
         }
     }
     
+    /// Parse and validate a received payload; invalid messages are answered with
+    /// `GameMessage::Rejected` and yield `None`
+    pub fn receive_message(&mut self, peer_id: u32, data: &[u8]) -> Option<GameMessage> {
+        let parsed = if self.ignore_unknown_messages {
+            GameMessage::from_bytes_lenient(data)
+        } else {
+            GameMessage::from_bytes(data)
+        };
+        match parsed {
+            Ok(GameMessage::Unknown { tag }) => {
+                warn!("Ignoring unknown message '{}' from peer {}", tag, peer_id);
+                None
+            }
+            Ok(message) => match sanitize_incoming(message) {
+                Ok(message) => Some(message),
+                Err(reason) => {
+                    warn!("Rejected message from peer {}: {}", peer_id, reason);
+                    if let Err(e) = self.send_message(peer_id, &GameMessage::Rejected { reason }, true) {
+                        warn!("Failed to notify peer {} of rejection: {}", peer_id, e);
+                    }
+                    None
+                }
+            },
+            Err(e) => {
+                warn!("Failed to parse message from peer {}: {}", peer_id, e);
+                None
+            }
+        }
+    }
+    
+    /// Send to each of `peers`; failures (e.g. rate limited) are logged and counted, not fatal.
+    /// Nothing is sent if any of them is not a tracked peer.
+    pub fn send_to(&mut self, peers: &[u32], data: &[u8], reliable: bool) -> Result<SendReport, String> {
+        if let Some(unknown) = peers.iter().find(|peer_id| !self.peer_rate_limits.contains_key(*peer_id)) {
+            return Err(format!("Unknown peer {}", unknown));
+        }
+        self.send_to_of_kind(peers, MessageKind::Raw, CONTROL_CHANNEL, data, reliable)
+    }
+    
     /// Broadcast message to all connected peers
-    pub fn broadcast(&mut self, data: &[u8], reliable: bool) -> Result<(), String> {
-        let peer_ids: Vec<u32> = self.peer_rate_limits.keys().cloned().collect();
-        
-        for peer_id in peer_ids {
-            if let Err(e) = self.send_packet(peer_id, data, reliable) {
-                warn!("Failed to send broadcast to peer {}: {}", peer_id, e);
+    pub fn broadcast(&mut self, data: &[u8], reliable: bool) -> Result<SendReport, String> {
+        let peers = self.connected_peers(None);
+        self.send_to(&peers, data, reliable)
+    }
+    
+    /// Broadcast message to all connected peers except `exclude`
+    pub fn broadcast_except(&mut self, exclude: u32, data: &[u8], reliable: bool) -> Result<SendReport, String> {
+        let peers = self.connected_peers(Some(exclude));
+        self.send_to(&peers, data, reliable)
+    }
+    
+    /// Relay a validated chat message from `sender` to every other peer
+    pub fn relay_chat(&mut self, sender: u32, message: &GameMessage) -> Result<SendReport, String> {
+        let data = message.to_bytes()?;
+        let peers = self.connected_peers(Some(sender));
+        self.send_to_of_kind(&peers, MessageKind::Chat, channel_for(message), &data, true)
+    }
+    
+    /// Tracked peers, optionally leaving one out
+    fn connected_peers(&self, exclude: Option<u32>) -> Vec<u32> {
+        self.peer_rate_limits.keys()
+            .copied()
+            .filter(|peer_id| Some(*peer_id) != exclude)
+            .collect()
+    }
+    
+    fn send_to_of_kind(
+        &mut self,
+        peers: &[u32],
+        kind: MessageKind,
+        channel: u8,
+        data: &[u8],
+        reliable: bool,
+    ) -> Result<SendReport, String> {
+        let mut report = SendReport::default();
+        for &peer_id in peers {
+            match self.send_packet_of_kind(peer_id, kind, channel, data, reliable) {
+                Ok(SendOutcome::Queued) => {
+                    report.delivered += 1;
+                    self.stats.broadcast_delivered += 1;
+                }
+                // Callers only pass tracked peers
+                Ok(SendOutcome::Skipped) => {}
+                Err(e) => {
+                    report.failed += 1;
+                    self.stats.broadcast_failed += 1;
+                    warn!("Failed to send to peer {}: {}", peer_id, e);
+                }
             }
         }
+        Ok(report)
+    }
+}
+
+/// What happened to a single send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// Queued for the next flush
+    Queued,
+    /// Dropped because the peer is no longer tracked, having disconnected
+    Skipped,
+}
+
+/// Per-peer outcome of a multi-peer send
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendReport {
+    /// Peers the packet was queued for
+    pub delivered: usize,
+    /// Peers the send failed for, e.g. because they were rate limited
+    pub failed: usize,
+}
+
+/// Frame each queued packet and hand it to `send`, which returns whether ENet accepted it;
+/// only accepted packets are counted as sent
+fn send_queued(
+    outbox: &mut Vec<OutgoingPacket>,
+    stats: &mut NetworkStats,
+    mut send: impl FnMut(&OutgoingPacket, Packet) -> bool,
+) -> usize {
+    let mut delivered = 0;
+    for outgoing in outbox.drain(..) {
+        let packet_mode = if outgoing.reliable {
+            PacketMode::ReliableSequenced
+        } else {
+            PacketMode::UnreliableSequenced
+        };
         
-        Ok(())
+        let bytes = frame(&outgoing.data);
+        let Some(packet) = Packet::new(&bytes, packet_mode) else {
+            warn!("Failed to create packet for peer {}", outgoing.peer_id);
+            continue;
+        };
+        if send(&outgoing, packet) {
+            stats.packets_sent += 1;
+            stats.bytes_sent += bytes.len() as u64;
+            delivered += 1;
+        }
+    }
+    delivered
+}
+
+/// Compress data with the given algorithm
+fn compress_data(algo: CompressionAlgo, data: &[u8]) -> Result<Vec<u8>, String> {
+    match algo {
+        CompressionAlgo::None => Ok(data.to_vec()),
+        CompressionAlgo::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+            encoder.write_all(data).map_err(|e| format!("Compression write error: {}", e))?;
+            encoder.finish().map_err(|e| format!("Compression finish error: {}", e))
+        }
+        CompressionAlgo::Zstd => {
+            zstd::encode_all(data, 3).map_err(|e| format!("Zstd compression error: {}", e))
+        }
+    }
+}
+
+/// Decompress data with the given algorithm
+fn decompress_data(algo: CompressionAlgo, data: &[u8]) -> Result<Vec<u8>, String> {
+    match algo {
+        CompressionAlgo::None => Ok(data.to_vec()),
+        CompressionAlgo::Gzip => {
+            let mut decoder = GzDecoder::new(Vec::new());
+            decoder.write_all(data).map_err(|e| format!("Decompression write error: {}", e))?;
+            decoder.finish().map_err(|e| format!("Decompression finish error: {}", e))
+        }
+        CompressionAlgo::Zstd => {
+            zstd::decode_all(data).map_err(|e| format!("Zstd decompression error: {}", e))
+        }
     }
 }
 
@@ -247,19 +705,81 @@ pub enum NetworkEvent {
 }
 
 /// Game message types for serialization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GameMessage {
     PlayerJoin { username: String },
     PlayerLeave { player_id: u32 },
     ResourceUpdate { player_id: u32, resources: f32 },
     QuestComplete { player_id: u32, quest_id: u32 },
-    MapGenerate { seed: i64 },
+    MapGenerate {
+        seed: i64,
+        /// Biome dictated by the host; the seed picks one when absent
+        #[serde(default)]
+        biome: Option<Biome>,
+    },
     Chat { player_id: u32, message: String },
     Ping,
     Pong,
+    /// Sent back to a peer whose message failed validation
+    Rejected { reason: String },
+    /// First message on every connection, announcing the sender's protocol version
+    Hello { protocol_version: u16 },
+    /// Sent before dropping a peer
+    Disconnect { reason: String },
+    /// Server reply to `PlayerJoin` carrying the player id it assigned
+    JoinAccepted { player_id: u32 },
+    /// A message from a newer protocol that this build doesn't recognize
+    #[serde(skip)]
+    Unknown { tag: String },
+}
+
+/// Tags of the `GameMessage` variants this build understands
+const KNOWN_MESSAGE_TAGS: &[&str] = &[
+    "PlayerJoin", "PlayerLeave", "ResourceUpdate", "QuestComplete",
+    "MapGenerate", "Chat", "Ping", "Pong", "Rejected", "Hello", "Disconnect",
+    "JoinAccepted",
+];
+
+/// Message kinds used to bucket per-type network statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    PlayerJoin,
+    PlayerLeave,
+    ResourceUpdate,
+    QuestComplete,
+    MapGenerate,
+    Chat,
+    Ping,
+    Pong,
+    Rejected,
+    Hello,
+    Disconnect,
+    JoinAccepted,
+    Unknown,
+    /// Untyped payloads sent through `send_packet`
+    Raw,
 }
 
 impl GameMessage {
+    /// Kind of this message for statistics
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            GameMessage::PlayerJoin { .. } => MessageKind::PlayerJoin,
+            GameMessage::PlayerLeave { .. } => MessageKind::PlayerLeave,
+            GameMessage::ResourceUpdate { .. } => MessageKind::ResourceUpdate,
+            GameMessage::QuestComplete { .. } => MessageKind::QuestComplete,
+            GameMessage::MapGenerate { .. } => MessageKind::MapGenerate,
+            GameMessage::Chat { .. } => MessageKind::Chat,
+            GameMessage::Ping => MessageKind::Ping,
+            GameMessage::Pong => MessageKind::Pong,
+            GameMessage::Rejected { .. } => MessageKind::Rejected,
+            GameMessage::Hello { .. } => MessageKind::Hello,
+            GameMessage::Disconnect { .. } => MessageKind::Disconnect,
+            GameMessage::JoinAccepted { .. } => MessageKind::JoinAccepted,
+            GameMessage::Unknown { .. } => MessageKind::Unknown,
+        }
+    }
+    
     /// Serialize message to bytes
     pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
         serde_json::to_vec(self).map_err(|e| format!("Serialization error: {}", e))
@@ -269,14 +789,165 @@ impl GameMessage {
     pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
         serde_json::from_slice(data).map_err(|e| format!("Deserialization error: {}", e))
     }
+    
+    /// Deserialize message from bytes, mapping well-formed messages with an
+    /// unrecognized tag to `GameMessage::Unknown` instead of failing
+    pub fn from_bytes_lenient(data: &[u8]) -> Result<Self, String> {
+        let error = match serde_json::from_slice(data) {
+            Ok(message) => return Ok(message),
+            Err(e) => format!("Deserialization error: {}", e),
+        };
+        
+        let value: serde_json::Value = serde_json::from_slice(data).map_err(|_| error.clone())?;
+        let tag = match value {
+            serde_json::Value::String(tag) => tag,
+            serde_json::Value::Object(map) if map.len() == 1 => map.into_iter().next().map(|(tag, _)| tag).unwrap_or_default(),
+            _ => return Err(error),
+        };
+        
+        // A known tag with a malformed body is still an error
+        if KNOWN_MESSAGE_TAGS.contains(&tag.as_str()) {
+            return Err(error);
+        }
+        Ok(GameMessage::Unknown { tag })
+    }
+}
+
+/// Validate user-supplied text in an incoming message, returning the sanitized message
+pub fn sanitize_incoming(message: GameMessage) -> Result<GameMessage, String> {
+    match message {
+        GameMessage::Chat { player_id, message } => {
+            let message = sanitize_text_input(&message)?;
+            Ok(GameMessage::Chat { player_id, message })
+        }
+        GameMessage::PlayerJoin { username } => {
+            let username = sanitize_username(&username)?;
+            Ok(GameMessage::PlayerJoin { username })
+        }
+        other => Ok(other),
+    }
+}
+
+/// Dispatch a validated message from `peer_id` to its handler. Until the peer's `Hello` has
+/// been accepted, only `Hello` and `Disconnect` are handled.
+pub fn handle_game_message(message: GameMessage, peer_id: u32, world: &mut World) {
+    let awaiting_hello = world.get_resource::<NetworkManager>()
+        .map_or(false, |network_manager| !network_manager.has_negotiated(peer_id));
+    if awaiting_hello && !matches!(message, GameMessage::Hello { .. } | GameMessage::Disconnect { .. }) {
+        warn!("Dropping {:?} from peer {} before protocol handshake", message.kind(), peer_id);
+        return;
+    }
+    match message {
+        GameMessage::PlayerJoin { username } => {
+            if let Some(mut player) = find_network_player(world, peer_id) {
+                info!("Peer {} joined as {}", peer_id, username);
+                player.username = username;
+            }
+        }
+        GameMessage::Hello { protocol_version } => {
+            if let Some(mut network_manager) = world.get_resource_mut::<NetworkManager>() {
+                network_manager.handle_hello(peer_id, protocol_version);
+            }
+        }
+        GameMessage::PlayerLeave { .. } | GameMessage::Disconnect { .. } => {
+            if let Some(mut player) = find_network_player(world, peer_id) {
+                player.connected = false;
+            }
+        }
+        GameMessage::ResourceUpdate { player_id, resources } => {
+            if player_id != peer_id {
+                warn!("Peer {} sent a resource update for player {}", peer_id, player_id);
+                return;
+            }
+            let previous = find_network_player(world, peer_id).map(|player| player.resources);
+            let Some(previous) = previous else {
+                warn!("Resource update from unknown peer {}", peer_id);
+                return;
+            };
+            
+            let gain = (resources - previous).max(0.0);
+            let result = match world.get_resource::<SecurityManager>() {
+                Some(security) => security.validate_resource_collection(peer_id, gain),
+                None => ValidationResult::Approved,
+            };
+            match result {
+                ValidationResult::Approved => {
+                    if let Some(mut player) = find_network_player(world, peer_id) {
+                        player.resources = resources;
+                    }
+                }
+                other => warn!("Resource update from peer {} not applied: {:?}", peer_id, other),
+            }
+        }
+        GameMessage::QuestComplete { quest_id, .. } => {
+            let result = match world.get_resource::<SecurityManager>() {
+                Some(security) => security.validate_quest_completion(peer_id, quest_id),
+                None => ValidationResult::Approved,
+            };
+            if !matches!(result, ValidationResult::Approved) {
+                warn!("Quest {} completion from peer {} not accepted: {:?}", quest_id, peer_id, result);
+                return;
+            }
+            if complete_quest_by_id(world, quest_id).is_none() {
+                warn!("Peer {} completed quest {}, which is not active", peer_id, quest_id);
+            }
+        }
+        GameMessage::MapGenerate { seed, biome } => {
+            let result = match world.get_resource::<SecurityManager>() {
+                Some(security) => security.validate_map_generation(peer_id),
+                None => ValidationResult::Approved,
+            };
+            if !matches!(result, ValidationResult::Approved) {
+                warn!("Map generation from peer {} not accepted: {:?}", peer_id, result);
+                return;
+            }
+            match world.get_resource_mut::<MapGenerator>() {
+                Some(mut generator) => generator.queued_seeds.push_back((seed, biome)),
+                None => warn!("Map generation requested by peer {} before the generator is ready", peer_id),
+            }
+        }
+        GameMessage::Chat { .. } => {
+            if let Some(mut network_manager) = world.get_resource_mut::<NetworkManager>() {
+                if let Err(e) = network_manager.relay_chat(peer_id, &message) {
+                    warn!("Failed to relay chat from peer {}: {}", peer_id, e);
+                }
+            }
+        }
+        GameMessage::JoinAccepted { player_id } => {
+            if let Some(mut multiplayer) = world.get_resource_mut::<MultiplayerState>() {
+                multiplayer.player_id = player_id;
+            }
+        }
+        GameMessage::Ping => {
+            if let Some(mut network_manager) = world.get_resource_mut::<NetworkManager>() {
+                if let Err(e) = network_manager.send_message(peer_id, &GameMessage::Pong, false) {
+                    warn!("Failed to answer ping from peer {}: {}", peer_id, e);
+                }
+            }
+        }
+        GameMessage::Pong | GameMessage::Rejected { .. } | GameMessage::Unknown { .. } => {
+            debug!("No handler for message from peer {}: {:?}", peer_id, message);
+        }
+    }
+}
+
+fn find_network_player(world: &mut World, peer_id: u32) -> Option<Mut<'_, NetworkPlayer>> {
+    let mut query = world.query::<&mut NetworkPlayer>();
+    query.iter_mut(world).find(|player| player.peer_id == peer_id)
 }
 
 /// System to initialize network manager
-pub fn setup_network_manager(mut commands: Commands) {
-    let mut network_manager = NetworkManager::default();
+pub fn setup_network_manager(mut commands: Commands, cfg: Option<Res<NetConfig>>) {
+    let default_cfg = NetConfig::default();
+    let cfg = cfg.as_deref().unwrap_or(&default_cfg);
+    let mut network_manager = NetworkManager {
+        default_rate_limit: cfg.default_rate_limit,
+        ..Default::default()
+    };
     
-    // Initialize server on port 8080
-    if let Err(e) = network_manager.initialize(4, 8080) {
+    // Listen on the configured port with its bandwidth caps, accepting several peers
+    let params = HostParams { max_peers: HostParams::default().max_peers, ..cfg.host_params() };
+    if let Err(e) = network_manager.initialize(cfg.port, params) {
         error!("Failed to initialize network manager: {}", e);
     } else {
         info!("Network manager initialized successfully");
@@ -289,53 +960,64 @@ pub fn setup_network_manager(mut commands: Commands) {
 pub fn process_network_events(
     mut network_manager: ResMut<NetworkManager>,
     mut commands: Commands,
+    mut game_state: Option<ResMut<GameState>>,
 ) {
     let events = network_manager.process_events();
     
     for event in events {
-        match event {
-            NetworkEvent::PeerConnected(peer_id) => {
-                // Spawn network player entity
-                commands.spawn(crate::components::NetworkPlayer {
-                    peer_id,
-                    username: format!("Player_{}", peer_id),
-                    connected: true,
-                });
-            }
-            NetworkEvent::PeerDisconnected(peer_id) => {
-                // Find and despawn network player entity
-                // This would require a proper query system in a real implementation
-                info!("Cleaning up resources for disconnected peer {}", peer_id);
-            }
-            NetworkEvent::DataReceived { peer_id, data } => {
-                // Process game message
-                match GameMessage::from_bytes(&data) {
-                    Ok(message) => {
-                        info!("Received message from peer {}: {:?}", peer_id, message);
-                        // Handle specific message types here
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse message from peer {}: {}", peer_id, e);
-                    }
-                }
-            }
-        }
+        handle_network_event(event, &mut network_manager, &mut commands, game_state.as_deref_mut());
     }
 }
 
-/// System to send periodic network statistics
-pub fn network_statistics(
-    network_manager: Res<NetworkManager>,
-    time: Res<Time>,
+/// React to one network event: greet and spawn new peers, count players and dispatch messages
+pub fn handle_network_event(
+    event: NetworkEvent,
+    network_manager: &mut NetworkManager,
+    commands: &mut Commands,
+    game_state: Option<&mut GameState>,
 ) {
-    // Log statistics every 30 seconds
-    if time.elapsed_seconds() as u64 % 30 == 0 {
-        let stats = network_manager.get_stats();
-        info!("Network Stats: Sent: {} packets/{} bytes, Received: {} packets/{} bytes, Compression: {:.2}, Rate violations: {}",
-            stats.packets_sent, stats.bytes_sent,
-            stats.packets_received, stats.bytes_received,
-            stats.compression_ratio,
-            stats.rate_limit_violations
-        );
+    match event {
+        NetworkEvent::PeerConnected(peer_id) => {
+            if let Some(game_state) = game_state {
+                game_state.peer_joined();
+            }
+            let hello = GameMessage::Hello { protocol_version: PROTOCOL_VERSION };
+            if let Err(e) = network_manager.send_message(peer_id, &hello, true) {
+                warn!("Failed to greet peer {}: {}", peer_id, e);
+            }
+            
+            // Spawn network player entity
+            commands.spawn(NetworkPlayer {
+                peer_id,
+                username: format!("Player_{}", peer_id),
+                connected: true,
+                resources: 0.0,
+            });
+        }
+        NetworkEvent::PeerDisconnected(peer_id) => {
+            if let Some(game_state) = game_state {
+                game_state.peer_left();
+            }
+            // Find and despawn network player entity
+            // This would require a proper query system in a real implementation
+            info!("Cleaning up resources for disconnected peer {}", peer_id);
+        }
+        NetworkEvent::DataReceived { peer_id, data } => {
+            // Process game message
+            if let Some(message) = network_manager.receive_message(peer_id, &data) {
+                debug!("Received message from peer {}: {:?}", peer_id, message);
+                commands.add(move |world: &mut World| handle_game_message(message, peer_id, world));
+            }
+        }
     }
+}
+
+/// Log a summary of traffic statistics
+pub fn log_stats(stats: &NetworkStats) {
+    info!("Network Stats: Sent: {} packets/{} bytes, Received: {} packets/{} bytes, Compression: {:.2}, Rate violations: {}",
+        stats.packets_sent, stats.bytes_sent,
+        stats.packets_received, stats.bytes_received,
+        stats.compression_ratio,
+        stats.rate_limit_violations
+    );
 }
\ No newline at end of file
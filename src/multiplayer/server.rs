@@ -0,0 +1,251 @@
+//! Socket-independent server logic driven by `src/server/main.rs`
+
+use log::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::multiplayer::network::{
+    check_protocol_version, sanitize_incoming, GameMessage, MessageKind, NetworkManager, NetworkStats, PROTOCOL_VERSION,
+};
+use crate::security::{SecurityManager, ValidationResult, FIRST_REMOTE_PLAYER_ID};
+
+/// How long a shutting-down server keeps servicing the host so disconnects are delivered
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Reason sent to every peer when the server shuts down
+pub const SHUTDOWN_REASON: &str = "Server shutting down";
+
+/// Transport-agnostic view of an ENet server event
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    /// `peer_id` comes from `ServerState::allocate_peer_id`
+    Connected { peer_id: u32, addr: String },
+    Disconnected { peer_id: u32 },
+    Received { peer_id: u32, channel_id: u8, data: Vec<u8> },
+}
+
+/// What the server loop should do in response to an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerAction {
+    /// Send a message back to the sender
+    Reply(GameMessage),
+    /// Send the payload to every other peer
+    Relay(Vec<u8>),
+    /// Send `GameMessage::JoinAccepted` to the sender and relay the announcement to every other peer
+    Joined { player_id: u32, announce: Vec<u8> },
+    /// Disconnect the sender
+    Disconnect,
+    /// Send `GameMessage::Disconnect` with the reason, then disconnect the sender
+    Kick(String),
+    /// Answer the sender with `GameMessage::Pong`
+    Pong,
+    Ignore,
+}
+
+/// What the server knows about one connected peer
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// Server-assigned id; also the key the peer is tracked under
+    pub player_id: u32,
+    /// Set once the peer has completed the `PlayerJoin` handshake
+    pub username: Option<String>,
+    pub addr: String,
+    /// Last resource total accepted from the peer
+    pub resources: f32,
+}
+
+impl Session {
+    pub fn is_authenticated(&self) -> bool {
+        self.username.is_some()
+    }
+}
+
+/// Server-side session bookkeeping
+#[derive(Debug, Default)]
+pub struct ServerState {
+    /// Sessions keyed by player id
+    pub sessions: HashMap<u32, Session>,
+    /// Last player id handed out
+    pub last_player_id: u32,
+    pub security: SecurityManager,
+    pub stats: NetworkStats,
+    /// Frames and compresses payloads the same way clients' `NetworkManager` does
+    pub codec: NetworkManager,
+}
+
+impl ServerState {
+    /// Hand out the id for a new connection, starting at `FIRST_REMOTE_PLAYER_ID`; the server loop
+    /// stores it as the peer's data
+    pub fn allocate_peer_id(&mut self) -> u32 {
+        self.last_player_id = self.last_player_id.max(FIRST_REMOTE_PLAYER_ID - 1) + 1;
+        self.last_player_id
+    }
+    
+    /// Whether a payload relayed from `sender` goes to `peer_id`: every other peer that has joined
+    pub fn relays_to(&self, sender: u32, peer_id: u32) -> bool {
+        peer_id != sender && self.sessions.get(&peer_id).map_or(false, Session::is_authenticated)
+    }
+    
+    /// Payloads of the frames completed by bytes received from a peer
+    pub fn decode(&mut self, peer_id: u32, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.codec.receive_bytes(peer_id, bytes)
+    }
+    
+    /// Encode a serialized payload for the wire
+    pub fn encode(&mut self, kind: MessageKind, data: &[u8]) -> Option<Vec<u8>> {
+        match self.codec.encode_frame(kind, data) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                error!("Failed to encode {:?} payload: {}", kind, e);
+                None
+            }
+        }
+    }
+    
+    /// Encode a message for the wire
+    pub fn encode_message(&mut self, message: &GameMessage) -> Option<Vec<u8>> {
+        let data = message.to_bytes().ok()?;
+        self.encode(message.kind(), &data)
+    }
+    
+    /// Count a packet of `bytes` queued to a peer
+    pub fn record_sent(&mut self, bytes: usize) {
+        self.stats.packets_sent += 1;
+        self.stats.bytes_sent += bytes as u64;
+    }
+}
+
+/// Decide how to react to a single server event
+pub fn handle_event(event: ServerEvent, state: &mut ServerState) -> ServerAction {
+    match event {
+        ServerEvent::Connected { peer_id, addr } => {
+            info!("Peer {} connected from {} ({} online)", peer_id, addr, state.sessions.len() + 1);
+            state.sessions.insert(peer_id, Session { player_id: peer_id, addr, ..Default::default() });
+            ServerAction::Ignore
+        }
+        ServerEvent::Disconnected { peer_id } => {
+            state.sessions.remove(&peer_id);
+            state.codec.remove_peer(peer_id);
+            info!("Peer {} disconnected ({} online)", peer_id, state.sessions.len());
+            ServerAction::Ignore
+        }
+        ServerEvent::Received { peer_id, channel_id, data } => {
+            state.stats.packets_received += 1;
+            state.stats.bytes_received += data.len() as u64;
+            match GameMessage::from_bytes_lenient(&data) {
+                Ok(GameMessage::Unknown { tag }) => {
+                    warn!("Ignoring unknown message '{}' from peer {}", tag, peer_id);
+                    ServerAction::Ignore
+                }
+                Ok(message) => match sanitize_incoming(message) {
+                    Ok(message) => handle_message(message, peer_id, state),
+                    Err(reason) => {
+                        warn!("Rejected message from peer {}: {}", peer_id, reason);
+                        ServerAction::Reply(GameMessage::Rejected { reason })
+                    }
+                },
+                Err(e) => {
+                    warn!("Dropping malformed packet from peer {} on ch {}: {}", peer_id, channel_id, e);
+                    ServerAction::Ignore
+                }
+            }
+        }
+    }
+}
+
+/// Act on a parsed, sanitized message from a connected peer
+fn handle_message(message: GameMessage, peer_id: u32, state: &mut ServerState) -> ServerAction {
+    if state.security.is_banned(peer_id) {
+        return ServerAction::Kick("banned".to_string());
+    }
+    let Some(session) = state.sessions.get_mut(&peer_id) else {
+        warn!("Dropping message from unknown peer {}", peer_id);
+        return ServerAction::Ignore;
+    };
+
+    let authenticated = session.is_authenticated();
+    let handshake = matches!(
+        message,
+        GameMessage::Hello { .. } | GameMessage::Ping | GameMessage::PlayerJoin { .. }
+            | GameMessage::PlayerLeave { .. } | GameMessage::Disconnect { .. }
+    );
+    if !authenticated && !handshake {
+        return reject(peer_id, "Join required before other messages");
+    }
+
+    match message {
+        GameMessage::Hello { protocol_version } => match check_protocol_version(protocol_version) {
+            Ok(_) => ServerAction::Reply(GameMessage::Hello { protocol_version: PROTOCOL_VERSION }),
+            Err(reason) => {
+                warn!("Peer {}: {}", peer_id, reason);
+                ServerAction::Kick(reason)
+            }
+        },
+        GameMessage::Ping => ServerAction::Pong,
+        GameMessage::PlayerJoin { username } => {
+            if authenticated {
+                return reject(peer_id, "Already joined");
+            }
+            info!("Peer {} ({}) joined as {}", peer_id, session.addr, username);
+            session.username = Some(username.clone());
+            let player_id = session.player_id;
+            match (GameMessage::PlayerJoin { username }).to_bytes() {
+                Ok(announce) => ServerAction::Joined { player_id, announce },
+                Err(e) => {
+                    error!("Failed to serialize join announcement: {}", e);
+                    ServerAction::Ignore
+                }
+            }
+        }
+        GameMessage::Chat { player_id, message } => {
+            if player_id != peer_id {
+                return reject(peer_id, "Chat sent on behalf of another player");
+            }
+            relay(&GameMessage::Chat { player_id, message })
+        }
+        GameMessage::ResourceUpdate { player_id, resources } => {
+            if player_id != peer_id {
+                return reject(peer_id, "Resource update for another player");
+            }
+            let gain = (resources - session.resources).max(0.0);
+            match state.security.validate_resource_collection(peer_id, gain) {
+                ValidationResult::Approved => {
+                    session.resources = resources;
+                    relay(&GameMessage::ResourceUpdate { player_id, resources })
+                }
+                other => reject(peer_id, &format!("Resource update not accepted: {:?}", other)),
+            }
+        }
+        GameMessage::QuestComplete { player_id, quest_id } => {
+            if player_id != peer_id {
+                return reject(peer_id, "Quest completion for another player");
+            }
+            match state.security.validate_quest_completion(peer_id, quest_id) {
+                ValidationResult::Approved => ServerAction::Ignore,
+                other => reject(peer_id, &format!("Quest completion not accepted: {:?}", other)),
+            }
+        }
+        GameMessage::MapGenerate { seed, biome } => match state.security.validate_map_generation(peer_id) {
+            ValidationResult::Approved => relay(&GameMessage::MapGenerate { seed, biome }),
+            other => reject(peer_id, &format!("Map generation not accepted: {:?}", other)),
+        },
+        GameMessage::PlayerLeave { .. } | GameMessage::Disconnect { .. } => ServerAction::Disconnect,
+        GameMessage::Pong | GameMessage::Rejected { .. } | GameMessage::JoinAccepted { .. } | GameMessage::Unknown { .. } => {
+            ServerAction::Ignore
+        }
+    }
+}
+
+fn relay(message: &GameMessage) -> ServerAction {
+    match message.to_bytes() {
+        Ok(data) => ServerAction::Relay(data),
+        Err(e) => {
+            error!("Failed to serialize relayed message: {}", e);
+            ServerAction::Ignore
+        }
+    }
+}
+
+fn reject(peer_id: u32, reason: &str) -> ServerAction {
+    warn!("Rejected message from peer {}: {}", peer_id, reason);
+    ServerAction::Reply(GameMessage::Rejected { reason: reason.to_string() })
+}
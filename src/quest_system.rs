@@ -3,9 +3,15 @@
 use bevy::prelude::*;
 use crate::components::*;
 use crate::resources::*;
+use crate::blockchain::client::{mint_sft_reward, BlockchainClient};
+use crate::config::keys::{Action, KeyBindings};
+use crate::config::multiplier::unix_now;
 use serde::{Deserialize, Serialize};
 use rand::prelude::*;
 
+/// Most quests that can be active at once
+pub const MAX_ACTIVE_QUESTS: usize = 3;
+
 /// Quest generation and management resource
 #[derive(Resource, Debug)]
 pub struct QuestManager {
@@ -13,6 +19,8 @@ pub struct QuestManager {
     pub completed_quests: Vec<u32>,
     pub next_quest_id: u32,
     pub quest_timer: f32,
+    /// Index into `active_quests` targeted by reroll and abandon
+    pub selected: usize,
 }
 
 impl Default for QuestManager {
@@ -22,6 +30,102 @@ impl Default for QuestManager {
             completed_quests: Vec::new(),
             next_quest_id: 1,
             quest_timer: 0.0,
+            selected: 0,
+        }
+    }
+}
+
+impl QuestManager {
+    /// Entity of the selected active quest, if any
+    pub fn selected_quest(&self) -> Option<Entity> {
+        self.active_quests.get(self.selected).copied()
+    }
+    
+    /// Drop the selected quest without completing it, keeping the selection in range
+    pub fn remove_selected(&mut self, commands: &mut Commands) -> Option<Entity> {
+        let entity = self.selected_quest()?;
+        self.active_quests.remove(self.selected);
+        commands.entity(entity).despawn();
+        self.clamp_selected();
+        Some(entity)
+    }
+    
+    /// Stop tracking an active quest, keeping the same quest selected when possible
+    pub fn remove_active(&mut self, entity: Entity) {
+        let Some(index) = self.active_quests.iter().position(|&e| e == entity) else { return };
+        self.active_quests.remove(index);
+        if index < self.selected {
+            self.selected -= 1;
+        }
+        self.clamp_selected();
+    }
+    
+    fn clamp_selected(&mut self) {
+        self.selected = self.selected.min(self.active_quests.len().saturating_sub(1));
+    }
+}
+
+/// Resources charged per player level to reroll a quest
+pub const REROLL_COST_PER_LEVEL: f64 = 5.0;
+
+/// Seconds in a UTC day
+pub const SECONDS_PER_DAY: u64 = 86_400;
+/// Number of quests in the daily set
+pub const DAILY_QUEST_COUNT: usize = 3;
+/// Reward multiplier of daily quests over their template
+pub const DAILY_REWARD_BONUS: f32 = 2.0;
+/// Extra reward multiplier per daily already completed today and per day of streak
+pub const DAILY_STREAK_STEP: f32 = 0.25;
+
+/// Marks a quest from the daily set; it completes `duration` seconds after `started_at`
+#[derive(Component, Debug, Clone)]
+pub struct DailyQuest {
+    pub started_at: f64,
+    pub duration: f32,
+}
+
+/// Daily quest progress, reset once per UTC day
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct DailyQuestState {
+    /// UNIX seconds of the last reset
+    pub last_reset: u64,
+    /// Dailies completed since the last reset
+    pub daily_completed: u32,
+    /// Consecutive days, before today, with at least one daily completed
+    pub streak: u32,
+}
+
+impl DailyQuestState {
+    /// Whether `now` (UNIX seconds) falls on a later UTC day than the last reset
+    pub fn needs_reset(&self, now: u64) -> bool {
+        now / SECONDS_PER_DAY > self.last_reset / SECONDS_PER_DAY
+    }
+    
+    /// Start a new day at `now`. However many days were missed this is a single reset,
+    /// and the streak only continues if the previous day had a completion.
+    pub fn reset(&mut self, now: u64) {
+        let consecutive = self.last_reset > 0
+            && now / SECONDS_PER_DAY == self.last_reset / SECONDS_PER_DAY + 1;
+        self.streak = if consecutive && self.daily_completed > 0 { self.streak + 1 } else { 0 };
+        self.daily_completed = 0;
+        self.last_reset = now;
+    }
+    
+    /// Reward multiplier for the next daily completed
+    pub fn reward_multiplier(&self) -> f32 {
+        1.0 + DAILY_STREAK_STEP * (self.daily_completed + self.streak) as f32
+    }
+    
+    /// Count a completion, returning the multiplier it earned
+    pub fn record_completion(&mut self) -> f32 {
+        let multiplier = self.reward_multiplier();
+        self.daily_completed += 1;
+        multiplier
+    }
+    
+    fn persist(&self, db: &DatabaseConnection, slot: u32) {
+        if let Err(e) = db.save_daily_quests(slot, self.last_reset, self.daily_completed, self.streak) {
+            warn!("Failed to save daily quests: {}", e);
         }
     }
 }
@@ -34,9 +138,51 @@ pub struct QuestTemplate {
     pub reward_resources: f32,
     pub completion_time: f32,
     pub difficulty: QuestDifficulty,
+    /// Themes such as "combat", "gathering" or "exploration"
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Player preference for quest themes; biases template selection without filtering
+#[derive(Resource, Debug, Clone)]
+pub struct QuestPreferences {
+    pub preferred_tags: Vec<String>,
+    /// Selection weight of templates matching a preferred tag (others weigh 1.0)
+    pub preference_weight: f32,
+}
+
+impl Default for QuestPreferences {
+    fn default() -> Self {
+        Self {
+            preferred_tags: Vec::new(),
+            preference_weight: 3.0,
+        }
+    }
+}
+
+impl QuestPreferences {
+    /// Read comma-separated preferred tags from `CQ_QUEST_TAGS`
+    pub fn from_env() -> Self {
+        let preferred_tags = std::env::var("CQ_QUEST_TAGS")
+            .map(|tags| {
+                tags.split(',')
+                    .map(|t| t.trim().to_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { preferred_tags, ..Default::default() }
+    }
+    
+    /// Selection weight for a template
+    pub fn weight_for(&self, template: &QuestTemplate) -> f32 {
+        let preferred = template.tags.iter()
+            .any(|tag| self.preferred_tags.iter().any(|p| p.eq_ignore_ascii_case(tag)));
+        if preferred { self.preference_weight } else { 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum QuestDifficulty {
     Easy,
     Medium,
@@ -53,55 +199,338 @@ impl QuestDifficulty {
             QuestDifficulty::Epic => 8.0,
         }
     }
+    
+    /// Relative chance of a template of this difficulty at a player level; 0 means never
+    pub fn level_weight(&self, player_level: u32) -> f32 {
+        use QuestDifficulty::*;
+        match (player_level, self) {
+            (0..=5, Easy) => 1.0,
+            (6..=15, Easy) => 0.7,
+            (6..=15, Medium) => 0.3,
+            (16..=30, Easy | Medium | Hard) => 1.0,
+            (31.., Medium | Epic) => 1.0,
+            (31.., Hard) => 2.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// What a completed quest paid out
+#[derive(Debug, Clone)]
+pub struct QuestRewardReceipt {
+    pub quest_id: u32,
+    pub resources: f32,
+    /// SFT to mint, if the quest carried one
+    pub sft: Option<SFTAttributes>,
+}
+
+/// Apply a completed quest's rewards to a player and record the completion once
+pub fn apply_quest_reward(player: &mut IdleProgress, quest: &Quest, manager: &mut QuestManager) -> QuestRewardReceipt {
+    player.resources += quest.reward_resources as f64;
+    if !manager.completed_quests.contains(&quest.id) {
+        manager.completed_quests.push(quest.id);
+    }
+    QuestRewardReceipt {
+        quest_id: quest.id,
+        resources: quest.reward_resources,
+        sft: quest.reward_sft.clone(),
+    }
+}
+
+/// Mint the SFT from a reward receipt, if any
+fn mint_receipt_sft(
+    receipt: &QuestRewardReceipt,
+    blockchain_client: &BlockchainClient,
+    db: &DatabaseConnection,
+    blockchain: &mut BlockchainState,
+) {
+    if let Some(ref sft_attributes) = receipt.sft {
+        info!("SFT reward earned: {:?}", sft_attributes);
+        match mint_sft_reward(blockchain_client, sft_attributes, db, blockchain) {
+            Ok(token_id) => info!("SFT reward {} queued for minting", token_id),
+            Err(e) => error!("Failed to mint SFT reward: {}", e),
+        }
+    }
+}
+
+/// Complete the active quest `quest_id` for a validated networked completion, rewarding the
+/// local player through `apply_quest_reward` like a local completion
+pub fn complete_quest_by_id(world: &mut World, quest_id: u32) -> Option<QuestRewardReceipt> {
+    if !world.contains_resource::<QuestManager>() {
+        return None;
+    }
+    let mut quests = world.query_filtered::<(Entity, &Quest), Without<DailyQuest>>();
+    let (entity, quest) = quests.iter(world)
+        .find(|(_, quest)| quest.id == quest_id && !quest.completed)
+        .map(|(entity, quest)| (entity, quest.clone()))?;
+    
+    let receipt = world.resource_scope(|world, mut quest_manager: Mut<QuestManager>| {
+        let mut players = world.query_filtered::<&mut IdleProgress, With<Player>>();
+        let mut player_progress = players.get_single_mut(world).ok()?;
+        let receipt = apply_quest_reward(&mut player_progress, &quest, &mut quest_manager);
+        quest_manager.remove_active(entity);
+        Some(receipt)
+    })?;
+    world.despawn(entity);
+    info!("Quest completed over the network! Gained {} resources. Quest: {}", receipt.resources, quest.name);
+    world.send_event(QuestCompleted { quest_id: receipt.quest_id, resources: receipt.resources });
+    
+    let client = world.get_resource::<BlockchainClient>().cloned();
+    let db = world.get_resource::<DatabaseConnection>().cloned();
+    if let (Some(client), Some(db)) = (client, db) {
+        if let Some(mut blockchain) = world.get_resource_mut::<BlockchainState>() {
+            mint_receipt_sft(&receipt, &client, &db, &mut blockchain);
+        }
+    }
+    Some(receipt)
 }
 
 /// Initialize quest system
 pub fn setup_quest_system(mut commands: Commands) {
     commands.insert_resource(QuestManager::default());
+    commands.insert_resource(QuestPreferences::from_env());
     info!("Quest system initialized");
 }
 
+/// Restore daily quest progress for the current save slot
+pub fn load_daily_quest_state(mut commands: Commands, db: Res<DatabaseConnection>, game_state: Res<GameState>) {
+    let state = db.load_daily_quests(game_state.save_slot)
+        .map(|(last_reset, daily_completed, streak)| DailyQuestState { last_reset, daily_completed, streak })
+        .unwrap_or_default();
+    commands.insert_resource(state);
+}
+
+/// Spawn the fixed daily set (minus `skip` already completed today) with boosted rewards
+pub fn spawn_daily_quests(
+    commands: &mut Commands,
+    quest_manager: &mut QuestManager,
+    player_level: u32,
+    skip: usize,
+    started_at: f64,
+) -> Vec<Entity> {
+    get_quest_templates()
+        .into_iter()
+        .take(DAILY_QUEST_COUNT)
+        .skip(skip)
+        .map(|template| {
+            let quest_id = quest_manager.next_quest_id;
+            quest_manager.next_quest_id += 1;
+            let reward = template.reward_resources * DAILY_REWARD_BONUS * (player_level as f32).sqrt();
+            let quest = Quest {
+                id: quest_id,
+                name: format!("Daily: {}", template.name_template.replace("{level}", &player_level.to_string())),
+                description: template.description_template.replace("{reward}", &reward.round().to_string()),
+                completed: false,
+                reward_resources: reward,
+                reward_sft: None,
+                objective: QuestObjective::new(ObjectiveKind::Elapsed, template.completion_time),
+            };
+            commands.spawn((quest, DailyQuest { started_at, duration: template.completion_time })).id()
+        })
+        .collect()
+}
+
+/// Once per UTC day, replace the daily set and clear daily progress. Also restores the
+/// day's remaining dailies after a restart.
+pub fn reset_daily_quests(
+    mut commands: Commands,
+    state: Option<ResMut<DailyQuestState>>,
+    mut quest_manager: ResMut<QuestManager>,
+    dailies: Query<Entity, With<DailyQuest>>,
+    players: Query<&IdleProgress, With<Player>>,
+    db: Option<Res<DatabaseConnection>>,
+    game_state: Option<Res<GameState>>,
+    time: Res<Time>,
+    mut restored: Local<bool>,
+) {
+    let Some(mut state) = state else { return };
+    let level = players.get_single().map_or(1, |p| p.level);
+    let now = unix_now() as u64;
+    
+    if state.needs_reset(now) {
+        for entity in dailies.iter() {
+            commands.entity(entity).despawn();
+        }
+        state.reset(now);
+        spawn_daily_quests(&mut commands, &mut quest_manager, level, 0, time.elapsed_seconds_f64());
+        info!("Daily quests reset (streak: {})", state.streak);
+        if let Some(db) = db {
+            state.persist(&db, game_state.map_or(DEFAULT_SAVE_SLOT, |s| s.save_slot));
+        }
+    } else if !*restored && dailies.is_empty() {
+        let skip = state.daily_completed as usize;
+        spawn_daily_quests(&mut commands, &mut quest_manager, level, skip, time.elapsed_seconds_f64());
+    }
+    *restored = true;
+}
+
+/// Complete dailies whose time is up, paying the escalating streak bonus
+pub fn complete_daily_quests(
+    mut commands: Commands,
+    mut dailies: Query<(Entity, &mut Quest, &DailyQuest)>,
+    mut players: Query<&mut IdleProgress, With<Player>>,
+    mut quest_manager: ResMut<QuestManager>,
+    state: Option<ResMut<DailyQuestState>>,
+    db: Option<Res<DatabaseConnection>>,
+    game_state: Option<Res<GameState>>,
+    time: Res<Time>,
+) {
+    let Some(mut state) = state else { return };
+    let now = time.elapsed_seconds_f64();
+    let mut completed_any = false;
+    for (entity, mut quest, daily) in dailies.iter_mut() {
+        if quest.completed || now - daily.started_at < daily.duration as f64 {
+            continue;
+        }
+        let Ok(mut player) = players.get_single_mut() else { break };
+        quest.completed = true;
+        quest.reward_resources *= state.record_completion();
+        let receipt = apply_quest_reward(&mut player, &quest, &mut quest_manager);
+        info!("Daily quest completed! Gained {} resources. Quest: {}", receipt.resources, quest.name);
+        commands.entity(entity).despawn();
+        completed_any = true;
+    }
+    if completed_any {
+        if let Some(db) = db {
+            state.persist(&db, game_state.map_or(DEFAULT_SAVE_SLOT, |s| s.save_slot));
+        }
+    }
+}
+
 /// Generate new quests periodically
 pub fn generate_quests(
     mut commands: Commands,
     mut quest_manager: ResMut<QuestManager>,
     time: Res<Time>,
     query: Query<&IdleProgress, With<Player>>,
+    preferences: Option<Res<QuestPreferences>>,
+    mut rng: Option<ResMut<GameRng>>,
 ) {
+    let mut fallback_rng = GameRng::default();
+    let rng = rng.as_deref_mut().unwrap_or(&mut fallback_rng);
     quest_manager.quest_timer += time.delta_seconds();
     
-    // Generate new quest every 30 seconds if less than 3 active
-    if quest_manager.quest_timer >= 30.0 && quest_manager.active_quests.len() < 3 {
+    // Generate new quest every 30 seconds if fewer than the maximum are active
+    if quest_manager.quest_timer >= 30.0 && quest_manager.active_quests.len() < MAX_ACTIVE_QUESTS {
         if let Ok(player_progress) = query.get_single() {
-            let quest_entity = spawn_quest(&mut commands, &mut quest_manager, player_progress.level);
+            let default_preferences = QuestPreferences::default();
+            let preferences = preferences.as_deref().unwrap_or(&default_preferences);
+            let quest_entity = spawn_quest(&mut commands, &mut quest_manager, player_progress.level, preferences, rng);
             quest_manager.active_quests.push(quest_entity);
             quest_manager.quest_timer = 0.0;
         }
     }
 }
 
-/// Spawn a new quest entity
-fn spawn_quest(commands: &mut Commands, quest_manager: &mut QuestManager, player_level: u32) -> Entity {
-    let mut rng = rand::thread_rng();
+/// Stepping onto a quest tile offers a quest right away; the tile is used up only if one was taken
+pub fn quest_tile_encounters(
+    mut commands: Commands,
+    mut entered: EventReader<crate::movement::TileEntered>,
+    mut quest_manager: ResMut<QuestManager>,
+    players: Query<&IdleProgress, With<Player>>,
+    mut tiles: Query<&mut MapTile>,
+    preferences: Option<Res<QuestPreferences>>,
+    mut rng: Option<ResMut<GameRng>>,
+) {
+    let mut fallback_rng = GameRng::default();
+    let rng = rng.as_deref_mut().unwrap_or(&mut fallback_rng);
+    let default_preferences = QuestPreferences::default();
+    let preferences = preferences.as_deref().unwrap_or(&default_preferences);
+    for event in entered.read().filter(|e| matches!(e.tile_type, TileType::Quest)) {
+        if quest_manager.active_quests.len() >= MAX_ACTIVE_QUESTS {
+            continue;
+        }
+        let Ok(progress) = players.get(event.player) else { continue };
+        let quest_entity = spawn_quest(&mut commands, &mut quest_manager, progress.level, preferences, rng);
+        quest_manager.active_quests.push(quest_entity);
+        if let Ok(mut tile) = tiles.get_mut(event.tile) {
+            tile.tile_type = TileType::Empty;
+        }
+    }
+}
+
+/// Run quest generation as if `seconds` had passed, spawning any quests that would have appeared
+pub fn fast_forward_quests<R: Rng + ?Sized>(
+    commands: &mut Commands,
+    quest_manager: &mut QuestManager,
+    player_level: u32,
+    preferences: &QuestPreferences,
+    seconds: f32,
+    rng: &mut R,
+) {
+    quest_manager.quest_timer += seconds;
+    while quest_manager.quest_timer >= 30.0 && quest_manager.active_quests.len() < MAX_ACTIVE_QUESTS {
+        let quest_entity = spawn_quest(commands, quest_manager, player_level, preferences, rng);
+        quest_manager.active_quests.push(quest_entity);
+        quest_manager.quest_timer -= 30.0;
+    }
+}
+
+/// Pick a template whose difficulty suits the player's level, weighting those matching the
+/// preferred tags. Falls back to the easiest template if none suits the level.
+pub fn select_template<'a, R: Rng + ?Sized>(
+    templates: &'a [QuestTemplate],
+    player_level: u32,
+    preferences: &QuestPreferences,
+    rng: &mut R,
+) -> Option<&'a QuestTemplate> {
+    templates
+        .choose_weighted(rng, |template| {
+            template.difficulty.level_weight(player_level) * preferences.weight_for(template)
+        })
+        .ok()
+        .or_else(|| templates.iter().min_by_key(|template| template.difficulty))
+}
+
+/// Objective for a generated quest: gathering quests count collected resources, exploration
+/// quests count levels and the rest complete after `reward / 10` seconds
+pub fn objective_for(template: &QuestTemplate, reward: f32) -> QuestObjective {
+    let has_tag = |tag: &str| template.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+    if has_tag("gathering") {
+        QuestObjective::new(ObjectiveKind::CollectResources, (reward * 5.0).round())
+    } else if has_tag("exploration") {
+        QuestObjective::new(ObjectiveKind::GainLevels, 1.0)
+    } else {
+        QuestObjective::new(ObjectiveKind::Elapsed, reward / 10.0)
+    }
+}
+
+/// Advance quest objectives from the time passed, idle production, approved collections and level-ups.
+/// Spending, rewards and rejected collections don't count toward collection objectives.
+pub fn advance_quest_objectives(
+    mut quests: Query<&mut Quest>,
+    time: Res<Time>,
+    mut produced: EventReader<ResourcesProduced>,
+    mut credited: EventReader<ResourcesCredited>,
+    mut level_ups: EventReader<LevelUpEvent>,
+) {
+    let resources_gained = produced.read().map(|e| e.amount).sum::<f64>()
+        + credited.read().map(|e| e.amount).sum::<f64>();
+    let levels_gained: u32 = level_ups.read().map(|e| e.new_level.saturating_sub(e.old_level)).sum();
     
-    let templates = get_quest_templates();
-    let template = templates.choose(&mut rng).unwrap();
-    
-    let difficulty = match player_level {
-        1..=5 => QuestDifficulty::Easy,
-        6..=15 => if rng.gen_bool(0.7) { QuestDifficulty::Easy } else { QuestDifficulty::Medium },
-        16..=30 => match rng.gen_range(0..3) {
-            0 => QuestDifficulty::Easy,
-            1 => QuestDifficulty::Medium,
-            _ => QuestDifficulty::Hard,
-        },
-        _ => match rng.gen_range(0..4) {
-            0 => QuestDifficulty::Medium,
-            1 => QuestDifficulty::Hard,
-            2 => QuestDifficulty::Hard,
-            _ => QuestDifficulty::Epic,
+    for mut quest in quests.iter_mut().filter(|q| !q.completed) {
+        let amount = match quest.objective.kind {
+            ObjectiveKind::Elapsed => time.delta_seconds(),
+            ObjectiveKind::CollectResources => resources_gained as f32,
+            ObjectiveKind::GainLevels => levels_gained as f32,
+        };
+        if amount > 0.0 {
+            quest.objective.advance(amount);
         }
-    };
+    }
+}
+
+/// Spawn a new quest entity
+fn spawn_quest<R: Rng + ?Sized>(
+    commands: &mut Commands,
+    quest_manager: &mut QuestManager,
+    player_level: u32,
+    preferences: &QuestPreferences,
+    rng: &mut R,
+) -> Entity {
+    let templates = get_quest_templates();
+    let template = select_template(&templates, player_level, preferences, rng).unwrap();
+    let difficulty = template.difficulty;
     
     let base_reward = template.reward_resources * difficulty.reward_multiplier();
     let level_multiplier = (player_level as f32).sqrt();
@@ -133,6 +562,7 @@ fn spawn_quest(commands: &mut Commands, quest_manager: &mut QuestManager, player
         completed: false,
         reward_resources: final_reward,
         reward_sft: sft_reward,
+        objective: objective_for(template, final_reward),
     };
     
     info!("Generated quest: {} (ID: {})", quest.name, quest.id);
@@ -140,49 +570,110 @@ fn spawn_quest(commands: &mut Commands, quest_manager: &mut QuestManager, player
     commands.spawn(quest).id()
 }
 
+/// Cycle the selected active quest (Tab by default)
+pub fn select_quest(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut quest_manager: ResMut<QuestManager>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::SelectQuest) && !quest_manager.active_quests.is_empty() {
+        quest_manager.selected = (quest_manager.selected + 1) % quest_manager.active_quests.len();
+    }
+}
+
+/// Replace the selected quest with a freshly generated one for a small resource fee (R by default)
+pub fn reroll_quest(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut quest_manager: ResMut<QuestManager>,
+    mut players: Query<&mut IdleProgress, With<Player>>,
+    preferences: Option<Res<QuestPreferences>>,
+    mut rng: Option<ResMut<GameRng>>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if !bindings.just_pressed(&keyboard, Action::RerollQuest) || quest_manager.selected_quest().is_none() {
+        return;
+    }
+    let Ok(mut player) = players.get_single_mut() else { return };
+    let cost = REROLL_COST_PER_LEVEL * player.level as f64;
+    if player.resources < cost {
+        info!("Rerolling a quest costs {:.0} resources, only {:.1} available", cost, player.resources);
+        return;
+    }
+    
+    player.resources -= cost;
+    let index = quest_manager.selected;
+    quest_manager.remove_selected(&mut commands);
+    let default_preferences = QuestPreferences::default();
+    let preferences = preferences.as_deref().unwrap_or(&default_preferences);
+    let mut fallback_rng = GameRng::default();
+    let rng = rng.as_deref_mut().unwrap_or(&mut fallback_rng);
+    let replacement = spawn_quest(&mut commands, &mut quest_manager, player.level, preferences, rng);
+    let index = index.min(quest_manager.active_quests.len());
+    quest_manager.active_quests.insert(index, replacement);
+    quest_manager.selected = index;
+    info!("Quest rerolled for {:.0} resources", cost);
+}
+
+/// Drop the selected quest without a replacement (X by default)
+pub fn abandon_quest(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut quest_manager: ResMut<QuestManager>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::AbandonQuest) {
+        if let Some(entity) = quest_manager.remove_selected(&mut commands) {
+            info!("Quest {:?} abandoned", entity);
+        }
+    }
+}
+
 /// Process quest completion
 pub fn process_quest_completion(
     mut commands: Commands,
     mut quest_manager: ResMut<QuestManager>,
     mut player_query: Query<&mut IdleProgress, With<Player>>,
-    mut quest_query: Query<(Entity, &mut Quest)>,
-    time: Res<Time>,
+    mut quest_query: Query<(Entity, &mut Quest), Without<DailyQuest>>,
+    db: Res<DatabaseConnection>,
+    blockchain_client: Res<BlockchainClient>,
+    mut blockchain: ResMut<BlockchainState>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut completions: EventWriter<QuestCompleted>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::KeyQ) {
-        // Complete oldest active quest when Q is pressed
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard_input, Action::Quest) {
+        // Complete oldest active quest when the quest key is pressed
         if let Some(&quest_entity) = quest_manager.active_quests.first() {
             if let Ok((entity, mut quest)) = quest_query.get_mut(quest_entity) {
                 if !quest.completed {
                     quest.completed = true;
-                    quest_manager.completed_quests.push(quest.id);
                     
                     // Reward player
                     if let Ok(mut player_progress) = player_query.get_single_mut() {
-                        player_progress.resources += quest.reward_resources;
-                        info!("Quest completed! Gained {} resources. Quest: {}", quest.reward_resources, quest.name);
-                        
-                        // TODO: Trigger SFT minting if quest.reward_sft is Some
-                        if let Some(ref sft_attributes) = quest.reward_sft {
-                            info!("SFT reward earned: {:?}", sft_attributes);
-                            // This will be connected to smart contract minting
-                        }
+                        let receipt = apply_quest_reward(&mut player_progress, &quest, &mut quest_manager);
+                        info!("Quest completed! Gained {} resources. Quest: {}", receipt.resources, quest.name);
+                        completions.send(QuestCompleted { quest_id: receipt.quest_id, resources: receipt.resources });
+                        mint_receipt_sft(&receipt, &blockchain_client, &db, &mut blockchain);
                     }
                     
                     // Remove from active quests
-                    quest_manager.active_quests.retain(|&e| e != entity);
+                    quest_manager.remove_active(entity);
                     commands.entity(entity).despawn();
                 }
             }
         }
     }
     
-    // Auto-complete quests after their completion time
-    let current_time = time.elapsed_seconds();
+    // Auto-complete quests whose objective is met
     let mut completed_entities = Vec::new();
     
     for (entity, mut quest) in quest_query.iter_mut() {
-        if !quest.completed && current_time >= quest.reward_resources / 10.0 { // Simple time-based completion
+        if !quest.completed && quest.objective.is_complete() {
             quest.completed = true;
             completed_entities.push(entity);
         }
@@ -190,15 +681,20 @@ pub fn process_quest_completion(
     
     for entity in completed_entities {
         if let Ok((_, quest)) = quest_query.get(entity) {
-            quest_manager.completed_quests.push(quest.id);
-            quest_manager.active_quests.retain(|&e| e != entity);
+            if let Ok(mut player_progress) = player_query.get_single_mut() {
+                let receipt = apply_quest_reward(&mut player_progress, quest, &mut quest_manager);
+                info!("Quest auto-completed! Gained {} resources. Quest: {}", receipt.resources, quest.name);
+                completions.send(QuestCompleted { quest_id: receipt.quest_id, resources: receipt.resources });
+                mint_receipt_sft(&receipt, &blockchain_client, &db, &mut blockchain);
+            }
+            quest_manager.remove_active(entity);
             commands.entity(entity).despawn();
         }
     }
 }
 
 /// Get predefined quest templates
-fn get_quest_templates() -> Vec<QuestTemplate> {
+pub fn get_quest_templates() -> Vec<QuestTemplate> {
     vec![
         QuestTemplate {
             name_template: "Collect Ancient Crystals (Lv.{level})".to_string(),
@@ -206,6 +702,7 @@ fn get_quest_templates() -> Vec<QuestTemplate> {
             reward_resources: 50.0,
             completion_time: 60.0,
             difficulty: QuestDifficulty::Easy,
+            tags: vec!["gathering".to_string()],
         },
         QuestTemplate {
             name_template: "Defeat Shadow Beasts (Lv.{level})".to_string(),
@@ -213,6 +710,7 @@ fn get_quest_templates() -> Vec<QuestTemplate> {
             reward_resources: 100.0,
             completion_time: 120.0,
             difficulty: QuestDifficulty::Medium,
+            tags: vec!["combat".to_string()],
         },
         QuestTemplate {
             name_template: "Explore Lost Dungeons (Lv.{level})".to_string(),
@@ -220,6 +718,7 @@ fn get_quest_templates() -> Vec<QuestTemplate> {
             reward_resources: 200.0,
             completion_time: 300.0,
             difficulty: QuestDifficulty::Hard,
+            tags: vec!["exploration".to_string()],
         },
         QuestTemplate {
             name_template: "Conquer Dragon's Lair (Lv.{level})".to_string(),
@@ -227,6 +726,7 @@ fn get_quest_templates() -> Vec<QuestTemplate> {
             reward_resources: 500.0,
             completion_time: 600.0,
             difficulty: QuestDifficulty::Epic,
+            tags: vec!["combat".to_string()],
         },
     ]
 }
\ No newline at end of file
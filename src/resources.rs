@@ -1,103 +1,321 @@
 //! Game resources and global state
 
 use bevy::prelude::*;
-use rusqlite::{Connection, Result};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rusqlite::{Connection, DatabaseName, Result};
+use serde::{Deserialize, Serialize};
 use serde_json;
-use crate::components::IdleProgress;
+use crate::components::{GeneratorKind, Health, IdleProgress, PlayerStats};
+use crate::multiplayer::network::GameMessage;
+use crate::security::PlayerActionHistory;
+use crate::security::input_sanitization::sanitize_username;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Save slot used when no other profile is selected
+pub const DEFAULT_SAVE_SLOT: u32 = 1;
+
+/// World slot used when no other world is selected; maps stored before worlds had slots belong to it
+pub const DEFAULT_WORLD_SLOT: u32 = 0;
 
 /// Global game state
-#[derive(Resource, Default)]
+#[derive(Resource)]
 pub struct GameState {
     pub current_map_seed: i64,
+    /// Portals taken since the first map; deeper maps are harder
+    pub map_depth: u32,
     pub multiplayer_connected: bool,
     pub blockchain_connected: bool,
+    /// Players in the session, including the local one
     pub total_players: usize,
+    /// Profile whose progress, stats and quests are loaded and saved
+    pub save_slot: u32,
+    /// World whose maps, active seed and portal depth are loaded and saved
+    pub world_slot: u32,
 }
 
-/// Database connection resource
-#[derive(Resource)]
+impl Default for GameState {
+    fn default() -> Self {
+        Self {
+            current_map_seed: 0,
+            map_depth: 0,
+            multiplayer_connected: false,
+            blockchain_connected: false,
+            total_players: 1,
+            save_slot: DEFAULT_SAVE_SLOT,
+            world_slot: DEFAULT_WORLD_SLOT,
+        }
+    }
+}
+
+impl GameState {
+    /// A remote player connected
+    pub fn peer_joined(&mut self) {
+        self.total_players += 1;
+    }
+    
+    /// A remote player disconnected; spurious disconnects never drop the local player from the count
+    pub fn peer_left(&mut self) {
+        self.total_players = self.total_players.saturating_sub(1).max(1);
+    }
+}
+
+/// Randomness for quest generation and map seeds: seeded from `CQ_RNG_SEED` so a session can be
+/// replayed, otherwise backed by `thread_rng`
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GameRng {
+    seeded: Option<ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self { seeded: Some(ChaCha8Rng::seed_from_u64(seed)) }
+    }
+    
+    /// Read `CQ_RNG_SEED`; unset or invalid values keep `thread_rng`
+    pub fn from_env() -> Self {
+        match std::env::var("CQ_RNG_SEED") {
+            Ok(value) if !value.trim().is_empty() => match value.trim().parse() {
+                Ok(seed) => {
+                    info!("Deterministic simulation with RNG seed {}", seed);
+                    Self::seeded(seed)
+                }
+                Err(_) => {
+                    warn!("Invalid CQ_RNG_SEED '{}', using an unseeded RNG", value);
+                    Self::default()
+                }
+            },
+            _ => Self::default(),
+        }
+    }
+    
+    pub fn is_seeded(&self) -> bool {
+        self.seeded.is_some()
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        match &mut self.seeded {
+            Some(rng) => rng.next_u32(),
+            None => rand::thread_rng().next_u32(),
+        }
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        match &mut self.seeded {
+            Some(rng) => rng.next_u64(),
+            None => rand::thread_rng().next_u64(),
+        }
+    }
+    
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match &mut self.seeded {
+            Some(rng) => rng.fill_bytes(dest),
+            None => rand::thread_rng().fill_bytes(dest),
+        }
+    }
+    
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        match &mut self.seeded {
+            Some(rng) => rng.try_fill_bytes(dest),
+            None => rand::thread_rng().try_fill_bytes(dest),
+        }
+    }
+}
+
+/// Whether the idle simulation is advancing
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SimState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Errors raised while opening or initializing the database
+#[derive(Debug)]
+pub enum DbError {
+    /// The database file could not be opened
+    Open(rusqlite::Error),
+    /// A schema migration could not be applied
+    Schema(rusqlite::Error),
+    /// A query failed while exporting or importing a save
+    Query(rusqlite::Error),
+    /// An imported save document is malformed or from another format version
+    Format(String),
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Open(e) => write!(f, "failed to open database: {}", e),
+            DbError::Schema(e) => write!(f, "failed to initialize database schema: {}", e),
+            DbError::Query(e) => write!(f, "database query failed: {}", e),
+            DbError::Format(e) => write!(f, "invalid save document: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+/// Database connection resource; clones share the same connection
+#[derive(Resource, Clone, Debug)]
 pub struct DatabaseConnection {
     conn: Arc<Mutex<Connection>>,
+    path: PathBuf,
 }
 
 impl DatabaseConnection {
-    /// Create new database connection
+    /// Open the database at `CQ_DB_PATH` (default `chainquest.db`), panicking if it cannot be opened
     pub fn new() -> Self {
-        let conn = Connection::open("chainquest.db")
-            .expect("Failed to open database");
-        
-        // Create tables if they don't exist
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS progress (
-                id INTEGER PRIMARY KEY,
-                resources REAL NOT NULL,
-                experience REAL NOT NULL,
-                level INTEGER NOT NULL,
-                last_update REAL NOT NULL
-            )",
-            [],
-        ).expect("Failed to create progress table");
-        
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS maps (
-                id INTEGER PRIMARY KEY,
-                seed INTEGER NOT NULL,
-                grid TEXT NOT NULL,
-                created_at REAL NOT NULL
-            )",
-            [],
-        ).expect("Failed to create maps table");
-        
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS sft_assets (
-                id INTEGER PRIMARY KEY,
-                token_id TEXT NOT NULL,
-                attributes TEXT NOT NULL,
-                staked INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        ).expect("Failed to create sft_assets table");
+        Self::open(db_path_from_env())
+    }
+    
+    /// Open the database at `CQ_DB_PATH` (default `chainquest.db`)
+    pub fn try_new() -> std::result::Result<Self, DbError> {
+        Self::try_open(db_path_from_env())
+    }
+    
+    /// Open a database at the given path (`:memory:` for an in-memory database).
+    ///
+    /// # Panics
+    /// If the database cannot be opened: playing on without it would silently lose all progress
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::try_open(path)
+            .unwrap_or_else(|e| panic!("Cannot open the database at {}: {}", path.display(), e))
+    }
+    
+    /// Open a database at the given path, propagating open and schema errors
+    pub fn try_open(path: impl AsRef<Path>) -> std::result::Result<Self, DbError> {
+        let path = path.as_ref();
+        let conn = Connection::open(path).map_err(DbError::Open)?;
+        // A crash mid-write leaves the main file intact; in-memory databases report "memory"
+        if let Err(e) = conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0)) {
+            warn!("Could not enable WAL mode for {}: {}", path.display(), e);
+        }
+        run_migrations(&conn).map_err(DbError::Schema)?;
         
         info!("Database initialized successfully");
         
-        Self {
+        Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
+            path: path.to_path_buf(),
+        })
+    }
+    
+    /// Where periodic backups are written: the database path with `.bak` appended, none for in-memory databases
+    pub fn backup_path(&self) -> Option<PathBuf> {
+        if self.path.as_os_str() == ":memory:" {
+            return None;
         }
+        let mut path = self.path.clone().into_os_string();
+        path.push(".bak");
+        Some(path.into())
     }
     
-    /// Save player progress
-    pub fn save_progress(&self, progress: &IdleProgress) -> Result<()> {
+    /// Copy the whole database to `path` using the SQLite online backup API
+    pub fn backup_to(&self, path: impl AsRef<Path>) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO progress (id, resources, experience, level, last_update) 
-             VALUES (1, ?1, ?2, ?3, ?4)",
-            [progress.resources, progress.experience, progress.level as f32, progress.last_update],
+        conn.backup(DatabaseName::Main, path, None)
+    }
+    
+    /// Save player progress into a save slot
+    pub fn save_progress(&self, slot: u32, progress: &IdleProgress) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO progress (id, resources, experience, level, last_update, auto_collect) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![slot, progress.resources, progress.experience, progress.level as f32, progress.last_update, progress.auto_collect],
         )?;
-        Ok(())
+        tx.commit()
     }
     
-    /// Load player progress
-    pub fn load_progress(&self) -> Result<IdleProgress> {
+    /// Save a save tick's progress, health and stats into a slot in one transaction,
+    /// so a tick is either stored whole or not at all
+    pub fn save_player_state(
+        &self,
+        slot: u32,
+        progress: &IdleProgress,
+        health: Option<&Health>,
+        stats: Option<&PlayerStats>,
+    ) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO progress (id, resources, experience, level, last_update, auto_collect) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![slot, progress.resources, progress.experience, progress.level as f32, progress.last_update, progress.auto_collect],
+        )?;
+        if let Some(health) = health {
+            tx.execute(
+                "INSERT OR REPLACE INTO player_health (slot, current, max) VALUES (?1, ?2, ?3)",
+                rusqlite::params![slot, health.current, health.max],
+            )?;
+        }
+        if let Some(stats) = stats {
+            tx.execute(
+                "INSERT OR REPLACE INTO player_stats (slot, lifetime_resources, milestones_reached, essence) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![slot, stats.lifetime_resources, stats.milestones_reached, stats.essence],
+            )?;
+        }
+        tx.commit()
+    }
+    
+    /// Load player progress from a save slot
+    pub fn load_progress(&self, slot: u32) -> Result<IdleProgress> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT resources, experience, level, last_update FROM progress WHERE id = 1"
+            "SELECT resources, experience, level, last_update, auto_collect FROM progress WHERE id = ?1"
         )?;
         
-        let progress = stmt.query_row([], |row| {
+        let progress = stmt.query_row([slot], |row| {
             Ok(IdleProgress {
                 resources: row.get(0)?,
                 experience: row.get(1)?,
                 level: row.get::<_, f32>(2)? as u32,
                 last_update: row.get(3)?,
+                auto_collect: row.get(4)?,
             })
         })?;
         
         Ok(progress)
     }
     
-    /// Save generated map
-    pub fn save_map(&self, seed: i64, grid: &str) -> Result<()> {
+    /// List save slots that contain progress
+    pub fn list_slots(&self) -> Result<Vec<u32>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id FROM progress ORDER BY id")?;
+        let slots = stmt.query_map([], |row| row.get(0))?;
+        slots.collect()
+    }
+    
+    /// Delete everything stored in a save slot, in one transaction so no table is left behind
+    pub fn delete_slot(&self, slot: u32) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (table, key) in SLOT_TABLES {
+            tx.execute(&format!("DELETE FROM {} WHERE {} = ?1", table, key), [slot])?;
+        }
+        tx.commit()
+    }
+    
+    /// Delete the maps and world state of a world slot
+    pub fn delete_world(&self, slot: u32) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for table in WORLD_TABLES {
+            tx.execute(&format!("DELETE FROM {} WHERE slot = ?1", table), [slot])?;
+        }
+        tx.commit()
+    }
+    
+    /// Save generated map into a world slot
+    pub fn save_map(&self, slot: u32, seed: i64, grid: &str) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -105,37 +323,1094 @@ impl DatabaseConnection {
             .as_secs_f64();
             
         conn.execute(
-            "INSERT INTO maps (seed, grid, created_at) VALUES (?1, ?2, ?3)",
-            [seed.to_string(), grid.to_string(), timestamp.to_string()],
+            "INSERT INTO maps (slot, seed, grid, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![slot, seed, grid, timestamp],
         )?;
         Ok(())
     }
     
-    /// Load map by seed
-    pub fn load_map(&self, seed: i64) -> Result<String> {
+    /// Load map by seed from a world slot
+    pub fn load_map(&self, slot: u32, seed: i64) -> Result<String> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT grid FROM maps WHERE seed = ?1")?;
-        let grid: String = stmt.query_row([seed], |row| row.get(0))?;
+        let mut stmt = conn.prepare(
+            "SELECT grid FROM maps WHERE slot = ?1 AND seed = ?2 ORDER BY id DESC LIMIT 1"
+        )?;
+        let grid: String = stmt.query_row(rusqlite::params![slot, seed], |row| row.get(0))?;
         Ok(grid)
     }
+    
+    /// Record the text a stored map's seed was derived from
+    pub fn save_map_seed_text(&self, slot: u32, seed: i64, seed_text: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE maps SET seed_text = ?3 WHERE slot = ?1 AND seed = ?2",
+            rusqlite::params![slot, seed, seed_text],
+        )?;
+        Ok(())
+    }
+    
+    /// Load the text a stored map's seed was derived from, if any
+    pub fn load_map_seed_text(&self, slot: u32, seed: i64) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT seed_text FROM maps WHERE slot = ?1 AND seed = ?2 ORDER BY id DESC LIMIT 1"
+        )?;
+        stmt.query_row(rusqlite::params![slot, seed], |row| row.get(0))
+    }
+    
+    /// Load the most recently stored map of a world slot as (seed, grid)
+    pub fn load_latest_map(&self, slot: u32) -> Result<(i64, String)> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT seed, grid FROM maps WHERE slot = ?1 ORDER BY id DESC LIMIT 1"
+        )?;
+        stmt.query_row([slot], |row| Ok((row.get(0)?, row.get(1)?)))
+    }
+    
+    /// Record a newly minted SFT asset (unstaked)
+    pub fn insert_sft(&self, token_id: &str, attributes: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sft_assets (token_id, attributes, staked) VALUES (?1, ?2, 0)",
+            [token_id, attributes],
+        )?;
+        Ok(())
+    }
+    
+    /// List all recorded SFT assets
+    pub fn list_sfts(&self) -> Result<Vec<SftRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT token_id, attributes, staked, mint_tx, stake_tx, owner FROM sft_assets ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SftRow {
+                token_id: row.get(0)?,
+                attributes: row.get(1)?,
+                staked: row.get::<_, i64>(2)? != 0,
+                mint_tx: row.get(3)?,
+                stake_tx: row.get(4)?,
+                owner: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+    
+    /// Number of recorded SFTs whose mint has settled
+    pub fn count_confirmed_sfts(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM sft_assets WHERE mint_tx IS NULL", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+    }
+    
+    /// Number of settled SFTs minted to `owner`
+    pub fn count_owned_sfts(&self, owner: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM sft_assets WHERE owner = ?1 AND mint_tx IS NULL",
+            [owner],
+            |row| row.get::<_, i64>(0),
+        ).map(|count| count as usize)
+    }
+    
+    /// Reserve the serial for the next recorded SFT. Serials come from a sequence, so one is never
+    /// handed out twice, even after the SFT it went to is deleted
+    pub fn next_sft_serial(&self) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE sft_serial SET last = MAX(last, (SELECT COALESCE(MAX(id), 0) FROM sft_assets)) + 1 WHERE id = 1",
+            [],
+        )?;
+        let serial = tx.query_row("SELECT last FROM sft_serial WHERE id = 1", [], |row| row.get(0))?;
+        tx.commit()?;
+        Ok(serial)
+    }
+    
+    /// Record an SFT minted to `owner` whose mint transaction `mint_tx` has not settled yet
+    pub fn insert_pending_sft(&self, token_id: &str, attributes: &str, mint_tx: &str, owner: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sft_assets (token_id, attributes, staked, mint_tx, owner) VALUES (?1, ?2, 0, ?3, ?4)",
+            [token_id, attributes, mint_tx, owner],
+        )?;
+        Ok(())
+    }
+    
+    /// Update the staked flag of an SFT asset ahead of the settlement of `stake_tx`
+    pub fn set_staked_pending(&self, token_id: &str, staked: bool, stake_tx: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sft_assets SET staked = ?1, stake_tx = ?2 WHERE token_id = ?3",
+            rusqlite::params![staked as i64, stake_tx, token_id],
+        )?;
+        Ok(())
+    }
+    
+    /// Hashes of the mint and stake transactions recorded SFTs are still waiting on
+    pub fn pending_sft_txs(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT mint_tx FROM sft_assets WHERE mint_tx IS NOT NULL
+             UNION SELECT stake_tx FROM sft_assets WHERE stake_tx IS NOT NULL"
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+    
+    /// Settle the SFT changes waiting on a transaction: keep them if it was confirmed,
+    /// otherwise delete the minted asset and restore the previous staked flag
+    pub fn resolve_sft_tx(&self, tx_hash: &str, confirmed: bool) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        if confirmed {
+            tx.execute("UPDATE sft_assets SET mint_tx = NULL WHERE mint_tx = ?1", [tx_hash])?;
+            tx.execute("UPDATE sft_assets SET stake_tx = NULL WHERE stake_tx = ?1", [tx_hash])?;
+        } else {
+            tx.execute("DELETE FROM sft_assets WHERE mint_tx = ?1", [tx_hash])?;
+            tx.execute(
+                "UPDATE sft_assets SET staked = 1 - staked, stake_tx = NULL WHERE stake_tx = ?1",
+                [tx_hash],
+            )?;
+        }
+        tx.commit()
+    }
+    
+    /// Update the staked flag of an SFT asset
+    pub fn set_staked(&self, token_id: &str, staked: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sft_assets SET staked = ?1 WHERE token_id = ?2",
+            rusqlite::params![staked as i64, token_id],
+        )?;
+        Ok(())
+    }
+    
+    /// Delete an SFT asset
+    pub fn delete_sft(&self, token_id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM sft_assets WHERE token_id = ?1", [token_id])?;
+        Ok(())
+    }
+    
+    /// Persist an unlocked achievement (no-op if already stored)
+    pub fn save_achievement(&self, achievement: Achievement) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        conn.execute(
+            "INSERT OR IGNORE INTO achievements (name, unlocked_at) VALUES (?1, ?2)",
+            rusqlite::params![achievement.as_str(), timestamp],
+        )?;
+        Ok(())
+    }
+    
+    /// Persist the player's username for a save slot
+    pub fn save_username(&self, slot: u32, username: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO player_profile (slot, username) VALUES (?1, ?2)",
+            rusqlite::params![slot, username],
+        )?;
+        Ok(())
+    }
+    
+    /// Load the player's username for a save slot
+    pub fn load_username(&self, slot: u32) -> Result<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT username FROM player_profile WHERE slot = ?1", [slot], |row| row.get(0))
+    }
+    
+    /// Persist a player's anti-cheat history
+    pub fn save_security_history(&self, player_id: u32, history: &PlayerActionHistory) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO security_events
+             (player_id, suspicious_activity_count, last_resource_collection, last_quest_completion, last_level_up)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                player_id,
+                history.suspicious_activity_count,
+                history.last_resource_collection as i64,
+                history.last_quest_completion as i64,
+                history.last_level_up as i64,
+            ],
+        )?;
+        Ok(())
+    }
+    
+    /// Load every persisted anti-cheat history
+    pub fn load_security_histories(&self) -> Result<Vec<(u32, PlayerActionHistory)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT player_id, suspicious_activity_count, last_resource_collection, last_quest_completion, last_level_up
+             FROM security_events"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, PlayerActionHistory {
+                suspicious_activity_count: row.get(1)?,
+                last_resource_collection: row.get::<_, i64>(2)? as u64,
+                last_quest_completion: row.get::<_, i64>(3)? as u64,
+                last_level_up: row.get::<_, i64>(4)? as u64,
+                ..Default::default()
+            }))
+        })?;
+        rows.collect()
+    }
+    
+    /// Delete persisted anti-cheat histories with no activity since `cutoff` (UNIX seconds)
+    pub fn delete_security_histories_before(&self, cutoff: u64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM security_events
+             WHERE MAX(last_resource_collection, last_quest_completion, last_level_up) < ?1",
+            [cutoff as i64],
+        )
+    }
+    
+    /// Persist a ban (keeps the original reason if already banned)
+    pub fn save_ban(&self, player_id: u32, reason: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO banned_players (player_id, reason, banned_at)
+             VALUES (?1, ?2, strftime('%s','now'))",
+            rusqlite::params![player_id, reason],
+        )?;
+        Ok(())
+    }
+    
+    /// List banned players with their ban reasons
+    pub fn load_bans(&self) -> Result<Vec<(u32, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT player_id, reason FROM banned_players ORDER BY banned_at")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+    
+    /// Store the lifetime number of maps generated by a source
+    pub fn save_generation_count(&self, source: &str, maps_generated: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO generation_stats (source, maps_generated) VALUES (?1, ?2)",
+            rusqlite::params![source, maps_generated],
+        )?;
+        Ok(())
+    }
+    
+    /// Load lifetime map generation counts keyed by source
+    pub fn load_generation_counts(&self) -> Result<Vec<(String, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT source, maps_generated FROM generation_stats ORDER BY source")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+    
+    /// Persist the tier of an owned generator
+    pub fn save_generator(&self, slot: u32, kind: &str, tier: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO generators (slot, kind, tier) VALUES (?1, ?2, ?3)",
+            rusqlite::params![slot, kind, tier],
+        )?;
+        Ok(())
+    }
+    
+    /// Load generator tiers for a save slot keyed by kind
+    pub fn load_generators(&self, slot: u32) -> Result<Vec<(String, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT kind, tier FROM generators WHERE slot = ?1 ORDER BY kind")?;
+        let rows = stmt.query_map([slot], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+    
+    /// Remember the seed of the map active in a world slot
+    pub fn save_current_map_seed(&self, slot: u32, seed: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO world_state (slot, current_map_seed) VALUES (?1, ?2)
+             ON CONFLICT(slot) DO UPDATE SET current_map_seed = excluded.current_map_seed",
+            rusqlite::params![slot, seed],
+        )?;
+        Ok(())
+    }
+    
+    /// Remember how many portals deep the player is in a world slot
+    pub fn save_map_depth(&self, slot: u32, depth: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE world_state SET map_depth = ?2 WHERE slot = ?1",
+            rusqlite::params![slot, depth],
+        )?;
+        Ok(())
+    }
+    
+    /// Portal depth of a world slot
+    pub fn load_map_depth(&self, slot: u32) -> Result<u32> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT map_depth FROM world_state WHERE slot = ?1", [slot], |row| row.get(0))
+    }
+    
+    /// Persist the player's lifetime statistics for a save slot
+    pub fn save_stats(&self, slot: u32, stats: &PlayerStats) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO player_stats (slot, lifetime_resources, milestones_reached, essence) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![slot, stats.lifetime_resources, stats.milestones_reached, stats.essence],
+        )?;
+        Ok(())
+    }
+    
+    /// Load the player's lifetime statistics for a save slot
+    pub fn load_stats(&self, slot: u32) -> Result<PlayerStats> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT lifetime_resources, milestones_reached, essence FROM player_stats WHERE slot = ?1",
+            [slot],
+            |row| Ok(PlayerStats {
+                lifetime_resources: row.get(0)?,
+                milestones_reached: row.get(1)?,
+                essence: row.get(2)?,
+                ..Default::default()
+            }),
+        )
+    }
+    
+    /// Persist the local player's health for a save slot
+    pub fn save_health(&self, slot: u32, health: &Health) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO player_health (slot, current, max) VALUES (?1, ?2, ?3)",
+            rusqlite::params![slot, health.current, health.max],
+        )?;
+        Ok(())
+    }
+    
+    /// Load the local player's health for a save slot
+    pub fn load_health(&self, slot: u32) -> Result<Health> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT current, max FROM player_health WHERE slot = ?1", [slot], |row| {
+            Ok(Health { current: row.get(0)?, max: row.get(1)? })
+        })
+    }
+    
+    /// Persist the local player's id for a save slot
+    pub fn save_player_id(&self, slot: u32, player_id: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO player_identity (slot, player_id) VALUES (?1, ?2)",
+            rusqlite::params![slot, player_id],
+        )?;
+        Ok(())
+    }
+    
+    /// Load the local player's id for a save slot
+    pub fn load_player_id(&self, slot: u32) -> Result<u32> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT player_id FROM player_identity WHERE slot = ?1", [slot], |row| row.get(0))
+    }
+    
+    /// Seed of the map last active in a world slot
+    pub fn load_current_map_seed(&self, slot: u32) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT current_map_seed FROM world_state WHERE slot = ?1", [slot], |row| row.get(0))
+    }
+    
+    /// Persist the daily quest reset time and progress for a save slot
+    pub fn save_daily_quests(&self, slot: u32, last_reset: u64, daily_completed: u32, streak: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO daily_quests (slot, last_reset, daily_completed, streak) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![slot, last_reset as i64, daily_completed, streak],
+        )?;
+        Ok(())
+    }
+    
+    /// Load `(last_reset, daily_completed, streak)` for a save slot
+    pub fn load_daily_quests(&self, slot: u32) -> Result<(u64, u32, u32)> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT last_reset, daily_completed, streak FROM daily_quests WHERE slot = ?1",
+            [slot],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get(1)?, row.get(2)?)),
+        )
+    }
+    
+    /// Load all persisted achievements
+    pub fn load_achievements(&self) -> Result<HashSet<Achievement>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name FROM achievements")?;
+        let names = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut unlocked = HashSet::new();
+        for name in names {
+            if let Some(achievement) = Achievement::from_name(&name?) {
+                unlocked.insert(achievement);
+            }
+        }
+        Ok(unlocked)
+    }
+    
+    /// Serialize every save slot's tables and the SFT assets into a portable JSON document
+    pub fn export_json(&self) -> std::result::Result<String, DbError> {
+        let export = self.read_export().map_err(DbError::Query)?;
+        serde_json::to_string_pretty(&export).map_err(|e| DbError::Format(e.to_string()))
+    }
+    
+    fn read_export(&self) -> Result<SaveExport> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, resources, experience, level, last_update, auto_collect FROM progress ORDER BY id"
+        )?;
+        let progress = stmt.query_map([], |row| {
+            Ok(SlotProgress {
+                slot: row.get(0)?,
+                progress: IdleProgress {
+                    resources: row.get(1)?,
+                    experience: row.get(2)?,
+                    level: row.get::<_, f32>(3)? as u32,
+                    last_update: row.get(4)?,
+                    auto_collect: row.get(5)?,
+                },
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, last_reset, daily_completed, streak FROM daily_quests ORDER BY slot")?;
+        let daily_quests = stmt.query_map([], |row| {
+            Ok(DailyQuestRow {
+                slot: row.get(0)?,
+                last_reset: row.get::<_, i64>(1)? as u64,
+                daily_completed: row.get(2)?,
+                streak: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, seed, grid, created_at, seed_text FROM maps ORDER BY id")?;
+        let maps = stmt.query_map([], |row| {
+            Ok(MapRow {
+                slot: row.get(0)?,
+                seed: row.get(1)?,
+                grid: row.get(2)?,
+                created_at: row.get(3)?,
+                seed_text: row.get(4)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT token_id, attributes, staked, mint_tx, stake_tx, owner FROM sft_assets ORDER BY id")?;
+        let sfts = stmt.query_map([], |row| {
+            Ok(SftRow {
+                token_id: row.get(0)?,
+                attributes: row.get(1)?,
+                staked: row.get::<_, i64>(2)? != 0,
+                mint_tx: row.get(3)?,
+                stake_tx: row.get(4)?,
+                owner: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, username FROM player_profile ORDER BY slot")?;
+        let profiles = stmt.query_map([], |row| Ok(ProfileRow { slot: row.get(0)?, username: row.get(1)? }))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, player_id FROM player_identity ORDER BY slot")?;
+        let identities = stmt.query_map([], |row| Ok(IdentityRow { slot: row.get(0)?, player_id: row.get(1)? }))?
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare(
+            "SELECT slot, lifetime_resources, milestones_reached, essence FROM player_stats ORDER BY slot"
+        )?;
+        let stats = stmt.query_map([], |row| {
+            Ok(StatsRow {
+                slot: row.get(0)?,
+                lifetime_resources: row.get(1)?,
+                milestones_reached: row.get(2)?,
+                essence: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, current, max FROM player_health ORDER BY slot")?;
+        let health = stmt.query_map([], |row| {
+            Ok(HealthRow { slot: row.get(0)?, current: row.get(1)?, max: row.get(2)? })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, kind, tier FROM generators ORDER BY slot, kind")?;
+        let generators = stmt.query_map([], |row| {
+            Ok(GeneratorRow { slot: row.get(0)?, kind: row.get(1)?, tier: row.get(2)? })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        let mut stmt = conn.prepare("SELECT slot, current_map_seed, map_depth FROM world_state ORDER BY slot")?;
+        let world_state = stmt.query_map([], |row| {
+            Ok(WorldStateRow { slot: row.get(0)?, current_map_seed: row.get(1)?, map_depth: row.get(2)? })
+        })?.collect::<Result<Vec<_>>>()?;
+        
+        Ok(SaveExport {
+            version: SAVE_EXPORT_VERSION,
+            progress,
+            daily_quests,
+            maps,
+            sfts,
+            profiles,
+            identities,
+            stats,
+            health,
+            generators,
+            world_state,
+        })
+    }
+    
+    /// Replace every save slot's tables and the SFT assets with those of an exported document;
+    /// nothing is changed if the document is rejected
+    pub fn import_json(&self, json: &str) -> std::result::Result<(), DbError> {
+        let export: SaveExport = serde_json::from_str(json).map_err(|e| DbError::Format(e.to_string()))?;
+        export.validate().map_err(DbError::Format)?;
+        self.write_import(&export).map_err(DbError::Query)
+    }
+    
+    fn write_import(&self, export: &SaveExport) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute_batch(
+            "DELETE FROM progress; DELETE FROM daily_quests; DELETE FROM maps; DELETE FROM sft_assets;
+             DELETE FROM player_profile; DELETE FROM player_identity; DELETE FROM player_stats;
+             DELETE FROM player_health; DELETE FROM generators; DELETE FROM world_state;"
+        )?;
+        for entry in &export.progress {
+            let p = &entry.progress;
+            tx.execute(
+                "INSERT INTO progress (id, resources, experience, level, last_update, auto_collect) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![entry.slot, p.resources, p.experience, p.level as f32, p.last_update, p.auto_collect],
+            )?;
+        }
+        for quests in &export.daily_quests {
+            tx.execute(
+                "INSERT INTO daily_quests (slot, last_reset, daily_completed, streak) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![quests.slot, quests.last_reset as i64, quests.daily_completed, quests.streak],
+            )?;
+        }
+        for map in &export.maps {
+            tx.execute(
+                "INSERT INTO maps (slot, seed, grid, created_at, seed_text) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![map.slot, map.seed, map.grid, map.created_at, map.seed_text],
+            )?;
+        }
+        for sft in &export.sfts {
+            tx.execute(
+                "INSERT INTO sft_assets (token_id, attributes, staked, mint_tx, stake_tx, owner)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![sft.token_id, sft.attributes, sft.staked as i64, sft.mint_tx, sft.stake_tx, sft.owner],
+            )?;
+        }
+        for profile in &export.profiles {
+            tx.execute(
+                "INSERT INTO player_profile (slot, username) VALUES (?1, ?2)",
+                rusqlite::params![profile.slot, profile.username],
+            )?;
+        }
+        for identity in &export.identities {
+            tx.execute(
+                "INSERT INTO player_identity (slot, player_id) VALUES (?1, ?2)",
+                rusqlite::params![identity.slot, identity.player_id],
+            )?;
+        }
+        for stats in &export.stats {
+            tx.execute(
+                "INSERT INTO player_stats (slot, lifetime_resources, milestones_reached, essence) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![stats.slot, stats.lifetime_resources, stats.milestones_reached, stats.essence],
+            )?;
+        }
+        for health in &export.health {
+            tx.execute(
+                "INSERT INTO player_health (slot, current, max) VALUES (?1, ?2, ?3)",
+                rusqlite::params![health.slot, health.current, health.max],
+            )?;
+        }
+        for generator in &export.generators {
+            tx.execute(
+                "INSERT INTO generators (slot, kind, tier) VALUES (?1, ?2, ?3)",
+                rusqlite::params![generator.slot, generator.kind, generator.tier],
+            )?;
+        }
+        for world in &export.world_state {
+            tx.execute(
+                "INSERT INTO world_state (slot, current_map_seed, map_depth) VALUES (?1, ?2, ?3)",
+                rusqlite::params![world.slot, world.current_map_seed, world.map_depth],
+            )?;
+        }
+        tx.commit()
+    }
+}
+
+/// Database path from `CQ_DB_PATH`, falling back to `chainquest.db`
+fn db_path_from_env() -> String {
+    std::env::var("CQ_DB_PATH").unwrap_or_else(|_| "chainquest.db".into())
+}
+
+/// Interval between database backups from `CQ_BACKUP_INTERVAL` seconds (default 600); `0` disables backups
+pub fn backup_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("CQ_BACKUP_INTERVAL").ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(600);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Tables holding per-save-slot data, with the column their slot is stored in
+const SLOT_TABLES: &[(&str, &str)] = &[
+    ("progress", "id"),
+    ("daily_quests", "slot"),
+    ("player_profile", "slot"),
+    ("player_identity", "slot"),
+    ("player_stats", "slot"),
+    ("player_health", "slot"),
+    ("generators", "slot"),
+];
+
+/// Tables keyed by world slot
+const WORLD_TABLES: &[&str] = &["maps", "world_state"];
+
+/// Ordered schema migrations; entry `i` upgrades the database to `user_version` `i + 1`
+pub const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE IF NOT EXISTS progress (
+        id INTEGER PRIMARY KEY,
+        resources REAL NOT NULL,
+        experience REAL NOT NULL,
+        level INTEGER NOT NULL,
+        last_update REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS maps (
+        id INTEGER PRIMARY KEY,
+        seed INTEGER NOT NULL,
+        grid TEXT NOT NULL,
+        created_at REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS sft_assets (
+        id INTEGER PRIMARY KEY,
+        token_id TEXT NOT NULL,
+        attributes TEXT NOT NULL,
+        staked INTEGER NOT NULL DEFAULT 0
+    );",
+    // 2: per-slot worlds
+    "ALTER TABLE maps ADD COLUMN slot INTEGER NOT NULL DEFAULT 0;",
+    // 3: unlocked achievements
+    "CREATE TABLE IF NOT EXISTS achievements (
+        name TEXT PRIMARY KEY,
+        unlocked_at REAL NOT NULL
+    );",
+    // 4: player profile per save slot
+    "CREATE TABLE IF NOT EXISTS player_profile (
+        slot INTEGER PRIMARY KEY,
+        username TEXT NOT NULL
+    );",
+    // 5: persisted anti-cheat state
+    "CREATE TABLE IF NOT EXISTS security_events (
+        player_id INTEGER PRIMARY KEY,
+        suspicious_activity_count INTEGER NOT NULL,
+        last_resource_collection INTEGER NOT NULL,
+        last_quest_completion INTEGER NOT NULL,
+        last_level_up INTEGER NOT NULL
+    );",
+    // 6: ban list
+    "CREATE TABLE IF NOT EXISTS banned_players (
+        player_id INTEGER PRIMARY KEY,
+        reason TEXT NOT NULL,
+        banned_at INTEGER NOT NULL
+    );",
+    // 7: lifetime map generation counts per source
+    "CREATE TABLE IF NOT EXISTS generation_stats (
+        source TEXT PRIMARY KEY,
+        maps_generated INTEGER NOT NULL
+    );",
+    // 8: human-readable seed a map was generated from
+    "ALTER TABLE maps ADD COLUMN seed_text TEXT;",
+    // 9: purchased generator tiers per save slot
+    "CREATE TABLE IF NOT EXISTS generators (
+        slot INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        tier INTEGER NOT NULL,
+        PRIMARY KEY (slot, kind)
+    );",
+    // 10: auto-collect unlock
+    "ALTER TABLE progress ADD COLUMN auto_collect INTEGER NOT NULL DEFAULT 0;",
+    // 11: daily quest reset tracking
+    "CREATE TABLE IF NOT EXISTS daily_quests (
+        slot INTEGER PRIMARY KEY,
+        last_reset INTEGER NOT NULL,
+        daily_completed INTEGER NOT NULL,
+        streak INTEGER NOT NULL
+    );",
+    // 12: active map per save slot
+    "CREATE TABLE IF NOT EXISTS world_state (
+        slot INTEGER PRIMARY KEY,
+        current_map_seed INTEGER NOT NULL
+    );",
+    // 13: local player id per save slot
+    "CREATE TABLE IF NOT EXISTS player_identity (
+        slot INTEGER PRIMARY KEY,
+        player_id INTEGER NOT NULL
+    );",
+    // 14: portal depth of the active map
+    "ALTER TABLE world_state ADD COLUMN map_depth INTEGER NOT NULL DEFAULT 0;",
+    // 15: player health per save slot
+    "CREATE TABLE IF NOT EXISTS player_health (
+        slot INTEGER PRIMARY KEY,
+        current REAL NOT NULL,
+        max REAL NOT NULL
+    );",
+    // 16: lifetime statistics per save slot
+    "CREATE TABLE IF NOT EXISTS player_stats (
+        slot INTEGER PRIMARY KEY,
+        lifetime_resources REAL NOT NULL,
+        milestones_reached INTEGER NOT NULL,
+        essence REAL NOT NULL
+    );",
+    // 17: transactions SFT assets are waiting on
+    "ALTER TABLE sft_assets ADD COLUMN mint_tx TEXT;
+    ALTER TABLE sft_assets ADD COLUMN stake_tx TEXT;",
+    // 18: local player ids moved from u32::MAX to 1, below the server-assigned range
+    "UPDATE OR REPLACE player_identity SET player_id = 1 WHERE player_id = 4294967295;
+    UPDATE OR REPLACE security_events SET player_id = 1 WHERE player_id = 4294967295;
+    UPDATE OR REPLACE banned_players SET player_id = 1 WHERE player_id = 4294967295;",
+    // 19: wallet each SFT was minted to; earlier rows have none and count for no wallet
+    "ALTER TABLE sft_assets ADD COLUMN owner TEXT;",
+    // 20: last SFT serial handed out, so serials of deleted SFTs aren't reused
+    "CREATE TABLE IF NOT EXISTS sft_serial (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        last INTEGER NOT NULL
+    );
+    INSERT INTO sft_serial (id, last) SELECT 1, COALESCE(MAX(id), 0) FROM sft_assets;",
+];
+
+/// Apply pending migrations based on `PRAGMA user_version`, returning the resulting version
+pub fn run_migrations(conn: &Connection) -> Result<u32> {
+    let mut version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    
+    for (idx, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let target = idx as u32 + 1;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(step)?;
+        tx.pragma_update(None, "user_version", target)?;
+        tx.commit()?;
+        version = target;
+        info!("Applied database migration {}", target);
+    }
+    
+    Ok(version)
+}
+
+/// Format version of `export_json` documents; `import_json` rejects any other
+pub const SAVE_EXPORT_VERSION: u32 = 2;
+
+/// Portable save document produced by `DatabaseConnection::export_json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveExport {
+    pub version: u32,
+    pub progress: Vec<SlotProgress>,
+    pub daily_quests: Vec<DailyQuestRow>,
+    pub maps: Vec<MapRow>,
+    pub sfts: Vec<SftRow>,
+    pub profiles: Vec<ProfileRow>,
+    pub identities: Vec<IdentityRow>,
+    pub stats: Vec<StatsRow>,
+    pub health: Vec<HealthRow>,
+    pub generators: Vec<GeneratorRow>,
+    pub world_state: Vec<WorldStateRow>,
+}
+
+impl SaveExport {
+    /// Check the version and that values could have been produced by the game
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.version != SAVE_EXPORT_VERSION {
+            return Err(format!("unsupported version {} (expected {})", self.version, SAVE_EXPORT_VERSION));
+        }
+        let mut slots = HashSet::new();
+        for entry in &self.progress {
+            let p = &entry.progress;
+            if !slots.insert(entry.slot) {
+                return Err(format!("duplicate progress for slot {}", entry.slot));
+            }
+            if !(p.resources.is_finite() && p.resources >= 0.0 && p.experience.is_finite() && p.experience >= 0.0) {
+                return Err(format!("invalid progress values for slot {}", entry.slot));
+            }
+        }
+        if let Some(sft) = self.sfts.iter().find(|sft| sft.token_id.is_empty()) {
+            return Err(format!("SFT asset with empty token id ({})", sft.attributes));
+        }
+        if let Some(health) = self.health.iter().find(|h| !(h.max > 0.0 && (0.0..=h.max).contains(&h.current))) {
+            return Err(format!("invalid health for slot {}", health.slot));
+        }
+        if let Some(stats) = self.stats.iter().find(|s| !(s.lifetime_resources >= 0.0 && s.essence >= 0.0)) {
+            return Err(format!("invalid statistics for slot {}", stats.slot));
+        }
+        Ok(())
+    }
+}
+
+/// Progress stored in one save slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotProgress {
+    pub slot: u32,
+    pub progress: IdleProgress,
+}
+
+/// Row of the daily_quests table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyQuestRow {
+    pub slot: u32,
+    pub last_reset: u64,
+    pub daily_completed: u32,
+    pub streak: u32,
+}
+
+/// Row of the maps table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapRow {
+    pub slot: u32,
+    pub seed: i64,
+    pub grid: String,
+    pub created_at: f64,
+    pub seed_text: Option<String>,
+}
+
+/// Row of the sft_assets table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SftRow {
+    pub token_id: String,
+    pub attributes: String,
+    pub staked: bool,
+    /// Mint transaction still awaiting settlement
+    #[serde(default)]
+    pub mint_tx: Option<String>,
+    /// Stake or unstake transaction still awaiting settlement
+    #[serde(default)]
+    pub stake_tx: Option<String>,
+    /// Wallet the SFT was minted to
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+/// Row of the player_profile table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileRow {
+    pub slot: u32,
+    pub username: String,
+}
+
+/// Row of the player_identity table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityRow {
+    pub slot: u32,
+    pub player_id: u32,
+}
+
+/// Row of the player_stats table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsRow {
+    pub slot: u32,
+    pub lifetime_resources: f64,
+    pub milestones_reached: u32,
+    pub essence: f64,
+}
+
+/// Row of the player_health table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthRow {
+    pub slot: u32,
+    pub current: f32,
+    pub max: f32,
+}
+
+/// Row of the generators table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorRow {
+    pub slot: u32,
+    pub kind: String,
+    pub tier: u32,
+}
+
+/// Row of the world_state table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldStateRow {
+    pub slot: u32,
+    pub current_map_seed: i64,
+    pub map_depth: u32,
+}
+
+/// Local player identity
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PlayerProfile {
+    pub username: String,
+}
+
+impl Default for PlayerProfile {
+    fn default() -> Self {
+        Self { username: "Adventurer".to_string() }
+    }
+}
+
+impl PlayerProfile {
+    /// Resolve the profile from a configured name (preferred) or the stored one,
+    /// sanitizing both and falling back to the default name
+    pub fn resolve(configured: Option<&str>, stored: Option<&str>) -> Self {
+        for candidate in [configured, stored].into_iter().flatten() {
+            match sanitize_username(candidate) {
+                Ok(username) => return Self { username },
+                Err(e) => warn!("Ignoring invalid username '{}': {}", candidate, e),
+            }
+        }
+        Self::default()
+    }
+    
+    /// Message announcing this player to the server
+    pub fn join_message(&self) -> GameMessage {
+        GameMessage::PlayerJoin { username: self.username.clone() }
+    }
+}
+
+/// Long-term milestones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Achievement {
+    ReachLevel10,
+    Collect10kResources,
+    Complete5Quests,
+    FirstSFT,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 4] = [
+        Achievement::ReachLevel10,
+        Achievement::Collect10kResources,
+        Achievement::Complete5Quests,
+        Achievement::FirstSFT,
+    ];
+    
+    /// Stable name used for persistence
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Achievement::ReachLevel10 => "ReachLevel10",
+            Achievement::Collect10kResources => "Collect10kResources",
+            Achievement::Complete5Quests => "Complete5Quests",
+            Achievement::FirstSFT => "FirstSFT",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str() == name)
+    }
+}
+
+/// Generator kind the upgrade key buys a tier of, cycled with the select-generator key
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectedGenerator(pub GeneratorKind);
+
+impl Default for SelectedGenerator {
+    fn default() -> Self {
+        Self(GeneratorKind::Miner)
+    }
+}
+
+/// Achievements unlocked by the player
+#[derive(Resource, Debug, Default)]
+pub struct Achievements {
+    pub unlocked: HashSet<Achievement>,
+}
+
+/// Fired once when an achievement is unlocked
+#[derive(Event, Debug, Clone)]
+pub struct AchievementUnlocked(pub Achievement);
+
+/// A player asked to collect resources; credited by `apply_validated_collection` once approved
+#[derive(Event, Debug, Clone)]
+pub struct ResourceCollected {
+    pub player_id: u32,
+    pub amount: f64,
+    /// Map tile the resources came from, used up only once the collection is credited
+    pub source: Option<Entity>,
+}
+
+/// An approved collection was credited to the player
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ResourcesCredited {
+    pub player_id: u32,
+    pub amount: f64,
+    /// Map tile the credited collection came from
+    pub source: Option<Entity>,
+}
+
+/// Idle production credited to a player during one tick
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ResourcesProduced {
+    pub player: Entity,
+    pub amount: f64,
+}
+
+/// A player gained one or more levels
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct LevelUpEvent {
+    pub player: Entity,
+    pub old_level: u32,
+    pub new_level: u32,
+}
+
+/// A quest was completed and its rewards applied
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct QuestCompleted {
+    pub quest_id: u32,
+    pub resources: f32,
 }
 
 /// Multiplayer connection state
 #[derive(Resource, Default)]
 pub struct MultiplayerState {
     pub server_address: String,
+    /// Id the server assigned this client on join; the local `PlayerId` is left unchanged
     pub player_id: u32,
     pub connected_peers: Vec<u32>,
     pub is_host: bool,
 }
 
+/// Lifecycle of a submitted transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    Failed,
+}
+
 /// Blockchain connection state
 #[derive(Resource, Default)]
 pub struct BlockchainState {
     pub wallet_address: String,
     pub testnet_connected: bool,
-    pub pending_transactions: Vec<String>,
+    /// Submitted transactions; resolved ones linger for a grace period before removal
+    pub pending_transactions: Vec<(String, TxStatus)>,
+    /// Elapsed game time at which each resolved transaction left `Pending`
+    pub tx_resolved_at: std::collections::HashMap<String, f64>,
     pub sft_balance: u32,
+    /// Why the last wallet connection attempt failed
+    pub connection_error: Option<String>,
+}
+
+impl BlockchainState {
+    /// Transactions still waiting for a result
+    pub fn pending_count(&self) -> usize {
+        self.pending_transactions.iter().filter(|(_, status)| *status == TxStatus::Pending).count()
+    }
+    
+    /// Wallet line for the HUD, if a connection was attempted
+    pub fn wallet_status(&self) -> Option<String> {
+        if let Some(error) = &self.connection_error {
+            return Some(format!("Wallet: connection failed ({})", error));
+        }
+        if self.wallet_address.is_empty() {
+            return None;
+        }
+        let address = &self.wallet_address;
+        let short = if address.len() > 12 {
+            format!("{}...{}", &address[..6], &address[address.len() - 4..])
+        } else {
+            address.clone()
+        };
+        let network = if self.testnet_connected { "testnet" } else { "offline" };
+        Some(format!("Wallet: {} ({})", short, network))
+    }
 }
 
 /// AI generation state
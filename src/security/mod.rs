@@ -1,44 +1,188 @@
 //! Security and anti-cheat systems for ChainQuest Idle
 
 use bevy::prelude::*;
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use parking_lot::RwLock;
 use std::sync::Arc;
+use crate::resources::DatabaseConnection;
+
+// Player ids come from two disjoint ranges, so a local player and a connected peer never share
+// security history or bans:
+// - `1..FIRST_REMOTE_PLAYER_ID`: local players, persisted per save slot in `player_identity`
+// - `FIRST_REMOTE_PLAYER_ID..`: ids a server hands out to connections (`ServerState::allocate_peer_id`)
+// A client keeps its local id after joining; the id the server assigned it is `MultiplayerState::player_id`.
+
+/// Player id the local player's own actions are validated under by default
+pub const LOCAL_PLAYER_ID: u32 = 1;
+
+/// First id a server assigns to a connection
+pub const FIRST_REMOTE_PLAYER_ID: u32 = 1 << 16;
 
 /// Security manager resource for anti-cheat protection
 #[derive(Resource, Debug)]
 pub struct SecurityManager {
     pub player_actions: Arc<RwLock<HashMap<u32, PlayerActionHistory>>>,
+    pub banned_players: Arc<RwLock<HashSet<u32>>>,
+    /// Trusted players (admins, testers) exempt from every check
+    pub whitelist: Arc<RwLock<HashSet<u32>>>,
+    /// Reason recorded for each ban, persisted alongside it
+    pub ban_reasons: Arc<RwLock<HashMap<u32, String>>>,
+    /// Most recent non-approved validations, oldest first
+    pub audit_log: Arc<RwLock<VecDeque<SecurityEvent>>>,
     pub validation_config: ValidationConfig,
+    /// Database bans are written to as soon as they are made, set by `from_db`
+    pub db: Option<DatabaseConnection>,
 }
 
+/// A recorded non-approved validation, for moderation
 #[derive(Debug, Clone)]
+pub struct SecurityEvent {
+    pub player_id: u32,
+    pub action: ActionType,
+    pub result: ValidationResult,
+    pub timestamp: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct PlayerActionHistory {
     pub last_resource_collection: u64,
     pub last_quest_completion: u64,
     pub last_level_up: u64,
+    pub last_map_generation: u64,
+    /// Highest current rate across action types
     pub actions_per_second: f32,
+    pub action_rates: HashMap<ActionType, ActionRate>,
     pub suspicious_activity_count: u32,
 }
 
+/// Span over which action rates are measured
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Rate tracking for one action type
+#[derive(Debug, Clone, Default)]
+pub struct ActionRate {
+    pub last_action: u64,
+    pub actions_per_second: f32,
+    /// Accepted actions within the last `RATE_WINDOW`, oldest first
+    pub recent: VecDeque<Instant>,
+}
+
+impl ActionRate {
+    /// Rate over the window ending at `now`, counting an action attempted at `now`
+    pub fn rate_with(&mut self, now: Instant) -> f32 {
+        while self.recent.front().is_some_and(|&oldest| now.duration_since(oldest) >= RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+        (self.recent.len() + 1) as f32 / RATE_WINDOW.as_secs_f32()
+    }
+}
+
+/// Kinds of player actions with independent rate limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionType {
+    ResourceCollection,
+    QuestCompletion,
+    LevelUp,
+    SftMint,
+    MapGeneration,
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidationConfig {
+    /// Fallback limit for action types without an entry in `action_rate_limits`
     pub max_actions_per_second: f32,
+    pub action_rate_limits: HashMap<ActionType, f32>,
     pub min_time_between_quests: u64, // seconds
+    /// Seconds a player must wait between map generation requests
+    pub min_time_between_map_generations: u64,
     pub max_resource_gain_per_action: f32,
     pub max_level_jumps: u32,
     pub suspicious_threshold: u32,
+    /// Maximum number of security events kept in memory
+    pub audit_log_capacity: usize,
 }
 
 impl Default for ValidationConfig {
     fn default() -> Self {
         Self {
             max_actions_per_second: 10.0,
+            action_rate_limits: HashMap::from([
+                (ActionType::ResourceCollection, 10.0),
+                (ActionType::QuestCompletion, 1.0),
+                (ActionType::LevelUp, 2.0),
+                (ActionType::SftMint, 1.0),
+                (ActionType::MapGeneration, 1.0),
+            ]),
             min_time_between_quests: 5, // 5 seconds minimum between quests
+            min_time_between_map_generations: 10,
             max_resource_gain_per_action: 1000.0,
             max_level_jumps: 5, // Max 5 levels at once
             suspicious_threshold: 10,
+            audit_log_capacity: 1000,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Rate limit (actions per second) for an action type
+    pub fn limit_for(&self, action_type: ActionType) -> f32 {
+        self.action_rate_limits.get(&action_type).copied().unwrap_or(self.max_actions_per_second)
+    }
+    
+    /// Read `CQ_MAX_APS`, `CQ_MIN_QUEST_GAP`, `CQ_MIN_MAP_GAP`, `CQ_MAX_RESOURCE_GAIN`, `CQ_MAX_LEVEL_JUMP`,
+    /// `CQ_SUSPICIOUS_THRESHOLD` and `CQ_AUDIT_LOG_CAPACITY` over the defaults;
+    /// unparsable or out-of-range values are ignored with a warning
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_actions_per_second: env_in_range("CQ_MAX_APS", 0.1, 1000.0, defaults.max_actions_per_second),
+            min_time_between_quests: env_in_range("CQ_MIN_QUEST_GAP", 0, 3600, defaults.min_time_between_quests),
+            min_time_between_map_generations: env_in_range("CQ_MIN_MAP_GAP", 0, 3600, defaults.min_time_between_map_generations),
+            max_resource_gain_per_action: env_in_range("CQ_MAX_RESOURCE_GAIN", 1.0, 1e12, defaults.max_resource_gain_per_action),
+            max_level_jumps: env_in_range("CQ_MAX_LEVEL_JUMP", 1, 1000, defaults.max_level_jumps),
+            suspicious_threshold: env_in_range("CQ_SUSPICIOUS_THRESHOLD", 1, 10_000, defaults.suspicious_threshold),
+            audit_log_capacity: env_in_range("CQ_AUDIT_LOG_CAPACITY", 1, 1_000_000, defaults.audit_log_capacity),
+            ..defaults
+        }
+    }
+    
+    /// Check every value is positive and within a sane range
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.max_actions_per_second > 0.0 && self.max_actions_per_second.is_finite()) {
+            return Err(format!("max_actions_per_second must be positive, got {}", self.max_actions_per_second));
+        }
+        if let Some((action, limit)) = self.action_rate_limits.iter().find(|(_, limit)| !(**limit > 0.0 && limit.is_finite())) {
+            return Err(format!("{:?} rate limit must be positive, got {}", action, limit));
+        }
+        if !(self.max_resource_gain_per_action > 0.0 && self.max_resource_gain_per_action.is_finite()) {
+            return Err(format!("max_resource_gain_per_action must be positive, got {}", self.max_resource_gain_per_action));
+        }
+        if self.max_level_jumps == 0 {
+            return Err("max_level_jumps must be at least 1".to_string());
+        }
+        if self.suspicious_threshold == 0 {
+            return Err("suspicious_threshold must be at least 1".to_string());
+        }
+        if self.audit_log_capacity == 0 {
+            return Err("audit_log_capacity must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Parse an env var within `[min, max]`, falling back to `default` with a warning
+fn env_in_range<T>(name: &str, min: T, max: T, default: T) -> T
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let Ok(raw) = std::env::var(name) else { return default };
+    match raw.trim().parse::<T>() {
+        Ok(value) if value >= min && value <= max => value,
+        _ => {
+            warn!("Ignoring {}={}: expected a number between {} and {}", name, raw, min, max);
+            default
         }
     }
 }
@@ -47,53 +191,180 @@ impl Default for SecurityManager {
     fn default() -> Self {
         Self {
             player_actions: Arc::new(RwLock::new(HashMap::new())),
+            banned_players: Arc::new(RwLock::new(HashSet::new())),
+            whitelist: Arc::new(RwLock::new(HashSet::new())),
+            ban_reasons: Arc::new(RwLock::new(HashMap::new())),
+            audit_log: Arc::new(RwLock::new(VecDeque::new())),
             validation_config: ValidationConfig::default(),
+            db: None,
         }
     }
 }
 
 impl SecurityManager {
-    /// Validate a resource collection action
-    pub fn validate_resource_collection(
-        &self, 
-        player_id: u32, 
-        amount: f32
+    /// Validate any player action against its per-type rate limit and type-specific rules.
+    ///
+    /// `amount` is the resource gain for collections, the level jump for level-ups,
+    /// and ignored otherwise. Banned players are always rejected, and players
+    /// crossing the suspicious threshold are banned automatically. Only the ban is
+    /// on record for a banned player, so their further actions don't fill the audit log.
+    pub fn validate_action(
+        &self,
+        player_id: u32,
+        action_type: ActionType,
+        amount: f32,
+    ) -> ValidationResult {
+        self.validate_scaled(player_id, action_type, amount, 1)
+    }
+    
+    /// `validate_action` with the per-action resource gain cap multiplied by `gain_scale`
+    fn validate_scaled(
+        &self,
+        player_id: u32,
+        action_type: ActionType,
+        amount: f32,
+        gain_scale: u32,
+    ) -> ValidationResult {
+        if self.is_whitelisted(player_id) {
+            debug!("Whitelisted player {} skipped {:?} validation", player_id, action_type);
+            return ValidationResult::Approved;
+        }
+        if self.is_banned(player_id) {
+            return ValidationResult::Rejected("banned".to_string());
+        }
+        
+        let result = self.evaluate_action(player_id, action_type, amount, gain_scale);
+        if !matches!(result, ValidationResult::Approved) {
+            self.record_event(player_id, action_type, &result, amount);
+        }
+        
+        let suspicious_count = self.player_actions.read()
+            .get(&player_id)
+            .map_or(0, |history| history.suspicious_activity_count);
+        if suspicious_count >= self.validation_config.suspicious_threshold {
+            self.ban_player(player_id, "suspicious activity threshold reached");
+        }
+        
+        result
+    }
+    
+    fn record_event(&self, player_id: u32, action: ActionType, result: &ValidationResult, amount: f32) {
+        let mut detail = match result {
+            ValidationResult::Rejected(reason) => reason.clone(),
+            ValidationResult::RateLimited => format!("{:?} rate limit exceeded", action),
+            ValidationResult::Flagged => "suspicious activity threshold reached".to_string(),
+            ValidationResult::Approved => String::new(),
+        };
+        if matches!(action, ActionType::ResourceCollection | ActionType::LevelUp) {
+            detail.push_str(&format!(" (amount: {})", amount));
+        }
+        
+        let mut log = self.audit_log.write();
+        while log.len() >= self.validation_config.audit_log_capacity.max(1) {
+            log.pop_front();
+        }
+        log.push_back(SecurityEvent {
+            player_id,
+            action,
+            result: result.clone(),
+            timestamp: get_current_timestamp(),
+            detail,
+        });
+    }
+    
+    /// Up to `limit` most recent security events, newest first
+    pub fn recent_events(&self, limit: usize) -> Vec<SecurityEvent> {
+        self.audit_log.read().iter().rev().take(limit).cloned().collect()
+    }
+    
+    /// All retained security events for a player, oldest first
+    pub fn events_for_player(&self, player_id: u32) -> Vec<SecurityEvent> {
+        self.audit_log.read().iter()
+            .filter(|event| event.player_id == player_id)
+            .cloned()
+            .collect()
+    }
+    
+    fn evaluate_action(
+        &self,
+        player_id: u32,
+        action_type: ActionType,
+        amount: f32,
+        gain_scale: u32,
     ) -> ValidationResult {
         let current_time = get_current_timestamp();
         let mut actions = self.player_actions.write();
-        let player_history = actions.entry(player_id).or_insert_with(|| PlayerActionHistory {
-            last_resource_collection: 0,
-            last_quest_completion: 0,
-            last_level_up: 0,
-            actions_per_second: 0.0,
-            suspicious_activity_count: 0,
-        });
+        let player_history = actions.entry(player_id).or_default();
+        let config = &self.validation_config;
         
-        // Check for excessive resource gain
-        if amount > self.validation_config.max_resource_gain_per_action {
-            player_history.suspicious_activity_count += 1;
-            warn!("Player {} attempting excessive resource gain: {}", player_id, amount);
-            return ValidationResult::Rejected("Excessive resource gain detected".to_string());
+        // Type-specific checks
+        match action_type {
+            ActionType::ResourceCollection => {
+                // Check for excessive resource gain
+                if amount > config.max_resource_gain_per_action * gain_scale.max(1) as f32 {
+                    player_history.suspicious_activity_count += 1;
+                    warn!("Player {} attempting excessive resource gain: {}", player_id, amount);
+                    return ValidationResult::Rejected("Excessive resource gain detected".to_string());
+                }
+            }
+            ActionType::QuestCompletion => {
+                // Check minimum time between quests
+                let time_since_last = current_time.saturating_sub(player_history.last_quest_completion);
+                if time_since_last < config.min_time_between_quests {
+                    player_history.suspicious_activity_count += 1;
+                    warn!("Player {} completing quests too quickly: {}s since last", player_id, time_since_last);
+                    return ValidationResult::Rejected("Quest completion too frequent".to_string());
+                }
+            }
+            ActionType::LevelUp => {
+                let level_jump = amount.max(0.0) as u32;
+                if level_jump > config.max_level_jumps {
+                    player_history.suspicious_activity_count += 5; // Severe penalty
+                    error!("Player {} suspicious level jump (+{})", player_id, level_jump);
+                    return ValidationResult::Rejected("Suspicious level progression".to_string());
+                }
+            }
+            ActionType::MapGeneration => {
+                let time_since_last = current_time.saturating_sub(player_history.last_map_generation);
+                if time_since_last < config.min_time_between_map_generations {
+                    player_history.suspicious_activity_count += 1;
+                    warn!("Player {} requesting maps too quickly: {}s since last", player_id, time_since_last);
+                    return ValidationResult::RateLimited;
+                }
+            }
+            ActionType::SftMint => {}
         }
         
-        // Check action rate
-        let time_since_last = current_time.saturating_sub(player_history.last_resource_collection);
-        if time_since_last < 1 { // Less than 1 second
-            player_history.actions_per_second += 1.0;
-        } else {
-            player_history.actions_per_second = 1.0;
-        }
+        // Check action rate for this type over a sliding window
+        let now = Instant::now();
+        let rate = player_history.action_rates.entry(action_type).or_default();
+        let actions_per_second = rate.rate_with(now);
+        rate.actions_per_second = actions_per_second;
+        player_history.actions_per_second = player_history.action_rates.values()
+            .map(|r| r.actions_per_second)
+            .fold(0.0, f32::max);
         
-        if player_history.actions_per_second > self.validation_config.max_actions_per_second {
+        let limit = config.limit_for(action_type);
+        if actions_per_second > limit {
             player_history.suspicious_activity_count += 1;
-            warn!("Player {} exceeding action rate limit: {} actions/sec", player_id, player_history.actions_per_second);
+            warn!("Player {} exceeding {:?} rate limit: {} actions/sec", player_id, action_type, actions_per_second);
             return ValidationResult::RateLimited;
         }
         
-        player_history.last_resource_collection = current_time;
+        if let Some(rate) = player_history.action_rates.get_mut(&action_type) {
+            rate.last_action = current_time;
+            rate.recent.push_back(now);
+        }
+        match action_type {
+            ActionType::ResourceCollection => player_history.last_resource_collection = current_time,
+            ActionType::QuestCompletion => player_history.last_quest_completion = current_time,
+            ActionType::LevelUp => player_history.last_level_up = current_time,
+            ActionType::MapGeneration => player_history.last_map_generation = current_time,
+            ActionType::SftMint => {}
+        }
         
         // Check suspicious activity threshold
-        if player_history.suspicious_activity_count >= self.validation_config.suspicious_threshold {
+        if player_history.suspicious_activity_count >= config.suspicious_threshold {
             error!("Player {} flagged for suspicious activity", player_id);
             return ValidationResult::Flagged;
         }
@@ -101,34 +372,37 @@ impl SecurityManager {
         ValidationResult::Approved
     }
     
+    /// Validate a resource collection action
+    pub fn validate_resource_collection(
+        &self, 
+        player_id: u32, 
+        amount: f32
+    ) -> ValidationResult {
+        self.validate_action(player_id, ActionType::ResourceCollection, amount)
+    }
+    
+    /// Validate a collection by a player at `level`. Collection amounts scale with the
+    /// level, so the gain cap does too: `max_resource_gain_per_action` applies per level.
+    pub fn validate_collection_at_level(
+        &self,
+        player_id: u32,
+        amount: f32,
+        level: u32,
+    ) -> ValidationResult {
+        self.validate_scaled(player_id, ActionType::ResourceCollection, amount, level)
+    }
+    
     /// Validate a quest completion
     pub fn validate_quest_completion(
         &self, 
         player_id: u32, 
         quest_id: u32
     ) -> ValidationResult {
-        let current_time = get_current_timestamp();
-        let mut actions = self.player_actions.write();
-        let player_history = actions.entry(player_id).or_insert_with(|| PlayerActionHistory {
-            last_resource_collection: 0,
-            last_quest_completion: 0,
-            last_level_up: 0,
-            actions_per_second: 0.0,
-            suspicious_activity_count: 0,
-        });
-        
-        // Check minimum time between quests
-        let time_since_last = current_time.saturating_sub(player_history.last_quest_completion);
-        if time_since_last < self.validation_config.min_time_between_quests {
-            player_history.suspicious_activity_count += 1;
-            warn!("Player {} completing quests too quickly: {}s since last", player_id, time_since_last);
-            return ValidationResult::Rejected("Quest completion too frequent".to_string());
+        let result = self.validate_action(player_id, ActionType::QuestCompletion, 1.0);
+        if matches!(result, ValidationResult::Approved) {
+            info!("Quest {} completed by player {} validated", quest_id, player_id);
         }
-        
-        player_history.last_quest_completion = current_time;
-        info!("Quest {} completed by player {} validated", quest_id, player_id);
-        
-        ValidationResult::Approved
+        result
     }
     
     /// Validate level progression
@@ -139,22 +413,16 @@ impl SecurityManager {
         new_level: u32
     ) -> ValidationResult {
         let level_jump = new_level.saturating_sub(old_level);
-        
-        if level_jump > self.validation_config.max_level_jumps {
-            let mut actions = self.player_actions.write();
-            if let Some(player_history) = actions.get_mut(&player_id) {
-                player_history.suspicious_activity_count += 5; // Severe penalty
-            }
+        let result = self.validate_action(player_id, ActionType::LevelUp, level_jump as f32);
+        if matches!(result, ValidationResult::Rejected(_)) {
             error!("Player {} suspicious level jump: {} -> {} (+{})", player_id, old_level, new_level, level_jump);
-            return ValidationResult::Rejected("Suspicious level progression".to_string());
         }
-        
-        let mut actions = self.player_actions.write();
-        if let Some(player_history) = actions.get_mut(&player_id) {
-            player_history.last_level_up = get_current_timestamp();
-        }
-        
-        ValidationResult::Approved
+        result
+    }
+    
+    /// Validate a request to generate a map, which is expensive enough to need its own interval
+    pub fn validate_map_generation(&self, player_id: u32) -> ValidationResult {
+        self.validate_action(player_id, ActionType::MapGeneration, 0.0)
     }
     
     /// Get player security status
@@ -162,7 +430,8 @@ impl SecurityManager {
         let actions = self.player_actions.read();
         actions.get(&player_id).map(|history| {
             let is_flagged = history.suspicious_activity_count >= self.validation_config.suspicious_threshold;
-            let is_rate_limited = history.actions_per_second > self.validation_config.max_actions_per_second;
+            let is_rate_limited = history.action_rates.iter()
+                .any(|(action_type, rate)| rate.actions_per_second > self.validation_config.limit_for(*action_type));
             
             PlayerSecurityStatus {
                 player_id,
@@ -170,16 +439,97 @@ impl SecurityManager {
                 actions_per_second: history.actions_per_second,
                 is_flagged,
                 is_rate_limited,
+                is_banned: self.is_banned(player_id),
             }
         })
     }
     
+    /// Ban a player; all further actions are rejected.
+    /// The ban is saved right away when the manager is backed by a database
+    pub fn ban_player(&self, player_id: u32, reason: &str) {
+        if self.banned_players.write().insert(player_id) {
+            self.ban_reasons.write().insert(player_id, reason.to_string());
+            error!("Player {} banned: {}", player_id, reason);
+            if let Some(db) = &self.db {
+                if let Err(e) = db.save_ban(player_id, reason) {
+                    error!("Failed to save the ban of player {}: {}", player_id, e);
+                }
+            }
+        }
+    }
+    
+    /// Check whether a player is banned
+    pub fn is_banned(&self, player_id: u32) -> bool {
+        self.banned_players.read().contains(&player_id)
+    }
+    
+    /// Rebuild a manager from histories persisted in the database, saving new bans back to it
+    pub fn from_db(db: &DatabaseConnection) -> Self {
+        let manager = Self { db: Some(db.clone()), ..Self::default() };
+        match db.load_security_histories() {
+            Ok(histories) => {
+                let mut actions = manager.player_actions.write();
+                actions.extend(histories);
+                info!("Restored security state for {} players", actions.len());
+            }
+            Err(e) => warn!("Failed to load security state: {}", e),
+        }
+        match db.load_bans() {
+            Ok(bans) => {
+                for (player_id, reason) in bans {
+                    manager.banned_players.write().insert(player_id);
+                    manager.ban_reasons.write().insert(player_id, reason);
+                }
+            }
+            Err(e) => warn!("Failed to load ban list: {}", e),
+        }
+        manager
+    }
+    
+    /// Persist every tracked player's history to the database
+    pub fn persist(&self, db: &DatabaseConnection) -> rusqlite::Result<()> {
+        let actions = self.player_actions.read();
+        for (player_id, history) in actions.iter() {
+            db.save_security_history(*player_id, history)?;
+        }
+        let reasons = self.ban_reasons.read();
+        for player_id in self.banned_players.read().iter() {
+            let reason = reasons.get(player_id).map(String::as_str).unwrap_or("");
+            db.save_ban(*player_id, reason)?;
+        }
+        Ok(())
+    }
+    
+    pub fn add_to_whitelist(&self, player_id: u32) {
+        self.whitelist.write().insert(player_id);
+        info!("Player {} whitelisted", player_id);
+    }
+    
+    pub fn remove_from_whitelist(&self, player_id: u32) {
+        if self.whitelist.write().remove(&player_id) {
+            info!("Player {} removed from whitelist", player_id);
+        }
+    }
+    
+    pub fn is_whitelisted(&self, player_id: u32) -> bool {
+        self.whitelist.read().contains(&player_id)
+    }
+    
+    /// Replace the validation rules; takes effect for the next validated action
+    pub fn update_config(&mut self, config: ValidationConfig) -> Result<(), String> {
+        config.validate()?;
+        info!("Security validation config updated: {:?}", config);
+        self.validation_config = config;
+        Ok(())
+    }
+    
     /// Reset player security status (admin function)
     pub fn reset_player_security(&self, player_id: u32) {
         let mut actions = self.player_actions.write();
         if let Some(player_history) = actions.get_mut(&player_id) {
             player_history.suspicious_activity_count = 0;
             player_history.actions_per_second = 0.0;
+            player_history.action_rates.clear();
             info!("Security status reset for player {}", player_id);
         }
     }
@@ -200,6 +550,7 @@ pub struct PlayerSecurityStatus {
     pub actions_per_second: f32,
     pub is_flagged: bool,
     pub is_rate_limited: bool,
+    pub is_banned: bool,
 }
 
 /// Get current timestamp in seconds
@@ -211,32 +562,63 @@ fn get_current_timestamp() -> u64 {
 }
 
 /// System to initialize security manager
-pub fn setup_security_manager(mut commands: Commands) {
-    commands.insert_resource(SecurityManager::default());
+pub fn setup_security_manager(mut commands: Commands, db: Res<DatabaseConnection>) {
+    let mut manager = SecurityManager::from_db(&db);
+    if let Err(e) = manager.update_config(ValidationConfig::from_env()) {
+        warn!("Invalid security config, using defaults: {}", e);
+    }
+    for player_id in parse_whitelist(&std::env::var("CQ_SECURITY_WHITELIST").unwrap_or_default()) {
+        manager.add_to_whitelist(player_id);
+    }
+    commands.insert_resource(manager);
     info!("Security manager initialized with anti-cheat protection");
 }
 
-/// System to periodically clean up old security data
+/// Parse a comma-separated list of player ids, as in `CQ_SECURITY_WHITELIST`; invalid entries are skipped
+pub fn parse_whitelist(list: &str) -> HashSet<u32> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.parse() {
+            Ok(player_id) => Some(player_id),
+            Err(_) => {
+                warn!("Ignoring invalid whitelist entry '{}'", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+/// System to periodically persist security state
+pub fn persist_security_state(security_manager: Res<SecurityManager>, db: Res<DatabaseConnection>) {
+    if let Err(e) = security_manager.persist(&db) {
+        error!("Failed to persist security state: {}", e);
+    }
+}
+
+/// System to clean up old security data; scheduled every 5 minutes with `on_timer`
 pub fn security_cleanup(
     security_manager: Res<SecurityManager>,
-    time: Res<Time>,
+    db: Res<DatabaseConnection>,
 ) {
-    // Run cleanup every 5 minutes
-    if time.elapsed_seconds() as u64 % 300 == 0 {
-        let current_time = get_current_timestamp();
-        let mut actions = security_manager.player_actions.write();
-        
-        // Remove entries older than 1 hour
-        actions.retain(|_, history| {
-            let last_activity = history.last_resource_collection
-                .max(history.last_quest_completion)
-                .max(history.last_level_up);
-            
-            current_time.saturating_sub(last_activity) < 3600 // 1 hour
-        });
+    let current_time = get_current_timestamp();
+    let mut actions = security_manager.player_actions.write();
+    
+    // Remove entries older than 1 hour
+    actions.retain(|_, history| {
+        let last_activity = history.last_resource_collection
+            .max(history.last_quest_completion)
+            .max(history.last_level_up)
+            .max(history.last_map_generation);
         
-        info!("Security cleanup completed, {} active players tracked", actions.len());
+        current_time.saturating_sub(last_activity) < 3600 // 1 hour
+    });
+    
+    if let Err(e) = db.delete_security_histories_before(current_time.saturating_sub(3600)) {
+        error!("Failed to delete expired security state: {}", e);
     }
+    
+    info!("Security cleanup completed, {} active players tracked", actions.len());
 }
 
 /// Input sanitization utilities
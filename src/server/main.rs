@@ -1,43 +1,154 @@
 use enet::{self, *};
-use std::time::Duration;
-use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use log::*;
 use env_logger;
+use chainquest_idle::config::env::ServerConfig;
+use chainquest_idle::multiplayer::network::{channel_for, GameMessage, MessageKind, CONTROL_CHANNEL, STATE_CHANNEL};
+use chainquest_idle::multiplayer::server::{handle_event, ServerAction, ServerEvent, ServerState, SHUTDOWN_GRACE, SHUTDOWN_REASON};
+use chainquest_idle::security::ValidationConfig;
+
+/// State updates may be dropped in favour of the next one; everything else is reliable
+fn packet_mode(channel: u8) -> PacketMode {
+    if channel == STATE_CHANNEL {
+        PacketMode::UnreliableSequenced
+    } else {
+        PacketMode::ReliableSequenced
+    }
+}
 
 fn main() {
     env_logger::Builder::from_default_env()
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    info!("Starting ENet server on 0.0.0.0:8080");
+    let config = match ServerConfig::from_env().with_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = config.validate() {
+        error!("Invalid server configuration: {}", e);
+        std::process::exit(2);
+    }
+    info!(
+        "Starting ENet server on {}:{} (max clients: {}, channels: {}, bandwidth in/out: {}/{} B/s)",
+        config.bind_ip, config.port, config.max_clients, config.channels,
+        config.incoming_bandwidth, config.outgoing_bandwidth
+    );
     let _enet = enet::initialize().expect("Failed to init ENet");
 
-    let address = Address::new(Ipv4Addr::UNSPECIFIED, 8080);
+    let address = Address::new(config.bind_ip, config.port);
+    let params = config.host_params();
     let mut server = Host::new(
         Some(&address),
-        8,   // max clients
-        2,   // channels
-        0,   // in bandwidth
-        0,   // out bandwidth
+        params.max_peers,
+        params.channels,
+        params.incoming_bandwidth,
+        params.outgoing_bandwidth,
     ).expect("failed to create server host");
+    let mut state = ServerState::default();
+    if let Err(e) = state.security.update_config(ValidationConfig::from_env()) {
+        warn!("Invalid security config, using defaults: {}", e);
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+    if let Err(e) = ctrlc::set_handler(move || flag.store(false, Ordering::SeqCst)) {
+        warn!("Failed to install Ctrl-C handler: {}", e);
+    }
 
-    loop {
+    while running.load(Ordering::SeqCst) {
+        let mut relays: Vec<(u32, u8, Vec<u8>)> = Vec::new();
         if let Some(event) = server.service(Duration::from_millis(50)).unwrap() {
             match event {
-                Event::Connect(peer) => {
+                Event::Connect(mut peer) => {
                     info!("Client connected: {:?}", peer.address());
+                    let peer_id = state.allocate_peer_id();
+                    peer.set_data(peer_id);
+                    let addr = format!("{:?}", peer.address());
+                    handle_event(ServerEvent::Connected { peer_id, addr }, &mut state);
                 }
                 Event::Disconnect(peer, reason) => {
                     info!("Client disconnected: {:?} reason={:?}", peer.address(), reason);
+                    handle_event(ServerEvent::Disconnected { peer_id: peer.data() }, &mut state);
                 }
                 Event::Receive{packet, channel_id, peer} => {
-                    let data = packet.data();
-                    info!("Received {} bytes on ch {} from {:?}", data.len(), channel_id, peer.address());
-                    // Echo back for MVP
-                    let _ = peer.send_packet(Packet::new(data, PacketMode::ReliableSequenced).unwrap(), channel_id);
+                    let peer_id = peer.data();
+                    info!("Received {} bytes on ch {} from {:?}", packet.data().len(), channel_id, peer.address());
+                    let mut kicked = false;
+                    for data in state.decode(peer_id, packet.data()) {
+                        let reply = match handle_event(ServerEvent::Received { peer_id, channel_id, data }, &mut state) {
+                            ServerAction::Reply(message) => Some(message),
+                            ServerAction::Pong => Some(GameMessage::Pong),
+                            ServerAction::Relay(data) => {
+                                relays.push((peer_id, channel_id, data));
+                                None
+                            }
+                            ServerAction::Joined { player_id, announce } => {
+                                relays.push((peer_id, channel_id, announce));
+                                Some(GameMessage::JoinAccepted { player_id })
+                            }
+                            ServerAction::Disconnect => {
+                                peer.disconnect(0);
+                                None
+                            }
+                            ServerAction::Kick(reason) => {
+                                kicked = true;
+                                Some(GameMessage::Disconnect { reason })
+                            }
+                            ServerAction::Ignore => None,
+                        };
+                        if let Some(message) = reply {
+                            let channel = channel_for(&message);
+                            if let Some(bytes) = state.encode_message(&message) {
+                                if peer.send_packet(Packet::new(&bytes, packet_mode(channel)).unwrap(), channel).is_ok() {
+                                    state.record_sent(bytes.len());
+                                }
+                            }
+                        }
+                    }
+                    if kicked {
+                        peer.disconnect_later(0);
+                    }
                 }
                 _ => {}
             }
         }
+
+        for (sender_id, channel_id, data) in relays {
+            let Some(bytes) = state.encode(MessageKind::Raw, &data) else { continue };
+            for mut peer in server.peers() {
+                if state.relays_to(sender_id, peer.data())
+                    && peer.send_packet(Packet::new(&bytes, packet_mode(channel_id)).unwrap(), channel_id).is_ok()
+                {
+                    state.record_sent(bytes.len());
+                }
+            }
+        }
     }
+
+    info!("Shutting down, disconnecting {} peers", state.sessions.len());
+    let goodbye = state.encode_message(&GameMessage::Disconnect { reason: SHUTDOWN_REASON.to_string() }).unwrap();
+    for mut peer in server.peers() {
+        if peer.send_packet(Packet::new(&goodbye, PacketMode::ReliableSequenced).unwrap(), CONTROL_CHANNEL).is_ok() {
+            state.record_sent(goodbye.len());
+        }
+        peer.disconnect_later(0);
+    }
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    while !state.sessions.is_empty() && Instant::now() < deadline {
+        if let Some(Event::Disconnect(peer, _)) = server.service(Duration::from_millis(50)).unwrap() {
+            handle_event(ServerEvent::Disconnected { peer_id: peer.data() }, &mut state);
+        }
+    }
+
+    let stats = &state.stats;
+    info!("Final network stats: Sent: {} packets/{} bytes, Received: {} packets/{} bytes",
+        stats.packets_sent, stats.bytes_sent,
+        stats.packets_received, stats.bytes_received
+    );
 }
@@ -1,169 +1,119 @@
-//! Game systems for Bevy ECS
+//! Collection crediting, pause and persistence systems for Bevy ECS
+//!
+//! Idle progression lives in `systems_idle` and startup systems in `systems_setup`.
 
 use bevy::prelude::*;
-use std::time::{SystemTime, UNIX_EPOCH};
 use crate::components::*;
 use crate::resources::*;
+use crate::config::keys::{Action, KeyBindings};
+use crate::security::{SecurityManager, ValidationResult};
 
-/// Setup camera for the game
-pub fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+/// Credit collections the anti-cheat approves to the player they name; rejected and
+/// rate-limited ones are dropped, as is everything while no `SecurityManager` exists
+pub fn apply_validated_collection(
+    mut collections: EventReader<ResourceCollected>,
+    mut query: Query<(&mut IdleProgress, Option<&PlayerId>), With<Player>>,
+    security: Option<Res<SecurityManager>>,
+    mut credited: EventWriter<ResourcesCredited>,
+) {
+    for collection in collections.read() {
+        let Some((mut progress, _)) = query.iter_mut()
+            .find(|(_, id)| id.copied().unwrap_or_default().0 == collection.player_id)
+        else {
+            warn!("Collection of {} for unknown player {} dropped", collection.amount, collection.player_id);
+            continue;
+        };
+        let result = match security.as_deref() {
+            Some(s) => s.validate_collection_at_level(collection.player_id, collection.amount as f32, progress.level),
+            None => ValidationResult::Rejected("anti-cheat not initialized".to_string()),
+        };
+        if !matches!(result, ValidationResult::Approved) {
+            warn!("Collection of {} by player {} dropped: {:?}", collection.amount, collection.player_id, result);
+            continue;
+        }
+        progress.resources += collection.amount;
+        info!("Resource collection by player {}! Total: {}", collection.player_id, progress.resources);
+        credited.send(ResourcesCredited { player_id: collection.player_id, amount: collection.amount, source: collection.source });
+    }
 }
 
-/// Setup initial UI
-pub fn setup_ui(mut commands: Commands) {
-    // Spawn player entity
-    commands.spawn((
-        Player,
-        IdleProgress::default(),
-        Position { x: 0.0, y: 0.0 },
-    ));
-    
-    info!("Game UI initialized");
+/// Resources granted by one manual (or automatic) collection
+pub fn collect_amount(level: u32) -> f64 {
+    10.0 * level as f64
 }
 
-/// Load saved progress from database
-pub fn load_saved_progress(
-    mut commands: Commands,
-    db: Res<DatabaseConnection>,
+/// Toggle the simulation between running and paused with the pause key (P by default)
+pub fn toggle_pause(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
 ) {
-    if let Ok(progress) = db.load_progress() {
-        info!("Loaded saved progress: {} resources", progress.resources);
-        // Update existing player or create new one
-        commands.spawn((
-            Player,
-            progress,
-            Position { x: 0.0, y: 0.0 },
-        ));
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::Pause) {
+        let next = match state.get() {
+            SimState::Running => SimState::Paused,
+            SimState::Paused => SimState::Running,
+        };
+        info!("Simulation {:?}", next);
+        next_state.set(next);
     }
 }
 
-/// Main idle progress update system
-pub fn update_idle_progress(
-    mut query: Query<&mut IdleProgress, With<Player>>,
-    time: Res<Time>,
-) {
-    for mut progress in query.iter_mut() {
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs_f64();
-        
-        let delta_time = current_time - progress.last_update;
-        if delta_time > 0.0 {
-            // Idle resource generation
-            let resource_rate = (progress.level as f32) * 0.5; // Resources per second
-            progress.resources += resource_rate * delta_time as f32;
-            
-            // Experience gain
-            progress.experience += 0.1 * delta_time as f32;
-            
-            // Level up check
-            let required_exp = (progress.level * progress.level) as f32 * 10.0;
-            if progress.experience >= required_exp {
-                progress.level += 1;
-                progress.experience = 0.0;
-                info!("Level up! New level: {}", progress.level);
-            }
-            
-            progress.last_update = current_time;
-        }
+/// Copy the database next to itself so a corrupted main file can be recovered
+pub fn backup_database(db: Res<DatabaseConnection>) {
+    let Some(path) = db.backup_path() else { return };
+    match db.backup_to(&path) {
+        Ok(()) => info!("Database backed up to {}", path.display()),
+        Err(e) => error!("Failed to back up database to {}: {}", path.display(), e),
     }
 }
 
-/// Handle user input
-pub fn handle_input(
-    mut query: Query<&mut IdleProgress, With<Player>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+/// Save progress; registered on a `SaveConfig::interval_secs` timer
+pub fn autosave(
+    query: Query<(&IdleProgress, Option<&Health>, Option<&PlayerStats>), With<Player>>,
+    db: Res<DatabaseConnection>,
+    game_state: Res<GameState>,
 ) {
-    if keyboard.just_pressed(KeyCode::Space) {
-        for mut progress in query.iter_mut() {
-            progress.resources += 10.0 * (progress.level as f32);
-            info!("Manual resource collection! Total: {}", progress.resources);
-        }
-    }
-    
-    if keyboard.just_pressed(KeyCode::KeyQ) {
-        info!("Quest system activated (placeholder)");
-        // TODO: Implement quest generation
-    }
+    write_progress(&query, &db, &game_state);
 }
 
-/// Render UI elements
-pub fn render_ui(
-    mut gizmos: Gizmos,
-    query: Query<&IdleProgress, With<Player>>,
+/// Save progress immediately when the save key is pressed
+pub fn save_now(
+    query: Query<(&IdleProgress, Option<&Health>, Option<&PlayerStats>), With<Player>>,
+    db: Res<DatabaseConnection>,
+    game_state: Res<GameState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
 ) {
-    // Simple visual feedback using gizmos
-    if let Ok(progress) = query.get_single() {
-        // Draw resource indicator
-        let resource_bar_length = (progress.resources / 100.0).min(200.0);
-        gizmos.line_2d(
-            Vec2::new(-300.0, 300.0),
-            Vec2::new(-300.0 + resource_bar_length, 300.0),
-            Color::GREEN,
-        );
-        
-        // Draw level indicator
-        for i in 0..progress.level {
-            gizmos.circle_2d(
-                Vec2::new(-280.0 + (i as f32 * 20.0), 250.0),
-                8.0,
-                Color::YELLOW,
-            );
-        }
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::Save) {
+        write_progress(&query, &db, &game_state);
     }
 }
 
-/// Save progress to database
-pub fn save_progress(
-    query: Query<&IdleProgress, With<Player>>,
+/// Save progress when the app is exiting; runs in `Last` so the write completes before the window closes
+pub fn save_on_exit(
+    mut exits: EventReader<AppExit>,
+    query: Query<(&IdleProgress, Option<&Health>, Option<&PlayerStats>), With<Player>>,
     db: Res<DatabaseConnection>,
-    mut timer: Local<f32>,
-    time: Res<Time>,
+    game_state: Res<GameState>,
 ) {
-    *timer += time.delta_seconds();
-    
-    // Save every 10 seconds
-    if *timer >= 10.0 {
-        if let Ok(progress) = query.get_single() {
-            if let Err(e) = db.save_progress(progress) {
-                error!("Failed to save progress: {}", e);
-            } else {
-                info!("Progress saved: {} resources, level {}", progress.resources, progress.level);
-            }
-        }
-        *timer = 0.0;
+    if exits.read().last().is_some() {
+        write_progress(&query, &db, &game_state);
     }
 }
 
-/// Generate AI map system (placeholder)
-pub fn generate_ai_map(
-    mut commands: Commands,
-    keyboard: Res<ButtonInput<KeyCode>>,
+fn write_progress(
+    query: &Query<(&IdleProgress, Option<&Health>, Option<&PlayerStats>), With<Player>>,
+    db: &DatabaseConnection,
+    game_state: &GameState,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyM) {
-        info!("Generating AI map...");
-        
-        // Simple 16x16 grid generation
-        for x in 0..16 {
-            for y in 0..16 {
-                let tile_type = match (x + y) % 4 {
-                    0 => TileType::Empty,
-                    1 => TileType::Resource,
-                    2 => TileType::Enemy,
-                    3 => TileType::Quest,
-                    _ => TileType::Portal,
-                };
-                
-                commands.spawn(MapTile {
-                    tile_type,
-                    grid_x: x,
-                    grid_y: y,
-                });
-            }
+    if let Ok((progress, health, stats)) = query.get_single() {
+        if let Err(e) = db.save_player_state(game_state.save_slot, progress, health, stats) {
+            error!("Failed to save progress: {}", e);
+        } else {
+            info!("Progress saved: {} resources, level {}", progress.resources, progress.level);
         }
-        
-        info!("AI map generated: 16x16 grid");
     }
-}
\ No newline at end of file
+}
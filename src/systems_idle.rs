@@ -1,23 +1,389 @@
 use bevy::prelude::*;
+use std::collections::HashMap;
 use crate::components::*;
 use crate::resources::*;
+use crate::quest_system::{fast_forward_quests, QuestManager, QuestPreferences};
+use crate::config::multiplier::{GlobalMultiplier, unix_now};
+use crate::config::progression::ProgressionConfig;
+use crate::config::debug::DebugConfig;
+use crate::blockchain::client::StakingBonus;
+use crate::config::keys::{Action, KeyBindings};
+use crate::security::{SecurityManager, ValidationResult};
+use crate::systems::collect_amount;
 
+/// Advance idle progression for every player.
+///
+/// Bevy's frame `Time` delta is the authoritative time source; `last_update`
+/// accumulates simulated seconds rather than wall-clock time.
 pub fn update_idle_progress(
-    mut query: Query<&mut IdleProgress, With<Player>>,
+    mut query: Query<(Entity, &mut IdleProgress, Option<&mut PlayerStats>, Option<&PlayerId>), With<Player>>,
+    generators: Query<&Generator>,
+    game_state: Option<Res<GameState>>,
     time: Res<Time>,
+    multiplier: Option<Res<GlobalMultiplier>>,
+    config: Option<Res<ProgressionConfig>>,
+    staking: Option<Res<StakingBonus>>,
+    security: Option<Res<SecurityManager>>,
+    mut level_ups: EventWriter<LevelUpEvent>,
+    mut produced: EventWriter<ResourcesProduced>,
+    mut level_up_retry_at: Local<HashMap<Entity, f64>>,
 ) {
-    for mut progress in query.iter_mut() {
-        let delta = time.delta_seconds_f64();
+    let default_config = ProgressionConfig::default();
+    let config = config.as_deref().unwrap_or(&default_config);
+    let now = time.elapsed_seconds_f64();
+    let factor = multiplier.map(|m| m.factor_at(unix_now())).unwrap_or(1.0)
+        * config.staking_factor(staking.map_or(0, |s| s.staked_power));
+    let generator_rate = slot_generator_rate(&generators, game_state.as_deref());
+    for (player, mut progress, stats, player_id) in query.iter_mut() {
         if progress.last_update == 0.0 { progress.last_update = time.elapsed_seconds_f64(); }
-        let resource_rate = (progress.level as f32) * 0.5;
-        progress.resources += resource_rate * delta as f32;
-        progress.experience += 0.1 * delta as f32;
-        let required_exp = (progress.level * progress.level) as f32 * 10.0;
-        if progress.experience >= required_exp {
-            progress.level += 1;
-            progress.experience = 0.0;
-            info!("Level up! New level: {}", progress.level);
-        }
-        progress.last_update += delta;
+        let (old_level, old_experience, old_resources) = (progress.level, progress.experience, progress.resources);
+        let overflow = tick_idle_progress(&mut progress, config, generator_rate, factor, time.delta_seconds_f64());
+        if progress.resources > old_resources {
+            produced.send(ResourcesProduced { player, amount: progress.resources - old_resources });
+        }
+        if let Some(mut stats) = stats.filter(|_| overflow > 0.0) {
+            stats.essence += overflow * config.overflow_conversion as f64;
+        }
+        let player_id = player_id.copied().unwrap_or_default().0;
+        let held = level_up_retry_at.get(&player).is_some_and(|&retry_at| now < retry_at);
+        if held && progress.level > old_level {
+            // Backing off a rejected level-up: stay at the threshold without asking again
+            progress.level = old_level;
+            progress.experience = old_experience;
+        } else if validate_level_change(security.as_deref(), player_id, &mut progress, old_level, old_experience) {
+            if progress.level > old_level {
+                level_up_retry_at.remove(&player);
+            }
+            send_level_up(&mut level_ups, player, old_level, progress.level);
+        } else {
+            level_up_retry_at.insert(player, now + LEVEL_UP_RETRY_SECS);
+        }
+    }
+}
+
+/// Seconds a rejected level-up waits before it is validated again
+pub const LEVEL_UP_RETRY_SECS: f64 = 5.0;
+
+/// Check a level change since `old_level` with the anti-cheat layer, reverting to `old_level`
+/// and `old_experience` unless it is approved, so a rejected level-up can be retried later.
+/// Returns whether the new level was kept.
+pub fn validate_level_change(
+    security: Option<&SecurityManager>,
+    player_id: u32,
+    progress: &mut IdleProgress,
+    old_level: u32,
+    old_experience: f64,
+) -> bool {
+    if progress.level <= old_level {
+        return true;
+    }
+    let result = security.map_or(ValidationResult::Approved, |s| s.validate_level_up(player_id, old_level, progress.level));
+    if matches!(result, ValidationResult::Approved) {
+        return true;
+    }
+    warn!("Level up {} -> {} not accepted: {:?}", old_level, progress.level, result);
+    progress.level = old_level;
+    progress.experience = old_experience;
+    false
+}
+
+fn send_level_up(level_ups: &mut EventWriter<LevelUpEvent>, player: Entity, old_level: u32, new_level: u32) {
+    if new_level > old_level {
+        info!("Level up! New level: {}", new_level);
+        level_ups.send(LevelUpEvent { player, old_level, new_level });
+    }
+}
+
+/// Advance one player's progression by `delta` simulated seconds, producing the level rate
+/// plus `generator_rate` (both scaled by `factor`). Returns the resources discarded for
+/// exceeding the storage cap.
+pub fn tick_idle_progress(
+    progress: &mut IdleProgress,
+    config: &ProgressionConfig,
+    generator_rate: f64,
+    factor: f32,
+    delta: f64,
+) -> f64 {
+    let resource_rate = (config.resource_rate(progress.level) as f64 + generator_rate) * factor as f64;
+    let cap = config.resource_cap(progress.level) as f64;
+    let produced = progress.resources + resource_rate * delta;
+    // Balances already above the cap (e.g. manual collection) are kept but don't grow
+    let overflow = (produced - cap.max(progress.resources)).max(0.0);
+    progress.resources = produced - overflow;
+    progress.experience += config.exp_per_second as f64 * delta;
+    let required_exp = config.required_exp(progress.level) as f64;
+    if progress.experience >= required_exp {
+        progress.level += 1;
+        progress.experience = 0.0;
+    }
+    progress.last_update += delta;
+    overflow
+}
+
+/// Advance progression by `seconds` in one-second ticks, evaluating the
+/// multiplier at each simulated instant after `start` (UNIX seconds) and
+/// applying the staking bonus like `update_idle_progress` does.
+/// Returns the total resources lost to the storage cap.
+pub fn fast_forward_progress(
+    progress: &mut IdleProgress,
+    config: &ProgressionConfig,
+    multiplier: Option<&GlobalMultiplier>,
+    staking: Option<&StakingBonus>,
+    generator_rate: f64,
+    start: f64,
+    seconds: f64,
+) -> f64 {
+    let staking_factor = config.staking_factor(staking.map_or(0, |s| s.staked_power));
+    let mut elapsed = 0.0;
+    let mut overflow = 0.0;
+    while elapsed < seconds {
+        let step = (seconds - elapsed).min(1.0);
+        let factor = multiplier.map(|m| m.factor_at(start + elapsed)).unwrap_or(1.0) * staking_factor;
+        overflow += tick_idle_progress(progress, config, generator_rate, factor, step);
+        elapsed += step;
+    }
+    overflow
+}
+
+/// Debug action (F5 with `CQ_DEBUG`): simulate `fast_forward_secs` of idle time in one step
+pub fn debug_fast_forward(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    debug: Option<Res<DebugConfig>>,
+    mut query: Query<(Entity, &mut IdleProgress, Option<&mut PlayerStats>, Option<&PlayerId>), With<Player>>,
+    generators: Query<&Generator>,
+    game_state: Option<Res<GameState>>,
+    config: Option<Res<ProgressionConfig>>,
+    multiplier: Option<Res<GlobalMultiplier>>,
+    staking: Option<Res<StakingBonus>>,
+    security: Option<Res<SecurityManager>>,
+    quest_manager: Option<ResMut<QuestManager>>,
+    preferences: Option<Res<QuestPreferences>>,
+    mut rng: Option<ResMut<GameRng>>,
+    mut level_ups: EventWriter<LevelUpEvent>,
+) {
+    let Some(debug) = debug.filter(|d| d.enabled) else { return };
+    if !keyboard.just_pressed(KeyCode::F5) {
+        return;
+    }
+    
+    let default_config = ProgressionConfig::default();
+    let config = config.as_deref().unwrap_or(&default_config);
+    let seconds = debug.fast_forward_secs;
+    let start = unix_now();
+    let generator_rate = slot_generator_rate(&generators, game_state.as_deref());
+    let mut level = 1;
+    for (player, mut progress, stats, player_id) in query.iter_mut() {
+        let (old_level, old_experience) = (progress.level, progress.experience);
+        let overflow = fast_forward_progress(
+            &mut progress, config, multiplier.as_deref(), staking.as_deref(), generator_rate, start, seconds,
+        );
+        if let Some(mut stats) = stats {
+            stats.essence += overflow * config.overflow_conversion as f64;
+        }
+        let player_id = player_id.copied().unwrap_or_default().0;
+        if validate_level_change(security.as_deref(), player_id, &mut progress, old_level, old_experience) {
+            send_level_up(&mut level_ups, player, old_level, progress.level);
+        }
+        level = progress.level;
+    }
+    
+    if let Some(mut quest_manager) = quest_manager {
+        let default_preferences = QuestPreferences::default();
+        let preferences = preferences.as_deref().unwrap_or(&default_preferences);
+        let mut fallback_rng = GameRng::default();
+        let rng = rng.as_deref_mut().unwrap_or(&mut fallback_rng);
+        fast_forward_quests(&mut commands, &mut quest_manager, level, preferences, seconds as f32, rng);
+    }
+    info!("Fast-forwarded {} simulated seconds", seconds);
+}
+
+/// Request a manual collection when the collect key is pressed; like every collection it is
+/// credited by `apply_validated_collection` only once the anti-cheat approves it
+pub fn manual_collect(
+    query: Query<(&IdleProgress, Option<&PlayerId>), With<Player>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut collections: EventWriter<ResourceCollected>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::Collect) {
+        for (progress, player_id) in query.iter() {
+            let player_id = player_id.copied().unwrap_or_default().0;
+            collections.send(ResourceCollected { player_id, amount: collect_amount(progress.level), source: None });
+        }
+    }
+}
+
+/// Unlock auto-collect at the configured level and, every interval, request the manual-collect
+/// bonus for unlocked players. Requests are credited by `apply_validated_collection` like any collection.
+pub fn auto_collect(
+    mut query: Query<(&mut IdleProgress, Option<&PlayerId>), With<Player>>,
+    config: Option<Res<ProgressionConfig>>,
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    mut collections: EventWriter<ResourceCollected>,
+) {
+    let default_config = ProgressionConfig::default();
+    let config = config.as_deref().unwrap_or(&default_config);
+    for (mut progress, _) in query.iter_mut() {
+        if !progress.auto_collect && progress.level >= config.auto_collect_level {
+            progress.auto_collect = true;
+            info!("Auto-collect unlocked at level {}", progress.level);
+        }
+    }
+    
+    *elapsed += time.delta_seconds();
+    if *elapsed < config.auto_collect_interval_secs {
+        return;
+    }
+    *elapsed = 0.0;
+    
+    for (progress, player_id) in query.iter().filter(|(p, _)| p.auto_collect) {
+        let player_id = player_id.copied().unwrap_or_default().0;
+        collections.send(ResourceCollected { player_id, amount: collect_amount(progress.level), source: None });
+    }
+}
+
+/// Spend resources on the next tier of a generator, returning the price paid
+pub fn purchase_upgrade(progress: &mut IdleProgress, generator: &mut Generator) -> Result<f64, String> {
+    let cost = generator.upgrade_cost();
+    if progress.resources < cost {
+        return Err(format!(
+            "{} tier {} costs {:.1}, only {:.1} available",
+            generator.kind.as_str(), generator.tier + 1, cost, progress.resources
+        ));
+    }
+    progress.resources -= cost;
+    generator.tier += 1;
+    Ok(cost)
+}
+
+/// Combined rate of the generators owned by the active save slot
+pub fn slot_generator_rate<'a>(generators: impl IntoIterator<Item = &'a Generator>, game_state: Option<&GameState>) -> f64 {
+    let slot = game_state.map_or(DEFAULT_SAVE_SLOT, |s| s.save_slot);
+    generators.into_iter().filter(|g| g.slot == slot).map(Generator::rate).sum()
+}
+
+/// Cycle the generator the upgrade key buys (G by default)
+pub fn select_generator(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    mut selected: ResMut<SelectedGenerator>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if bindings.just_pressed(&keyboard, Action::SelectGenerator) {
+        selected.0 = selected.0.next();
+        info!("Selected generator: {}", selected.0.as_str());
+    }
+}
+
+/// Buy a tier of the selected generator of the active save slot when the upgrade key (U by default) is pressed
+pub fn buy_upgrade(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Option<Res<KeyBindings>>,
+    selected: Option<Res<SelectedGenerator>>,
+    mut players: Query<&mut IdleProgress, With<Player>>,
+    mut generators: Query<&mut Generator>,
+    db: Option<Res<DatabaseConnection>>,
+    game_state: Option<Res<GameState>>,
+) {
+    let bindings = bindings.map(|b| b.clone()).unwrap_or_default();
+    if !bindings.just_pressed(&keyboard, Action::BuyUpgrade) {
+        return;
+    }
+    let kind = selected.map(|s| *s).unwrap_or_default().0;
+    let slot = game_state.map_or(DEFAULT_SAVE_SLOT, |s| s.save_slot);
+    let Ok(mut progress) = players.get_single_mut() else { return };
+    let Some(mut generator) = generators.iter_mut().find(|g| g.kind == kind && g.slot == slot) else { return };
+    
+    match purchase_upgrade(&mut progress, &mut generator) {
+        Ok(cost) => {
+            info!("Upgraded {} to tier {} for {:.1} resources", generator.kind.as_str(), generator.tier, cost);
+            if let Some(db) = db {
+                if let Err(e) = db.save_generator(slot, generator.kind.as_str(), generator.tier) {
+                    warn!("Failed to save generator: {}", e);
+                }
+            }
+        }
+        Err(reason) => info!("{}", reason),
+    }
+}
+
+/// Track lifetime resources and grant experience once per milestone crossed
+pub fn check_resource_milestones(
+    mut query: Query<(&mut IdleProgress, &mut PlayerStats), With<Player>>,
+    config: Option<Res<ProgressionConfig>>,
+) {
+    let default_config = ProgressionConfig::default();
+    let config = config.as_deref().unwrap_or(&default_config);
+    for (mut progress, mut stats) in query.iter_mut() {
+        // Only gains count; the first observation just sets the baseline (e.g. a loaded save)
+        if let Some(last_seen) = stats.last_seen_resources {
+            let gained = progress.resources - last_seen;
+            if gained > 0.0 {
+                stats.lifetime_resources += gained;
+            }
+        }
+        stats.last_seen_resources = Some(progress.resources);
+        
+        let milestones = config.milestones_for(stats.lifetime_resources);
+        if milestones > stats.milestones_reached {
+            let crossed = milestones - stats.milestones_reached;
+            progress.experience += (crossed as f32 * config.milestone_exp) as f64;
+            stats.milestones_reached = milestones;
+            info!("Resource milestone {} reached: +{} exp", milestones, crossed as f32 * config.milestone_exp);
+        }
+    }
+}
+
+/// Achievements whose thresholds are met by the given totals
+pub fn evaluate_achievements(
+    progress: &IdleProgress,
+    lifetime_resources: f64,
+    quests_completed: usize,
+    sfts_earned: usize,
+) -> Vec<Achievement> {
+    Achievement::ALL
+        .into_iter()
+        .filter(|achievement| match achievement {
+            Achievement::ReachLevel10 => progress.level >= 10,
+            Achievement::Collect10kResources => lifetime_resources >= 10_000.0,
+            Achievement::Complete5Quests => quests_completed >= 5,
+            Achievement::FirstSFT => sfts_earned >= 1,
+        })
+        .collect()
+}
+
+/// Unlock achievements once their thresholds are crossed; registered on a one-second timer,
+/// since FirstSFT needs a database lookup
+pub fn check_achievements(
+    query: Query<(&IdleProgress, Option<&PlayerStats>), With<Player>>,
+    quest_manager: Option<Res<QuestManager>>,
+    db: Res<DatabaseConnection>,
+    mut achievements: ResMut<Achievements>,
+    mut unlocked_events: EventWriter<AchievementUnlocked>,
+) {
+    let Ok((progress, stats)) = query.get_single() else { return };
+    let lifetime_resources = stats.map_or(0.0, |stats| stats.lifetime_resources);
+    let quests_completed = quest_manager.map(|qm| qm.completed_quests.len()).unwrap_or(0);
+    // Only minted SFTs count; skip the lookup once the achievement is in
+    let sfts_earned = if achievements.unlocked.contains(&Achievement::FirstSFT) {
+        0
+    } else {
+        db.count_confirmed_sfts().unwrap_or_else(|e| {
+            warn!("Failed to count SFTs: {}", e);
+            0
+        })
+    };
+    
+    for achievement in evaluate_achievements(progress, lifetime_resources, quests_completed, sfts_earned) {
+        if achievements.unlocked.insert(achievement) {
+            info!("Achievement unlocked: {}", achievement.as_str());
+            if let Err(e) = db.save_achievement(achievement) {
+                error!("Failed to save achievement {}: {}", achievement.as_str(), e);
+            }
+            unlocked_events.send(AchievementUnlocked(achievement));
+        }
     }
 }
@@ -1,20 +1,137 @@
 use bevy::prelude::*;
-use crate::ai::startup::{init_map_system, MapSeed};
+use crate::ai::startup::init_map_system;
+use crate::ai::integration::restore_map_seed;
 
 pub fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
 
-pub fn setup_ui(mut commands: Commands) {
+pub fn setup_ui(
+    mut commands: Commands,
+    db: Option<Res<crate::resources::DatabaseConnection>>,
+    game_state: Option<Res<crate::resources::GameState>>,
+) {
     use crate::components::{Player, IdleProgress, Position};
+    let slot = game_state.map_or(crate::resources::DEFAULT_SAVE_SLOT, |s| s.save_slot);
     commands.spawn((
         Player,
+        local_player_id(db.as_deref(), slot),
+        saved_health(db.as_deref(), slot),
         IdleProgress::default(),
+        saved_stats(db.as_deref(), slot),
         Position { x: 0.0, y: 0.0 },
     ));
     info!("Game UI initialized");
 }
 
-pub fn setup_map(mut commands: Commands, db: Res<crate::resources::DatabaseConnection>) {
-    init_map_system(commands, db, Res::from(MapSeed(1337)));
+/// The player id stored for a save slot, assigning and persisting the default on first run
+pub fn local_player_id(db: Option<&crate::resources::DatabaseConnection>, slot: u32) -> crate::components::PlayerId {
+    use crate::components::PlayerId;
+    let Some(db) = db else { return PlayerId::default() };
+    match db.load_player_id(slot) {
+        Ok(id) => PlayerId(id),
+        Err(_) => {
+            let id = PlayerId::default();
+            if let Err(e) = db.save_player_id(slot, id.0) {
+                warn!("Failed to save player id: {}", e);
+            }
+            id
+        }
+    }
+}
+
+/// The player's health stored for a save slot, full health if none was saved
+pub fn saved_health(db: Option<&crate::resources::DatabaseConnection>, slot: u32) -> crate::components::Health {
+    db.and_then(|db| db.load_health(slot).ok()).unwrap_or_default()
+}
+
+/// The player's lifetime statistics stored for a save slot, fresh ones if none were saved
+pub fn saved_stats(db: Option<&crate::resources::DatabaseConnection>, slot: u32) -> crate::components::PlayerStats {
+    db.and_then(|db| db.load_stats(slot).ok()).unwrap_or_default()
+}
+
+/// Apply saved progress to the player spawned by `setup_ui`; must run after it
+pub fn load_saved_progress(
+    mut commands: Commands,
+    db: Res<crate::resources::DatabaseConnection>,
+    game_state: Res<crate::resources::GameState>,
+    mut players: Query<&mut crate::components::IdleProgress, With<crate::components::Player>>,
+) {
+    use crate::components::{Player, Position};
+    if let Ok(progress) = db.load_progress(game_state.save_slot) {
+        info!("Loaded saved progress from slot {}: {} resources", game_state.save_slot, progress.resources);
+        match players.get_single_mut() {
+            Ok(mut existing) => *existing = progress,
+            Err(_) => {
+                commands.spawn((
+                    Player,
+                    local_player_id(Some(&db), game_state.save_slot),
+                    saved_health(Some(&db), game_state.save_slot),
+                    progress,
+                    saved_stats(Some(&db), game_state.save_slot),
+                    Position { x: 0.0, y: 0.0 },
+                ));
+            }
+        }
+    }
+}
+
+/// Load the player profile from `CQ_USERNAME` or the save slot, persisting the result
+pub fn load_player_profile(
+    mut commands: Commands,
+    db: Res<crate::resources::DatabaseConnection>,
+    game_state: Res<crate::resources::GameState>,
+) {
+    use crate::resources::PlayerProfile;
+    let configured = std::env::var("CQ_USERNAME").ok();
+    let stored = db.load_username(game_state.save_slot).ok();
+    let profile = PlayerProfile::resolve(configured.as_deref(), stored.as_deref());
+    if let Err(e) = db.save_username(game_state.save_slot, &profile.username) {
+        warn!("Failed to save username: {}", e);
+    }
+    info!("Playing as {}", profile.username);
+    commands.insert_resource(profile);
+}
+
+/// Restore unlocked achievements so thresholds crossed before a reload don't re-fire
+pub fn load_achievements(mut commands: Commands, db: Res<crate::resources::DatabaseConnection>) {
+    let unlocked = db.load_achievements().unwrap_or_else(|e| {
+        warn!("Failed to load achievements: {}", e);
+        Default::default()
+    });
+    commands.insert_resource(crate::resources::Achievements { unlocked });
+}
+
+/// Spawn every generator kind of the active save slot with its saved tier (0 if never bought)
+pub fn load_generators(
+    mut commands: Commands,
+    db: Res<crate::resources::DatabaseConnection>,
+    game_state: Res<crate::resources::GameState>,
+) {
+    use crate::components::{Generator, GeneratorKind};
+    let slot = game_state.save_slot;
+    let saved = db.load_generators(slot).unwrap_or_else(|e| {
+        warn!("Failed to load generators: {}", e);
+        Vec::new()
+    });
+    for kind in GeneratorKind::ALL {
+        let mut generator = Generator::new(kind, slot);
+        generator.tier = saved.iter()
+            .find(|(name, _)| GeneratorKind::from_name(name) == Some(kind))
+            .map_or(0, |(_, tier)| *tier);
+        commands.spawn(generator);
+    }
+}
+
+/// Load the world slot's last active map (or the default one) and make it current
+pub fn setup_map(
+    mut commands: Commands,
+    db: Res<crate::resources::DatabaseConnection>,
+    mut game_state: ResMut<crate::resources::GameState>,
+) {
+    let seed = restore_map_seed(game_state.world_slot, &db);
+    game_state.current_map_seed = seed.0;
+    game_state.map_depth = db.load_map_depth(game_state.world_slot).unwrap_or(0);
+    commands.insert_resource(seed);
+    init_map_system(commands, &db, game_state.world_slot, &seed);
 }
@@ -0,0 +1,206 @@
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use std::collections::VecDeque;
+use crate::ai::{GenerationSource, MapGenerator};
+use crate::multiplayer::network::{NetworkManager, NetworkStats};
+use crate::resources::GameState;
+use crate::ui::font::UiFont;
+
+/// Debug overlay visibility; toggled with F3, initially shown when `CQ_DEBUG` is set
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+impl DebugOverlay {
+    pub fn from_env() -> Self {
+        Self { visible: std::env::var("CQ_DEBUG").map(|v| v != "0" && !v.is_empty()).unwrap_or(false) }
+    }
+}
+
+#[derive(Component)]
+pub struct DebugHud;
+
+#[derive(Component)]
+pub struct FpsHud;
+
+/// Recent frame times, for the FPS readout's 1% low
+#[derive(Debug, Clone, Default)]
+pub struct FrameTimeHistory {
+    samples: VecDeque<f64>,
+}
+
+impl FrameTimeHistory {
+    /// Frames kept in the rolling window
+    pub const CAPACITY: usize = 1000;
+    
+    pub fn push(&mut self, frame_time_ms: f64) {
+        if self.samples.len() == Self::CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time_ms);
+    }
+    
+    /// Average FPS over the slowest 1% of frames in the window
+    pub fn one_percent_low(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut slowest: Vec<f64> = self.samples.iter().copied().collect();
+        slowest.sort_by(|a, b| b.total_cmp(a));
+        let count = (slowest.len() / 100).max(1);
+        let average_ms = slowest[..count].iter().sum::<f64>() / count as f64;
+        (average_ms > 0.0).then(|| 1000.0 / average_ms)
+    }
+}
+
+/// Smoothed network throughput derived from frame-to-frame deltas of the cumulative totals
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NetRateTracker {
+    last_bytes_sent: u64,
+    last_bytes_received: u64,
+    initialized: bool,
+    pub sent_per_sec: f64,
+    pub received_per_sec: f64,
+}
+
+impl NetRateTracker {
+    /// Weight of the newest sample in the moving average
+    pub const SMOOTHING: f64 = 0.1;
+    
+    /// Fold in the totals observed after `delta` seconds
+    pub fn update(&mut self, stats: &NetworkStats, delta: f64) {
+        if self.initialized && delta > 0.0 {
+            let sent = stats.bytes_sent.saturating_sub(self.last_bytes_sent) as f64 / delta;
+            let received = stats.bytes_received.saturating_sub(self.last_bytes_received) as f64 / delta;
+            self.sent_per_sec += Self::SMOOTHING * (sent - self.sent_per_sec);
+            self.received_per_sec += Self::SMOOTHING * (received - self.received_per_sec);
+        }
+        self.last_bytes_sent = stats.bytes_sent;
+        self.last_bytes_received = stats.bytes_received;
+        self.initialized = true;
+    }
+}
+
+/// Toggle the debug overlay with F3
+pub fn toggle_debug_overlay(keyboard: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+pub fn debug_overlay_setup(mut commands: Commands, ui_font: Res<UiFont>) {
+    let font = ui_font.handle.clone();
+    commands.spawn((
+        FpsHud,
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle { font: font.clone(), font_size: 18.0, color: Color::YELLOW }
+            ),
+            transform: Transform::from_xyz(-480.0, 200.0, 0.0),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+    commands.spawn((
+        DebugHud,
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle { font, font_size: 18.0, color: Color::YELLOW }
+            ),
+            text_2d_bounds: Text2dBounds { size: Vec2::new(800.0, 200.0) },
+            transform: Transform::from_xyz(-480.0, 160.0, 0.0),
+            ..default()
+        },
+    ));
+}
+
+/// Build the FPS line of the debug overlay
+pub fn format_fps(fps: f64, one_percent_low: Option<f64>) -> String {
+    match one_percent_low {
+        Some(low) => format!("FPS: {:.0} | 1% low: {:.0}", fps, low),
+        None => format!("FPS: {:.0}", fps),
+    }
+}
+
+/// Build the network section of the debug overlay
+pub fn format_network_stats(stats: &NetworkStats, rates: &NetRateTracker) -> String {
+    format!(
+        "Net sent: {} pkts / {} B ({:.0} B/s) | recv: {} pkts / {} B ({:.0} B/s)\nCompression: {:.2} ({:?}, {} skipped) | Rate violations: {}",
+        stats.packets_sent, stats.bytes_sent, rates.sent_per_sec,
+        stats.packets_received, stats.bytes_received, rates.received_per_sec,
+        stats.compression_ratio, stats.last_compression_algo, stats.compression_skipped,
+        stats.rate_limit_violations
+    )
+}
+
+/// Build the map generation section of the debug overlay
+pub fn format_generation_stats(generator: &MapGenerator) -> String {
+    let stats = generator.get_stats();
+    let sources = GenerationSource::ALL
+        .iter()
+        .map(|source| format!("{}: {}", source.as_str(), stats.lifetime_count(*source)))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    format!(
+        "Maps (lifetime) {}\nCache hits: {} | Session {}",
+        sources, stats.cache_hits, stats.timing_summary()
+    )
+}
+
+pub fn debug_overlay_update(
+    mut q: Query<(&mut Text, &mut Visibility), With<DebugHud>>,
+    overlay: Option<Res<DebugOverlay>>,
+    map_generator: Option<Res<MapGenerator>>,
+    network_manager: Option<Res<NetworkManager>>,
+    game_state: Option<Res<GameState>>,
+    mut rates: Local<NetRateTracker>,
+    time: Res<Time>,
+) {
+    if let Some(network_manager) = network_manager.as_deref() {
+        rates.update(network_manager.get_stats(), time.delta_seconds_f64());
+    }
+    
+    let visible = overlay.map_or(false, |o| o.visible);
+    for (mut text, mut visibility) in q.iter_mut() {
+        *visibility = if visible { Visibility::Visible } else { Visibility::Hidden };
+        if !visible {
+            continue;
+        }
+        let sections: Vec<String> = [
+            network_manager.as_deref().map(|nm| format_network_stats(nm.get_stats(), &rates)),
+            map_generator.as_deref().map(format_generation_stats),
+            game_state.as_deref().map(|gs| format!("Map seed: {} (depth {})", gs.current_map_seed, gs.map_depth)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        text.sections[0].value = sections.join("\n");
+    }
+}
+
+/// Show the current FPS and 1% low alongside the debug overlay
+pub fn fps_update(
+    mut q: Query<(&mut Text, &mut Visibility), With<FpsHud>>,
+    overlay: Option<Res<DebugOverlay>>,
+    diagnostics: Option<Res<DiagnosticsStore>>,
+    mut history: Local<FrameTimeHistory>,
+) {
+    let Some(diagnostics) = diagnostics else { return };
+    if let Some(frame_time) = diagnostics.get(FrameTimeDiagnosticsPlugin::FRAME_TIME).and_then(|d| d.value()) {
+        history.push(frame_time);
+    }
+    
+    let visible = overlay.map_or(false, |o| o.visible);
+    for (mut text, mut visibility) in q.iter_mut() {
+        *visibility = if visible { Visibility::Visible } else { Visibility::Hidden };
+        if !visible {
+            continue;
+        }
+        let fps = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS).and_then(|d| d.smoothed()).unwrap_or(0.0);
+        text.sections[0].value = format_fps(fps, history.one_percent_low());
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use std::env;
+use std::path::PathBuf;
+
+/// HUD font loaded when `CQ_FONT` is not set, relative to the asset folder
+pub const DEFAULT_FONT_PATH: &str = "fonts/DejaVuSans-Bold.ttf";
+
+/// Asset folder used when `CQ_ASSET_DIR` is not set
+pub const DEFAULT_ASSET_DIR: &str = "assets";
+
+/// Embedded copy of the default font, so the HUD stays legible without an asset folder
+const FALLBACK_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// Which font the HUD text uses
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct FontConfig {
+    /// Path relative to the asset folder
+    pub path: String,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self { path: DEFAULT_FONT_PATH.to_string() }
+    }
+}
+
+impl FontConfig {
+    /// Read `CQ_FONT` over the default
+    pub fn from_env() -> Self {
+        env::var("CQ_FONT").ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| Self { path })
+            .unwrap_or_default()
+    }
+}
+
+/// Asset folder from `CQ_ASSET_DIR`, passed to `AssetPlugin`
+pub fn asset_dir_from_env() -> String {
+    env::var("CQ_ASSET_DIR").unwrap_or_else(|_| DEFAULT_ASSET_DIR.to_string())
+}
+
+/// Where the asset server looks for `path`: the asset folder under `BEVY_ASSET_ROOT`, the
+/// manifest directory when run through cargo, or the executable's directory
+pub fn resolve_asset_path(path: &str) -> PathBuf {
+    FileAssetReader::get_base_path().join(asset_dir_from_env()).join(path)
+}
+
+/// Font shared by all HUD text
+#[derive(Resource, Debug, Clone)]
+pub struct UiFont {
+    pub handle: Handle<Font>,
+    /// Configured path, kept for diagnostics
+    pub path: String,
+    /// Whether the embedded fallback is in use
+    pub fallback: bool,
+}
+
+/// The embedded fallback font
+pub fn fallback_font() -> Font {
+    Font::try_from_bytes(FALLBACK_FONT.to_vec()).expect("embedded fallback font is valid")
+}
+
+/// Load the configured font, or the embedded fallback when the file is missing
+pub fn setup_ui_font(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut fonts: ResMut<Assets<Font>>,
+    config: Option<Res<FontConfig>>,
+) {
+    let path = config.map(|c| c.path.clone()).unwrap_or_else(|| DEFAULT_FONT_PATH.to_string());
+    let full_path = resolve_asset_path(&path);
+    let ui_font = if full_path.is_file() {
+        UiFont { handle: asset_server.load(path.clone()), path, fallback: false }
+    } else {
+        warn!("HUD font not found at {}, using the embedded fallback", full_path.display());
+        UiFont { handle: fonts.add(fallback_font()), path, fallback: true }
+    };
+    commands.insert_resource(ui_font);
+}
+
+/// Switch text to the fallback font if the configured one exists but fails to load
+pub fn watch_ui_font(
+    asset_server: Res<AssetServer>,
+    ui_font: Option<ResMut<UiFont>>,
+    mut fonts: ResMut<Assets<Font>>,
+    mut texts: Query<&mut Text>,
+) {
+    let Some(mut ui_font) = ui_font else { return };
+    if ui_font.fallback || asset_server.get_load_state(&ui_font.handle) != Some(LoadState::Failed) {
+        return;
+    }
+    warn!("Failed to load HUD font {}, using the embedded fallback", ui_font.path);
+    let failed = std::mem::replace(&mut ui_font.handle, fonts.add(fallback_font()));
+    ui_font.fallback = true;
+    for mut text in texts.iter_mut() {
+        for section in text.sections.iter_mut().filter(|s| s.style.font == failed) {
+            section.style.font = ui_font.handle.clone();
+        }
+    }
+}
@@ -1,14 +1,20 @@
 use bevy::prelude::*;
 use bevy::text::Text2dBounds;
-use crate::resources::GameState;
-use crate::components::IdleProgress;
-use crate::multiplayer::client::NetState;
+use crate::resources::{BlockchainState, GameState, LevelUpEvent, PlayerProfile, SimState};
+use crate::components::{IdleProgress, Quest};
+use crate::quest_system::QuestManager;
+use crate::multiplayer::client::{NetState, NetworkMode};
+use crate::config::multiplier::{GlobalMultiplier, unix_now};
+use crate::config::progression::ProgressionConfig;
+use crate::ai::MapGenerationTask;
+use crate::ui::font::UiFont;
+use crate::ui::i18n::{tr, Lang};
 
 #[derive(Component)]
 pub struct Hud;
 
-pub fn ui_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+pub fn ui_setup(mut commands: Commands, ui_font: Res<UiFont>) {
+    let font = ui_font.handle.clone();
     commands.spawn((
         Hud,
         Text2dBundle {
@@ -23,20 +29,146 @@ pub fn ui_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
+/// How long the HUD stays highlighted after a level-up, in seconds
+pub const LEVEL_UP_FLASH_SECS: f32 = 1.5;
+
+/// Highlight the HUD text for a moment whenever the player levels up
+pub fn flash_hud_on_level_up(
+    mut level_ups: EventReader<LevelUpEvent>,
+    mut q: Query<&mut Text, With<Hud>>,
+    mut remaining: Local<f32>,
+    time: Res<Time>,
+) {
+    if level_ups.read().last().is_some() {
+        *remaining = LEVEL_UP_FLASH_SECS;
+    } else if *remaining > 0.0 {
+        *remaining = (*remaining - time.delta_seconds()).max(0.0);
+    } else {
+        return;
+    }
+    let color = if *remaining > 0.0 { Color::GOLD } else { Color::WHITE };
+    for mut text in q.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
+
+/// Values shown in the HUD
+#[derive(Debug, Clone, Default)]
+pub struct HudData {
+    pub lang: Lang,
+    pub username: String,
+    pub resources: f64,
+    /// Storage cap at the current level; not shown when zero
+    pub resource_cap: f32,
+    pub level: u32,
+    /// Reward SFTs owned by the wallet
+    pub sft_balance: u32,
+    /// Submitted transactions awaiting a result
+    pub pending_txs: usize,
+    /// Wallet connection line, if a connection was attempted
+    pub wallet_status: Option<String>,
+    /// Show the multiplayer/peers lines
+    pub multiplayer: bool,
+    pub connected: bool,
+    pub last_msg: String,
+    pub total_players: usize,
+    pub event_factor: Option<f32>,
+    /// Spinner frame while a map is generating in the background
+    pub map_generating: Option<usize>,
+    pub paused: bool,
+    /// Selected quest's name and objective progress
+    pub quest: Option<(String, String)>,
+}
+
+/// Build the HUD text in `data.lang`
+pub fn format_hud(data: &HudData) -> String {
+    let t = |key| tr(data.lang, key);
+    let resources = if data.resource_cap > 0.0 {
+        format!("{:.1} / {:.0}", data.resources, data.resource_cap)
+    } else {
+        format!("{:.1}", data.resources)
+    };
+    let mut hud = format!(
+        "ChainQuest - {}\n{}: {} | {}: {} | {}: {}",
+        data.username,
+        t("resources"), resources,
+        t("level"), data.level,
+        t("sfts"), data.sft_balance
+    );
+    if data.pending_txs > 0 {
+        hud.push_str(&format!(" ({} {})", data.pending_txs, t("pending_tx")));
+    }
+    if data.multiplayer {
+        let conn = if data.connected { t("online") } else { t("offline") };
+        hud.push_str(&format!(
+            "\n{}: {} | {}: {}\n{}: {}",
+            t("multiplayer"), conn, t("last"), data.last_msg, t("players"), data.total_players
+        ));
+    }
+    if let Some(wallet) = &data.wallet_status {
+        hud.push('\n');
+        hud.push_str(wallet);
+    }
+    if let Some(factor) = data.event_factor {
+        hud.push_str(&format!("\n{}: x{} {}!", t("event"), factor, t("event_resources")));
+    }
+    if let Some((name, progress)) = &data.quest {
+        hud.push_str(&format!("\n{}: {} ({})", t("quest"), name, progress));
+    }
+    if data.paused {
+        hud.push_str(&format!("\n{}", t("paused")));
+    }
+    if let Some(frame) = data.map_generating {
+        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+        hud.push_str(&format!("\n{} {}", t("generating_map"), SPINNER[frame % SPINNER.len()]));
+    }
+    hud
+}
+
 pub fn ui_update(
     mut q: Query<&mut Text, With<Hud>>,
     progress: Query<&IdleProgress>,
-    net: Res<NetState>,
+    net: Option<Res<NetState>>,
+    mode: Option<Res<NetworkMode>>,
     gs: Res<GameState>,
+    profile: Option<Res<PlayerProfile>>,
+    multiplier: Option<Res<GlobalMultiplier>>,
+    blockchain: Option<Res<BlockchainState>>,
+    lang: Option<Res<Lang>>,
+    map_task: Option<Res<MapGenerationTask>>,
+    sim_state: Option<Res<State<SimState>>>,
+    config: Option<Res<ProgressionConfig>>,
+    quest_manager: Option<Res<QuestManager>>,
+    quests: Query<&Quest>,
+    time: Res<Time>,
 ) {
     if let Ok(mut text) = q.get_single_mut() {
         let p = progress.get_single().ok();
-        let res = p.map(|v| v.resources).unwrap_or(0.0);
-        let lvl = p.map(|v| v.level).unwrap_or(1);
-        let conn = if net.connected { "online" } else { "offline" };
-        text.sections[0].value = format!(
-            "ChainQuest\nResurse: {:.1} | Level: {}\nMultiplayer: {} | Last: {}\nPlayers: {}",
-            res, lvl, conn, net.last_msg, gs.total_players
-        );
+        let default_config = ProgressionConfig::default();
+        let config = config.as_deref().unwrap_or(&default_config);
+        let data = HudData {
+            lang: lang.map(|l| *l).unwrap_or_default(),
+            username: profile.map(|pr| pr.username.clone()).unwrap_or_default(),
+            resources: p.map(|v| v.resources).unwrap_or(0.0),
+            resource_cap: config.resource_cap(p.map(|v| v.level).unwrap_or(1)),
+            level: p.map(|v| v.level).unwrap_or(1),
+            sft_balance: blockchain.as_ref().map_or(0, |b| b.sft_balance),
+            pending_txs: blockchain.as_ref().map_or(0, |b| b.pending_count()),
+            wallet_status: blockchain.and_then(|b| b.wallet_status()),
+            multiplayer: mode.map_or(true, |m| *m == NetworkMode::Multiplayer),
+            connected: net.as_ref().map_or(false, |n| n.connected),
+            last_msg: net.map(|n| n.last_msg.clone()).unwrap_or_default(),
+            total_players: gs.total_players,
+            event_factor: multiplier.filter(|m| m.is_active(unix_now())).map(|m| m.factor),
+            map_generating: map_task.map(|_| (time.elapsed_seconds() * 8.0) as usize),
+            paused: sim_state.map_or(false, |s| *s.get() == SimState::Paused),
+            quest: quest_manager
+                .and_then(|m| m.selected_quest())
+                .and_then(|e| quests.get(e).ok())
+                .map(|q| (q.name.clone(), q.objective.progress_text())),
+        };
+        text.sections[0].value = format_hud(&data);
     }
 }
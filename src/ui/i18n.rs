@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+/// HUD language, selected with `CQ_LANG`
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    English,
+    Romanian,
+}
+
+impl Lang {
+    /// Read `CQ_LANG` ("en" or "ro"), defaulting to English
+    pub fn from_env() -> Self {
+        std::env::var("CQ_LANG").map(|l| Self::from_code(&l)).unwrap_or_default()
+    }
+    
+    /// Parse a language code or name, defaulting to English
+    pub fn from_code(code: &str) -> Self {
+        match code.trim().to_lowercase().as_str() {
+            "ro" | "ro_ro" | "romanian" | "romana" => Lang::Romanian,
+            _ => Lang::English,
+        }
+    }
+}
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("resources", "Resources"),
+    ("level", "Level"),
+    ("sfts", "SFTs"),
+    ("pending_tx", "pending tx"),
+    ("multiplayer", "Multiplayer"),
+    ("online", "online"),
+    ("offline", "offline"),
+    ("last", "Last"),
+    ("players", "Players"),
+    ("event", "EVENT"),
+    ("event_resources", "resources"),
+    ("generating_map", "Generating map"),
+    ("paused", "PAUSED (P to resume)"),
+    ("quest", "Quest"),
+];
+
+const ROMANIAN: &[(&str, &str)] = &[
+    ("resources", "Resurse"),
+    ("level", "Nivel"),
+    ("sfts", "SFT-uri"),
+    ("pending_tx", "tranzacții în așteptare"),
+    ("multiplayer", "Multiplayer"),
+    ("online", "conectat"),
+    ("offline", "deconectat"),
+    ("last", "Ultimul"),
+    ("players", "Jucători"),
+    ("event", "EVENIMENT"),
+    ("event_resources", "resurse"),
+    ("generating_map", "Se generează harta"),
+    ("paused", "PAUZĂ (P pentru a continua)"),
+    ("quest", "Misiune"),
+];
+
+/// Translation table for a language
+pub fn strings(lang: Lang) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        Lang::English => ENGLISH,
+        Lang::Romanian => ROMANIAN,
+    }
+}
+
+/// Look up `key`, falling back to English and then to the key itself
+pub fn tr(lang: Lang, key: &'static str) -> &'static str {
+    let lookup = |table: &'static [(&'static str, &'static str)]| {
+        table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    };
+    lookup(strings(lang)).or_else(|| lookup(ENGLISH)).unwrap_or(key)
+}
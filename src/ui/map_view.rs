@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::ai::MAP_SIZE;
+use crate::components::{MapTile, Player, Position, TileType};
+use crate::ui::font::UiFont;
+
+/// Side length of a rendered tile in pixels
+pub const TILE_SIZE: f32 = 24.0;
+
+/// Fill color of a tile type
+pub fn tile_color(tile_type: &TileType) -> Color {
+    match tile_type {
+        TileType::Empty => Color::rgb(0.25, 0.45, 0.25),
+        TileType::Resource => Color::rgb(0.9, 0.75, 0.2),
+        TileType::Enemy => Color::rgb(0.8, 0.2, 0.2),
+        TileType::Quest => Color::rgb(0.3, 0.5, 0.95),
+        TileType::Portal => Color::rgb(0.7, 0.3, 0.9),
+    }
+}
+
+/// Center of a tile in world space, with the grid centered on screen behind the HUD
+pub fn tile_translation(grid_x: i32, grid_y: i32) -> Vec3 {
+    let offset = (MAP_SIZE as f32 - 1.0) / 2.0;
+    Vec3::new((grid_x as f32 - offset) * TILE_SIZE, (grid_y as f32 - offset) * TILE_SIZE, -1.0)
+}
+
+/// Grid cell under a world-space point; the inverse of `tile_translation`, none outside the map
+pub fn grid_at(world: Vec2) -> Option<(i32, i32)> {
+    let offset = (MAP_SIZE as f32 - 1.0) / 2.0;
+    let grid_x = (world.x / TILE_SIZE + offset).round() as i32;
+    let grid_y = (world.y / TILE_SIZE + offset).round() as i32;
+    let range = 0..MAP_SIZE as i32;
+    (range.contains(&grid_x) && range.contains(&grid_y)).then_some((grid_x, grid_y))
+}
+
+/// Text shown when hovering a tile
+pub fn format_tile_tooltip(tile: &MapTile) -> String {
+    format!("{:?} ({}, {})", tile.tile_type, tile.grid_x, tile.grid_y)
+}
+
+#[derive(Component)]
+pub struct TileTooltip;
+
+pub fn tile_tooltip_setup(mut commands: Commands, ui_font: Res<UiFont>) {
+    commands.spawn((
+        TileTooltip,
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle { font: ui_font.handle.clone(), font_size: 16.0, color: Color::WHITE }
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+/// Show the type and grid coordinates of the tile under the cursor, hiding the tooltip off the map
+pub fn map_tile_hover(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tiles: Query<&MapTile>,
+    mut tooltip: Query<(&mut Text, &mut Transform, &mut Visibility), With<TileTooltip>>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = tooltip.get_single_mut() else { return };
+    let cursor = windows.get_single().ok()
+        .and_then(|window| window.cursor_position())
+        .zip(cameras.get_single().ok())
+        .and_then(|(cursor, (camera, camera_transform))| camera.viewport_to_world_2d(camera_transform, cursor));
+    let hovered = cursor.and_then(|world| {
+        let (grid_x, grid_y) = grid_at(world)?;
+        tiles.iter().find(|tile| tile.grid_x == grid_x && tile.grid_y == grid_y).map(|tile| (world, tile))
+    });
+    
+    match hovered {
+        Some((world, tile)) => {
+            text.sections[0].value = format_tile_tooltip(tile);
+            // Just above the cursor, in front of the tiles
+            transform.translation = (world + Vec2::new(0.0, TILE_SIZE)).extend(1.0);
+            *visibility = Visibility::Visible;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// Give newly spawned map tiles a solid-color sprite
+pub fn render_map_tiles(mut commands: Commands, tiles: Query<(Entity, &MapTile), Added<MapTile>>) {
+    for (entity, tile) in tiles.iter() {
+        commands.entity(entity).insert(SpriteBundle {
+            sprite: Sprite {
+                color: tile_color(&tile.tile_type),
+                // Leave a one-pixel gap so the grid lines show
+                custom_size: Some(Vec2::splat(TILE_SIZE - 1.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(tile_translation(tile.grid_x, tile.grid_y)),
+            ..default()
+        });
+    }
+}
+
+/// Recolor tiles whose type changed, e.g. a resource tile the player used up
+pub fn refresh_tile_sprites(mut tiles: Query<(&MapTile, &mut Sprite), Changed<MapTile>>) {
+    for (tile, mut sprite) in tiles.iter_mut() {
+        sprite.color = tile_color(&tile.tile_type);
+    }
+}
+
+/// Draw the player as a small white square on top of the map
+pub fn render_player_marker(mut commands: Commands, players: Query<(Entity, &Position), Added<Player>>) {
+    for (entity, position) in players.iter() {
+        commands.entity(entity).insert(SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::splat(TILE_SIZE * 0.6)),
+                ..default()
+            },
+            transform: Transform::from_translation(player_translation(position)),
+            ..default()
+        });
+    }
+}
+
+/// Keep the player marker on the grid cell it has moved to
+pub fn sync_player_marker(mut players: Query<(&Position, &mut Transform), (With<Player>, Changed<Position>)>) {
+    for (position, mut transform) in players.iter_mut() {
+        transform.translation = player_translation(position);
+    }
+}
+
+fn player_translation(position: &Position) -> Vec3 {
+    // Between the tiles and the tooltip
+    tile_translation(position.x.round() as i32, position.y.round() as i32).truncate().extend(0.5)
+}
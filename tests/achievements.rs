@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use chainquest_idle::components::{IdleProgress, Player, PlayerStats};
+use chainquest_idle::resources::*;
+use chainquest_idle::systems_idle::check_achievements;
+
+fn achievements_app(db: DatabaseConnection, unlocked: std::collections::HashSet<Achievement>) -> App {
+    let mut app = App::new();
+    app.add_event::<AchievementUnlocked>();
+    app.insert_resource(db);
+    app.insert_resource(Achievements { unlocked });
+    app.world.spawn((
+        Player,
+        IdleProgress { resources: 500.0, experience: 0.0, level: 10, last_update: 0.0, auto_collect: false },
+        PlayerStats { lifetime_resources: 12_000.0, ..Default::default() },
+    ));
+    app.add_systems(Update, check_achievements);
+    app
+}
+
+#[test]
+fn achievements_fire_once_and_survive_reload() {
+    let path = std::env::temp_dir().join("cq_test_achievements.db");
+    let _ = std::fs::remove_file(&path);
+
+    let mut app = achievements_app(DatabaseConnection::open(&path), Default::default());
+    let mut reader = app.world.resource::<Events<AchievementUnlocked>>().get_reader();
+    app.update();
+    let fired: Vec<_> = reader.read(app.world.resource::<Events<AchievementUnlocked>>()).map(|e| e.0).collect();
+    assert_eq!(fired.len(), 2);
+    assert!(fired.contains(&Achievement::ReachLevel10));
+    assert!(fired.contains(&Achievement::Collect10kResources));
+
+    app.update();
+    assert_eq!(reader.read(app.world.resource::<Events<AchievementUnlocked>>()).count(), 0);
+    drop(app);
+
+    // Simulate a reload: unlocked set comes back from the DB and nothing re-fires
+    let db = DatabaseConnection::open(&path);
+    let unlocked = db.load_achievements().expect("load achievements");
+    assert_eq!(unlocked.len(), 2);
+    let mut app = achievements_app(db, unlocked);
+    let mut reader = app.world.resource::<Events<AchievementUnlocked>>().get_reader();
+    app.update();
+    assert_eq!(reader.read(app.world.resource::<Events<AchievementUnlocked>>()).count(), 0);
+
+    drop(app);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn first_sft_waits_for_a_settled_mint() {
+    let db = DatabaseConnection::open(":memory:");
+    db.insert_pending_sft("CQSFT-00000001-0001", "{}", "tx-1", "erd1player").expect("insert ok");
+    let mut app = achievements_app(db.clone(), Default::default());
+    app.update();
+    assert!(!app.world.resource::<Achievements>().unlocked.contains(&Achievement::FirstSFT));
+
+    db.resolve_sft_tx("tx-1", true).expect("settle ok");
+    app.update();
+    assert!(app.world.resource::<Achievements>().unlocked.contains(&Achievement::FirstSFT));
+}
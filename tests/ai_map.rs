@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use chainquest_idle::ai::{generate_procedural, MapGenerator};
 
 #[test]
 fn ai_map_generation_placeholder_runs() {
@@ -6,3 +7,598 @@ fn ai_map_generation_placeholder_runs() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins);
 }
+
+#[test]
+fn map_file_loads_into_expected_grid() {
+    let path = std::env::temp_dir().join("cq_test_valid_map.csv");
+    std::fs::write(&path, "0,1,2\n3,4,0\n1,1,1\n").expect("write map file");
+    let grid = MapGenerator::load_from_file(&path).expect("valid map loads");
+    assert_eq!(grid, vec![vec![0, 1, 2], vec![3, 4, 0], vec![1, 1, 1]]);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn invalid_map_file_returns_error() {
+    let path = std::env::temp_dir().join("cq_test_invalid_map.csv");
+    std::fs::write(&path, "0,1,2\n3,9\n").expect("write map file");
+    assert!(MapGenerator::load_from_file(&path).is_err());
+    let _ = std::fs::remove_file(&path);
+    assert!(MapGenerator::load_from_file("does/not/exist.csv").is_err());
+}
+
+#[test]
+fn switching_slots_loads_each_slots_world() {
+    use chainquest_idle::ai::integration::switch_slot;
+    use chainquest_idle::ai::MapSeed;
+    use chainquest_idle::resources::{DatabaseConnection, GameState};
+
+    let db = DatabaseConnection::open(":memory:");
+    db.save_map(1, 111, "0,1\n1,0").expect("save slot 1");
+    db.save_map(2, 222, "3,3\n4,2").expect("save slot 2");
+    db.save_map(2, 333, "0,0\n0,0").expect("save slot 2 portal map");
+    db.save_current_map_seed(2, 222).expect("slot 2 active map");
+    db.save_map_depth(2, 3).expect("slot 2 depth");
+
+    let mut state = GameState::default();
+    let mut map_seed = MapSeed::default();
+    let grid = switch_slot(2, &mut state, &mut map_seed, &db).expect("slot 2 world");
+    assert_eq!(grid, vec![vec![3, 3], vec![4, 2]], "the active map, not the newest");
+    assert_eq!((state.current_map_seed, map_seed, state.map_depth), (222, MapSeed(222), 3));
+
+    let grid = switch_slot(1, &mut state, &mut map_seed, &db).expect("slot 1 world");
+    assert_eq!(grid, vec![vec![0, 1], vec![1, 0]]);
+    assert_eq!((state.world_slot, state.current_map_seed, map_seed, state.map_depth), (1, 111, MapSeed(111), 0));
+    assert_eq!(state.save_slot, GameState::default().save_slot, "the progress slot is separate");
+}
+
+#[test]
+fn stored_maps_load_with_the_generators_orientation() {
+    use chainquest_idle::ai::integration::load_map_into_world;
+    use chainquest_idle::ai::map_generator::parse_map;
+    use chainquest_idle::components::{MapTile, TileType};
+    use chainquest_idle::resources::DatabaseConnection;
+
+    // Two columns of three tiles; only (x 0, y 2) holds a resource
+    let serialized = "0,0,1\n0,0,0";
+    assert_eq!(parse_map(serialized).expect("valid map")[0][2], 1);
+    let db = DatabaseConnection::open(":memory:");
+    db.save_map(0, 5, serialized).expect("save map");
+
+    let mut app = App::new();
+    app.insert_resource(db);
+    app.add_systems(Update, |db: Res<DatabaseConnection>, commands: Commands| {
+        load_map_into_world(0, 5, &db, commands);
+    });
+    app.update();
+
+    let mut tiles = app.world.query::<&MapTile>();
+    assert_eq!(tiles.iter(&app.world).count(), 6);
+    let resources: Vec<(i32, i32)> = tiles.iter(&app.world)
+        .filter(|tile| matches!(tile.tile_type, TileType::Resource))
+        .map(|tile| (tile.grid_x, tile.grid_y))
+        .collect();
+    assert_eq!(resources, vec![(0, 2)]);
+}
+
+#[test]
+fn async_map_generation_spawns_tiles_after_polling() {
+    use chainquest_idle::ai::{generate_procedural, poll_map_generation, spawn_pending_map_tiles, MapGenerationTask, MapRoot, PendingMapSpawn};
+    use chainquest_idle::components::MapTile;
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(MapGenerator::default());
+    app.init_resource::<MapRoot>();
+    app.add_systems(Update, (poll_map_generation, spawn_pending_map_tiles.after(poll_map_generation)));
+    app.update(); // task pools are initialized by the plugins
+    app.insert_resource(MapGenerationTask::spawn(42));
+
+    for _ in 0..200 {
+        app.update();
+        if !app.world.contains_resource::<MapGenerationTask>() && !app.world.contains_resource::<PendingMapSpawn>() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(!app.world.contains_resource::<MapGenerationTask>(), "task should complete");
+    assert!(!app.world.contains_resource::<PendingMapSpawn>(), "tiles should finish spawning");
+
+    let tiles = app.world.query::<&MapTile>().iter(&app.world).count();
+    assert_eq!(tiles, 16 * 16);
+    assert_eq!(app.world.resource::<MapGenerator>().cache.get(&42), Some(&generate_procedural(42)));
+}
+
+#[test]
+fn generation_source_counts_accumulate_across_restarts() {
+    use chainquest_idle::ai::GenerationSource;
+    use chainquest_idle::resources::DatabaseConnection;
+
+    let db = DatabaseConnection::open(":memory:");
+
+    let mut first_run = MapGenerator::default();
+    first_run.load_persisted_stats(&db).expect("load");
+    first_run.generate_map(1);
+    first_run.generate_map(2);
+    first_run.generate_map(2); // cache hit, not a new generation
+    first_run.persist_stats(&db).expect("persist");
+
+    let mut second_run = MapGenerator::default();
+    second_run.load_persisted_stats(&db).expect("load");
+    assert_eq!(second_run.get_stats().lifetime_count(GenerationSource::Procedural), 2);
+    second_run.generate_map(3);
+    second_run.persist_stats(&db).expect("persist");
+
+    let counts = db.load_generation_counts().expect("counts");
+    assert!(counts.contains(&("procedural".to_string(), 3)));
+    assert!(counts.contains(&("ai".to_string(), 0)));
+}
+
+#[test]
+fn missing_model_file_falls_back_to_procedural() {
+    std::env::set_var("CQ_MAP_MODEL", "does/not/exist.pt");
+    let mut generator = MapGenerator::default();
+    generator.initialize_model().expect("fallback is not an error");
+    std::env::remove_var("CQ_MAP_MODEL");
+
+    assert!(generator.model.is_none());
+    assert_eq!(generator.generate_map(5), generate_procedural(5));
+}
+
+mod stored_maps {
+    use super::*;
+    use chainquest_idle::ai::serialize_map;
+    use chainquest_idle::resources::DatabaseConnection;
+
+    #[test]
+    fn generated_maps_are_reused_after_restart() {
+        let db = DatabaseConnection::open(":memory:");
+
+        let mut first_run = MapGenerator::default().with_database(db.clone(), 1);
+        let map = first_run.generate_map(7);
+        assert_eq!(db.load_map(1, 7).expect("stored"), serialize_map(&map));
+
+        let mut second_run = MapGenerator::default().with_database(db.clone(), 1);
+        assert_eq!(second_run.generate_map(7), map);
+        assert_eq!(second_run.get_stats().maps_generated, 0);
+        assert_eq!(second_run.get_stats().cache_hits, 1);
+    }
+
+    #[test]
+    fn corrupt_stored_map_is_regenerated() {
+        let db = DatabaseConnection::open(":memory:");
+        db.save_map(1, 9, "0,1,x\n").expect("save");
+
+        let mut generator = MapGenerator::default().with_database(db.clone(), 1);
+        assert_eq!(generator.generate_map(9), generate_procedural(9));
+        assert_eq!(generator.get_stats().maps_generated, 1);
+        assert_eq!(db.load_map(1, 9).expect("stored"), serialize_map(&generate_procedural(9)));
+    }
+}
+
+mod connectivity {
+    use chainquest_idle::ai::{generate_procedural, validate_connectivity};
+
+    #[test]
+    fn walled_off_quest_becomes_reachable() {
+        let mut grid = vec![vec![0; 16]; 16];
+        // Quest in a corner, sealed in by enemies
+        grid[1][1] = 3;
+        for (x, y) in [(0, 0), (0, 1), (0, 2), (1, 0), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            grid[x][y] = 2;
+        }
+
+        let report = validate_connectivity(&mut grid);
+
+        assert_eq!(report.quests_reachable, 1);
+        assert!(report.tiles_carved > 0);
+        assert_eq!(report.reachable_tiles, report.walkable_tiles);
+        assert_eq!(grid[1][1], 3);
+    }
+
+    #[test]
+    fn generated_maps_have_reachable_quests_and_portals() {
+        for seed in 0..50 {
+            let mut grid = generate_procedural(seed);
+            let quests = grid.iter().flatten().filter(|&&t| t == 3).count();
+            let portals = grid.iter().flatten().filter(|&&t| t == 4).count();
+
+            let report = validate_connectivity(&mut grid);
+            assert_eq!(report.tiles_carved, 0, "seed {}", seed);
+            assert_eq!(report.quests_reachable, quests, "seed {}", seed);
+            assert_eq!(report.portals_reachable, portals, "seed {}", seed);
+        }
+    }
+}
+
+mod text_seeds {
+    use super::*;
+    use chainquest_idle::ai::seed_from_str;
+    use chainquest_idle::resources::DatabaseConnection;
+
+    #[test]
+    fn text_seed_hash_is_stable() {
+        // FNV-1a reference values
+        assert_eq!(seed_from_str(""), 0xcbf2_9ce4_8422_2325_u64 as i64);
+        assert_eq!(seed_from_str("a"), 0xaf63_dc4c_8601_ec8c_u64 as i64);
+        assert_ne!(seed_from_str("dragon-valley"), seed_from_str("dragon-valley "));
+    }
+
+    #[test]
+    fn text_seed_is_stored_with_the_map() {
+        let db = DatabaseConnection::open(":memory:");
+        let mut generator = MapGenerator::default().with_database(db.clone(), 1);
+
+        let map = generator.generate_map_from_str("dragon-valley");
+        let seed = seed_from_str("dragon-valley");
+
+        assert_eq!(map, generate_procedural(seed));
+        assert_eq!(db.load_map_seed_text(1, seed).expect("load"), Some("dragon-valley".to_string()));
+    }
+}
+
+mod biomes {
+    use super::*;
+    use chainquest_idle::ai::{generate_procedural_with_biome, Biome};
+    use chainquest_idle::multiplayer::network::GameMessage;
+
+    fn count(grid: &[Vec<i32>], tile: i32) -> usize {
+        grid.iter().flatten().filter(|&&t| t == tile).count()
+    }
+
+    #[test]
+    fn desert_is_harsher_than_forest() {
+        let generator = MapGenerator::default();
+        for seed in [1, 42, 9001] {
+            let forest = generator.generate_procedural_biome(seed, Biome::Forest);
+            let desert = generator.generate_procedural_biome(seed, Biome::Desert);
+            assert!(count(&desert, 2) > count(&forest, 2), "seed {}", seed);
+            assert!(count(&desert, 1) < count(&forest, 1), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn unspecified_biome_matches_default_generation() {
+        assert_eq!(generate_procedural_with_biome(7, None), generate_procedural(7));
+    }
+
+    #[test]
+    fn map_generate_biome_is_optional_on_the_wire() {
+        let legacy = GameMessage::from_bytes(br#"{"MapGenerate":{"seed":5}}"#).expect("parse");
+        assert!(matches!(legacy, GameMessage::MapGenerate { seed: 5, biome: None }));
+
+        let bytes = GameMessage::MapGenerate { seed: 5, biome: Some(Biome::Swamp) }.to_bytes().expect("encode");
+        let parsed = GameMessage::from_bytes(&bytes).expect("parse");
+        assert!(matches!(parsed, GameMessage::MapGenerate { seed: 5, biome: Some(Biome::Swamp) }));
+    }
+}
+
+#[test]
+fn startup_map_matches_map_generator_output() {
+    use chainquest_idle::ai::integration::generate_and_store_map;
+    use chainquest_idle::ai::{parse_map, serialize_map};
+    use chainquest_idle::resources::DatabaseConnection;
+
+    let db = DatabaseConnection::open(":memory:");
+    generate_and_store_map(1, 1337, &db);
+    generate_and_store_map(1, 1337, &db);
+
+    let stored = parse_map(&db.load_map(1, 1337).expect("stored")).expect("valid");
+    assert_eq!(stored, MapGenerator::default().generate_map(1337));
+    assert_eq!(serialize_map(&stored), serialize_map(&generate_procedural(1337)));
+}
+
+mod chunked_spawning {
+    use super::*;
+    use chainquest_idle::ai::{spawn_pending_map_tiles, MapRoot, PendingMapSpawn};
+    use chainquest_idle::components::MapTile;
+    use chainquest_idle::config::map::MapConfig;
+
+    fn tile_count(app: &mut App) -> usize {
+        app.world.query::<&MapTile>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn tiles_spawn_at_most_n_per_frame() {
+        let mut app = App::new();
+        app.insert_resource(MapConfig { tiles_per_frame: 100 });
+        app.init_resource::<MapRoot>();
+        app.insert_resource(PendingMapSpawn::new(generate_procedural(3)));
+        app.add_systems(Update, spawn_pending_map_tiles);
+
+        app.update();
+        assert_eq!(tile_count(&mut app), 100);
+        app.update();
+        assert_eq!(tile_count(&mut app), 200);
+        app.update();
+        assert_eq!(tile_count(&mut app), 256);
+        assert!(!app.world.contains_resource::<PendingMapSpawn>());
+    }
+
+    #[test]
+    fn new_map_request_cancels_pending_spawn() {
+        use chainquest_idle::ai::handle_map_generation;
+
+        let mut generator = MapGenerator::default();
+        generator.cache.insert(5, vec![vec![0; 2]; 2]);
+        generator.queued_seeds.push_back((5, None));
+
+        let mut app = App::new();
+        app.insert_resource(generator);
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        app.insert_resource(PendingMapSpawn::new(generate_procedural(3)));
+        app.add_systems(Update, handle_map_generation);
+        app.update();
+
+        assert_eq!(app.world.resource::<PendingMapSpawn>().remaining(), 4);
+    }
+
+    #[test]
+    fn second_map_replaces_the_first() {
+        let mut app = App::new();
+        app.insert_resource(MapConfig { tiles_per_frame: 1000 });
+        app.init_resource::<MapRoot>();
+        app.add_systems(Update, spawn_pending_map_tiles);
+
+        app.insert_resource(PendingMapSpawn::new(generate_procedural(1)));
+        app.update();
+        app.insert_resource(PendingMapSpawn::new(generate_procedural(2)));
+        app.update();
+
+        assert_eq!(tile_count(&mut app), 16 * 16);
+        assert_eq!(app.world.resource::<MapRoot>().tiles.len(), 16 * 16);
+    }
+
+    #[test]
+    fn cancelled_partial_spawn_is_cleaned_up() {
+        let mut app = App::new();
+        app.insert_resource(MapConfig { tiles_per_frame: 100 });
+        app.init_resource::<MapRoot>();
+        app.add_systems(Update, spawn_pending_map_tiles);
+
+        app.insert_resource(PendingMapSpawn::new(generate_procedural(1)));
+        app.update();
+        app.insert_resource(PendingMapSpawn::new(vec![vec![0; 2]; 2]));
+        app.update();
+
+        assert_eq!(tile_count(&mut app), 4);
+    }
+}
+
+#[test]
+fn generation_times_are_averaged_per_source() {
+    use chainquest_idle::ai::{GenerationSource, GenerationStats};
+
+    let mut stats = GenerationStats::default();
+    stats.record_generation(GenerationSource::Ai, 30.0);
+    stats.record_generation(GenerationSource::Ai, 50.0);
+    stats.record_generation(GenerationSource::Procedural, 2.0);
+
+    assert_eq!(stats.maps_generated, 3);
+    assert_eq!(stats.session_count(GenerationSource::Ai), 2);
+    assert_eq!(stats.session_count(GenerationSource::Procedural), 1);
+    assert_eq!(stats.avg_ai_time_ms, 40.0);
+    assert_eq!(stats.avg_procedural_time_ms, 2.0);
+    assert_eq!(stats.timing_summary(), "ai: 2 maps, avg 40.00 ms | procedural: 1 maps, avg 2.00 ms");
+}
+
+#[test]
+fn spawned_tiles_get_positioned_sprites() {
+    use chainquest_idle::ai::{spawn_pending_map_tiles, MapRoot, PendingMapSpawn};
+    use chainquest_idle::ui::map_view::{render_map_tiles, tile_translation, TILE_SIZE};
+
+    let mut app = App::new();
+    app.init_resource::<MapRoot>();
+    app.insert_resource(chainquest_idle::config::map::MapConfig { tiles_per_frame: 1000 });
+    app.insert_resource(PendingMapSpawn::new(generate_procedural(4)));
+    app.add_systems(Update, (spawn_pending_map_tiles, render_map_tiles.before(spawn_pending_map_tiles)));
+    app.update();
+    app.update();
+
+    let mut sprites = app.world.query::<(&chainquest_idle::components::MapTile, &Sprite, &Transform)>();
+    assert_eq!(sprites.iter(&app.world).count(), 16 * 16);
+    for (tile, sprite, transform) in sprites.iter(&app.world) {
+        assert_eq!(transform.translation, tile_translation(tile.grid_x, tile.grid_y));
+        assert_eq!(sprite.custom_size, Some(Vec2::splat(TILE_SIZE - 1.0)));
+    }
+    assert_eq!(tile_translation(0, 0).x, -tile_translation(15, 15).x);
+}
+
+#[test]
+fn cursor_positions_map_back_to_the_rendered_grid() {
+    use chainquest_idle::components::{MapTile, TileType};
+    use chainquest_idle::ui::map_view::{format_tile_tooltip, grid_at, tile_translation, TILE_SIZE};
+
+    for (x, y) in [(0, 0), (3, 11), (15, 15)] {
+        let center = tile_translation(x, y).truncate();
+        assert_eq!(grid_at(center), Some((x, y)));
+        // Anywhere inside the tile, not just its center
+        assert_eq!(grid_at(center + Vec2::splat(TILE_SIZE * 0.45)), Some((x, y)));
+    }
+    assert_eq!(grid_at(tile_translation(0, 0).truncate() - Vec2::new(TILE_SIZE, 0.0)), None);
+    assert_eq!(grid_at(tile_translation(15, 15).truncate() + Vec2::new(0.0, TILE_SIZE)), None);
+
+    let tile = MapTile { tile_type: TileType::Portal, grid_x: 4, grid_y: 9 };
+    assert_eq!(format_tile_tooltip(&tile), "Portal (4, 9)");
+}
+
+#[test]
+fn tile_tooltip_hides_without_a_cursor() {
+    use chainquest_idle::ui::map_view::{map_tile_hover, TileTooltip};
+
+    let mut app = App::new();
+    let tooltip = app.world.spawn((TileTooltip, Text::from_section("Resource (1, 1)", TextStyle::default()), Transform::default(), Visibility::Visible)).id();
+    app.add_systems(Update, map_tile_hover);
+    app.update();
+    assert_eq!(app.world.get::<Visibility>(tooltip), Some(&Visibility::Hidden));
+}
+
+mod active_seed {
+    use bevy::prelude::*;
+    use chainquest_idle::ai::{MapSeed, DEFAULT_MAP_SEED};
+    use chainquest_idle::ai::{handle_map_generation, poll_map_generation, MapGenerationTask, MapGenerator};
+    use chainquest_idle::resources::{DatabaseConnection, GameState, DEFAULT_SAVE_SLOT};
+    use chainquest_idle::systems_setup::setup_map;
+
+    #[test]
+    fn generated_map_becomes_the_current_seed() {
+        let db = DatabaseConnection::open(":memory:");
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(MapGenerator::default().with_database(db.clone(), DEFAULT_SAVE_SLOT));
+        app.insert_resource(GameState::default());
+        app.insert_resource(MapSeed::default());
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        app.add_systems(Update, (handle_map_generation, poll_map_generation.after(handle_map_generation)));
+        app.update();
+
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyM);
+        app.update();
+        app.world.resource_mut::<ButtonInput<KeyCode>>().clear();
+        for _ in 0..200 {
+            app.update();
+            if !app.world.contains_resource::<MapGenerationTask>() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert!(!app.world.contains_resource::<MapGenerationTask>(), "generation finished");
+        let seed = app.world.resource::<GameState>().current_map_seed;
+        assert_ne!(seed, DEFAULT_MAP_SEED);
+        assert_eq!(*app.world.resource::<MapSeed>(), MapSeed(seed));
+        assert_eq!(db.load_current_map_seed(DEFAULT_SAVE_SLOT).expect("persisted"), seed);
+    }
+
+    #[test]
+    fn startup_restores_the_last_used_seed() {
+        let db = DatabaseConnection::open(":memory:");
+        let mut app = App::new();
+        app.insert_resource(db.clone());
+        app.insert_resource(GameState::default());
+        app.add_systems(Startup, setup_map);
+        app.update();
+        assert_eq!(app.world.resource::<GameState>().current_map_seed, DEFAULT_MAP_SEED);
+
+        db.save_current_map_seed(DEFAULT_SAVE_SLOT, 99).expect("save");
+        let mut app = App::new();
+        app.insert_resource(db);
+        app.insert_resource(GameState::default());
+        app.add_systems(Startup, setup_map);
+        app.update();
+        assert_eq!(app.world.resource::<GameState>().current_map_seed, 99);
+        assert_eq!(*app.world.resource::<MapSeed>(), MapSeed(99));
+    }
+}
+
+mod portals {
+    use bevy::prelude::*;
+    use chainquest_idle::ai::{MapSeed, DEFAULT_MAP_SEED};
+    use chainquest_idle::ai::{
+        enter_portal, entry_point, generate_procedural, poll_map_generation, portal_seed, spawn_pending_map_tiles,
+        MapGenerationTask, MapGenerator, MapRoot, PendingMapSpawn,
+    };
+    use chainquest_idle::components::{MapTile, Player, Position, TileType};
+    use chainquest_idle::movement::TileEntered;
+    use chainquest_idle::resources::{DatabaseConnection, GameState, DEFAULT_SAVE_SLOT};
+    use chainquest_idle::systems_setup::setup_map;
+
+    fn portal_app(db: &DatabaseConnection) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(MapGenerator::default().with_database(db.clone(), DEFAULT_SAVE_SLOT));
+        app.insert_resource(GameState { current_map_seed: DEFAULT_MAP_SEED, ..Default::default() });
+        app.insert_resource(MapSeed::default());
+        app.add_event::<TileEntered>();
+        app.add_systems(Update, (enter_portal, poll_map_generation.after(enter_portal)));
+        app.update(); // task pools are initialized by the plugins
+        let portal = app.world.spawn(MapTile { tile_type: TileType::Portal, grid_x: 0, grid_y: 5 }).id();
+        app.insert_resource(MapRoot { tiles: vec![portal] });
+        let player = app.world.spawn((Player, Position { x: 0.0, y: 5.0 })).id();
+        (app, player, portal)
+    }
+
+    fn step_on_portal(app: &mut App, player: Entity, portal: Entity) {
+        app.world.send_event(TileEntered { player, tile: portal, tile_type: TileType::Portal, grid_x: 0, grid_y: 5 });
+        app.update();
+        for _ in 0..200 {
+            if !app.world.contains_resource::<MapGenerationTask>() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.update();
+        }
+        assert!(!app.world.contains_resource::<MapGenerationTask>(), "generation finished");
+    }
+
+    #[test]
+    fn portal_seeds_are_stable_and_differ_by_depth() {
+        assert_eq!(portal_seed(42, 1), portal_seed(42, 1));
+        assert_ne!(portal_seed(42, 1), portal_seed(42, 2));
+        assert_ne!(portal_seed(42, 1), portal_seed(43, 1));
+    }
+
+    #[test]
+    fn entry_point_avoids_enemies_near_the_center() {
+        let mut grid = vec![vec![0; 16]; 16];
+        assert_eq!(entry_point(&grid), (8, 8));
+        grid[8][8] = 2;
+        let (x, y) = entry_point(&grid);
+        assert_eq!((x - 8).abs() + (y - 8).abs(), 1);
+        assert_eq!(grid[x as usize][y as usize], 0);
+    }
+
+    #[test]
+    fn stepping_on_a_portal_loads_a_deeper_stored_map() {
+        let db = DatabaseConnection::open(":memory:");
+        let (mut app, player, portal) = portal_app(&db);
+        step_on_portal(&mut app, player, portal);
+
+        let seed = portal_seed(DEFAULT_MAP_SEED, 1);
+        let state = app.world.resource::<GameState>();
+        assert_eq!((state.current_map_seed, state.map_depth), (seed, 1));
+        assert_eq!(*app.world.resource::<MapSeed>(), MapSeed(seed));
+        assert_eq!(app.world.resource::<PendingMapSpawn>().grid, generate_procedural(seed));
+
+        let (x, y) = entry_point(&generate_procedural(seed));
+        let position = app.world.get::<Position>(player).unwrap();
+        assert_eq!((position.x, position.y), (x as f32, y as f32));
+
+        assert!(db.load_map(DEFAULT_SAVE_SLOT, seed).is_ok(), "map stored");
+        assert_eq!(db.load_current_map_seed(DEFAULT_SAVE_SLOT).expect("seed saved"), seed);
+        assert_eq!(db.load_map_depth(DEFAULT_SAVE_SLOT).expect("depth saved"), 1);
+
+        app.add_systems(Update, spawn_pending_map_tiles);
+        while app.world.contains_resource::<PendingMapSpawn>() {
+            app.update();
+        }
+        assert!(app.world.get_entity(portal).is_none(), "old map despawned");
+        assert_eq!(app.world.resource::<MapRoot>().tiles.len(), 16 * 16);
+    }
+
+    #[test]
+    fn a_portal_cancels_the_map_being_generated() {
+        let db = DatabaseConnection::open(":memory:");
+        let (mut app, player, portal) = portal_app(&db);
+        app.insert_resource(MapGenerationTask::spawn(7));
+        step_on_portal(&mut app, player, portal);
+
+        let seed = portal_seed(DEFAULT_MAP_SEED, 1);
+        assert_eq!(app.world.resource::<GameState>().current_map_seed, seed, "the cancelled map never activates");
+        assert!(db.load_map(DEFAULT_SAVE_SLOT, 7).is_err());
+    }
+
+    #[test]
+    fn depth_survives_a_restart() {
+        let db = DatabaseConnection::open(":memory:");
+        let (mut app, player, portal) = portal_app(&db);
+        step_on_portal(&mut app, player, portal);
+
+        let mut app = App::new();
+        app.insert_resource(db);
+        app.insert_resource(GameState::default());
+        app.add_systems(Startup, setup_map);
+        app.update();
+        let state = app.world.resource::<GameState>();
+        assert_eq!((state.current_map_seed, state.map_depth), (portal_seed(DEFAULT_MAP_SEED, 1), 1));
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use chainquest_idle::audio::{play_sound_effects, AudioConfig, SoundEffects};
+use chainquest_idle::resources::{LevelUpEvent, QuestCompleted, ResourcesCredited};
+
+fn app_with_effects(effects: SoundEffects, config: AudioConfig) -> App {
+    let mut app = App::new();
+    app.insert_resource(effects);
+    app.insert_resource(config);
+    app.add_event::<ResourcesCredited>();
+    app.add_event::<LevelUpEvent>();
+    app.add_event::<QuestCompleted>();
+    app.add_systems(Update, play_sound_effects);
+    app
+}
+
+fn playing(app: &mut App) -> Vec<f32> {
+    app.world.query::<&PlaybackSettings>().iter(&app.world).map(|s| *s.volume).collect()
+}
+
+#[test]
+fn events_play_their_effect_once_per_frame_at_master_volume() {
+    let effects = SoundEffects { collect: Some(Handle::default()), level_up: None, quest_complete: Some(Handle::default()) };
+    let mut app = app_with_effects(effects, AudioConfig { master_volume: 0.5 });
+
+    app.update();
+    assert!(playing(&mut app).is_empty());
+
+    app.world.send_event(ResourcesCredited { player_id: 1, amount: 10.0, source: None });
+    app.world.send_event(ResourcesCredited { player_id: 1, amount: 10.0, source: None });
+    app.world.send_event(QuestCompleted { quest_id: 3, resources: 50.0 });
+    app.update();
+    assert_eq!(playing(&mut app), vec![0.5, 0.5]);
+}
+
+#[test]
+fn missing_sounds_and_muted_volume_stay_silent() {
+    let mut app = app_with_effects(SoundEffects::default(), AudioConfig::default());
+    app.world.send_event(ResourcesCredited { player_id: 1, amount: 10.0, source: None });
+    app.update();
+    assert!(playing(&mut app).is_empty());
+
+    let effects = SoundEffects { collect: Some(Handle::default()), ..Default::default() };
+    let mut app = app_with_effects(effects, AudioConfig { master_volume: 0.0 });
+    app.world.send_event(ResourcesCredited { player_id: 1, amount: 10.0, source: None });
+    app.update();
+    assert!(playing(&mut app).is_empty());
+}
@@ -0,0 +1,268 @@
+use chainquest_idle::blockchain::client::{mint_sft_reward, BlockchainClient, MintTransaction};
+use chainquest_idle::components::{Rarity, SFTAttributes};
+use chainquest_idle::resources::{BlockchainState, DatabaseConnection, TxStatus};
+
+fn sample_attributes() -> SFTAttributes {
+    SFTAttributes {
+        quest_id: 7,
+        map_seed: 1337,
+        rarity: Rarity::Epic,
+        power: 120,
+        metadata: "Quest 7 Reward".to_string(),
+    }
+}
+
+#[test]
+fn mint_sft_reward_records_pending_tx_and_asset() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut state = BlockchainState::default();
+    let client = BlockchainClient { dry_run: true, ..Default::default() };
+    let token_id = mint_sft_reward(&client, &sample_attributes(), &db, &mut state).expect("mint ok");
+    assert_eq!(token_id, "CQSFT-00000007-0001");
+    assert_eq!(state.pending_transactions.len(), 1);
+
+    let rows = db.list_sfts().expect("list ok");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].token_id, token_id);
+    assert!(!rows[0].staked);
+    assert_eq!(rows[0].mint_tx.as_deref(), Some(state.pending_transactions[0].0.as_str()));
+
+    let again = mint_sft_reward(&client, &sample_attributes(), &db, &mut state).expect("mint ok");
+    assert_eq!(again, "CQSFT-00000007-0002", "repeat rewards get their own token");
+    assert_ne!(state.pending_transactions[0].0, state.pending_transactions[1].0);
+}
+
+#[test]
+fn dry_run_mint_succeeds_with_synthetic_tx_hash() {
+    let client = BlockchainClient { dry_run: true, gateway_url: "http://unreachable.invalid".into(), ..Default::default() };
+    let mut state = BlockchainState::default();
+
+    let tx = client.build_mint_tx(&sample_attributes()).expect("tx builds");
+    assert!(tx.data.starts_with("mintReward@"));
+
+    let tx_hash = client.submit_mint(&sample_attributes(), &mut state).expect("dry run succeeds");
+    assert!(tx_hash.starts_with("dryrun-"));
+    assert_eq!(state.pending_transactions, vec![(tx_hash, TxStatus::Pending)]);
+}
+
+#[test]
+fn submissions_fail_outside_dry_run_and_record_nothing() {
+    let db = DatabaseConnection::open(":memory:");
+    let client = BlockchainClient::default();
+    let mut state = BlockchainState::default();
+
+    let tx = MintTransaction { receiver: "erd1contract".into(), data: "mintReward@00".into(), nonce: 1 };
+    assert_eq!(client.tx_hash(&tx), "014e5c9f6eb96211", "hashes are stable across builds");
+
+    let error = mint_sft_reward(&client, &sample_attributes(), &db, &mut state).unwrap_err();
+    assert!(error.contains("CQ_MX_DRY_RUN"), "{}", error);
+    assert!(state.pending_transactions.is_empty());
+    assert!(db.list_sfts().expect("list ok").is_empty(), "no pending reward is left behind");
+}
+
+#[test]
+fn attribute_encoding_matches_contract_layout() {
+    use chainquest_idle::blockchain::client::{decode_sft_attributes, encode_sft_attributes};
+
+    let bytes = encode_sft_attributes(&sample_attributes()).expect("encodes");
+    let metadata = b"Quest 7 Reward";
+    let mut expected = vec![0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0x05, 0x39, 3, 0, 0, 0, 120, 0, 0, 0, metadata.len() as u8];
+    expected.extend_from_slice(metadata);
+    assert_eq!(bytes, expected);
+
+    let decoded = decode_sft_attributes(&bytes).expect("decodes");
+    assert_eq!((decoded.quest_id, decoded.map_seed, decoded.power), (7, 1337, 120));
+    assert!(matches!(decoded.rarity, Rarity::Epic));
+    assert_eq!(decoded.metadata, "Quest 7 Reward");
+}
+
+#[test]
+fn out_of_range_attributes_are_rejected() {
+    use chainquest_idle::blockchain::client::{decode_sft_attributes, encode_sft_attributes};
+    use chainquest_idle::blockchain::MAX_SFT_POWER;
+
+    let mut too_strong = sample_attributes();
+    too_strong.power = MAX_SFT_POWER + 1;
+    assert!(encode_sft_attributes(&too_strong).is_err());
+
+    let mut bytes = encode_sft_attributes(&sample_attributes()).expect("encodes");
+    bytes[12] = 9; // rarity index
+    assert!(decode_sft_attributes(&bytes).unwrap_err().contains("rarity"));
+}
+
+#[test]
+fn balance_refresh_counts_the_wallets_sfts() {
+    use bevy::prelude::*;
+    use chainquest_idle::blockchain::client::{poll_pending_transactions, refresh_sft_balance};
+
+    let db = DatabaseConnection::open(":memory:");
+    let client = BlockchainClient { dry_run: true, ..Default::default() };
+    let mut state = BlockchainState { wallet_address: "erd1player".into(), ..Default::default() };
+    mint_sft_reward(&client, &sample_attributes(), &db, &mut state).expect("mint");
+    mint_sft_reward(&client, &SFTAttributes { quest_id: 8, ..sample_attributes() }, &db, &mut state).expect("mint");
+    let mut other = BlockchainState { wallet_address: "erd1other".into(), ..Default::default() };
+    mint_sft_reward(&client, &sample_attributes(), &db, &mut other).expect("mint");
+    state.pending_transactions.append(&mut other.pending_transactions);
+    assert_eq!(client.fetch_sft_balance("erd1player", &db), Ok(0), "mints not settled yet");
+    assert!(client.fetch_sft_balance("", &db).is_err());
+
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    app.insert_resource(client);
+    app.insert_resource(db.clone());
+    app.insert_resource(state);
+    app.add_systems(Update, (poll_pending_transactions, refresh_sft_balance).chain());
+    app.update();
+
+    assert_eq!(app.world.resource::<BlockchainState>().sft_balance, 2, "only the wallet's own SFTs count");
+    assert_eq!(app.world.resource::<BlockchainClient>().fetch_sft_balance("erd1other", &db), Ok(1));
+}
+
+#[test]
+fn resolved_transactions_are_removed_after_grace_period() {
+    use bevy::prelude::*;
+    use chainquest_idle::blockchain::client::{poll_pending_transactions, TX_GRACE_PERIOD_SECS};
+    use std::time::Duration;
+
+    let client = BlockchainClient { dry_run: true, ..Default::default() };
+    let mut state = BlockchainState::default();
+    client.submit_mint(&sample_attributes(), &mut state).expect("mint");
+    state.pending_transactions.push(("unknown-tx".to_string(), TxStatus::Pending));
+    assert_eq!(state.pending_count(), 2);
+
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    app.insert_resource(client);
+    app.insert_resource(DatabaseConnection::open(":memory:"));
+    app.insert_resource(state);
+    app.add_systems(Update, poll_pending_transactions);
+
+    app.update();
+    let state = app.world.resource::<BlockchainState>();
+    assert_eq!(state.pending_count(), 1);
+    assert_eq!(state.pending_transactions[0].1, TxStatus::Confirmed);
+
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f64(TX_GRACE_PERIOD_SECS + 1.0));
+    app.update();
+    let state = app.world.resource::<BlockchainState>();
+    assert_eq!(state.pending_transactions, vec![("unknown-tx".to_string(), TxStatus::Pending)]);
+}
+
+#[test]
+fn pending_sft_transactions_are_resumed_after_a_restart() {
+    use bevy::prelude::*;
+    use chainquest_idle::blockchain::client::{poll_pending_transactions, restore_pending_transactions};
+
+    let db = DatabaseConnection::open(":memory:");
+    let mut state = BlockchainState::default();
+    let token_id = {
+        let previous_session = BlockchainClient { dry_run: true, ..Default::default() };
+        mint_sft_reward(&previous_session, &sample_attributes(), &db, &mut state).expect("mint")
+    };
+
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    app.insert_resource(BlockchainClient { dry_run: true, ..Default::default() });
+    app.insert_resource(db.clone());
+    app.init_resource::<BlockchainState>();
+    app.add_systems(Startup, restore_pending_transactions);
+    app.add_systems(Update, poll_pending_transactions);
+    app.update();
+
+    let rows = db.list_sfts().expect("list ok");
+    assert_eq!((rows[0].token_id.as_str(), rows[0].mint_tx.as_deref()), (token_id.as_str(), None));
+    let state = app.world.resource::<BlockchainState>();
+    assert_eq!(state.pending_transactions[0].1, TxStatus::Confirmed);
+}
+
+#[test]
+fn staked_sft_raises_resource_rate() {
+    use bevy::prelude::*;
+    use chainquest_idle::blockchain::client::{refresh_staking_bonus, stake_sft, unstake_sft, StakingBonus};
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+    use chainquest_idle::systems_idle::{fast_forward_progress, update_idle_progress};
+    use std::time::Duration;
+
+    let db = DatabaseConnection::open(":memory:");
+    let client = BlockchainClient { dry_run: true, ..Default::default() };
+    let mut state = BlockchainState::default();
+    let token_id = mint_sft_reward(&client, &sample_attributes(), &db, &mut state).expect("mint ok");
+    assert!(stake_sft(&client, &token_id, &db, &mut state).is_err(), "mint not settled yet");
+    db.resolve_sft_tx(&state.pending_transactions[0].0, true).expect("settle mint");
+    stake_sft(&client, &token_id, &db, &mut state).expect("stake ok");
+    assert!(stake_sft(&client, &token_id, &db, &mut state).is_err(), "already staked");
+
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    app.insert_resource(db.clone());
+    app.init_resource::<StakingBonus>();
+    app.add_event::<LevelUpEvent>();
+    app.add_event::<ResourcesProduced>();
+    app.world.spawn((Player, IdleProgress::default()));
+    app.add_systems(Update, (refresh_staking_bonus, update_idle_progress).chain());
+    app.update();
+    assert_eq!(app.world.resource::<StakingBonus>().staked_power, 120);
+
+    let produced_in_ten_seconds = |app: &mut App| {
+        let mut players = app.world.query::<&IdleProgress>();
+        let before = players.single(&app.world).resources;
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(10));
+        app.update();
+        players.single(&app.world).resources - before
+    };
+    let boosted = produced_in_ten_seconds(&mut app);
+    let bonus = app.world.resource::<StakingBonus>().clone();
+
+    unstake_sft(&client, &token_id, &db, &mut state).expect("unstake ok");
+    app.update();
+    assert_eq!(app.world.resource::<StakingBonus>().staked_power, 0);
+    let base = produced_in_ten_seconds(&mut app);
+    assert!(boosted > base, "staked {} vs unstaked {}", boosted, base);
+
+    // Offline catch-up gets the same bonus
+    let config = ProgressionConfig::default();
+    let mut forwarded = IdleProgress::default();
+    let mut forwarded_boosted = IdleProgress::default();
+    fast_forward_progress(&mut forwarded, &config, None, None, 0.0, 0.0, 10.0);
+    fast_forward_progress(&mut forwarded_boosted, &config, None, Some(&bonus), 0.0, 0.0, 10.0);
+    assert!((forwarded_boosted.resources - boosted).abs() < 1e-6);
+    assert!((forwarded.resources - base).abs() < 1e-6);
+}
+
+mod wallet {
+    use super::*;
+    use chainquest_idle::blockchain::client::validate_wallet_address;
+    use chainquest_idle::resources::GameState;
+
+    const ALICE: &str = "erd1qyu5wthldzr8wx5c9ucg8kjagg0jfs53s8nr3zpz3hypefsdd8ssycr6th";
+
+    #[test]
+    fn bech32_addresses_are_validated() {
+        assert_eq!(validate_wallet_address(&ALICE.to_uppercase()), Ok(ALICE.to_string()));
+        assert!(validate_wallet_address("erd1qyu5wthldzr8wx5c9ucg8kjagg0jfs53s8nr3zpz3hypefsdd8ssycr6tt").is_err());
+        assert!(validate_wallet_address("bc1qyu5wthldzr8wx5c9ucg8kjagg0jfs53s8nr3zpz3hypefsdd8ssycr6th").is_err());
+        assert!(validate_wallet_address("erd1short").is_err());
+    }
+
+    #[test]
+    fn connect_and_disconnect_update_state() {
+        let client = BlockchainClient { dry_run: true, ..Default::default() };
+        let mut state = BlockchainState::default();
+        let mut game_state = GameState::default();
+
+        assert!(client.connect_wallet("erd1nope", &mut state, &mut game_state).is_err());
+        assert!(!game_state.blockchain_connected);
+        assert!(state.wallet_status().expect("status").contains("failed"));
+
+        client.connect_wallet(ALICE, &mut state, &mut game_state).expect("connects");
+        assert!(state.testnet_connected && game_state.blockchain_connected);
+        assert_eq!(state.wallet_address, ALICE);
+        assert_eq!(state.wallet_status().as_deref(), Some("Wallet: erd1qy...r6th (testnet)"));
+
+        client.disconnect_wallet(&mut state, &mut game_state);
+        assert!(!state.testnet_connected && !game_state.blockchain_connected);
+        assert_eq!(state.wallet_status(), None);
+    }
+}
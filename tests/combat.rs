@@ -0,0 +1,171 @@
+use chainquest_idle::combat::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+#[test]
+fn auto_mode_resolves_on_contact() {
+    let config = CombatConfig { mode: CombatMode::Auto, ..Default::default() };
+    let mut state = CombatState::default();
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let outcome = begin_combat(&config, &mut state, 5, 20, &mut rng);
+    assert!(matches!(outcome, Some(CombatOutcome::Victory | CombatOutcome::Defeat)));
+    assert!(state.active_fight.is_none());
+}
+
+#[test]
+fn timed_mode_defers_until_duration_elapses() {
+    let config = CombatConfig { mode: CombatMode::Timed, fight_duration: 2.0, ..Default::default() };
+    let mut state = CombatState::default();
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    assert!(begin_combat(&config, &mut state, 5, 20, &mut rng).is_none());
+    assert!(state.active_fight.is_some());
+
+    assert!(advance_combat(&config, &mut state, 5, 1.0, false, &mut rng).is_none());
+    assert!(advance_combat(&config, &mut state, 5, 0.5, false, &mut rng).is_none());
+    let outcome = advance_combat(&config, &mut state, 5, 0.5, false, &mut rng);
+    assert!(matches!(outcome, Some(CombatOutcome::Victory | CombatOutcome::Defeat)));
+    assert!(state.active_fight.is_none());
+}
+
+mod encounters {
+    use bevy::prelude::*;
+    use chainquest_idle::ai::MapRoot;
+    use chainquest_idle::combat::*;
+    use chainquest_idle::components::{Health, IdleProgress, MapTile, Player, Position, TileType};
+    use chainquest_idle::movement::TileEntered;
+    use chainquest_idle::resources::{DatabaseConnection, GameRng, GameState, DEFAULT_SAVE_SLOT};
+    use chainquest_idle::systems_setup::setup_ui;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const SEED: u64 = 42;
+
+    fn combat_app(health: Health) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(GameState::default());
+        app.insert_resource(GameRng::seeded(SEED));
+        app.insert_resource(CombatConfig::default());
+        app.init_resource::<CombatState>();
+        app.add_event::<TileEntered>();
+        app.add_systems(Update, combat_system);
+        let tile = app.world.spawn(MapTile { tile_type: TileType::Enemy, grid_x: 3, grid_y: 4 }).id();
+        app.insert_resource(MapRoot { tiles: vec![tile] });
+        let player = app.world.spawn((
+            Player,
+            IdleProgress { level: 5, resources: 200.0, ..Default::default() },
+            health,
+            Position { x: 3.0, y: 4.0 },
+        )).id();
+        app.world.send_event(TileEntered { player, tile, tile_type: TileType::Enemy, grid_x: 3, grid_y: 4 });
+        (app, player, tile)
+    }
+
+    #[test]
+    fn enemy_power_depends_on_the_tile_and_depth() {
+        assert_eq!(enemy_power(3, 4, 0), enemy_power(3, 4, 0));
+        assert!(enemy_power(3, 4, 2) > enemy_power(3, 4, 1));
+        assert_eq!(combat_damage(20, CombatOutcome::Victory), 5.0);
+        assert_eq!(combat_damage(20, CombatOutcome::Defeat), 20.0);
+    }
+
+    #[test]
+    fn fixed_seed_resolves_the_exchange_deterministically() {
+        let power = enemy_power(3, 4, 0);
+        let expected = resolve_combat(5, power, &mut ChaCha8Rng::seed_from_u64(SEED));
+
+        let (mut app, player, tile) = combat_app(Health::default());
+        app.update();
+
+        let health = app.world.get::<Health>(player).unwrap().current;
+        let progress = app.world.get::<IdleProgress>(player).unwrap();
+        let tile_type = &app.world.get::<MapTile>(tile).unwrap().tile_type;
+        match expected {
+            CombatOutcome::Victory => {
+                assert_eq!(health, 100.0 - combat_damage(power, expected));
+                assert!(matches!(tile_type, TileType::Empty));
+                assert_eq!(progress.experience, power as f64 * EXP_PER_ENEMY_POWER);
+            }
+            _ => {
+                let wounded = 100.0 - combat_damage(power, expected);
+                assert_eq!(health, wounded.max(100.0 * RESPAWN_HEALTH_FRACTION), "a defeat doesn't heal the player");
+                assert!(matches!(tile_type, TileType::Enemy));
+                assert_eq!(progress.experience, 0.0);
+                assert_eq!(progress.resources, 200.0 * (1.0 - DEFEAT_RESOURCE_PENALTY));
+            }
+        }
+    }
+
+    #[test]
+    fn running_out_of_health_respawns_at_the_center_with_a_penalty() {
+        let (mut app, player, _) = combat_app(Health { current: 1.0, max: 100.0 });
+        app.update();
+
+        assert_eq!(*app.world.get::<Health>(player).unwrap(), Health { current: 100.0 * RESPAWN_HEALTH_FRACTION, max: 100.0 });
+        assert_eq!(app.world.get::<IdleProgress>(player).unwrap().resources, 200.0 * (1.0 - DEFEAT_RESOURCE_PENALTY));
+        let position = app.world.get::<Position>(player).unwrap();
+        assert_eq!((position.x, position.y), (8.0, 8.0));
+    }
+
+    #[test]
+    fn respawns_use_the_entry_point_away_from_enemies() {
+        let (mut app, player, _) = combat_app(Health::default());
+        // Deep enough that even a win deals more damage than the player can take
+        app.world.resource_mut::<GameState>().map_depth = 100;
+        let center = app.world.spawn(MapTile { tile_type: TileType::Enemy, grid_x: 8, grid_y: 8 }).id();
+        app.world.resource_mut::<MapRoot>().tiles.push(center);
+        app.update();
+
+        assert_eq!(app.world.get::<IdleProgress>(player).unwrap().resources, 200.0 * (1.0 - DEFEAT_RESOURCE_PENALTY));
+        let position = app.world.get::<Position>(player).unwrap();
+        assert_ne!((position.x, position.y), (8.0, 8.0), "the center holds an enemy");
+        assert_eq!((position.x - 8.0).abs() + (position.y - 8.0).abs(), 1.0);
+    }
+
+    #[test]
+    fn respawns_ignore_tiles_of_a_map_being_replaced() {
+        let (mut app, player, _) = combat_app(Health::default());
+        app.world.resource_mut::<GameState>().map_depth = 100;
+        // Left over from the previous map and no longer tracked by the root
+        app.world.spawn(MapTile { tile_type: TileType::Enemy, grid_x: 8, grid_y: 8 });
+        app.update();
+
+        let position = app.world.get::<Position>(player).unwrap();
+        assert_eq!((position.x, position.y), (8.0, 8.0));
+    }
+
+    #[test]
+    fn health_regenerates_outside_fights() {
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.init_resource::<CombatState>();
+        app.add_systems(Update, regenerate_health);
+        let player = app.world.spawn((Player, Health { current: 50.0, max: 100.0 })).id();
+
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(10));
+        app.update();
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 50.0 + 10.0 * HEALTH_REGEN_PER_SEC);
+
+        app.world.resource_mut::<CombatState>().active_fight = Some(ActiveFight { enemy_power: 10, elapsed: 0.0 });
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(10));
+        app.update();
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 60.0, "no regeneration mid-fight");
+    }
+
+    #[test]
+    fn health_is_restored_from_the_save_slot() {
+        let db = DatabaseConnection::open(":memory:");
+        db.save_health(DEFAULT_SAVE_SLOT, &Health { current: 35.0, max: 100.0 }).expect("save health");
+
+        let mut app = App::new();
+        app.insert_resource(db);
+        app.insert_resource(GameState::default());
+        app.add_systems(Startup, setup_ui);
+        app.update();
+
+        let health = app.world.query_filtered::<&Health, With<Player>>().single(&app.world);
+        assert_eq!(*health, Health { current: 35.0, max: 100.0 });
+    }
+}
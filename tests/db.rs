@@ -1,12 +1,318 @@
-use chainquest_idle::resources::DatabaseConnection;
+use chainquest_idle::resources::{DatabaseConnection, DEFAULT_SAVE_SLOT};
 use chainquest_idle::components::IdleProgress;
 
 #[test]
 fn db_save_and_load_roundtrip() {
-    let db = DatabaseConnection::new();
-    let p = IdleProgress { resources: 42.0, experience: 7.0, level: 3, last_update: 12345.0 };
-    db.save_progress(&p).expect("save ok");
-    let loaded = db.load_progress().expect("load ok");
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { resources: 42.0, experience: 7.0, level: 3, last_update: 12345.0, auto_collect: false };
+    db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+    let loaded = db.load_progress(DEFAULT_SAVE_SLOT).expect("load ok");
     assert!((loaded.resources - 42.0).abs() < 1e-6);
     assert_eq!(loaded.level, 3);
 }
+
+#[test]
+fn auto_collect_unlock_persists() {
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { level: 10, auto_collect: true, ..Default::default() };
+    db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+    assert!(db.load_progress(DEFAULT_SAVE_SLOT).expect("load ok").auto_collect);
+}
+
+#[test]
+fn large_balances_roundtrip_without_rounding() {
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { resources: 123_456_789.125, experience: 0.001, level: 40, last_update: 0.0, auto_collect: false };
+    db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+    let loaded = db.load_progress(DEFAULT_SAVE_SLOT).expect("load ok");
+    assert_eq!(loaded.resources, p.resources);
+    assert_eq!(loaded.experience, p.experience);
+}
+
+#[test]
+fn sft_crud_roundtrip() {
+    let db = DatabaseConnection::open(":memory:");
+    let token_id = "CQSFT-TEST-CRUD";
+    db.delete_sft(token_id).expect("cleanup ok");
+    db.insert_sft(token_id, "{\"power\":10}").expect("insert ok");
+
+    let row = db.list_sfts().expect("list ok").into_iter().find(|r| r.token_id == token_id).expect("row present");
+    assert_eq!(row.attributes, "{\"power\":10}");
+    assert!(!row.staked);
+
+    db.set_staked(token_id, true).expect("stake ok");
+    let row = db.list_sfts().expect("list ok").into_iter().find(|r| r.token_id == token_id).expect("row present");
+    assert!(row.staked);
+
+    db.delete_sft(token_id).expect("delete ok");
+    assert!(db.list_sfts().expect("list ok").iter().all(|r| r.token_id != token_id));
+}
+
+#[test]
+fn sft_serials_are_not_reused_after_a_failed_mint() {
+    let db = DatabaseConnection::open(":memory:");
+    let first = db.next_sft_serial().expect("serial");
+    db.insert_pending_sft("CQSFT-A", "{}", "tx-1", "erd1player").expect("insert ok");
+    db.resolve_sft_tx("tx-1", false).expect("revert ok");
+    assert!(db.list_sfts().expect("list ok").is_empty());
+
+    let second = db.next_sft_serial().expect("serial");
+    assert!(second > first, "serial {} was handed out again", second);
+}
+
+#[test]
+fn save_slots_roundtrip_independently() {
+    let db = DatabaseConnection::open(":memory:");
+    let first = IdleProgress { resources: 100.0, experience: 1.0, level: 2, last_update: 1.0, auto_collect: false };
+    let second = IdleProgress { resources: 900.0, experience: 5.0, level: 8, last_update: 2.0, auto_collect: false };
+    db.save_progress(101, &first).expect("save slot 101");
+    db.save_progress(102, &second).expect("save slot 102");
+
+    let a = db.load_progress(101).expect("load slot 101");
+    let b = db.load_progress(102).expect("load slot 102");
+    assert!((a.resources - 100.0).abs() < 1e-6);
+    assert!((b.resources - 900.0).abs() < 1e-6);
+    assert_eq!(b.level, 8);
+
+    let slots = db.list_slots().expect("list ok");
+    assert!(slots.contains(&101) && slots.contains(&102));
+
+    db.delete_slot(101).expect("delete ok");
+    assert!(db.load_progress(101).is_err());
+    assert!(db.load_progress(102).is_ok());
+    db.delete_slot(102).expect("cleanup ok");
+}
+
+#[test]
+fn a_save_tick_stores_progress_health_and_stats_together() {
+    use chainquest_idle::components::{Health, PlayerStats};
+
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { resources: 9.0, level: 2, ..Default::default() };
+    let stats = PlayerStats { lifetime_resources: 50.0, ..Default::default() };
+    db.save_player_state(2, &p, Some(&Health { current: 7.0, max: 20.0 }), Some(&stats)).expect("save ok");
+
+    assert_eq!(db.load_progress(2).expect("progress").level, 2);
+    assert_eq!(db.load_health(2).expect("health").current, 7.0);
+    assert_eq!(db.load_stats(2).expect("stats").lifetime_resources, 50.0);
+
+    db.save_player_state(3, &p, None, None).expect("progress only");
+    assert!(db.load_health(3).is_err() && db.load_stats(3).is_err());
+}
+
+#[test]
+fn deleting_a_slot_clears_every_per_slot_table() {
+    use chainquest_idle::components::{Health, PlayerStats};
+
+    let db = DatabaseConnection::open(":memory:");
+    for slot in [1, 2] {
+        db.save_progress(slot, &IdleProgress::default()).expect("progress");
+        db.save_stats(slot, &PlayerStats { lifetime_resources: 10.0, ..Default::default() }).expect("stats");
+        db.save_health(slot, &Health { current: 5.0, max: 10.0 }).expect("health");
+        db.save_generator(slot, "Miner", 2).expect("generator");
+        db.save_daily_quests(slot, 1, 1, 1).expect("daily quests");
+        db.save_username(slot, "Explorer").expect("profile");
+        db.save_player_id(slot, 3).expect("identity");
+        db.save_map(slot, 42, "0,1").expect("map");
+        db.save_current_map_seed(slot, 42).expect("world state");
+    }
+
+    db.delete_slot(1).expect("delete ok");
+    assert!(db.load_progress(1).is_err());
+    assert!(db.load_stats(1).is_err());
+    assert!(db.load_health(1).is_err());
+    assert!(db.load_generators(1).expect("generators").is_empty());
+    assert!(db.load_daily_quests(1).is_err());
+    assert!(db.load_username(1).is_err());
+    assert!(db.load_player_id(1).is_err());
+    assert!(db.load_latest_map(1).is_ok(), "worlds are keyed separately from save slots");
+
+    db.delete_world(1).expect("delete world ok");
+    assert!(db.load_latest_map(1).is_err());
+    assert!(db.load_current_map_seed(1).is_err());
+    assert!(db.load_latest_map(2).is_ok(), "other worlds are untouched");
+
+    assert!(db.load_stats(2).is_ok() && db.load_health(2).is_ok(), "other slots are untouched");
+    assert_eq!(db.load_generators(2).expect("generators").len(), 1);
+}
+
+#[test]
+fn unopenable_path_errors_in_try_open() {
+    assert!(DatabaseConnection::try_open("/nonexistent-dir/cq/chainquest.db").is_err());
+}
+
+#[test]
+#[should_panic(expected = "Cannot open the database at /nonexistent-dir/cq/chainquest.db")]
+fn unopenable_path_panics_in_open_instead_of_losing_progress() {
+    DatabaseConnection::open("/nonexistent-dir/cq/chainquest.db");
+}
+
+#[test]
+fn migrations_bring_fresh_and_legacy_databases_to_latest() {
+    use chainquest_idle::resources::{run_migrations, MIGRATIONS};
+    use rusqlite::Connection;
+
+    let fresh = Connection::open_in_memory().expect("open fresh");
+    assert_eq!(run_migrations(&fresh).expect("migrate fresh"), MIGRATIONS.len() as u32);
+    // Re-running is a no-op
+    assert_eq!(run_migrations(&fresh).expect("re-migrate"), MIGRATIONS.len() as u32);
+
+    // A database created before migrations existed (user_version 0, no maps.slot)
+    let path = std::env::temp_dir().join("cq_test_legacy_schema.db");
+    let _ = std::fs::remove_file(&path);
+    {
+        let legacy = Connection::open(&path).expect("open legacy");
+        legacy.execute_batch(
+            "CREATE TABLE progress (id INTEGER PRIMARY KEY, resources REAL NOT NULL, experience REAL NOT NULL, level INTEGER NOT NULL, last_update REAL NOT NULL);
+             CREATE TABLE maps (id INTEGER PRIMARY KEY, seed INTEGER NOT NULL, grid TEXT NOT NULL, created_at REAL NOT NULL);
+             CREATE TABLE sft_assets (id INTEGER PRIMARY KEY, token_id TEXT NOT NULL, attributes TEXT NOT NULL, staked INTEGER NOT NULL DEFAULT 0);",
+        ).expect("legacy schema");
+    }
+    let db = DatabaseConnection::try_open(&path).expect("legacy db migrates");
+    db.save_map(3, 42, "0,1").expect("slot column available");
+    assert_eq!(db.load_map(3, 42).expect("load"), "0,1");
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn legacy_local_player_ids_move_below_the_server_range() {
+    use chainquest_idle::resources::{run_migrations, MIGRATIONS};
+    use chainquest_idle::security::LOCAL_PLAYER_ID;
+    use rusqlite::Connection;
+
+    // Schema as of migration 17, before local ids moved
+    let conn = Connection::open_in_memory().expect("open");
+    for step in &MIGRATIONS[..17] {
+        conn.execute_batch(step).expect("legacy schema");
+    }
+    conn.pragma_update(None, "user_version", 17).expect("version");
+    conn.execute_batch(
+        "INSERT INTO player_identity (slot, player_id) VALUES (0, 4294967295), (1, 7);
+         INSERT INTO banned_players (player_id, reason, banned_at) VALUES (4294967295, 'test', 0);",
+    ).expect("legacy rows");
+    run_migrations(&conn).expect("re-migrate");
+
+    let ids: Vec<u32> = conn.prepare("SELECT player_id FROM player_identity ORDER BY slot").unwrap()
+        .query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(ids, vec![LOCAL_PLAYER_ID, 7]);
+    let banned: u32 = conn.query_row("SELECT player_id FROM banned_players", [], |row| row.get(0)).unwrap();
+    assert_eq!(banned, LOCAL_PLAYER_ID);
+}
+
+#[test]
+fn backup_survives_corrupted_main_file() {
+    let path = std::env::temp_dir().join("cq_test_backup_main.db");
+    let backup = std::env::temp_dir().join("cq_test_backup_main.db.bak");
+    let sidecars = ["cq_test_backup_main.db-wal", "cq_test_backup_main.db-shm"].map(|f| std::env::temp_dir().join(f));
+    for f in [&path, &backup].into_iter().chain(sidecars.iter()) {
+        let _ = std::fs::remove_file(f);
+    }
+
+    let p = IdleProgress { resources: 512.5, experience: 9.0, level: 6, last_update: 100.0, auto_collect: false };
+    {
+        let db = DatabaseConnection::try_open(&path).expect("open main");
+        db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+        assert_eq!(db.backup_path().as_deref(), Some(backup.as_path()));
+        db.backup_to(&backup).expect("backup ok");
+    }
+
+    std::fs::write(&path, b"definitely not a sqlite database, just garbage bytes".repeat(100)).expect("corrupt main");
+    for f in &sidecars {
+        let _ = std::fs::remove_file(f);
+    }
+    assert!(DatabaseConnection::try_open(&path).is_err());
+
+    let restored = DatabaseConnection::try_open(&backup).expect("backup opens");
+    let loaded = restored.load_progress(DEFAULT_SAVE_SLOT).expect("backup has progress");
+    assert_eq!(loaded.resources, p.resources);
+    assert_eq!(loaded.level, p.level);
+
+    drop(restored);
+    for f in [&path, &backup].into_iter().chain(sidecars.iter()) {
+        let _ = std::fs::remove_file(f);
+    }
+}
+
+#[test]
+fn in_memory_databases_have_no_backup_path() {
+    assert!(DatabaseConnection::open(":memory:").backup_path().is_none());
+}
+
+#[test]
+fn json_export_import_roundtrip() {
+    use chainquest_idle::components::{Health, PlayerStats};
+    use chainquest_idle::resources::{SaveExport, SAVE_EXPORT_VERSION};
+
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { resources: 2048.25, experience: 17.5, level: 12, last_update: 999.0, auto_collect: true };
+    db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+    db.save_daily_quests(DEFAULT_SAVE_SLOT, 86_400, 2, 5).expect("quests ok");
+    db.save_map(DEFAULT_SAVE_SLOT, 77, "0,1,2").expect("map ok");
+    db.save_map_seed_text(DEFAULT_SAVE_SLOT, 77, "lucky").expect("seed text ok");
+    db.insert_sft("CQSFT-EXPORT", "{\"power\":3}").expect("sft ok");
+    db.set_staked_pending("CQSFT-EXPORT", true, "tx-stake").expect("stake ok");
+    db.save_username(DEFAULT_SAVE_SLOT, "Explorer").expect("profile ok");
+    db.save_player_id(DEFAULT_SAVE_SLOT, 9).expect("identity ok");
+    let stats = PlayerStats { lifetime_resources: 5000.0, milestones_reached: 3, essence: 1.5, ..Default::default() };
+    db.save_stats(DEFAULT_SAVE_SLOT, &stats).expect("stats ok");
+    db.save_health(DEFAULT_SAVE_SLOT, &Health { current: 40.0, max: 120.0 }).expect("health ok");
+    db.save_generator(DEFAULT_SAVE_SLOT, "Miner", 4).expect("generator ok");
+    db.save_current_map_seed(DEFAULT_SAVE_SLOT, 77).expect("world ok");
+    db.save_map_depth(DEFAULT_SAVE_SLOT, 2).expect("depth ok");
+
+    let json = db.export_json().expect("export ok");
+    let doc: SaveExport = serde_json::from_str(&json).expect("valid document");
+    assert_eq!(doc.version, SAVE_EXPORT_VERSION);
+
+    // Wipe everything the export covers, then restore it
+    db.delete_slot(DEFAULT_SAVE_SLOT).expect("wipe progress");
+    db.delete_sft("CQSFT-EXPORT").expect("wipe sft");
+    db.save_progress(2, &IdleProgress::default()).expect("stray slot");
+    db.import_json(&json).expect("import ok");
+
+    let loaded = db.load_progress(DEFAULT_SAVE_SLOT).expect("progress restored");
+    assert_eq!(loaded.resources, p.resources);
+    assert_eq!(loaded.experience, p.experience);
+    assert_eq!(loaded.level, p.level);
+    assert!(loaded.auto_collect);
+    assert_eq!(db.list_slots().expect("slots"), vec![DEFAULT_SAVE_SLOT]);
+    assert_eq!(db.load_daily_quests(DEFAULT_SAVE_SLOT).expect("quests restored"), (86_400, 2, 5));
+    assert_eq!(db.load_map(DEFAULT_SAVE_SLOT, 77).expect("map restored"), "0,1,2");
+    assert_eq!(db.load_map_seed_text(DEFAULT_SAVE_SLOT, 77).expect("seed text").as_deref(), Some("lucky"));
+    let sfts = db.list_sfts().expect("sfts");
+    assert_eq!(sfts.len(), 1);
+    assert!(sfts[0].staked);
+    assert_eq!(sfts[0].stake_tx.as_deref(), Some("tx-stake"));
+    assert_eq!(db.load_username(DEFAULT_SAVE_SLOT).expect("profile restored"), "Explorer");
+    assert_eq!(db.load_player_id(DEFAULT_SAVE_SLOT).expect("identity restored"), 9);
+    let loaded_stats = db.load_stats(DEFAULT_SAVE_SLOT).expect("stats restored");
+    assert_eq!((loaded_stats.lifetime_resources, loaded_stats.milestones_reached, loaded_stats.essence), (5000.0, 3, 1.5));
+    assert_eq!(db.load_health(DEFAULT_SAVE_SLOT).expect("health restored"), Health { current: 40.0, max: 120.0 });
+    assert_eq!(db.load_generators(DEFAULT_SAVE_SLOT).expect("generators restored"), vec![("Miner".to_string(), 4)]);
+    assert_eq!(db.load_current_map_seed(DEFAULT_SAVE_SLOT).expect("world restored"), 77);
+    assert_eq!(db.load_map_depth(DEFAULT_SAVE_SLOT).expect("depth restored"), 2);
+
+    // A fresh database accepts the same document
+    let other = DatabaseConnection::open(":memory:");
+    other.import_json(&json).expect("import into fresh db");
+    assert_eq!(other.load_progress(DEFAULT_SAVE_SLOT).expect("progress").level, p.level);
+}
+
+#[test]
+fn json_import_rejects_malformed_and_mismatched_documents() {
+    use chainquest_idle::resources::SAVE_EXPORT_VERSION;
+
+    let db = DatabaseConnection::open(":memory:");
+    let p = IdleProgress { resources: 5.0, ..Default::default() };
+    db.save_progress(DEFAULT_SAVE_SLOT, &p).expect("save ok");
+    let json = db.export_json().expect("export ok");
+
+    assert!(db.import_json("{ not json").is_err());
+    let version = format!("\"version\": {}", SAVE_EXPORT_VERSION);
+    assert!(db.import_json(&json.replacen(&version, "\"version\": 99", 1)).is_err());
+    assert!(db.import_json(&json.replacen("\"resources\": 5.0", "\"resources\": -5.0", 1)).is_err());
+
+    // Rejected imports leave the database untouched
+    assert_eq!(db.load_progress(DEFAULT_SAVE_SLOT).expect("still there").resources, 5.0);
+}
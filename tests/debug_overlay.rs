@@ -0,0 +1,45 @@
+use chainquest_idle::multiplayer::network::NetworkStats;
+use chainquest_idle::ui::debug::{format_network_stats, NetRateTracker};
+
+#[test]
+fn byte_rates_are_smoothed_from_deltas() {
+    let mut stats = NetworkStats::default();
+    let mut rates = NetRateTracker::default();
+
+    stats.bytes_sent = 10_000; // history before the overlay starts doesn't count
+    rates.update(&stats, 0.1);
+    assert_eq!(rates.sent_per_sec, 0.0);
+
+    // Steady 1000 B/s converges towards 1000 without jumping there
+    for _ in 0..100 {
+        stats.bytes_sent += 100;
+        rates.update(&stats, 0.1);
+    }
+    assert!((rates.sent_per_sec - 1000.0).abs() < 1.0, "got {}", rates.sent_per_sec);
+
+    stats.bytes_sent += 10_000; // one burst frame
+    rates.update(&stats, 0.1);
+    assert!(rates.sent_per_sec < 20_000.0, "a burst is damped: {}", rates.sent_per_sec);
+
+    let text = format_network_stats(&stats, &rates);
+    assert!(text.contains("Net sent:"));
+    assert!(text.contains("Rate violations: 0"));
+}
+
+#[test]
+fn one_percent_low_tracks_slowest_frames() {
+    use chainquest_idle::ui::debug::{format_fps, FrameTimeHistory};
+
+    let mut history = FrameTimeHistory::default();
+    assert_eq!(history.one_percent_low(), None);
+
+    // 198 smooth 60 FPS frames and two 50 ms hitches
+    for _ in 0..198 {
+        history.push(1000.0 / 60.0);
+    }
+    history.push(50.0);
+    history.push(50.0);
+
+    assert_eq!(history.one_percent_low(), Some(20.0));
+    assert_eq!(format_fps(59.6, history.one_percent_low()), "FPS: 60 | 1% low: 20");
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use chainquest_idle::ui::font::{fallback_font, resolve_asset_path, setup_ui_font, FontConfig, UiFont, DEFAULT_FONT_PATH};
+
+#[test]
+fn embedded_fallback_font_parses() {
+    fallback_font();
+    assert_eq!(FontConfig::default().path, DEFAULT_FONT_PATH);
+}
+
+#[test]
+fn default_font_is_shipped_where_the_asset_server_looks() {
+    // Under cargo the asset root is the manifest directory, like `cargo run`
+    assert!(resolve_asset_path(DEFAULT_FONT_PATH).is_file());
+}
+
+#[test]
+fn missing_font_file_uses_embedded_fallback() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Font>();
+    app.insert_resource(FontConfig { path: "fonts/does-not-exist.ttf".to_string() });
+    app.add_systems(Startup, setup_ui_font);
+    app.update();
+
+    let ui_font = app.world.resource::<UiFont>();
+    assert!(ui_font.fallback);
+    assert_eq!(ui_font.path, "fonts/does-not-exist.ttf");
+    assert!(app.world.resource::<Assets<Font>>().get(&ui_font.handle).is_some());
+}
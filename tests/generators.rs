@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use chainquest_idle::components::{Generator, GeneratorKind, IdleProgress, Player};
+use chainquest_idle::config::progression::ProgressionConfig;
+use chainquest_idle::resources::{DatabaseConnection, GameState, SelectedGenerator, DEFAULT_SAVE_SLOT};
+use chainquest_idle::systems_idle::{buy_upgrade, purchase_upgrade, select_generator, slot_generator_rate, tick_idle_progress};
+use chainquest_idle::systems_setup::load_generators;
+
+#[test]
+fn buying_a_tier_spends_resources_and_raises_rate() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut app = App::new();
+    app.insert_resource(db.clone());
+    app.insert_resource(GameState::default());
+    app.insert_resource(ButtonInput::<KeyCode>::default());
+    app.init_resource::<SelectedGenerator>();
+    app.world.spawn((Player, IdleProgress { resources: 500.0, ..Default::default() }));
+    app.world.spawn(Generator::new(GeneratorKind::Miner, DEFAULT_SAVE_SLOT));
+    app.world.spawn(Generator::new(GeneratorKind::Harvester, DEFAULT_SAVE_SLOT));
+    app.add_systems(Update, buy_upgrade);
+
+    let cost = Generator::new(GeneratorKind::Miner, DEFAULT_SAVE_SLOT).upgrade_cost();
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyU);
+    app.update();
+
+    let balance = app.world.query::<&IdleProgress>().single(&app.world).resources;
+    assert_eq!(balance, 500.0 - cost);
+    let generators: Vec<Generator> = app.world.query::<&Generator>().iter(&app.world).cloned().collect();
+    let rate = slot_generator_rate(&generators, Some(&GameState::default()));
+    assert!(rate > 0.0);
+
+    let config = ProgressionConfig::default();
+    let mut without = IdleProgress::default();
+    let mut with = IdleProgress::default();
+    tick_idle_progress(&mut without, &config, 0.0, 1.0, 1.0);
+    tick_idle_progress(&mut with, &config, rate, 1.0, 1.0);
+    assert!(with.resources > without.resources);
+
+    assert_eq!(db.load_generators(DEFAULT_SAVE_SLOT).expect("load"), vec![("Miner".to_string(), 1)]);
+}
+
+#[test]
+fn the_upgrade_key_buys_the_selected_generator_of_the_active_slot() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut app = App::new();
+    app.insert_resource(db.clone());
+    app.insert_resource(GameState { save_slot: 2, ..Default::default() });
+    app.insert_resource(ButtonInput::<KeyCode>::default());
+    app.init_resource::<SelectedGenerator>();
+    app.world.spawn((Player, IdleProgress { resources: 1000.0, ..Default::default() }));
+    for slot in [1, 2] {
+        for kind in GeneratorKind::ALL {
+            app.world.spawn(Generator::new(kind, slot));
+        }
+    }
+    app.add_systems(Update, (select_generator, buy_upgrade).chain());
+
+    let press = |app: &mut App, key: KeyCode| {
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+        let mut input = app.world.resource_mut::<ButtonInput<KeyCode>>();
+        input.release(key);
+        input.clear();
+    };
+    press(&mut app, KeyCode::KeyG);
+    assert_eq!(*app.world.resource::<SelectedGenerator>(), SelectedGenerator(GeneratorKind::Harvester));
+    press(&mut app, KeyCode::KeyU);
+
+    let cost = Generator::new(GeneratorKind::Harvester, 2).upgrade_cost();
+    assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 1000.0 - cost);
+    let owned: Vec<(u32, GeneratorKind)> = app.world.query::<&Generator>().iter(&app.world)
+        .filter(|g| g.tier > 0)
+        .map(|g| (g.slot, g.kind))
+        .collect();
+    assert_eq!(owned, vec![(2, GeneratorKind::Harvester)], "not the cheaper Miner, nor another slot's");
+    assert_eq!(db.load_generators(2).expect("load"), vec![("Harvester".to_string(), 1)]);
+    assert!(db.load_generators(1).expect("load").is_empty());
+}
+
+#[test]
+fn upgrade_cost_grows_and_requires_funds() {
+    let mut generator = Generator::new(GeneratorKind::Miner, DEFAULT_SAVE_SLOT);
+    let mut progress = IdleProgress { resources: generator.cost, ..Default::default() };
+    assert_eq!(purchase_upgrade(&mut progress, &mut generator), Ok(generator.cost));
+    assert_eq!(progress.resources, 0.0);
+    assert!(generator.upgrade_cost() > generator.cost);
+
+    assert!(purchase_upgrade(&mut progress, &mut generator).is_err());
+    assert_eq!(generator.tier, 1);
+}
+
+#[test]
+fn saved_tiers_are_restored() {
+    let db = DatabaseConnection::open(":memory:");
+    db.save_generator(DEFAULT_SAVE_SLOT, "Refinery", 3).expect("save");
+
+    let mut app = App::new();
+    app.insert_resource(db);
+    app.insert_resource(GameState::default());
+    app.add_systems(Startup, load_generators);
+    app.update();
+
+    let mut q = app.world.query::<&Generator>();
+    assert_eq!(q.iter(&app.world).count(), GeneratorKind::ALL.len());
+    let refinery = q.iter(&app.world).find(|g| g.kind == GeneratorKind::Refinery).unwrap();
+    assert_eq!(refinery.tier, 3);
+}
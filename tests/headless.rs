@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+use chainquest_idle::app::{headless_app, RunMode};
+use chainquest_idle::components::{IdleProgress, Player};
+use chainquest_idle::ui::hud::Hud;
+
+#[test]
+fn headless_app_runs_logic_without_rendering() {
+    std::env::set_var("CQ_DB_PATH", ":memory:");
+    std::env::set_var("CQ_NETWORK_MODE", "single");
+
+    let mut app = headless_app();
+    for _ in 0..3 {
+        app.update();
+    }
+
+    assert_eq!(*app.world.resource::<RunMode>(), RunMode::Headless);
+    let mut players = app.world.query_filtered::<&IdleProgress, With<Player>>();
+    assert_eq!(players.iter(&app.world).count(), 1);
+    assert_eq!(app.world.query::<&Camera>().iter(&app.world).count(), 0);
+    assert_eq!(app.world.query::<&Hud>().iter(&app.world).count(), 0);
+}
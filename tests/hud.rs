@@ -0,0 +1,39 @@
+use chainquest_idle::ui::hud::{format_hud, HudData};
+
+#[test]
+fn hud_hides_multiplayer_lines_in_single_player() {
+    let base = HudData {
+        username: "solo".to_string(),
+        level: 2,
+        last_msg: "Echo 4 bytes".to_string(),
+        total_players: 3,
+        ..Default::default()
+    };
+
+    let single = format_hud(&HudData { multiplayer: false, ..base.clone() });
+    assert!(single.contains("solo"));
+    assert!(!single.contains("Multiplayer:"));
+    assert!(!single.contains("Last:"));
+    assert!(!single.contains("Players:"));
+
+    let multi = format_hud(&HudData { multiplayer: true, ..base });
+    assert!(multi.contains("Multiplayer: offline | Last: Echo 4 bytes"));
+    assert!(multi.contains("Players: 3"));
+}
+
+#[test]
+fn hud_follows_selected_language() {
+    use chainquest_idle::ui::i18n::{tr, Lang};
+
+    let base = HudData { username: "erou".to_string(), level: 4, multiplayer: true, total_players: 2, ..Default::default() };
+
+    let english = format_hud(&base);
+    assert!(english.contains("Resources: 0.0 | Level: 4"));
+
+    let romanian = format_hud(&HudData { lang: Lang::from_code("ro"), ..base });
+    assert!(romanian.contains("Resurse: 0.0 | Nivel: 4"));
+    assert!(romanian.contains("Jucători: 2"));
+
+    assert_eq!(Lang::from_code("klingon"), Lang::English);
+    assert_eq!(tr(Lang::Romanian, "no_such_key"), "no_such_key");
+}
@@ -2,6 +2,7 @@
 mod tests {
     use bevy::prelude::*;
     use chainquest_idle::systems_idle::update_idle_progress;
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
     use chainquest_idle::components::{IdleProgress, Player};
 
     #[test]
@@ -9,7 +10,9 @@ mod tests {
         let mut app = App::new();
         // Insert Time resource (starts at 0) and a player
         app.insert_resource(Time::default());
-        app.world.spawn((Player, IdleProgress { resources: 0.0, experience: 0.0, level: 1, last_update: 0.0 }));
+        app.world.spawn((Player, IdleProgress { resources: 0.0, experience: 0.0, level: 1, last_update: 0.0, auto_collect: false }));
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
         app.add_systems(Update, update_idle_progress);
 
         // Simulate 1.0 second of game time in two 0.5s steps
@@ -24,5 +27,441 @@ mod tests {
             assert!(p.resources > 0.0, "resources should increase with time delta");
             assert_eq!(p.level, 1);
         }
+
+        let resources = app.world.query::<&IdleProgress>().single(&app.world).resources;
+        let produced: f64 = app.world.resource_mut::<Events<ResourcesProduced>>().drain().map(|e| e.amount).sum();
+        assert!((produced - resources).abs() < 1e-9, "every tick's production is announced");
+    }
+}
+
+mod happy_hour {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::config::multiplier::{unix_now, GlobalMultiplier};
+    use chainquest_idle::systems_idle::update_idle_progress;
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+
+    fn resources_after_one_second(multiplier: GlobalMultiplier) -> f64 {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(multiplier);
+        app.world.spawn((Player, IdleProgress { resources: 0.0, experience: 0.0, level: 1, last_update: 0.0, auto_collect: false }));
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        app.add_systems(Update, update_idle_progress);
+        app.update();
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+        app.update();
+        let mut q = app.world.query::<&IdleProgress>();
+        q.single(&app.world).resources
+    }
+
+    #[test]
+    fn multiplier_boosts_resources_only_inside_window() {
+        let now = unix_now();
+        let active = GlobalMultiplier { factor: 2.0, start: now - 60.0, end: now + 3600.0 };
+        let expired = GlobalMultiplier { factor: 2.0, start: now - 7200.0, end: now - 3600.0 };
+
+        let boosted = resources_after_one_second(active);
+        let normal = resources_after_one_second(expired);
+        assert!((normal - 0.5).abs() < 1e-3, "base rate is 0.5/s at level 1, got {}", normal);
+        assert!((boosted - 1.0).abs() < 1e-3, "boosted rate is 1.0/s, got {}", boosted);
+    }
+}
+
+mod resource_milestones {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, PlayerStats};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::systems_idle::check_resource_milestones;
+
+    fn set_resources(app: &mut App, resources: f64) {
+        let mut q = app.world.query::<&mut IdleProgress>();
+        q.single_mut(&mut app.world).resources = resources;
+    }
+
+    fn experience(app: &mut App) -> f64 {
+        let mut q = app.world.query::<&IdleProgress>();
+        q.single(&app.world).experience
+    }
+
+    #[test]
+    fn crossing_a_milestone_grants_experience_once() {
+        let mut app = App::new();
+        app.insert_resource(ProgressionConfig { resource_milestone: 1000.0, milestone_exp: 50.0, ..Default::default() });
+        app.world.spawn((Player, IdleProgress::default(), PlayerStats::default()));
+        app.add_systems(Update, check_resource_milestones);
+
+        app.update(); // baseline at 0 resources
+        set_resources(&mut app, 1200.0);
+        app.update();
+        assert_eq!(experience(&mut app), 50.0);
+
+        // Still below the next milestone (2000 lifetime): no re-grant
+        set_resources(&mut app, 1500.0);
+        app.update();
+        app.update();
+        assert_eq!(experience(&mut app), 50.0);
+
+        // Spending doesn't count, but earning past 2000 lifetime does
+        set_resources(&mut app, 100.0);
+        app.update();
+        set_resources(&mut app, 700.0);
+        app.update();
+        assert_eq!(experience(&mut app), 100.0);
+    }
+}
+
+mod fast_forward {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::systems_idle::{fast_forward_progress, update_idle_progress};
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+
+    #[test]
+    fn fast_forward_matches_one_second_ticks() {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.world.spawn((Player, IdleProgress::default()));
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        app.add_systems(Update, update_idle_progress);
+        app.update();
+        for _ in 0..3600 {
+            app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+            app.update();
+        }
+        let mut q = app.world.query::<&IdleProgress>();
+        let ticked = q.single(&app.world).clone();
+
+        let mut forwarded = IdleProgress::default();
+        fast_forward_progress(&mut forwarded, &ProgressionConfig::default(), None, None, 0.0, 0.0, 3600.0);
+
+        assert_eq!(forwarded.level, ticked.level);
+        assert!(ticked.level > 1, "an hour should level up at default rates");
+        let tolerance = ticked.resources * 1e-3;
+        assert!((forwarded.resources - ticked.resources).abs() <= tolerance,
+            "fast-forward {} vs ticked {}", forwarded.resources, ticked.resources);
+    }
+}
+
+mod pause {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced, SimState};
+    use chainquest_idle::systems_idle::update_idle_progress;
+    use std::time::Duration;
+
+    fn resources(app: &mut App) -> f64 {
+        app.world.query::<&IdleProgress>().single(&app.world).resources
+    }
+
+    fn set_state(app: &mut App, state: SimState) {
+        app.world.resource_mut::<NextState<SimState>>().set(state);
+    }
+
+    #[test]
+    fn no_resources_accrue_while_paused() {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.init_state::<SimState>();
+        app.world.spawn((Player, IdleProgress { resources: 0.0, experience: 0.0, level: 1, last_update: 0.0, auto_collect: false }));
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        app.add_systems(Update, update_idle_progress.run_if(in_state(SimState::Running)));
+        app.update();
+
+        set_state(&mut app, SimState::Paused);
+        app.update();
+        let before = resources(&mut app);
+        let last_update = app.world.query::<&IdleProgress>().single(&app.world).last_update;
+
+        for _ in 0..10 {
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs(60));
+            app.update();
+        }
+        assert_eq!(resources(&mut app), before);
+        assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).last_update, last_update);
+
+        // The first frame after resuming only covers its own delta
+        set_state(&mut app, SimState::Running);
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        app.update();
+        let gained = resources(&mut app) - before;
+        assert!((gained - 0.5).abs() < 1e-3, "one second at 0.5/s, got {}", gained);
+    }
+}
+
+mod resource_cap {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, PlayerStats};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::systems_idle::{tick_idle_progress, update_idle_progress};
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+    use chainquest_idle::ui::hud::{format_hud, HudData};
+
+    #[test]
+    fn resources_never_exceed_the_cap() {
+        let config = ProgressionConfig { resource_cap_base: 50.0, exp_per_second: 0.0, ..Default::default() };
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(config.clone());
+        app.world.spawn((Player, IdleProgress::default(), PlayerStats::default()));
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        app.add_systems(Update, update_idle_progress);
+        app.update();
+        for _ in 0..200 {
+            app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+            app.update();
+            let mut q = app.world.query::<&IdleProgress>();
+            assert!(q.single(&app.world).resources <= config.resource_cap(1) as f64);
+        }
+
+        // 200s at 0.5/s produces 100; the 50 beyond the cap become essence
+        let mut q = app.world.query::<(&IdleProgress, &PlayerStats)>();
+        let (progress, stats) = q.single(&app.world);
+        assert_eq!(progress.resources, 50.0);
+        assert!((stats.essence - 50.0 * config.overflow_conversion as f64).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cap_grows_with_level() {
+        let config = ProgressionConfig::default();
+        assert_eq!(config.resource_cap(1), config.resource_cap_base);
+        assert!(config.resource_cap(5) > config.resource_cap(4));
+
+        let mut progress = IdleProgress { resources: config.resource_cap(1) as f64 - 1.0, ..Default::default() };
+        let overflow = tick_idle_progress(&mut progress, &config, 0.0, 1.0, 10.0);
+        assert_eq!(progress.resources, config.resource_cap(1) as f64);
+        assert!(overflow > 0.0);
+    }
+
+    #[test]
+    fn hud_shows_resources_against_cap() {
+        let hud = format_hud(&HudData { resources: 12.0, resource_cap: 1000.0, level: 1, ..Default::default() });
+        assert!(hud.contains("Resources: 12.0 / 1000"), "{}", hud);
+    }
+}
+
+mod precision {
+    use chainquest_idle::components::IdleProgress;
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::systems_idle::tick_idle_progress;
+
+    #[test]
+    fn small_increments_apply_to_large_balances() {
+        let config = ProgressionConfig { resource_cap_base: 1e12, ..Default::default() };
+        let start = 12_345_678.0;
+        let mut progress = IdleProgress { resources: start, ..Default::default() };
+        // One 60 FPS frame at level 1 earns ~0.0083, far below f32 spacing at this magnitude
+        for _ in 0..60 {
+            tick_idle_progress(&mut progress, &config, 0.0, 1.0, 1.0 / 60.0);
+        }
+        assert!((progress.resources - (start + 0.5)).abs() < 1e-6, "got {}", progress.resources);
+    }
+}
+
+mod auto_collect {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+    use chainquest_idle::security::SecurityManager;
+    use chainquest_idle::systems::apply_validated_collection;
+    use chainquest_idle::systems_idle::auto_collect;
+    use std::time::Duration;
+
+    fn app_with_player(level: u32, security: SecurityManager) -> App {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(ProgressionConfig { auto_collect_level: 5, auto_collect_interval_secs: 2.0, ..Default::default() });
+        app.insert_resource(security);
+        app.add_event::<ResourceCollected>();
+        app.add_event::<ResourcesCredited>();
+        app.world.spawn((Player, IdleProgress { level, ..Default::default() }));
+        app.add_systems(Update, (auto_collect, apply_validated_collection.after(auto_collect)));
+        app
+    }
+
+    fn advance(app: &mut App, secs: u64) {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(secs));
+        app.update();
+    }
+
+    fn progress(app: &mut App) -> IdleProgress {
+        app.world.query::<&IdleProgress>().single(&app.world).clone()
+    }
+
+    fn credited(app: &mut App) -> Vec<f64> {
+        app.world.resource_mut::<Events<ResourcesCredited>>().drain().map(|e| e.amount).collect()
+    }
+
+    #[test]
+    fn collects_on_interval_once_unlocked() {
+        let mut app = app_with_player(5, SecurityManager::default());
+        advance(&mut app, 1);
+        assert!(progress(&mut app).auto_collect);
+        assert_eq!(progress(&mut app).resources, 0.0);
+        advance(&mut app, 1);
+        assert_eq!(progress(&mut app).resources, 50.0);
+        assert_eq!(credited(&mut app), vec![50.0], "auto-collect is announced like a manual collection");
+    }
+
+    #[test]
+    fn stays_locked_below_the_unlock_level() {
+        let mut app = app_with_player(4, SecurityManager::default());
+        advance(&mut app, 3);
+        assert!(!progress(&mut app).auto_collect);
+        assert_eq!(progress(&mut app).resources, 0.0);
+    }
+
+    #[test]
+    fn automated_gains_are_validated() {
+        let mut security = SecurityManager::default();
+        security.validation_config.max_resource_gain_per_action = 5.0;
+        let mut app = app_with_player(5, security);
+        advance(&mut app, 2);
+        assert_eq!(progress(&mut app).resources, 0.0);
+        assert!(credited(&mut app).is_empty());
+        assert!(!app.world.resource::<SecurityManager>().recent_events(1).is_empty());
+    }
+
+    #[test]
+    fn default_cap_holds_at_high_levels() {
+        let mut app = app_with_player(150, SecurityManager::default());
+        advance(&mut app, 2);
+        assert_eq!(progress(&mut app).resources, 1500.0);
+    }
+}
+
+mod level_up_event {
+    use bevy::ecs::event::Events;
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+    use chainquest_idle::systems_idle::update_idle_progress;
+
+    #[test]
+    fn crossing_the_exp_threshold_sends_one_event() {
+        let config = ProgressionConfig::default();
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        let experience = config.required_exp(3) as f64 - config.exp_per_second as f64 * 0.5;
+        let player = app.world.spawn((Player, IdleProgress { experience, level: 3, ..Default::default() })).id();
+        app.add_systems(Update, update_idle_progress);
+
+        app.update();
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+        app.update();
+
+        let events = app.world.resource::<Events<LevelUpEvent>>();
+        let sent: Vec<_> = events.get_reader().read(events).cloned().collect();
+        assert_eq!(sent, vec![LevelUpEvent { player, old_level: 3, new_level: 4 }]);
+    }
+}
+
+mod level_up_validation {
+    use bevy::ecs::event::Events;
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, PlayerId};
+    use chainquest_idle::config::progression::ProgressionConfig;
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesProduced};
+    use chainquest_idle::security::SecurityManager;
+    use chainquest_idle::config::debug::DebugConfig;
+    use chainquest_idle::systems_idle::{debug_fast_forward, update_idle_progress, validate_level_change};
+
+    #[test]
+    fn six_level_jump_is_rejected_and_reverted() {
+        let security = SecurityManager::default();
+        let mut progress = IdleProgress { level: 10, ..Default::default() };
+        assert!(!validate_level_change(Some(&security), 7, &mut progress, 4, 55.0));
+        assert_eq!((progress.level, progress.experience), (4, 55.0));
+
+        let mut progress = IdleProgress { level: 5, ..Default::default() };
+        assert!(validate_level_change(Some(&security), 7, &mut progress, 4, 55.0));
+        assert_eq!((progress.level, progress.experience), (5, 0.0));
+    }
+
+    #[test]
+    fn banned_player_keeps_their_level_and_sends_no_event() {
+        let config = ProgressionConfig::default();
+        let security = SecurityManager::default();
+        security.ban_player(42, "test");
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(security);
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        let experience = config.required_exp(2) as f64;
+        app.world.spawn((Player, PlayerId(42), IdleProgress { experience, level: 2, ..Default::default() }));
+        app.add_systems(Update, update_idle_progress);
+
+        app.update();
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+        app.update();
+
+        let progress = app.world.query::<&IdleProgress>().single(&app.world);
+        assert_eq!(progress.level, 2);
+        // The experience earned toward the rejected level is kept, not reset
+        assert!(progress.experience >= experience);
+        assert!(app.world.resource::<Events<LevelUpEvent>>().is_empty());
+    }
+
+    #[test]
+    fn rejected_level_up_is_reported_once_per_retry() {
+        use chainquest_idle::systems_idle::LEVEL_UP_RETRY_SECS;
+
+        let config = ProgressionConfig::default();
+        // Reject every level-up without ever banning
+        let mut security = SecurityManager::default();
+        security.validation_config.max_level_jumps = 0;
+        security.validation_config.suspicious_threshold = u32::MAX;
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(security);
+        app.add_event::<LevelUpEvent>();
+        app.add_event::<ResourcesProduced>();
+        let experience = config.required_exp(2) as f64;
+        app.world.spawn((Player, PlayerId(42), IdleProgress { experience, level: 2, ..Default::default() }));
+        app.add_systems(Update, update_idle_progress);
+
+        for _ in 0..10 {
+            app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_millis(100));
+            app.update();
+        }
+        assert_eq!(app.world.resource::<SecurityManager>().events_for_player(42).len(), 1);
+
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs_f64(LEVEL_UP_RETRY_SECS));
+        app.update();
+        assert_eq!(app.world.resource::<SecurityManager>().events_for_player(42).len(), 2);
+        assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).level, 2);
+    }
+
+    #[test]
+    fn fast_forward_level_ups_are_validated() {
+        let security = SecurityManager::default();
+        security.ban_player(42, "test");
+
+        let mut app = App::new();
+        app.insert_resource(security);
+        app.insert_resource(DebugConfig { enabled: true, fast_forward_secs: 3600.0 });
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        app.add_event::<LevelUpEvent>();
+        app.world.spawn((Player, PlayerId(42), IdleProgress { experience: 3.0, level: 2, ..Default::default() }));
+        app.add_systems(Update, debug_fast_forward);
+
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::F5);
+        app.update();
+
+        let progress = app.world.query::<&IdleProgress>().single(&app.world);
+        assert_eq!((progress.level, progress.experience), (2, 3.0));
+        assert!(app.world.resource::<Events<LevelUpEvent>>().is_empty());
     }
 }
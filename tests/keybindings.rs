@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+use chainquest_idle::components::{IdleProgress, Player};
+use chainquest_idle::config::keys::{parse_key, Action, KeyBindings};
+use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+use chainquest_idle::security::SecurityManager;
+use chainquest_idle::systems::apply_validated_collection;
+use chainquest_idle::systems_idle::manual_collect;
+
+#[test]
+fn key_names_parse() {
+    assert_eq!(parse_key("space"), Some(KeyCode::Space));
+    assert_eq!(parse_key("q"), Some(KeyCode::KeyQ));
+    assert_eq!(parse_key("KeyM"), Some(KeyCode::KeyM));
+    assert_eq!(parse_key("7"), Some(KeyCode::Digit7));
+    assert_eq!(parse_key("F12"), Some(KeyCode::F12));
+    assert_eq!(parse_key("F13"), None);
+    assert_eq!(parse_key("banana"), None);
+}
+
+#[test]
+fn remapped_collect_key_is_honoured() {
+    let bindings = KeyBindings { collect: KeyCode::KeyC, ..Default::default() };
+    assert_eq!(bindings.key(Action::Quest), KeyCode::KeyQ);
+
+    let mut app = App::new();
+    app.insert_resource(bindings);
+    app.insert_resource(ButtonInput::<KeyCode>::default());
+    app.insert_resource(SecurityManager::default());
+    app.world.spawn((Player, IdleProgress { resources: 0.0, experience: 0.0, level: 1, last_update: 0.0, auto_collect: false }));
+    app.add_event::<ResourceCollected>();
+    app.add_event::<ResourcesCredited>();
+    app.add_systems(Update, (manual_collect, apply_validated_collection.after(manual_collect)));
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Space);
+    app.update();
+    assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 0.0);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyC);
+    app.update();
+    assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 10.0);
+}
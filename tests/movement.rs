@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use std::time::Duration;
+use chainquest_idle::components::{IdleProgress, MapTile, Player, PlayerId, Position, TileType};
+use chainquest_idle::combat::{ActiveFight, CombatState};
+use chainquest_idle::config::keys::KeyBindings;
+use chainquest_idle::movement::{collect_resource_tiles, consume_collected_tiles, player_movement, step_target, TileEntered, RESOURCE_TILE_COLLECTIONS};
+use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+use chainquest_idle::security::SecurityManager;
+use chainquest_idle::systems::{apply_validated_collection, collect_amount};
+
+fn movement_app() -> App {
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    app.insert_resource(ButtonInput::<KeyCode>::default());
+    app.insert_resource(KeyBindings::default());
+    app.add_event::<TileEntered>();
+    app.insert_resource(SecurityManager::default());
+    app.add_event::<ResourceCollected>();
+    app.add_event::<ResourcesCredited>();
+    app.add_systems(Update, (
+        player_movement,
+        collect_resource_tiles.after(player_movement),
+        apply_validated_collection.after(collect_resource_tiles),
+        consume_collected_tiles.after(apply_validated_collection),
+    ));
+    app
+}
+
+fn spawn_player(app: &mut App, x: f32, y: f32) -> Entity {
+    app.world.spawn((Player, PlayerId(7), IdleProgress { level: 2, ..Default::default() }, Position { x, y })).id()
+}
+
+fn spawn_tile(app: &mut App, tile_type: TileType, grid_x: i32, grid_y: i32) -> Entity {
+    app.world.spawn(MapTile { tile_type, grid_x, grid_y }).id()
+}
+
+fn frame(app: &mut App, millis: u64) {
+    app.world.resource_mut::<Time>().advance_by(Duration::from_millis(millis));
+    app.update();
+}
+
+fn position(app: &App, player: Entity) -> (f32, f32) {
+    let position = app.world.get::<Position>(player).unwrap();
+    (position.x, position.y)
+}
+
+#[test]
+fn steps_stay_on_the_map_and_avoid_blocked_cells() {
+    let open = |_| false;
+    assert_eq!(step_target(IVec2::new(3, 3), IVec2::X, open), Some(IVec2::new(4, 3)));
+    assert_eq!(step_target(IVec2::new(0, 3), IVec2::NEG_X, open), None);
+    assert_eq!(step_target(IVec2::new(3, 15), IVec2::Y, open), None);
+    assert_eq!(step_target(IVec2::new(3, 3), IVec2::Y, |cell| cell == IVec2::new(3, 4)), None);
+}
+
+#[test]
+fn pressing_a_key_moves_the_player_and_reports_the_tile() {
+    let mut app = movement_app();
+    let player = spawn_player(&mut app, 2.0, 2.0);
+    let tile = spawn_tile(&mut app, TileType::Empty, 3, 2);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_right);
+    frame(&mut app, 16);
+    assert_eq!(position(&app, player), (3.0, 2.0));
+
+    let events = app.world.resource::<Events<TileEntered>>();
+    let entered: Vec<_> = events.get_reader().read(events).cloned().collect();
+    assert_eq!(entered.len(), 1);
+    assert_eq!(entered[0].tile, tile);
+    assert_eq!((entered[0].grid_x, entered[0].grid_y), (3, 2));
+}
+
+#[test]
+fn arrow_keys_move_too() {
+    let mut app = movement_app();
+    let player = spawn_player(&mut app, 2.0, 2.0);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::ArrowUp);
+    frame(&mut app, 16);
+    assert_eq!(position(&app, player), (2.0, 3.0));
+}
+
+#[test]
+fn enemy_tiles_can_be_entered_to_fight() {
+    let mut app = movement_app();
+    let player = spawn_player(&mut app, 2.0, 2.0);
+    spawn_tile(&mut app, TileType::Enemy, 1, 2);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_left);
+    frame(&mut app, 16);
+    assert_eq!(position(&app, player), (1.0, 2.0));
+    let events = app.world.resource::<Events<TileEntered>>();
+    assert!(events.get_reader().read(events).any(|e| matches!(e.tile_type, TileType::Enemy)));
+}
+
+#[test]
+fn enemy_tiles_block_movement_during_a_fight() {
+    let mut app = movement_app();
+    app.insert_resource(CombatState { active_fight: Some(ActiveFight { enemy_power: 10, elapsed: 0.0 }) });
+    let player = spawn_player(&mut app, 2.0, 2.0);
+    spawn_tile(&mut app, TileType::Enemy, 1, 2);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_left);
+    frame(&mut app, 500);
+    assert_eq!(position(&app, player), (2.0, 2.0));
+}
+
+#[test]
+fn resource_tiles_grant_resources_once() {
+    let mut app = movement_app();
+    let player = spawn_player(&mut app, 2.0, 2.0);
+    let tile = spawn_tile(&mut app, TileType::Resource, 2, 1);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_down);
+    frame(&mut app, 16);
+
+    let events = app.world.resource::<Events<ResourceCollected>>();
+    let collected: Vec<_> = events.get_reader().read(events).map(|e| (e.player_id, e.amount)).collect();
+    assert_eq!(collected, vec![(7, collect_amount(2) * RESOURCE_TILE_COLLECTIONS)]);
+    assert_eq!(app.world.get::<IdleProgress>(player).unwrap().resources, collect_amount(2) * RESOURCE_TILE_COLLECTIONS);
+    assert!(matches!(app.world.get::<MapTile>(tile).unwrap().tile_type, TileType::Empty));
+}
+
+#[test]
+fn rejected_tile_collections_leave_the_tile() {
+    let mut app = movement_app();
+    app.world.resource_mut::<SecurityManager>().validation_config.max_resource_gain_per_action = 1.0;
+    let player = spawn_player(&mut app, 2.0, 2.0);
+    let tile = spawn_tile(&mut app, TileType::Resource, 2, 1);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_down);
+    frame(&mut app, 16);
+
+    assert_eq!(app.world.get::<IdleProgress>(player).unwrap().resources, 0.0);
+    assert!(matches!(app.world.get::<MapTile>(tile).unwrap().tile_type, TileType::Resource));
+}
+
+#[test]
+fn held_keys_move_at_the_same_speed_at_any_frame_rate() {
+    let steps_after_one_second = |frame_millis: u64| {
+        let mut app = movement_app();
+        let player = spawn_player(&mut app, 0.0, 0.0);
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().move_right);
+        for _ in 0..1000 / frame_millis {
+            frame(&mut app, frame_millis);
+        }
+        position(&app, player).0
+    };
+    assert_eq!(steps_after_one_second(100), 7.0);
+    assert_eq!(steps_after_one_second(20), 7.0);
+}
@@ -0,0 +1,76 @@
+use chainquest_idle::config::env::{EnvConfig, ServerConfig};
+use chainquest_idle::multiplayer::config::{HostParams, NetConfig};
+use std::net::Ipv4Addr;
+
+#[test]
+fn cq_host_is_resolved_and_used() {
+    std::env::set_var("CQ_HOST", "localhost");
+    std::env::set_var("CQ_PORT", "9001");
+    let env = EnvConfig::from_env();
+    std::env::remove_var("CQ_HOST");
+    std::env::remove_var("CQ_PORT");
+
+    let cfg = NetConfig { host: env.host, port: env.port, ..Default::default() };
+    assert_eq!(cfg.port, 9001);
+    assert_eq!(cfg.resolve_ip(), Ipv4Addr::LOCALHOST);
+
+    let literal = NetConfig { host: "10.1.2.3".to_string(), ..Default::default() };
+    assert_eq!(literal.resolve_ip(), Ipv4Addr::new(10, 1, 2, 3));
+
+    let bogus = NetConfig { host: "not a host name!".to_string(), ..Default::default() };
+    assert_eq!(bogus.resolve_ip(), Ipv4Addr::LOCALHOST, "unresolvable hosts fall back to localhost");
+}
+
+#[test]
+fn server_config_reads_env_and_args() {
+    std::env::set_var("CQ_BIND_IP", "127.0.0.1");
+    std::env::set_var("CQ_MAX_CLIENTS", "16");
+    let env = ServerConfig::from_env();
+    std::env::remove_var("CQ_BIND_IP");
+    std::env::remove_var("CQ_MAX_CLIENTS");
+    assert_eq!(env.bind_ip, Ipv4Addr::LOCALHOST);
+    assert_eq!(env.max_clients, 16);
+    assert_eq!(env.channels, 2);
+
+    let args = ["--port", "8081", "--channels", "4"].map(String::from);
+    let cfg = env.with_args(args).expect("valid args");
+    assert_eq!((cfg.port, cfg.channels, cfg.max_clients), (8081, 4, 16));
+    assert!(cfg.validate().is_ok());
+
+    assert!(ServerConfig::default().with_args(["--port".to_string()]).is_err(), "flag without a value");
+    assert!(ServerConfig::default().with_args(["--bind", "nope"].map(String::from)).is_err());
+    assert!(ServerConfig::default().with_args(["--verbose", "1"].map(String::from)).is_err());
+}
+
+#[test]
+fn server_config_rejects_invalid_port_clients_and_channels() {
+    assert!(ServerConfig { port: 0, ..Default::default() }.validate().is_err());
+    assert!(ServerConfig { max_clients: 0, ..Default::default() }.validate().is_err());
+    assert!(ServerConfig { channels: 1, ..Default::default() }.validate().is_err(), "state needs its own channel");
+    assert!(ServerConfig::default().validate().is_ok());
+}
+
+#[test]
+fn bandwidth_caps_are_forwarded_to_host_creation() {
+    let client = NetConfig { incoming_bandwidth: 64_000, outgoing_bandwidth: 16_000, ..Default::default() };
+    assert_eq!(
+        client.host_params(),
+        HostParams { max_peers: 1, channels: 2, incoming_bandwidth: 64_000, outgoing_bandwidth: 16_000 }
+    );
+
+    let args = ["--in-bandwidth", "128000", "--out-bandwidth", "32000"].map(String::from);
+    let server = ServerConfig::default().with_args(args).expect("valid args");
+    assert_eq!(
+        server.host_params(),
+        HostParams { max_peers: 8, channels: 2, incoming_bandwidth: 128_000, outgoing_bandwidth: 32_000 }
+    );
+    assert_eq!(NetConfig::default().host_params().incoming_bandwidth, 0, "unlimited by default");
+}
+
+#[test]
+fn rate_limit_default_reads_env() {
+    std::env::set_var("CQ_RATE_LIMIT", "40");
+    let env = EnvConfig::from_env();
+    std::env::remove_var("CQ_RATE_LIMIT");
+    assert_eq!(env.default_rate_limit, 40);
+}
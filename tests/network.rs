@@ -0,0 +1,450 @@
+use chainquest_idle::multiplayer::network::{CompressionAlgo, GameMessage, MessageKind, NetworkManager, SendOutcome};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[test]
+fn compression_ratio_is_tracked_per_message_kind() {
+    let mut manager = NetworkManager::default();
+
+    let chat = GameMessage::Chat { player_id: 1, message: "gg ".repeat(60) };
+    let chat_bytes = chat.to_bytes().expect("serialize chat");
+    manager.prepare_payload(chat.kind(), &chat_bytes).expect("compress chat");
+
+    let mut random = vec![0u8; 512];
+    ChaCha8Rng::seed_from_u64(7).fill_bytes(&mut random);
+    manager.prepare_payload(MessageKind::Raw, &random).expect("compress raw");
+
+    let stats = manager.get_stats();
+    let chat_ratio = stats.compression_ratio_for(MessageKind::Chat).expect("chat ratio");
+    let raw_ratio = stats.compression_ratio_for(MessageKind::Raw).expect("raw ratio");
+    assert!(chat_ratio < 0.5, "repetitive chat should compress well: {}", chat_ratio);
+    assert!(raw_ratio > 0.9, "random payload should not compress: {}", raw_ratio);
+}
+
+#[test]
+fn unknown_message_tags_parse_as_unknown_without_breaking_the_stream() {
+    let stream: Vec<&[u8]> = vec![
+        br#"{"Teleport":{"x":3,"y":4}}"#,
+        br#""Ping""#,
+        br#""Emote""#,
+        br#"{"Chat":{"player_id":2,"message":"hi"}}"#,
+    ];
+    let parsed: Vec<GameMessage> = stream
+        .iter()
+        .map(|data| GameMessage::from_bytes_lenient(data).expect("lenient parse"))
+        .collect();
+
+    assert!(matches!(&parsed[0], GameMessage::Unknown { tag } if tag == "Teleport"));
+    assert!(matches!(parsed[1], GameMessage::Ping));
+    assert!(matches!(&parsed[2], GameMessage::Unknown { tag } if tag == "Emote"));
+    assert!(matches!(&parsed[3], GameMessage::Chat { player_id: 2, message } if message == "hi"));
+
+    // Known tags with malformed bodies and non-JSON data are still errors
+    assert!(GameMessage::from_bytes_lenient(br#"{"Chat":{"oops":true}}"#).is_err());
+    assert!(GameMessage::from_bytes_lenient(b"\x00\x01garbage").is_err());
+    assert!(GameMessage::from_bytes(br#""Emote""#).is_err());
+}
+
+#[test]
+fn broadcast_after_disconnect_skips_gone_peer_quietly() {
+    let mut manager = NetworkManager::default();
+    for peer_id in [1, 2, 3] {
+        manager.register_peer(peer_id);
+    }
+    manager.remove_peer(2);
+
+    assert_eq!(manager.send_packet(2, b"late update", true), Ok(SendOutcome::Skipped), "send to a gone peer is not an error");
+    manager.broadcast(b"hello", true).expect("broadcast ok");
+
+    let mut recipients: Vec<u32> = manager.outbox.iter().map(|p| p.peer_id).collect();
+    recipients.sort();
+    assert_eq!(recipients, vec![1, 3]);
+}
+
+#[test]
+fn invalid_chat_and_usernames_are_rejected_back_to_the_sender() {
+    let mut manager = NetworkManager::default();
+    manager.register_peer(5);
+
+    let chat = GameMessage::Chat { player_id: 5, message: "  good game!  ".to_string() };
+    let accepted = manager.receive_message(5, &chat.to_bytes().unwrap());
+    assert!(matches!(accepted, Some(GameMessage::Chat { message, .. }) if message == "good game!"));
+    assert!(manager.outbox.is_empty());
+
+    let injected = GameMessage::Chat { player_id: 5, message: "hi\u{1b}[2J".to_string() };
+    assert!(manager.receive_message(5, &injected.to_bytes().unwrap()).is_none());
+    let oversized = GameMessage::Chat { player_id: 5, message: "a".repeat(500) };
+    assert!(manager.receive_message(5, &oversized.to_bytes().unwrap()).is_none());
+    let bad_name = GameMessage::PlayerJoin { username: "<script>".to_string() };
+    assert!(manager.receive_message(5, &bad_name.to_bytes().unwrap()).is_none());
+
+    assert_eq!(manager.outbox.len(), 3);
+    for packet in &manager.outbox {
+        assert_eq!(packet.peer_id, 5);
+        let reply = GameMessage::from_bytes(&manager.decode_payload(&packet.data).unwrap()).expect("rejection");
+        assert!(matches!(reply, GameMessage::Rejected { .. }));
+    }
+}
+
+#[test]
+fn chat_is_relayed_to_everyone_but_the_sender() {
+    let mut manager = NetworkManager::default();
+    for peer_id in [1, 2, 3] {
+        manager.register_peer(peer_id);
+    }
+    manager.set_peer_rate_limit(3, 0);
+
+    let chat = GameMessage::Chat { player_id: 2, message: " hello all ".to_string() };
+    let sanitized = manager.receive_message(2, &chat.to_bytes().unwrap()).expect("valid chat");
+    manager.relay_chat(2, &sanitized).expect("relay ok");
+
+    let recipients: Vec<u32> = manager.outbox.iter().map(|p| p.peer_id).collect();
+    assert_eq!(recipients, vec![1], "sender is excluded and rate-limited peer 3 fails");
+    let relayed = GameMessage::from_bytes(&manager.decode_payload(&manager.outbox[0].data).unwrap()).unwrap();
+    assert!(matches!(relayed, GameMessage::Chat { player_id: 2, message } if message == "hello all"));
+    assert_eq!(manager.get_stats().broadcast_delivered, 1);
+    assert_eq!(manager.get_stats().broadcast_failed, 1);
+
+    manager.outbox.clear();
+    manager.broadcast_except(1, b"state", false).unwrap();
+    let recipients: Vec<u32> = manager.outbox.iter().map(|p| p.peer_id).collect();
+    assert_eq!(recipients, vec![2]);
+}
+
+mod dispatch {
+    use bevy::prelude::*;
+    use chainquest_idle::components::NetworkPlayer;
+    use chainquest_idle::multiplayer::network::{handle_game_message, GameMessage};
+    use chainquest_idle::security::{SecurityManager, ValidationResult};
+
+    fn world_with_peer(peer_id: u32) -> World {
+        let mut world = World::new();
+        world.insert_resource(SecurityManager::default());
+        world.spawn(NetworkPlayer { peer_id, username: "peer".to_string(), connected: true, resources: 0.0 });
+        world
+    }
+
+    fn resources_of(world: &mut World, peer_id: u32) -> f32 {
+        let mut q = world.query::<&NetworkPlayer>();
+        q.iter(world).find(|p| p.peer_id == peer_id).unwrap().resources
+    }
+
+    #[test]
+    fn resource_updates_apply_only_after_security_approval() {
+        let mut world = world_with_peer(7);
+
+        handle_game_message(GameMessage::ResourceUpdate { player_id: 7, resources: 250.0 }, 7, &mut world);
+        assert_eq!(resources_of(&mut world, 7), 250.0);
+
+        // A jump beyond the per-action limit is rejected and leaves the value alone
+        handle_game_message(GameMessage::ResourceUpdate { player_id: 7, resources: 50_000.0 }, 7, &mut world);
+        assert_eq!(resources_of(&mut world, 7), 250.0);
+
+        // Peers can't update someone else's resources
+        handle_game_message(GameMessage::ResourceUpdate { player_id: 8, resources: 300.0 }, 7, &mut world);
+        assert_eq!(resources_of(&mut world, 7), 250.0);
+    }
+
+    #[test]
+    fn quest_completions_are_validated() {
+        let mut world = world_with_peer(3);
+
+        handle_game_message(GameMessage::QuestComplete { player_id: 3, quest_id: 1 }, 3, &mut world);
+        handle_game_message(GameMessage::QuestComplete { player_id: 3, quest_id: 2 }, 3, &mut world);
+
+        let security = world.resource::<SecurityManager>();
+        let events = security.events_for_player(3);
+        assert_eq!(events.len(), 1, "only the too-quick second completion is rejected");
+        assert!(matches!(events[0].result, ValidationResult::Rejected(_)));
+    }
+
+    #[test]
+    fn accepted_quest_completions_pay_the_quest_reward() {
+        use chainquest_idle::components::{IdleProgress, ObjectiveKind, Player, Quest, QuestObjective};
+        use chainquest_idle::quest_system::QuestManager;
+
+        let mut world = world_with_peer(3);
+        world.spawn((Player, IdleProgress::default()));
+        let quest = world.spawn(Quest {
+            id: 4,
+            name: "Shared".to_string(),
+            description: String::new(),
+            completed: false,
+            reward_resources: 75.0,
+            reward_sft: None,
+            objective: QuestObjective::new(ObjectiveKind::Elapsed, 60.0),
+        }).id();
+        world.insert_resource(QuestManager { active_quests: vec![quest], ..Default::default() });
+
+        handle_game_message(GameMessage::QuestComplete { player_id: 3, quest_id: 4 }, 3, &mut world);
+
+        assert_eq!(world.query::<&IdleProgress>().single(&world).resources, 75.0);
+        assert!(world.get_entity(quest).is_none(), "the completed quest is removed");
+        let manager = world.resource::<QuestManager>();
+        assert_eq!((manager.completed_quests.clone(), manager.active_quests.len()), (vec![4], 0));
+    }
+}
+
+#[test]
+fn payloads_round_trip_with_each_compression_algorithm() {
+    let message = GameMessage::Chat { player_id: 1, message: "zstd please ".repeat(20) }.to_bytes().unwrap();
+
+    for algo in [CompressionAlgo::None, CompressionAlgo::Gzip, CompressionAlgo::Zstd] {
+        let mut manager = NetworkManager::default();
+        manager.compression = algo;
+        let payload = manager.prepare_payload(MessageKind::Chat, &message).expect("prepare");
+        assert_eq!(payload[0], algo.prefix());
+        assert_eq!(manager.decode_payload(&payload).expect("decode"), message);
+    }
+
+    let mut manager = NetworkManager::default();
+    manager.compression = CompressionAlgo::Zstd;
+    manager.prepare_payload(MessageKind::Chat, &message).unwrap();
+    assert_eq!(manager.get_stats().last_compression_algo, CompressionAlgo::Zstd);
+    assert!(manager.get_stats().compression_ratio_for_algo(CompressionAlgo::Zstd).unwrap() < 0.5);
+
+    // Raw data starting with the gzip magic is no longer mistaken for compressed data
+    let raw = [0x1f, 0x8b, 0x00, 0x01, 0x02];
+    let payload = manager.prepare_payload(MessageKind::Raw, &raw).unwrap();
+    assert_eq!(manager.decode_payload(&payload).unwrap(), raw);
+    assert!(manager.decode_payload(&[9, 1, 2]).is_err());
+}
+
+#[test]
+fn incompressible_payloads_are_sent_raw() {
+    let mut manager = NetworkManager::default();
+
+    let mut random = vec![0u8; 512];
+    ChaCha8Rng::seed_from_u64(11).fill_bytes(&mut random);
+    let payload = manager.prepare_payload(MessageKind::Raw, &random).unwrap();
+    assert_eq!(payload[0], CompressionAlgo::None.prefix());
+    assert_eq!(payload.len(), random.len() + 1);
+    assert_eq!(manager.decode_payload(&payload).unwrap(), random);
+    assert_eq!(manager.get_stats().compression_skipped, 1);
+
+    let repetitive = vec![b'a'; 512];
+    let payload = manager.prepare_payload(MessageKind::Raw, &repetitive).unwrap();
+    assert_eq!(payload[0], CompressionAlgo::Gzip.prefix());
+    assert!(payload.len() < repetitive.len());
+    assert_eq!(manager.get_stats().compression_skipped, 1);
+}
+
+#[test]
+fn framed_stream_split_at_any_boundary_yields_whole_messages() {
+    use chainquest_idle::multiplayer::network::frame;
+
+    let messages = vec![
+        GameMessage::Ping,
+        GameMessage::Chat { player_id: 4, message: "long message ".repeat(30) },
+        GameMessage::ResourceUpdate { player_id: 4, resources: 12.5 },
+    ];
+    let mut sender = NetworkManager::default();
+    let stream: Vec<u8> = messages
+        .iter()
+        .flat_map(|m| frame(&sender.prepare_payload(m.kind(), &m.to_bytes().unwrap()).unwrap()))
+        .collect();
+
+    for chunk_size in [1, 3, 7, 64, stream.len()] {
+        let mut receiver = NetworkManager::default();
+        let mut received = Vec::new();
+        for chunk in stream.chunks(chunk_size) {
+            for payload in receiver.receive_bytes(9, chunk) {
+                received.push(GameMessage::from_bytes(&payload).expect("complete message"));
+            }
+        }
+        assert_eq!(received.len(), messages.len(), "chunk size {}", chunk_size);
+        assert!(matches!(received[0], GameMessage::Ping));
+        assert!(matches!(&received[1], GameMessage::Chat { message, .. } if message.len() == 13 * 30));
+        assert!(matches!(received[2], GameMessage::ResourceUpdate { resources, .. } if resources == 12.5));
+        assert_eq!(receiver.reassembly[&9].pending_len(), 0);
+    }
+}
+
+#[test]
+fn hello_with_wrong_protocol_version_disconnects_the_peer() {
+    use chainquest_idle::multiplayer::network::PROTOCOL_VERSION;
+
+    let mut manager = NetworkManager::default();
+    manager.register_peer(1);
+    manager.register_peer(2);
+
+    assert!(manager.handle_hello(1, PROTOCOL_VERSION));
+    assert_eq!(manager.peer_versions.get(&1), Some(&PROTOCOL_VERSION));
+    assert!(manager.outbox.is_empty());
+
+    assert!(!manager.handle_hello(2, PROTOCOL_VERSION + 1));
+    assert!(!manager.peer_versions.contains_key(&2));
+    assert_eq!(manager.pending_disconnects, vec![2]);
+    let reply = GameMessage::from_bytes(&manager.decode_payload(&manager.outbox[0].data).unwrap()).unwrap();
+    assert!(matches!(reply, GameMessage::Disconnect { reason } if reason.contains("mismatch")));
+}
+
+#[test]
+fn messages_before_hello_are_dropped() {
+    use bevy::prelude::*;
+    use chainquest_idle::components::NetworkPlayer;
+    use chainquest_idle::multiplayer::network::{handle_game_message, PROTOCOL_VERSION};
+
+    let mut manager = NetworkManager::default();
+    manager.register_peer(5);
+    let mut world = World::new();
+    world.insert_resource(manager);
+    world.spawn(NetworkPlayer { peer_id: 5, username: "peer".to_string(), connected: true, resources: 0.0 });
+    let resources = |world: &mut World| world.query::<&NetworkPlayer>().single(world).resources;
+
+    handle_game_message(GameMessage::ResourceUpdate { player_id: 5, resources: 100.0 }, 5, &mut world);
+    assert_eq!(resources(&mut world), 0.0);
+
+    handle_game_message(GameMessage::Hello { protocol_version: PROTOCOL_VERSION }, 5, &mut world);
+    handle_game_message(GameMessage::ResourceUpdate { player_id: 5, resources: 100.0 }, 5, &mut world);
+    assert_eq!(resources(&mut world), 100.0);
+}
+
+#[test]
+fn peers_that_never_say_hello_are_disconnected() {
+    use chainquest_idle::multiplayer::network::{HANDSHAKE_TIMEOUT, PROTOCOL_VERSION};
+    use std::time::{Duration, Instant};
+
+    let mut manager = NetworkManager::default();
+    manager.register_peer(1);
+    manager.register_peer(2);
+    assert!(manager.handle_hello(1, PROTOCOL_VERSION));
+
+    assert!(manager.expire_handshakes(Instant::now()).is_empty());
+    let later = Instant::now() + HANDSHAKE_TIMEOUT + Duration::from_secs(1);
+    assert_eq!(manager.expire_handshakes(later), vec![2]);
+    assert_eq!(manager.pending_disconnects, vec![2]);
+    assert!(manager.expire_handshakes(later).is_empty(), "only disconnected once");
+}
+
+mod reconnect {
+    use chainquest_idle::multiplayer::client::{reconnect_delay, NetState};
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let delays: Vec<f64> = (0..7).map(reconnect_delay).collect();
+        assert_eq!(delays, vec![1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0]);
+    }
+
+    #[test]
+    fn attempts_wait_for_backoff_and_never_overlap() {
+        let mut state = NetState::default();
+        assert!(state.begin_attempt(0.0, 5));
+        assert!(!state.begin_attempt(10.0, 5), "an attempt is already in flight");
+
+        state.on_disconnected(); // attempt failed
+        assert!(!state.begin_attempt(0.5, 5), "still inside the 1s backoff");
+        assert!(state.begin_attempt(1.0, 5));
+        state.on_disconnected();
+        assert!(!state.begin_attempt(2.9, 5), "second backoff is 2s");
+        assert!(state.begin_attempt(3.0, 5));
+
+        state.on_connected();
+        assert_eq!(state.reconnect_attempts, 0);
+        state.on_disconnected();
+        assert!(state.begin_attempt(3.0, 5), "backoff resets after a successful connect");
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut state = NetState::default();
+        let mut now = 0.0;
+        for _ in 0..3 {
+            assert!(state.begin_attempt(now, 3));
+            state.on_disconnected();
+            now += 60.0;
+        }
+        assert!(!state.begin_attempt(now, 3));
+        assert!(state.gave_up);
+        assert!(state.last_msg.contains("Giving up"));
+    }
+}
+
+#[test]
+fn player_count_follows_connects_and_disconnects() {
+    use bevy::prelude::*;
+    use chainquest_idle::multiplayer::network::{handle_network_event, NetworkEvent};
+    use chainquest_idle::resources::GameState;
+
+    let mut app = App::new();
+    app.insert_resource(NetworkManager::default());
+    app.insert_resource(GameState::default());
+    assert_eq!(app.world.resource::<GameState>().total_players, 1, "the local player counts");
+
+    app.add_systems(Update, |mut commands: Commands, mut manager: ResMut<NetworkManager>, mut state: ResMut<GameState>| {
+        let events = [
+            NetworkEvent::PeerConnected(1),
+            NetworkEvent::PeerConnected(2),
+            NetworkEvent::PeerDisconnected(1),
+        ];
+        for event in events {
+            handle_network_event(event, &mut manager, &mut commands, Some(&mut state));
+        }
+    });
+    app.update();
+    assert_eq!(app.world.resource::<GameState>().total_players, 2);
+
+    let mut state = GameState::default();
+    state.peer_left();
+    state.peer_left();
+    assert_eq!(state.total_players, 1, "spurious disconnects don't underflow");
+}
+
+#[test]
+fn state_updates_use_their_own_channel() {
+    use chainquest_idle::multiplayer::network::{channel_for, CONTROL_CHANNEL, STATE_CHANNEL};
+
+    let update = GameMessage::ResourceUpdate { player_id: 1, resources: 10.0 };
+    let chat = GameMessage::Chat { player_id: 1, message: "hi".into() };
+    assert_eq!(channel_for(&update), STATE_CHANNEL);
+    assert_eq!(channel_for(&chat), CONTROL_CHANNEL);
+    assert_eq!(channel_for(&GameMessage::QuestComplete { player_id: 1, quest_id: 2 }), CONTROL_CHANNEL);
+
+    let mut manager = NetworkManager::default();
+    manager.register_peer(1);
+    manager.send_message(1, &update, false).unwrap();
+    manager.send_message(1, &chat, true).unwrap();
+    manager.send_packet(1, b"raw", true).unwrap();
+    let channels: Vec<u8> = manager.outbox.iter().map(|packet| packet.channel).collect();
+    assert_eq!(channels, vec![STATE_CHANNEL, CONTROL_CHANNEL, CONTROL_CHANNEL]);
+}
+
+#[test]
+fn send_to_reaches_only_the_listed_peers_and_reports_counts() {
+    use chainquest_idle::multiplayer::network::SendReport;
+
+    let mut manager = NetworkManager::default();
+    for peer_id in [1, 2, 3, 4] {
+        manager.register_peer(peer_id);
+    }
+    manager.set_peer_rate_limit(3, 0);
+
+    assert!(manager.send_to(&[1, 9], b"subset", true).is_err(), "peer 9 is not tracked");
+    assert!(manager.outbox.is_empty(), "nothing is sent when a peer is unknown");
+
+    let report = manager.send_to(&[1, 3, 4], b"subset", true).unwrap();
+    assert_eq!(report, SendReport { delivered: 2, failed: 1 });
+    let mut recipients: Vec<u32> = manager.outbox.iter().map(|p| p.peer_id).collect();
+    recipients.sort();
+    assert_eq!(recipients, vec![1, 4]);
+
+    manager.outbox.clear();
+    let report = manager.broadcast_except(4, b"state", false).unwrap();
+    assert_eq!(report, SendReport { delivered: 2, failed: 1 }, "peer 3 is still rate limited");
+}
+
+#[test]
+fn new_peers_get_the_configured_default_rate_limit() {
+    use chainquest_idle::multiplayer::config::DEFAULT_RATE_LIMIT;
+
+    let mut manager = NetworkManager::default();
+    manager.register_peer(1);
+    assert_eq!(manager.peer_rate_limits[&1].max_packets_per_second, DEFAULT_RATE_LIMIT);
+
+    let mut manager = NetworkManager { default_rate_limit: 50, ..Default::default() };
+    manager.register_peer(1);
+    manager.register_peer(2);
+    manager.set_peer_rate_limit(2, 5);
+    assert_eq!(manager.peer_rate_limits[&1].max_packets_per_second, 50);
+    assert_eq!(manager.peer_rate_limits[&2].max_packets_per_second, 5, "individual overrides still apply");
+}
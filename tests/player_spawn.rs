@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use chainquest_idle::components::{IdleProgress, Player};
+use chainquest_idle::resources::{DatabaseConnection, GameState, DEFAULT_SAVE_SLOT};
+use chainquest_idle::systems_setup::{load_saved_progress, setup_ui};
+
+#[test]
+fn saved_profile_yields_exactly_one_player() {
+    let db = DatabaseConnection::open(":memory:");
+    let saved = IdleProgress { resources: 42.0, experience: 7.0, level: 3, last_update: 12345.0, auto_collect: false };
+    db.save_progress(DEFAULT_SAVE_SLOT, &saved).expect("save ok");
+
+    let mut app = App::new();
+    app.insert_resource(db);
+    app.insert_resource(GameState::default());
+    app.add_systems(Startup, (setup_ui, load_saved_progress.after(setup_ui)));
+    app.update();
+
+    let mut q = app.world.query_filtered::<&IdleProgress, With<Player>>();
+    let players: Vec<_> = q.iter(&app.world).collect();
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].level, 3);
+    assert!((players[0].resources - 42.0).abs() < 1e-6);
+}
+
+#[test]
+fn spawned_player_id_is_stable_across_reloads() {
+    use chainquest_idle::components::PlayerId;
+    use chainquest_idle::security::LOCAL_PLAYER_ID;
+
+    fn spawned_id(db: &DatabaseConnection) -> PlayerId {
+        let mut app = App::new();
+        app.insert_resource(db.clone());
+        app.insert_resource(GameState::default());
+        app.add_systems(Startup, (setup_ui, load_saved_progress.after(setup_ui)));
+        app.update();
+        *app.world.query_filtered::<&PlayerId, With<Player>>().single(&app.world)
+    }
+
+    let db = DatabaseConnection::open(":memory:");
+    assert_eq!(spawned_id(&db), PlayerId(LOCAL_PLAYER_ID));
+    assert_eq!(db.load_player_id(DEFAULT_SAVE_SLOT).expect("id persisted"), LOCAL_PLAYER_ID);
+
+    db.save_player_id(DEFAULT_SAVE_SLOT, 7).expect("save id");
+    db.save_progress(DEFAULT_SAVE_SLOT, &IdleProgress { level: 2, ..Default::default() }).expect("save ok");
+    assert_eq!(spawned_id(&db), PlayerId(7));
+    assert_eq!(spawned_id(&db), PlayerId(7));
+}
@@ -0,0 +1,25 @@
+use chainquest_idle::multiplayer::network::GameMessage;
+use chainquest_idle::resources::{DatabaseConnection, PlayerProfile, DEFAULT_SAVE_SLOT};
+use chainquest_idle::ui::hud::{format_hud, HudData};
+
+#[test]
+fn configured_username_is_sanitized_stored_and_displayed() {
+    let db = DatabaseConnection::open(":memory:");
+    let profile = PlayerProfile::resolve(Some("  hero_42  "), None);
+    assert_eq!(profile.username, "hero_42");
+
+    db.save_username(DEFAULT_SAVE_SLOT, &profile.username).expect("save username");
+    let stored = db.load_username(DEFAULT_SAVE_SLOT).expect("load username");
+    assert_eq!(PlayerProfile::resolve(None, Some(&stored)), profile);
+
+    assert!(matches!(profile.join_message(), GameMessage::PlayerJoin { username } if username == "hero_42"));
+
+    let hud = format_hud(&HudData { username: profile.username.clone(), level: 1, ..Default::default() });
+    assert!(hud.contains("hero_42"));
+}
+
+#[test]
+fn invalid_usernames_fall_back() {
+    assert_eq!(PlayerProfile::resolve(Some("x"), Some("stored_name")).username, "stored_name");
+    assert_eq!(PlayerProfile::resolve(Some("<script>"), None), PlayerProfile::default());
+}
@@ -0,0 +1,335 @@
+use chainquest_idle::quest_system::{get_quest_templates, select_template, QuestPreferences, QuestTemplate};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+fn with_tags(mut template: QuestTemplate, tags: &[&str]) -> QuestTemplate {
+    template.tags = tags.iter().map(|t| t.to_string()).collect();
+    template
+}
+
+#[test]
+fn preferred_tags_bias_selection_without_filtering() {
+    let base = get_quest_templates();
+    let templates = vec![
+        with_tags(base[0].clone(), &["gathering"]),
+        with_tags(base[1].clone(), &["combat"]),
+        with_tags(base[2].clone(), &[]),
+    ];
+    let preferences = QuestPreferences { preferred_tags: vec!["combat".to_string()], preference_weight: 5.0 };
+
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let mut counts = [0usize; 3];
+    for _ in 0..2000 {
+        // Level 20 weighs Easy, Medium and Hard templates equally
+        let chosen = select_template(&templates, 20, &preferences, &mut rng).expect("template");
+        let idx = templates.iter().position(|t| t.name_template == chosen.name_template).unwrap();
+        counts[idx] += 1;
+    }
+
+    // Expected shares: combat 5/7, the others 1/7 each
+    assert!(counts[1] > counts[0] * 3, "combat should dominate: {:?}", counts);
+    assert!(counts[1] > counts[2] * 3, "combat should dominate: {:?}", counts);
+    assert!(counts[2] > 0, "untagged templates remain eligible: {:?}", counts);
+    assert!(counts[0] > 0, "non-preferred templates remain eligible: {:?}", counts);
+}
+
+#[test]
+fn level_one_never_gets_the_epic_template() {
+    let templates = get_quest_templates();
+    let preferences = QuestPreferences { preferred_tags: vec!["combat".to_string()], preference_weight: 5.0 };
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+    for _ in 0..2000 {
+        let chosen = select_template(&templates, 1, &preferences, &mut rng).expect("template");
+        assert!(!chosen.name_template.starts_with("Conquer Dragon's Lair"));
+    }
+
+    let mut saw_epic = false;
+    for _ in 0..2000 {
+        saw_epic |= select_template(&templates, 50, &preferences, &mut rng).unwrap().name_template.starts_with("Conquer");
+    }
+    assert!(saw_epic, "high levels can still draw the Epic template");
+}
+
+#[test]
+fn quest_reward_is_applied_and_recorded_once() {
+    use chainquest_idle::components::{IdleProgress, Quest, Rarity, SFTAttributes};
+    use chainquest_idle::quest_system::{apply_quest_reward, QuestManager};
+
+    let quest = Quest {
+        id: 12,
+        name: "Conquer Dragon's Lair".to_string(),
+        description: String::new(),
+        completed: true,
+        reward_resources: 400.0,
+        reward_sft: Some(SFTAttributes { quest_id: 12, map_seed: 1, rarity: Rarity::Epic, power: 80, metadata: "Quest 12 Reward".to_string() }),
+        objective: Default::default(),
+    };
+    let mut player = IdleProgress::default();
+    let mut manager = QuestManager::default();
+
+    let receipt = apply_quest_reward(&mut player, &quest, &mut manager);
+    assert_eq!(player.resources, 400.0);
+    assert_eq!(receipt.quest_id, 12);
+    assert_eq!(receipt.resources, 400.0);
+    assert_eq!(receipt.sft.as_ref().map(|s| s.power), Some(80));
+
+    apply_quest_reward(&mut player, &quest, &mut manager);
+    assert_eq!(manager.completed_quests, vec![12], "completion is recorded once");
+}
+
+mod daily {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, Quest};
+    use chainquest_idle::config::multiplier::unix_now;
+    use chainquest_idle::quest_system::{
+        complete_daily_quests, reset_daily_quests, DailyQuest, DailyQuestState, QuestManager,
+        DAILY_QUEST_COUNT, SECONDS_PER_DAY,
+    };
+    use chainquest_idle::resources::{DatabaseConnection, DEFAULT_SAVE_SLOT};
+
+    const DAY: u64 = SECONDS_PER_DAY;
+
+    #[test]
+    fn returning_after_several_days_resets_once_and_breaks_the_streak() {
+        let mut state = DailyQuestState { last_reset: 10 * DAY + 5, daily_completed: 2, streak: 4 };
+        assert!(!state.needs_reset(10 * DAY + 80_000));
+        assert!(state.needs_reset(13 * DAY + 1));
+
+        state.reset(13 * DAY + 1);
+        assert_eq!(state, DailyQuestState { last_reset: 13 * DAY + 1, daily_completed: 0, streak: 0 });
+        assert!(!state.needs_reset(13 * DAY + 2));
+    }
+
+    #[test]
+    fn consecutive_days_extend_the_streak_and_rewards_escalate() {
+        let mut state = DailyQuestState { last_reset: 10 * DAY, daily_completed: 1, streak: 2 };
+        state.reset(11 * DAY + 30);
+        assert_eq!(state.streak, 3);
+
+        let first = state.record_completion();
+        let second = state.record_completion();
+        assert!(second > first && first > 1.0);
+        assert_eq!(state.daily_completed, 2);
+    }
+
+    #[test]
+    fn stale_state_spawns_the_daily_set_and_completions_persist() {
+        let db = DatabaseConnection::open(":memory:");
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(db.clone());
+        app.insert_resource(QuestManager::default());
+        app.insert_resource(DailyQuestState { last_reset: unix_now() as u64 - 3 * DAY, daily_completed: 3, streak: 5 });
+        app.world.spawn((Player, IdleProgress::default()));
+        app.add_systems(Update, (reset_daily_quests, complete_daily_quests.after(reset_daily_quests)));
+
+        app.update();
+        app.update();
+        let dailies = app.world.query::<&DailyQuest>().iter(&app.world).count();
+        assert_eq!(dailies, DAILY_QUEST_COUNT, "reset once, not once per missed day");
+        assert_eq!(app.world.resource::<DailyQuestState>().streak, 0);
+        assert!(app.world.resource::<QuestManager>().active_quests.is_empty(), "dailies are separate from rolling quests");
+
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(3600));
+        app.update();
+        app.update();
+        assert_eq!(app.world.query::<&Quest>().iter(&app.world).count(), 0);
+        assert!(app.world.query::<&IdleProgress>().single(&app.world).resources > 0.0);
+
+        let (_, completed, _) = db.load_daily_quests(DEFAULT_SAVE_SLOT).expect("persisted");
+        assert_eq!(completed, DAILY_QUEST_COUNT as u32);
+    }
+}
+
+mod reroll {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, Quest};
+    use chainquest_idle::quest_system::{abandon_quest, reroll_quest, select_quest, QuestManager, REROLL_COST_PER_LEVEL};
+
+    fn quest(id: u32) -> Quest {
+        Quest { id, name: format!("Quest {}", id), description: String::new(), completed: false, reward_resources: 10.0, reward_sft: None, objective: Default::default() }
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.insert_resource(ButtonInput::<KeyCode>::default());
+        app.world.spawn((Player, IdleProgress { resources: 100.0, ..Default::default() }));
+        let first = app.world.spawn(quest(1)).id();
+        let second = app.world.spawn(quest(2)).id();
+        app.insert_resource(QuestManager { active_quests: vec![first, second], next_quest_id: 3, ..Default::default() });
+        app.add_systems(Update, (select_quest, reroll_quest, abandon_quest).chain());
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+        app.world.resource_mut::<ButtonInput<KeyCode>>().clear();
+    }
+
+    #[test]
+    fn reroll_replaces_the_selected_quest_for_a_fee() {
+        let mut app = setup();
+        let original = app.world.resource::<QuestManager>().active_quests.clone();
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::KeyR);
+
+        let manager = app.world.resource::<QuestManager>();
+        assert_eq!(manager.active_quests.len(), 2);
+        assert_eq!(manager.active_quests[0], original[0]);
+        assert_ne!(manager.active_quests[1], original[1]);
+        assert_eq!(manager.next_quest_id, 4);
+        assert!(manager.completed_quests.is_empty(), "rerolled quests don't count as completed");
+        assert!(app.world.get_entity(original[1]).is_none());
+        assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 100.0 - REROLL_COST_PER_LEVEL);
+    }
+
+    #[test]
+    fn abandon_removes_without_replacement() {
+        let mut app = setup();
+        press(&mut app, KeyCode::KeyX);
+
+        let manager = app.world.resource::<QuestManager>();
+        assert_eq!(manager.active_quests.len(), 1);
+        assert_eq!(manager.next_quest_id, 3);
+        assert!(manager.completed_quests.is_empty());
+        assert_eq!(app.world.query::<&Quest>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn completing_a_quest_keeps_the_selection_in_range() {
+        let mut world = World::new();
+        let quests: Vec<Entity> = (1..=3).map(|id| world.spawn(quest(id)).id()).collect();
+        let mut manager = QuestManager { active_quests: quests.clone(), selected: 2, ..Default::default() };
+
+        manager.remove_active(quests[0]);
+        assert_eq!(manager.selected_quest(), Some(quests[2]), "the selection follows its quest");
+        manager.remove_active(quests[2]);
+        assert_eq!(manager.selected_quest(), Some(quests[1]));
+        manager.remove_active(quests[1]);
+        assert_eq!((manager.selected, manager.selected_quest()), (0, None));
+    }
+}
+
+mod objectives {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{ObjectiveKind, Quest, QuestObjective};
+    use chainquest_idle::quest_system::{advance_quest_objectives, get_quest_templates, objective_for};
+    use chainquest_idle::resources::{LevelUpEvent, ResourcesCredited, ResourcesProduced};
+    use chainquest_idle::ui::hud::{format_hud, HudData};
+
+    fn quest(objective: QuestObjective) -> Quest {
+        Quest { id: 1, name: "Collect".to_string(), description: String::new(), completed: false, reward_resources: 50.0, reward_sft: None, objective }
+    }
+
+    #[test]
+    fn collection_advances_toward_target() {
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.add_event::<ResourcesProduced>();
+        app.add_event::<ResourcesCredited>();
+        app.add_event::<LevelUpEvent>();
+        let player = app.world.spawn_empty().id();
+        let collect = app.world.spawn(quest(QuestObjective::new(ObjectiveKind::CollectResources, 500.0))).id();
+        let timed = app.world.spawn(quest(QuestObjective::new(ObjectiveKind::Elapsed, 5.0))).id();
+        let levels = app.world.spawn(quest(QuestObjective::new(ObjectiveKind::GainLevels, 3.0))).id();
+        app.add_systems(Update, advance_quest_objectives);
+
+        app.world.send_event(ResourcesProduced { player, amount: 150.0 });
+        app.world.send_event(ResourcesCredited { player_id: 1, amount: 50.0, source: None });
+        app.world.send_event(LevelUpEvent { player, old_level: 1, new_level: 3 });
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(2));
+        app.update();
+        let progress = &app.world.get::<Quest>(collect).unwrap().objective;
+        assert_eq!(progress.current, 200.0);
+        assert!(!progress.is_complete());
+        assert_eq!(app.world.get::<Quest>(timed).unwrap().objective.current, 2.0);
+        assert_eq!(app.world.get::<Quest>(levels).unwrap().objective.current, 2.0);
+
+        app.world.send_event(ResourcesCredited { player_id: 1, amount: 400.0, source: None });
+        app.update();
+        let progress = &app.world.get::<Quest>(collect).unwrap().objective;
+        assert!(progress.is_complete());
+        assert_eq!(progress.progress_text(), "500/500");
+    }
+
+    #[test]
+    fn auto_collect_advances_collection_objectives() {
+        use chainquest_idle::components::{IdleProgress, Player};
+        use chainquest_idle::config::progression::ProgressionConfig;
+        use chainquest_idle::resources::ResourceCollected;
+        use chainquest_idle::security::SecurityManager;
+        use chainquest_idle::systems::apply_validated_collection;
+        use chainquest_idle::systems_idle::auto_collect;
+
+        let mut app = App::new();
+        app.insert_resource(Time::default());
+        app.insert_resource(SecurityManager::default());
+        app.insert_resource(ProgressionConfig { auto_collect_level: 1, auto_collect_interval_secs: 1.0, ..Default::default() });
+        app.add_event::<ResourceCollected>();
+        app.add_event::<ResourcesProduced>();
+        app.add_event::<ResourcesCredited>();
+        app.add_event::<LevelUpEvent>();
+        app.world.spawn((Player, IdleProgress { level: 3, ..Default::default() }));
+        let collect = app.world.spawn(quest(QuestObjective::new(ObjectiveKind::CollectResources, 500.0))).id();
+        app.add_systems(Update, (
+            auto_collect,
+            apply_validated_collection.after(auto_collect),
+            advance_quest_objectives.after(apply_validated_collection),
+        ));
+
+        app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(1));
+        app.update();
+        assert_eq!(app.world.get::<Quest>(collect).unwrap().objective.current, 30.0);
+    }
+
+    #[test]
+    fn templates_map_to_objectives() {
+        let templates = get_quest_templates();
+        assert_eq!(objective_for(&templates[0], 50.0).kind, ObjectiveKind::CollectResources);
+        assert_eq!(objective_for(&templates[1], 100.0), QuestObjective::new(ObjectiveKind::Elapsed, 10.0));
+    }
+
+    #[test]
+    fn hud_shows_quest_progress() {
+        let hud = format_hud(&HudData { quest: Some(("Collect".to_string(), "120/500".to_string())), ..Default::default() });
+        assert!(hud.contains("Quest: Collect (120/500)"), "{}", hud);
+    }
+}
+
+mod seeded {
+    use bevy::prelude::*;
+    use chainquest_idle::components::Quest;
+    use chainquest_idle::quest_system::{fast_forward_quests, QuestManager, QuestPreferences};
+    use chainquest_idle::resources::GameRng;
+
+    /// Quests generated by a fresh session over a few minutes of simulated time
+    fn session(rng: GameRng) -> Vec<(u32, String, f32, Option<u32>)> {
+        let mut app = App::new();
+        app.insert_resource(QuestManager::default());
+        app.insert_resource(rng);
+        app.add_systems(Update, |mut commands: Commands, mut manager: ResMut<QuestManager>, mut rng: ResMut<GameRng>| {
+            fast_forward_quests(&mut commands, &mut manager, 25, &QuestPreferences::default(), 90.0, &mut *rng);
+        });
+        app.update();
+
+        let mut quests: Vec<_> = app.world.query::<&Quest>().iter(&app.world)
+            .map(|q| (q.id, q.name.clone(), q.reward_resources, q.reward_sft.as_ref().map(|s| s.power)))
+            .collect();
+        quests.sort_by_key(|q| q.0);
+        quests
+    }
+
+    #[test]
+    fn same_seed_generates_identical_quests() {
+        let first = session(GameRng::seeded(7));
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, session(GameRng::seeded(7)));
+    }
+
+    #[test]
+    fn unseeded_rng_still_generates_quests() {
+        assert!(!GameRng::default().is_seeded());
+        assert_eq!(session(GameRng::default()).len(), 3);
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use chainquest_idle::components::{IdleProgress, Player, PlayerStats};
+use chainquest_idle::config::keys::KeyBindings;
+use chainquest_idle::config::save::SaveConfig;
+use chainquest_idle::resources::{DatabaseConnection, GameState, DEFAULT_SAVE_SLOT};
+use chainquest_idle::systems::{save_now, save_on_exit};
+use chainquest_idle::systems_setup::setup_ui;
+
+fn app_with_player(db: &DatabaseConnection, resources: f64) -> App {
+    let mut app = App::new();
+    app.insert_resource(db.clone());
+    app.insert_resource(GameState::default());
+    app.insert_resource(ButtonInput::<KeyCode>::default());
+    app.world.spawn((Player, IdleProgress { resources, level: 4, ..Default::default() }));
+    app
+}
+
+#[test]
+fn save_interval_must_be_positive() {
+    assert_eq!(SaveConfig::default().interval_secs, 10.0);
+    assert!(SaveConfig::new(300.0).is_ok());
+    assert!(SaveConfig::new(0.0).is_err());
+    assert!(SaveConfig::new(-5.0).is_err());
+    assert!(SaveConfig::new(f32::NAN).is_err());
+}
+
+#[test]
+fn save_key_writes_progress_immediately() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut app = app_with_player(&db, 77.0);
+    app.insert_resource(KeyBindings::default());
+    app.add_systems(Update, save_now);
+
+    app.update();
+    assert!(db.load_progress(DEFAULT_SAVE_SLOT).is_err());
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyBindings::default().save);
+    app.update();
+    assert_eq!(db.load_progress(DEFAULT_SAVE_SLOT).expect("saved").resources, 77.0);
+}
+
+#[test]
+fn exit_event_saves_in_last_schedule() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut app = app_with_player(&db, 123.0);
+    app.add_event::<AppExit>();
+    app.add_systems(Last, save_on_exit);
+
+    app.update();
+    assert!(db.load_progress(DEFAULT_SAVE_SLOT).is_err());
+
+    app.world.send_event(AppExit);
+    app.update();
+    let loaded = db.load_progress(DEFAULT_SAVE_SLOT).expect("saved on exit");
+    assert_eq!(loaded.resources, 123.0);
+    assert_eq!(loaded.level, 4);
+}
+
+#[test]
+fn lifetime_stats_are_saved_and_restored() {
+    let db = DatabaseConnection::open(":memory:");
+    let mut app = app_with_player(&db, 10.0);
+    let stats = PlayerStats { lifetime_resources: 12_000.0, milestones_reached: 3, essence: 2.5, ..Default::default() };
+    let player = app.world.query_filtered::<Entity, With<Player>>().single(&app.world);
+    app.world.entity_mut(player).insert(stats);
+    app.add_event::<AppExit>();
+    app.add_systems(Last, save_on_exit);
+    app.world.send_event(AppExit);
+    app.update();
+
+    let mut restarted = App::new();
+    restarted.insert_resource(db);
+    restarted.insert_resource(GameState::default());
+    restarted.add_systems(Startup, setup_ui);
+    restarted.update();
+    let restored = restarted.world.query_filtered::<&PlayerStats, With<Player>>().single(&restarted.world);
+    assert_eq!((restored.lifetime_resources, restored.milestones_reached, restored.essence), (12_000.0, 3, 2.5));
+}
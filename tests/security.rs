@@ -0,0 +1,267 @@
+use chainquest_idle::security::{ActionType, SecurityManager, ValidationResult};
+
+#[test]
+fn action_types_have_independent_rate_limits() {
+    let manager = SecurityManager::default();
+
+    for _ in 0..5 {
+        assert!(matches!(manager.validate_action(1, ActionType::ResourceCollection, 10.0), ValidationResult::Approved));
+    }
+    assert!(matches!(manager.validate_action(1, ActionType::SftMint, 0.0), ValidationResult::Approved));
+    assert!(matches!(manager.validate_action(1, ActionType::SftMint, 0.0), ValidationResult::RateLimited));
+
+    // The throttled mint doesn't affect collection
+    assert!(matches!(manager.validate_resource_collection(1, 10.0), ValidationResult::Approved));
+}
+
+#[test]
+fn flags_survive_a_restart_through_the_db() {
+    use chainquest_idle::resources::DatabaseConnection;
+
+    let db = DatabaseConnection::open(":memory:");
+    let manager = SecurityManager::default();
+    for _ in 0..3 {
+        let _ = manager.validate_resource_collection(9, 1_000_000.0);
+    }
+    assert_eq!(manager.get_player_status(9).unwrap().suspicious_activity_count, 3);
+    manager.persist(&db).expect("persist ok");
+
+    // "Restart": rebuild the manager purely from the database
+    let restored = SecurityManager::from_db(&db);
+    assert_eq!(restored.get_player_status(9).unwrap().suspicious_activity_count, 3);
+}
+
+#[test]
+fn crossing_the_threshold_bans_and_the_ban_persists() {
+    use chainquest_idle::resources::DatabaseConnection;
+
+    let db = DatabaseConnection::open(":memory:");
+    let manager = SecurityManager::from_db(&db);
+    // Each suspicious level jump adds 5, so two reach the default threshold of 10
+    let _ = manager.validate_level_up(4, 1, 50);
+    assert!(!manager.is_banned(4));
+    let _ = manager.validate_level_up(4, 1, 50);
+    assert!(manager.is_banned(4));
+    let logged = manager.events_for_player(4).len();
+
+    // Every validator now short-circuits, without filling the audit log
+    match manager.validate_resource_collection(4, 1.0) {
+        ValidationResult::Rejected(reason) => assert_eq!(reason, "banned"),
+        other => panic!("expected rejection, got {:?}", other),
+    }
+    assert!(matches!(manager.validate_quest_completion(4, 1), ValidationResult::Rejected(_)));
+    assert_eq!(manager.events_for_player(4).len(), logged);
+
+    // Saved at once, without waiting for the periodic persist
+    let bans = db.load_bans().expect("bans");
+    assert_eq!(bans.len(), 1);
+    assert_eq!(bans[0].0, 4);
+    assert!(SecurityManager::from_db(&db).is_banned(4));
+}
+
+#[test]
+fn audit_log_records_non_approved_results_with_a_cap() {
+    let mut manager = SecurityManager::default();
+    manager.validation_config.audit_log_capacity = 3;
+
+    assert!(matches!(manager.validate_resource_collection(1, 5.0), ValidationResult::Approved));
+    let _ = manager.validate_resource_collection(1, 5000.0);
+    assert!(!manager.recent_events(10).is_empty());
+
+    let events = manager.events_for_player(1);
+    assert_eq!(events.len(), 1, "approved actions are not recorded");
+    assert_eq!(events[0].action, ActionType::ResourceCollection);
+    assert!(matches!(events[0].result, ValidationResult::Rejected(_)));
+    assert!(events[0].detail.contains("5000"), "detail includes the amount: {}", events[0].detail);
+
+    for player in 2..6 {
+        let _ = manager.validate_resource_collection(player, 5000.0);
+    }
+    let recent = manager.recent_events(10);
+    assert_eq!(recent.len(), 3, "buffer is capped");
+    assert_eq!(recent[0].player_id, 5, "newest first");
+    assert!(manager.events_for_player(1).is_empty(), "oldest events are evicted");
+}
+
+#[test]
+fn collections_are_credited_only_when_approved() {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+    use chainquest_idle::security::LOCAL_PLAYER_ID;
+    use chainquest_idle::systems::apply_validated_collection;
+
+    let mut manager = SecurityManager::default();
+    manager.validation_config.max_resource_gain_per_action = 100.0;
+    let mut app = App::new();
+    app.insert_resource(manager);
+    app.add_event::<ResourceCollected>();
+    app.add_event::<ResourcesCredited>();
+    app.world.spawn((Player, IdleProgress::default()));
+    app.add_systems(Update, apply_validated_collection);
+
+    app.world.send_event(ResourceCollected { player_id: LOCAL_PLAYER_ID, amount: 40.0, source: None });
+    app.world.send_event(ResourceCollected { player_id: LOCAL_PLAYER_ID, amount: 5000.0, source: None });
+    app.update();
+
+    assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 40.0);
+    let credited: Vec<f64> = app.world.resource_mut::<Events<ResourcesCredited>>().drain().map(|e| e.amount).collect();
+    assert_eq!(credited, vec![40.0], "only the approved collection is announced");
+    let status = app.world.resource::<SecurityManager>().get_player_status(LOCAL_PLAYER_ID).unwrap();
+    assert_eq!(status.suspicious_activity_count, 1);
+}
+
+#[test]
+fn collections_credit_only_the_named_player_with_a_level_scaled_cap() {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player, PlayerId};
+    use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+    use chainquest_idle::systems::apply_validated_collection;
+
+    let mut manager = SecurityManager::default();
+    manager.validation_config.max_resource_gain_per_action = 100.0;
+    let mut app = App::new();
+    app.insert_resource(manager);
+    app.add_event::<ResourceCollected>();
+    app.add_event::<ResourcesCredited>();
+    let veteran = app.world.spawn((Player, PlayerId(1), IdleProgress { level: 30, ..Default::default() })).id();
+    let newcomer = app.world.spawn((Player, PlayerId(2), IdleProgress::default())).id();
+    app.add_systems(Update, apply_validated_collection);
+
+    app.world.send_event(ResourceCollected { player_id: 1, amount: 2500.0, source: None });
+    app.world.send_event(ResourceCollected { player_id: 2, amount: 2500.0, source: None });
+    app.world.send_event(ResourceCollected { player_id: 3, amount: 10.0, source: None });
+    app.update();
+
+    assert_eq!(app.world.get::<IdleProgress>(veteran).unwrap().resources, 2500.0, "cap is 100 per level");
+    assert_eq!(app.world.get::<IdleProgress>(newcomer).unwrap().resources, 0.0, "level 1 cap is 100");
+    let credited: Vec<u32> = app.world.resource_mut::<Events<ResourcesCredited>>().drain().map(|e| e.player_id).collect();
+    assert_eq!(credited, vec![1]);
+}
+
+#[test]
+fn collections_are_dropped_without_a_security_manager() {
+    use bevy::prelude::*;
+    use chainquest_idle::components::{IdleProgress, Player};
+    use chainquest_idle::resources::{ResourceCollected, ResourcesCredited};
+    use chainquest_idle::security::LOCAL_PLAYER_ID;
+    use chainquest_idle::systems::apply_validated_collection;
+
+    let mut app = App::new();
+    app.add_event::<ResourceCollected>();
+    app.add_event::<ResourcesCredited>();
+    app.world.spawn((Player, IdleProgress::default()));
+    app.add_systems(Update, apply_validated_collection);
+
+    app.world.send_event(ResourceCollected { player_id: LOCAL_PLAYER_ID, amount: 10.0, source: None });
+    app.update();
+
+    assert_eq!(app.world.query::<&IdleProgress>().single(&app.world).resources, 0.0);
+    assert!(app.world.resource::<Events<ResourcesCredited>>().is_empty());
+}
+
+#[test]
+fn validation_config_reads_env_and_ignores_bad_values() {
+    use chainquest_idle::security::ValidationConfig;
+
+    std::env::set_var("CQ_MAX_APS", "4");
+    std::env::set_var("CQ_MIN_QUEST_GAP", "-3");
+    std::env::set_var("CQ_SUSPICIOUS_THRESHOLD", "0");
+    let config = ValidationConfig::from_env();
+    std::env::remove_var("CQ_MAX_APS");
+    std::env::remove_var("CQ_MIN_QUEST_GAP");
+    std::env::remove_var("CQ_SUSPICIOUS_THRESHOLD");
+
+    let defaults = ValidationConfig::default();
+    assert_eq!(config.max_actions_per_second, 4.0);
+    assert_eq!(config.min_time_between_quests, defaults.min_time_between_quests, "negative gap is ignored");
+    assert_eq!(config.suspicious_threshold, defaults.suspicious_threshold, "zero threshold is ignored");
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn config_can_be_changed_live() {
+    use chainquest_idle::security::ValidationConfig;
+
+    let mut manager = SecurityManager::default();
+    assert!(matches!(manager.validate_resource_collection(1, 500.0), ValidationResult::Approved));
+
+    let invalid = ValidationConfig { suspicious_threshold: 0, ..Default::default() };
+    assert!(manager.update_config(invalid).is_err());
+    assert_eq!(manager.validation_config.suspicious_threshold, ValidationConfig::default().suspicious_threshold);
+
+    let strict = ValidationConfig { max_resource_gain_per_action: 100.0, ..Default::default() };
+    manager.update_config(strict).expect("valid config");
+    assert!(matches!(manager.validate_resource_collection(1, 500.0), ValidationResult::Rejected(_)));
+}
+
+#[test]
+fn bursts_spanning_a_second_boundary_are_rate_limited() {
+    use chainquest_idle::security::{ActionRate, ValidationConfig};
+    use std::time::{Duration, Instant};
+
+    let limit = ValidationConfig::default().limit_for(ActionType::ResourceCollection);
+    let mut rate = ActionRate::default();
+    let start = Instant::now();
+    let mut limited = 0;
+    // 15 collections 80ms apart span a second boundary; the window ending at 800ms already holds 10
+    for i in 0..15 {
+        let now = start + Duration::from_millis(80 * i);
+        if rate.rate_with(now) > limit {
+            limited += 1;
+        } else {
+            rate.recent.push_back(now);
+        }
+    }
+    assert_eq!(limited, 3, "the 800-960ms attempts are limited, then the window slides past the first ones");
+}
+
+#[test]
+fn whitelisted_players_skip_rate_limits() {
+    use chainquest_idle::security::parse_whitelist;
+
+    let manager = SecurityManager::default();
+    for player_id in parse_whitelist("7, 9,bogus,") {
+        manager.add_to_whitelist(player_id);
+    }
+    assert!(manager.is_whitelisted(7) && manager.is_whitelisted(9));
+
+    for _ in 0..1000 {
+        assert!(matches!(manager.validate_resource_collection(7, 10.0), ValidationResult::Approved));
+    }
+    assert!(!manager.is_banned(7));
+
+    manager.remove_from_whitelist(7);
+    let limited = (0..20).any(|_| matches!(manager.validate_resource_collection(7, 10.0), ValidationResult::RateLimited));
+    assert!(limited, "limits apply again once removed");
+}
+
+#[test]
+fn map_generation_requests_need_a_minimum_interval() {
+    let manager = SecurityManager::default();
+    assert!(matches!(manager.validate_map_generation(1), ValidationResult::Approved));
+    assert!(matches!(manager.validate_map_generation(1), ValidationResult::RateLimited));
+    assert!(matches!(manager.validate_map_generation(2), ValidationResult::Approved), "limits are per player");
+}
+
+#[test]
+fn cleanup_drops_stale_histories_whenever_it_runs() {
+    use bevy::prelude::*;
+    use chainquest_idle::resources::DatabaseConnection;
+    use chainquest_idle::security::{security_cleanup, PlayerActionHistory};
+
+    let manager = SecurityManager::default();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    manager.player_actions.write().insert(1, PlayerActionHistory { last_resource_collection: now - 7200, ..Default::default() });
+    manager.player_actions.write().insert(2, PlayerActionHistory { last_resource_collection: now, ..Default::default() });
+
+    let mut app = App::new();
+    app.insert_resource(manager);
+    app.insert_resource(DatabaseConnection::open(":memory:"));
+    app.add_systems(Update, security_cleanup);
+    app.update();
+
+    let actions = app.world.resource::<SecurityManager>().player_actions.read().clone();
+    assert!(!actions.contains_key(&1), "stale history removed");
+    assert!(actions.contains_key(&2), "recent history kept");
+}
@@ -0,0 +1,175 @@
+use chainquest_idle::multiplayer::network::{GameMessage, PROTOCOL_VERSION};
+use chainquest_idle::multiplayer::server::{handle_event, ServerAction, ServerEvent, ServerState};
+use chainquest_idle::security::{FIRST_REMOTE_PLAYER_ID, LOCAL_PLAYER_ID};
+
+/// Id the first connection is assigned
+const PEER: u32 = FIRST_REMOTE_PLAYER_ID;
+
+fn connected() -> ServerState {
+    let mut state = ServerState::default();
+    let peer_id = state.allocate_peer_id();
+    handle_event(ServerEvent::Connected { peer_id, addr: "127.0.0.1:5000".into() }, &mut state);
+    state
+}
+
+fn joined() -> ServerState {
+    let mut state = connected();
+    receive(GameMessage::PlayerJoin { username: "alice".into() }.to_bytes().unwrap(), &mut state);
+    state
+}
+
+fn receive(data: Vec<u8>, state: &mut ServerState) -> ServerAction {
+    handle_event(ServerEvent::Received { peer_id: PEER, channel_id: 0, data }, state)
+}
+
+#[test]
+fn ping_yields_pong() {
+    let mut state = connected();
+    assert_eq!(receive(GameMessage::Ping.to_bytes().unwrap(), &mut state), ServerAction::Pong);
+}
+
+#[test]
+fn chat_is_relayed() {
+    let mut state = joined();
+    let chat = GameMessage::Chat { player_id: PEER, message: "hello".into() }.to_bytes().unwrap();
+    assert_eq!(receive(chat.clone(), &mut state), ServerAction::Relay(chat));
+}
+
+#[test]
+fn unknown_and_malformed_payloads_are_ignored() {
+    let mut state = ServerState::default();
+    assert_eq!(receive(br#"{"Teleport":{"x":1}}"#.to_vec(), &mut state), ServerAction::Ignore);
+    assert_eq!(receive(b"\xff\x00not json".to_vec(), &mut state), ServerAction::Ignore);
+}
+
+#[test]
+fn connections_are_tracked() {
+    let mut state = ServerState::default();
+    handle_event(ServerEvent::Connected { peer_id: 1, addr: "a".into() }, &mut state);
+    handle_event(ServerEvent::Connected { peer_id: 2, addr: "b".into() }, &mut state);
+    handle_event(ServerEvent::Disconnected { peer_id: 1 }, &mut state);
+    assert_eq!(state.sessions.len(), 1);
+}
+
+#[test]
+fn hello_is_answered_with_the_server_version() {
+    let mut state = connected();
+    let hello = GameMessage::Hello { protocol_version: PROTOCOL_VERSION }.to_bytes().unwrap();
+    assert_eq!(receive(hello, &mut state), ServerAction::Reply(GameMessage::Hello { protocol_version: PROTOCOL_VERSION }));
+    let stale = GameMessage::Hello { protocol_version: PROTOCOL_VERSION + 1 }.to_bytes().unwrap();
+    assert!(matches!(receive(stale, &mut state), ServerAction::Kick(_)));
+}
+
+#[test]
+fn join_records_the_username() {
+    let mut state = connected();
+    let bad = GameMessage::PlayerJoin { username: "<script>".into() }.to_bytes().unwrap();
+    assert!(matches!(receive(bad, &mut state), ServerAction::Reply(GameMessage::Rejected { .. })));
+    assert!(!state.sessions[&PEER].is_authenticated());
+
+    let join = GameMessage::PlayerJoin { username: "alice".into() }.to_bytes().unwrap();
+    assert_eq!(receive(join.clone(), &mut state), ServerAction::Joined { player_id: PEER, announce: join.clone() });
+    assert_eq!(state.sessions[&PEER].username.as_deref(), Some("alice"));
+    assert!(matches!(receive(join, &mut state), ServerAction::Reply(GameMessage::Rejected { .. })), "joining twice");
+}
+
+#[test]
+fn resource_updates_are_validated() {
+    let mut state = joined();
+    let update = GameMessage::ResourceUpdate { player_id: PEER, resources: 50.0 }.to_bytes().unwrap();
+    assert_eq!(receive(update.clone(), &mut state), ServerAction::Relay(update));
+    assert_eq!(state.sessions[&PEER].resources, 50.0);
+
+    let excessive = GameMessage::ResourceUpdate { player_id: PEER, resources: 1_000_000.0 }.to_bytes().unwrap();
+    assert!(matches!(receive(excessive, &mut state), ServerAction::Reply(GameMessage::Rejected { .. })));
+    assert_eq!(state.sessions[&PEER].resources, 50.0, "rejected updates are not applied");
+
+    let spoofed = GameMessage::ResourceUpdate { player_id: PEER + 1, resources: 10.0 }.to_bytes().unwrap();
+    assert!(matches!(receive(spoofed, &mut state), ServerAction::Reply(GameMessage::Rejected { .. })));
+}
+
+#[test]
+fn banned_peers_are_kicked() {
+    let mut state = connected();
+    state.security.ban_player(PEER, "test");
+    assert!(matches!(receive(GameMessage::Ping.to_bytes().unwrap(), &mut state), ServerAction::Kick(_)));
+}
+
+#[test]
+fn relays_skip_the_sender_and_peers_that_have_not_joined() {
+    let mut state = joined();
+    let second = state.allocate_peer_id();
+    let third = state.allocate_peer_id();
+    handle_event(ServerEvent::Connected { peer_id: second, addr: "10.0.0.2:5000".into() }, &mut state);
+    handle_event(ServerEvent::Connected { peer_id: third, addr: "10.0.0.3:5000".into() }, &mut state);
+    let join = GameMessage::PlayerJoin { username: "bob".into() }.to_bytes().unwrap();
+    handle_event(ServerEvent::Received { peer_id: second, channel_id: 0, data: join }, &mut state);
+
+    assert!(!state.relays_to(PEER, PEER));
+    assert!(state.relays_to(PEER, second));
+    assert!(!state.relays_to(PEER, third), "never completed the handshake");
+}
+
+#[test]
+fn traffic_is_counted() {
+    let mut state = connected();
+    receive(GameMessage::Ping.to_bytes().unwrap(), &mut state);
+    receive(b"not a message".to_vec(), &mut state);
+    state.record_sent(12);
+    assert_eq!(state.stats.packets_received, 2);
+    assert!(state.stats.bytes_received > 4);
+    assert_eq!((state.stats.packets_sent, state.stats.bytes_sent), (1, 12));
+}
+
+#[test]
+fn messages_before_join_are_rejected() {
+    let mut state = connected();
+    let chat = GameMessage::Chat { player_id: PEER, message: "hello".into() }.to_bytes().unwrap();
+    assert!(matches!(receive(chat.clone(), &mut state), ServerAction::Reply(GameMessage::Rejected { .. })));
+    assert_eq!(receive(GameMessage::Ping.to_bytes().unwrap(), &mut state), ServerAction::Pong, "ping needs no handshake");
+
+    receive(GameMessage::PlayerJoin { username: "alice".into() }.to_bytes().unwrap(), &mut state);
+    assert_eq!(receive(chat.clone(), &mut state), ServerAction::Relay(chat));
+}
+
+#[test]
+fn each_connection_gets_its_own_player_id() {
+    let mut state = ServerState::default();
+    let first = state.allocate_peer_id();
+    let second = state.allocate_peer_id();
+    assert_eq!(first, PEER);
+    assert_ne!(first, second);
+    assert!(first > LOCAL_PLAYER_ID, "peers never share the local player's id");
+    handle_event(ServerEvent::Connected { peer_id: second, addr: "10.0.0.2:7000".into() }, &mut state);
+    assert_eq!(state.sessions[&second].player_id, second);
+    assert_eq!(state.sessions[&second].addr, "10.0.0.2:7000");
+}
+
+#[test]
+fn client_manager_packets_decode_on_the_server() {
+    use chainquest_idle::multiplayer::client::SERVER_PEER_ID;
+    use chainquest_idle::multiplayer::network::{frame, NetworkManager};
+
+    let mut client = NetworkManager::default();
+    client.register_peer(SERVER_PEER_ID);
+    client.send_message(SERVER_PEER_ID, &GameMessage::Ping, true).expect("queued");
+    assert_eq!(client.get_stats().packets_sent, 0, "counted once handed to ENet, not when queued");
+    let wire = frame(&client.outbox.pop().expect("queued packet").data);
+
+    let mut state = connected();
+    let payloads = state.decode(PEER, &wire);
+    assert_eq!(payloads.len(), 1);
+    let data = payloads.into_iter().next().unwrap();
+    assert_eq!(handle_event(ServerEvent::Received { peer_id: PEER, channel_id: 0, data }, &mut state), ServerAction::Pong);
+
+    let reply = state.encode_message(&GameMessage::Pong).expect("encoded");
+    assert_eq!(client.receive_bytes(SERVER_PEER_ID, &reply), vec![GameMessage::Pong.to_bytes().unwrap()]);
+}
+
+#[test]
+fn map_generation_spam_is_rejected() {
+    let mut state = joined();
+    let request = GameMessage::MapGenerate { seed: 42, biome: None }.to_bytes().unwrap();
+    assert_eq!(receive(request.clone(), &mut state), ServerAction::Relay(request.clone()));
+    assert!(matches!(receive(request, &mut state), ServerAction::Reply(GameMessage::Rejected { .. })));
+}